@@ -0,0 +1,78 @@
+//! API keys (personal access tokens), long-lived credentials for programmatic access.
+//!
+//! Notes:
+//!
+//! - String format: `gk_<key_id>_<secret>`, the `key_id` (a simple uuid) being the public lookup
+//!   id and the `secret` 256 random bits, base64url encoded.
+//! - The key is shown once at creation, only the hash of its secret is stored, with the `pwd`
+//!   schemes and the `key_id` as salt (see `api_key_to_hash`).
+
+use crate::pwd::ContentToHash;
+use lib_utils::b64::b64u_encode;
+use rand::RngCore;
+use uuid::Uuid;
+
+const API_KEY_PREFIX: &str = "gk_";
+
+/// Returns the new `(key_id, key)`.
+pub fn generate_api_key() -> (Uuid, String) {
+	let key_id = Uuid::new_v4();
+	let mut secret = [0u8; 32]; // 256 bits
+	rand::thread_rng().fill_bytes(&mut secret);
+
+	let key = format!(
+		"{API_KEY_PREFIX}{}_{}",
+		key_id.simple(),
+		b64u_encode(secret)
+	);
+
+	(key_id, key)
+}
+
+/// Returns the `(key_id, secret)` of the key, or None if not an API key.
+pub fn parse_api_key(key: &str) -> Option<(Uuid, &str)> {
+	let (key_id, secret) = key
+		.strip_prefix(API_KEY_PREFIX)?
+		.split_once('_')?;
+	let key_id = Uuid::try_parse(key_id).ok()?;
+
+	(!secret.is_empty()).then_some((key_id, secret))
+}
+
+/// The content to hash (or validate) for the API key secret.
+pub fn api_key_to_hash(
+	key_id: Uuid,
+	secret: &str,
+) -> ContentToHash {
+	ContentToHash {
+		content: secret.to_string(),
+		salt: key_id,
+	}
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+
+	#[test]
+	fn test_api_key_generate_and_parse_ok() -> Result<()> {
+		// -- Exec
+		let (key_id, key) = generate_api_key();
+		let (parsed_id, secret) = parse_api_key(&key).ok_or("Should parse")?;
+
+		// -- Check
+		assert_eq!(
+			parsed_id, key_id
+		);
+		assert!(key.ends_with(secret));
+		assert!(parse_api_key("gk_not-a-uuid_secret").is_none());
+		assert!(parse_api_key(&key.replacen("gk_", "xx_", 1)).is_none());
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -0,0 +1,103 @@
+//! Client credentials (OAuth2 `client_id`/`client_secret`) of the service accounts,
+//! and of the client apps of the authorization server.
+//!
+//! Notes:
+//!
+//! - String formats: `sa_<uuid>` (service account) or `app_<uuid>` (client app), with a simple uuid,
+//!   for the `client_id`, and 256 random bits, base64url encoded, for the `client_secret`.
+//! - Like the API keys (see `api_key`), the secret is shown once and only stored as a `pwd`
+//!   hash, salted by the `client_id` uuid (see `client_secret_to_hash`).
+
+use crate::pwd::ContentToHash;
+use lib_utils::b64::b64u_encode;
+use rand::RngCore;
+use uuid::Uuid;
+
+const CLIENT_ID_PREFIX: &str = "sa_";
+const APP_CLIENT_ID_PREFIX: &str = "app_";
+
+/// Returns the new `(client_id, client_secret)` of a service account.
+pub fn generate_client_credentials() -> (String, String) {
+	let client_id = format!(
+		"{CLIENT_ID_PREFIX}{}",
+		Uuid::new_v4().simple()
+	);
+
+	(client_id, generate_client_secret())
+}
+
+/// The new `client_id` of a client app (the public clients have no secret).
+pub fn generate_app_client_id() -> String {
+	format!(
+		"{APP_CLIENT_ID_PREFIX}{}",
+		Uuid::new_v4().simple()
+	)
+}
+
+/// A new secret for a `client_id` (e.g., on rotation).
+pub fn generate_client_secret() -> String {
+	let mut secret = [0u8; 32]; // 256 bits
+	rand::thread_rng().fill_bytes(&mut secret);
+	b64u_encode(secret)
+}
+
+/// The content to hash (or validate) for the client secret, or None if not a `client_id`.
+pub fn client_secret_to_hash(
+	client_id: &str,
+	client_secret: &str,
+) -> Option<ContentToHash> {
+	let salt = client_id
+		.strip_prefix(CLIENT_ID_PREFIX)
+		.or_else(|| client_id.strip_prefix(APP_CLIENT_ID_PREFIX))
+		.and_then(|uuid| Uuid::try_parse(uuid).ok())?;
+
+	Some(
+		ContentToHash {
+			content: client_secret.to_string(),
+			salt,
+		},
+	)
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+
+	#[test]
+	fn test_client_credentials_to_hash_ok() -> Result<()> {
+		// -- Exec
+		let (client_id, client_secret) = generate_client_credentials();
+		let to_hash = client_secret_to_hash(
+			&client_id,
+			&client_secret,
+		)
+		.ok_or("Should be a client_id")?;
+
+		// -- Check
+		assert_eq!(
+			to_hash.content,
+			client_secret
+		);
+		assert!(client_id.ends_with(&to_hash.salt.simple().to_string()));
+		assert!(
+			client_secret_to_hash(
+				&generate_app_client_id(),
+				&client_secret
+			)
+			.is_some()
+		);
+		assert!(
+			client_secret_to_hash(
+				"user-01", "secret"
+			)
+			.is_none()
+		);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -1,4 +1,6 @@
-use lib_utils::envs::{get_env_b64u_as_u8s, get_env_parse};
+use crate::keyring::{parse_keys, KeyRing, LEGACY_KID};
+use crate::token::{Ed25519Key, TokenFormat, TokenSource};
+use lib_utils::envs::{get_env, get_env_b64u_as_u8s, get_env_parse, Error};
 use std::sync::OnceLock;
 
 pub fn auth_config() -> &'static AuthConfig {
@@ -15,10 +17,24 @@ pub fn auth_config() -> &'static AuthConfig {
 #[allow(non_snake_case)]
 pub struct AuthConfig {
 	// -- Crypt
-	pub PWD_KEY: Vec<u8>,
+	/// Pepper of the pwd schemes, with rotation (see `keyring`).
+	pub PWD_KEYS: KeyRing,
 
-	pub TOKEN_KEY: Vec<u8>,
+	/// Signing keys of the custom and HS256/HS512 tokens, with rotation (see `keyring`).
+	pub TOKEN_KEYS: KeyRing,
 	pub TOKEN_DURATION_SEC: f64,
+	pub REFRESH_TOKEN_DURATION_SEC: f64,
+	pub TOKEN_FORMAT: TokenFormat,
+	/// Signing key of the `jwt-eddsa` format, published at `/.well-known/jwks.json`.
+	pub TOKEN_ED25519_KEY: Option<Ed25519Key>,
+	/// The token source read first when a request has both a cookie and a bearer token.
+	pub TOKEN_SOURCE_FIRST: TokenSource,
+
+	// -- MFA
+	/// Encryption keys of the stored TOTP secrets, with rotation (see `keyring`).
+	pub MFA_KEYS: KeyRing,
+	/// The issuer shown by the authenticator apps (`otpauth` URI).
+	pub MFA_TOTP_ISSUER: String,
 }
 
 impl AuthConfig {
@@ -26,11 +42,71 @@ impl AuthConfig {
 		Ok(
 			AuthConfig {
 				// -- Crypt
-				PWD_KEY: get_env_b64u_as_u8s("SERVICE_PWD_KEY")?,
+				PWD_KEYS: load_keyring(
+					"SERVICE_PWD_KEYS",
+					"SERVICE_PWD_KEY",
+				)?,
 
-				TOKEN_KEY: get_env_b64u_as_u8s("SERVICE_TOKEN_KEY")?,
+				TOKEN_KEYS: load_keyring(
+					"SERVICE_TOKEN_KEYS",
+					"SERVICE_TOKEN_KEY",
+				)?,
 				TOKEN_DURATION_SEC: get_env_parse("SERVICE_TOKEN_DURATION_SEC")?,
+				REFRESH_TOKEN_DURATION_SEC: get_env_parse("SERVICE_REFRESH_TOKEN_DURATION_SEC")?,
+				TOKEN_FORMAT: get_env_parse("SERVICE_TOKEN_FORMAT")?,
+				TOKEN_ED25519_KEY: load_ed25519_key()?,
+				TOKEN_SOURCE_FIRST: get_env_parse("SERVICE_TOKEN_SOURCE_FIRST")?,
+
+				// -- MFA
+				MFA_KEYS: load_keyring(
+					"SERVICE_MFA_KEYS",
+					"SERVICE_MFA_KEY",
+				)?,
+				MFA_TOTP_ISSUER: get_env("SERVICE_MFA_TOTP_ISSUER")?,
 			},
 		)
 	}
 }
+
+/// Loads the `keys_env` ring (active key first), with the `key_env` single key as the `LEGACY_KID` key.
+/// At least one of the two envs must be set.
+fn load_keyring(
+	keys_env: &'static str,
+	key_env: &'static str,
+) -> lib_utils::envs::Result<KeyRing> {
+	let mut keys = match get_env(keys_env) {
+		Ok(keys) => parse_keys(&keys).ok_or(Error::WrongFormat(keys_env))?,
+		Err(_) => Vec::new(),
+	};
+
+	match get_env_b64u_as_u8s(key_env) {
+		Ok(key) => {
+			if !keys.iter().any(|(kid, _)| kid == LEGACY_KID) {
+				keys.push((LEGACY_KID.to_string(), key));
+			}
+		},
+		Err(Error::MissingEnv(_)) if !keys.is_empty() => (),
+		Err(ex) => return Err(ex),
+	}
+
+	KeyRing::new(keys).ok_or(Error::MissingEnv(keys_env))
+}
+
+/// Loads the optional Ed25519 PKCS#8 PEM from the `SERVICE_TOKEN_ED25519_KEY_FILE` path,
+/// or the `SERVICE_TOKEN_ED25519_KEY` env, with the `SERVICE_TOKEN_ED25519_KID` key id.
+fn load_ed25519_key() -> lib_utils::envs::Result<Option<Ed25519Key>> {
+	let pem = if let Ok(file) = get_env("SERVICE_TOKEN_ED25519_KEY_FILE") {
+		std::fs::read_to_string(file).map_err(|_| Error::WrongFormat("SERVICE_TOKEN_ED25519_KEY_FILE"))?
+	} else if let Ok(pem) = get_env("SERVICE_TOKEN_ED25519_KEY") {
+		pem
+	} else {
+		return Ok(None);
+	};
+	let kid = get_env("SERVICE_TOKEN_ED25519_KID")?;
+
+	Ed25519Key::from_pkcs8_pem(
+		kid, &pem,
+	)
+	.map(Some)
+	.map_err(|_| Error::WrongFormat("SERVICE_TOKEN_ED25519_KEY"))
+}
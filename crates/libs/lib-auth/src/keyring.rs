@@ -0,0 +1,113 @@
+//! Rotatable symmetric keys, for the `TOKEN_KEY` and the `PWD_KEY` pepper.
+//!
+//! - The first key of the ring is the active one, used to sign the tokens and hash the passwords.
+//!   The others are only used to validate what they signed (or hashed) before the rotation.
+//! - Each key has a key id (kid), embedded in the tokens and the stored password hashes.
+//! - Tokens and hashes without a kid (issued before the ring) are validated with the `LEGACY_KID` key,
+//!   which is the single key env (e.g., `SERVICE_TOKEN_KEY`).
+//!
+//! Keys env format: `"kid-2:key_b64u,kid-1:key_b64u"` (e.g., `SERVICE_TOKEN_KEYS`, active first).
+
+use lazy_regex::regex_is_match;
+use lib_utils::b64::b64u_decode;
+
+/// Key id of the keys configured before the ring (e.g., `SERVICE_TOKEN_KEY`).
+pub const LEGACY_KID: &str = "0";
+
+pub struct KeyRing {
+	/// (kid, key), the first is the active key.
+	keys: Vec<(String, Vec<u8>)>,
+}
+
+impl KeyRing {
+	/// Returns `None` if `keys` is empty.
+	pub fn new(keys: Vec<(String, Vec<u8>)>) -> Option<Self> {
+		(!keys.is_empty()).then_some(Self { keys })
+	}
+
+	/// The (kid, key) to sign or hash with.
+	pub fn active(&self) -> (&str, &[u8]) {
+		let (kid, key) = &self.keys[0];
+		(kid, key)
+	}
+
+	/// The key of `kid` (the `LEGACY_KID` key when `None`).
+	pub fn get(
+		&self,
+		kid: Option<&str>,
+	) -> Option<&[u8]> {
+		let kid = kid.unwrap_or(LEGACY_KID);
+		self.keys
+			.iter()
+			.find(|(key_kid, _)| key_kid == kid)
+			.map(|(_, key)| key.as_slice())
+	}
+
+	/// True if `kid` (the `LEGACY_KID` when `None`) is the active kid.
+	pub fn is_active(
+		&self,
+		kid: Option<&str>,
+	) -> bool {
+		kid.unwrap_or(LEGACY_KID) == self.active().0
+	}
+}
+
+/// Parses the `"kid:key_b64u,..."` keys env format (see module doc).
+/// Kids are `[A-Za-z0-9_-]+`, since they are embedded in the tokens and hashes.
+pub fn parse_keys(keys: &str) -> Option<Vec<(String, Vec<u8>)>> {
+	keys.split(',')
+		.map(str::trim)
+		.filter(|entry| !entry.is_empty())
+		.map(
+			|entry| {
+				let (kid, key_b64u) = entry.split_once(':')?;
+				if !regex_is_match!(r"^[A-Za-z0-9_-]+$", kid) {
+					return None;
+				}
+				let key = b64u_decode(key_b64u).ok()?;
+				Some((kid.to_string(), key))
+			},
+		)
+		.collect()
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+
+	#[test]
+	fn test_keyring_parse_keys_ok() -> Result<()> {
+		// -- Exec
+		let keys = parse_keys("k-02:AQI, k-01:AwQ").ok_or("Should parse")?;
+		let ring = KeyRing::new(keys).ok_or("Should not be empty")?;
+
+		// -- Check
+		assert_eq!(
+			ring.active(),
+			("k-02", [1u8, 2].as_slice())
+		);
+		assert_eq!(
+			ring.get(Some("k-01")),
+			Some([3u8, 4].as_slice())
+		);
+		assert!(ring.get(None).is_none());
+		assert!(!ring.is_active(Some("k-01")));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_keyring_parse_keys_err() -> Result<()> {
+		// -- Exec & Check
+		assert!(parse_keys("no-kid-key").is_none());
+		assert!(parse_keys("bad.kid:AQI").is_none());
+		assert!(parse_keys("k-01:not b64u").is_none());
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -1,5 +1,10 @@
+pub mod api_key;
+pub mod client_credentials;
 mod config;
+pub mod keyring;
+pub mod oauth;
+pub mod oidc;
 pub mod pwd;
 pub mod token;
+pub mod totp;
 
-use config::auth_config;
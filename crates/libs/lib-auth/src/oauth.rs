@@ -0,0 +1,90 @@
+//! OAuth2 authorization server primitives: the authorization codes, the PKCE verification,
+//! and the `scope` strings.
+//!
+//! Notes:
+//!
+//! - An authorization code is 256 random bits, base64url encoded, and only stored hashed
+//!   (like the refresh tokens), for its short lifetime.
+//! - Only the `S256` PKCE method is supported (RFC 7636, and OAuth 2.1).
+//! - A scope is the name of the privilege (role) it grants to the client, see `lib_core::model::oauth`.
+
+use crate::oidc::{generate_state, pkce_challenge};
+use lib_utils::b64::b64u_encode;
+
+pub const PKCE_METHOD_S256: &str = "S256";
+
+pub fn generate_authorization_code() -> String {
+	generate_state()
+}
+
+/// The stored (and looked up) form of the authorization code.
+pub fn hash_authorization_code(code: &str) -> String {
+	b64u_encode(blake3::hash(code.as_bytes()).as_bytes())
+}
+
+/// True if the `code_verifier` is the one of the `S256` `code_challenge`.
+pub fn verify_pkce(
+	code_verifier: &str,
+	code_challenge: &str,
+) -> bool {
+	// Note: RFC 7636 (section 4.1) verifier length.
+	(43..=128).contains(&code_verifier.len()) && pkce_challenge(code_verifier) == code_challenge
+}
+
+/// The scopes of a space-delimited `scope` (deduplicated, in order).
+pub fn parse_scope(scope: &str) -> Vec<String> {
+	let mut scopes: Vec<String> = Vec::new();
+	for scope in scope.split_whitespace() {
+		if !scopes.iter().any(|s| s == scope) {
+			scopes.push(scope.to_string());
+		}
+	}
+
+	scopes
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use crate::oidc::generate_pkce;
+
+	#[test]
+	fn test_verify_pkce_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let pkce = generate_pkce();
+
+		// -- Exec & Check
+		assert!(verify_pkce(
+			&pkce.verifier,
+			&pkce.challenge
+		));
+		assert!(!verify_pkce(
+			&generate_pkce().verifier,
+			&pkce.challenge
+		));
+		assert!(!verify_pkce(
+			"short", &pkce.challenge
+		));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_parse_scope_ok() -> Result<()> {
+		// -- Exec
+		let scopes = parse_scope(" read  write read ");
+
+		// -- Check
+		assert_eq!(
+			scopes,
+			vec!["read".to_string(), "write".to_string()]
+		);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -0,0 +1,33 @@
+use serde::Serialize;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Serialize)]
+pub enum Error {
+	// -- ID Token
+	IdTokenInvalidFormat,
+	IdTokenAlgNotSupported,
+	IdTokenKeyNotFound,
+	IdTokenKeyInvalid,
+	IdTokenSignatureNotMatching,
+	IdTokenExpired,
+	/// Not matching `iss` or `aud`.
+	IdTokenClaimsNotMatching,
+	IdTokenNonceNotMatching,
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for Error {
+	fn fmt(
+		&self,
+		fmt: &mut core::fmt::Formatter,
+	) -> core::result::Result<(), core::fmt::Error> {
+		write!(
+			fmt,
+			"{self:?}"
+		)
+	}
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error Boilerplate
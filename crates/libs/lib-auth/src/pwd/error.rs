@@ -7,6 +7,8 @@ pub type Result<T> = core::result::Result<T, Error>;
 #[derive(Debug, Serialize, From)]
 pub enum Error {
 	PwdWithSchemeFailedParse,
+	/// The pepper key id is not in `PWD_KEYS` (anymore).
+	PwdKeyNotFound(Option<String>),
 
 	FailSpawnBlockForValidate,
 	FailSpawnBlockForHash,
@@ -13,6 +13,9 @@
 //! - The two public async functions `hash_pwd(...)` and `validate_pwd(...)` call the scheme using
 //!   `spawn_blocking` to ensure that long hashing/validation processes do not hinder the execution of smaller tasks.
 //! - Schemes are designed to be agnostic of whether they are in an async or sync context, hence they are async-free.
+//! - The stored pwd is `#scheme@kid#hashed`, the `kid` being the `PWD_KEYS` pepper key id (see `keyring`).
+//!   A pwd hashed with an older scheme or pepper validates as `SchemeStatus::Outdated`, to be re-hashed.
+//!   `#scheme#hashed` (before the key ring) uses the `LEGACY_KID` pepper.
 
 // region:    --- Modules;
 
@@ -22,6 +25,7 @@ mod scheme;
 pub use self::error::{Error, Result};
 pub use scheme::SchemeStatus;
 
+use crate::config::auth_config;
 use crate::pwd::scheme::{get_scheme, Scheme, DEFAULT_SCHEME};
 use lazy_regex::regex_captures;
 use std::str::FromStr;
@@ -67,15 +71,17 @@ pub async fn validate_pwd(
 ) -> Result<SchemeStatus> {
 	let PwdParts {
 		scheme_name,
+		kid,
 		hashed,
 	} = pwd_ref.parse()?;
 
 	// Note: We do first, so that we do not have to clonse the scheme_name.
-	let scheme_status = if scheme_name == DEFAULT_SCHEME {
-		SchemeStatus::Ok
-	} else {
-		SchemeStatus::Outdated
-	};
+	let scheme_status =
+		if scheme_name == DEFAULT_SCHEME && auth_config().PWD_KEYS.is_active(kid.as_deref()) {
+			SchemeStatus::Ok
+		} else {
+			SchemeStatus::Outdated
+		};
 
 	// Note: Since validate might take some time depending on algo
 	//       doing a spawn_blocking to avoid
@@ -83,6 +89,7 @@ pub async fn validate_pwd(
 		move || {
 			validate_for_scheme(
 				&scheme_name,
+				kid.as_deref(),
 				to_hash,
 				hashed,
 			)
@@ -102,18 +109,26 @@ fn hash_for_scheme(
 	scheme_name: &str,
 	to_hash: ContentToHash,
 ) -> Result<String> {
-	let pwd_hashed = get_scheme(scheme_name)?.hash(&to_hash)?;
+	let (kid, key) = auth_config().PWD_KEYS.active();
+	let pwd_hashed = get_scheme(scheme_name)?.hash(
+		&to_hash, key,
+	)?;
 
-	Ok(format!("#{scheme_name}#{pwd_hashed}"))
+	Ok(format!("#{scheme_name}@{kid}#{pwd_hashed}"))
 }
 
 fn validate_for_scheme(
 	scheme_name: &str,
+	kid: Option<&str>,
 	to_hash: ContentToHash,
 	pwd_ref: String,
 ) -> Result<()> {
+	let key = auth_config()
+		.PWD_KEYS
+		.get(kid)
+		.ok_or_else(|| Error::PwdKeyNotFound(kid.map(str::to_string)))?;
 	get_scheme(scheme_name)?.validate(
-		&to_hash, &pwd_ref,
+		&to_hash, &pwd_ref, key,
 	)?;
 	Ok(())
 }
@@ -121,6 +136,8 @@ fn validate_for_scheme(
 struct PwdParts {
 	/// The scheme only (e.g., "01")
 	scheme_name: String,
+	/// The pepper key id (`None` for the `LEGACY_KID`).
+	kid: Option<String>,
 	/// The hashed password,
 	hashed: String,
 }
@@ -130,12 +147,13 @@ impl FromStr for PwdParts {
 
 	fn from_str(pwd_with_scheme: &str) -> Result<Self> {
 		regex_captures!(
-			r#"^#(\w+)#(.*)"#, // a literal regex
+			r#"^#(\w+)(?:@([\w-]+))?#(.*)"#, // a literal regex
 			pwd_with_scheme
 		)
 		.map(
-			|(_, scheme, hashed)| Self {
+			|(_, scheme, kid, hashed)| Self {
 				scheme_name: scheme.to_string(),
+				kid: (!kid.is_empty()).then(|| kid.to_string()),
 				hashed: hashed.to_string(),
 			},
 		)
@@ -184,5 +202,66 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[tokio::test]
+	async fn test_legacy_pwd_without_kid_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_to_hash = ContentToHash {
+			content: "hello world".to_string(),
+			salt: Uuid::parse_str("f05e8961-d6ad-4086-9e78-a6de065e5453")?,
+		};
+		let pwd_hashed = hash_for_scheme(
+			DEFAULT_SCHEME,
+			fx_to_hash.clone(),
+		)?;
+		let fx_legacy_pwd = pwd_hashed.replacen(
+			"@0#", "#", 1,
+		);
+
+		// -- Exec
+		let pwd_validate = validate_pwd(
+			fx_to_hash,
+			fx_legacy_pwd,
+		)
+		.await?;
+
+		// -- Check
+		assert!(
+			matches!(
+				pwd_validate,
+				SchemeStatus::Ok
+			),
+			"status should be SchemeStatus::Ok"
+		);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_pwd_unknown_kid_err() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_to_hash = ContentToHash {
+			content: "hello world".to_string(),
+			salt: Uuid::new_v4(),
+		};
+
+		// -- Exec
+		let res = validate_pwd(
+			fx_to_hash,
+			"#02@retired-key#some-hash".to_string(),
+		)
+		.await;
+
+		// -- Check
+		assert!(
+			matches!(
+				res,
+				Err(super::Error::PwdKeyNotFound(Some(ref kid))) if kid == "retired-key"
+			),
+			"Should have matched `Err(Error::PwdKeyNotFound(..))` but was `{res:?}`"
+		);
+
+		Ok(())
+	}
 }
 // endregion: --- Tests
@@ -21,15 +21,18 @@ pub enum SchemeStatus {
 
 #[enum_dispatch]
 pub trait Scheme {
+	/// `key` is the `PWD_KEYS` pepper.
 	fn hash(
 		&self,
 		to_hash: &ContentToHash,
+		key: &[u8],
 	) -> Result<String>;
 
 	fn validate(
 		&self,
 		to_hash: &ContentToHash,
 		pwd_ref: &str,
+		key: &[u8],
 	) -> Result<()>;
 }
 
@@ -1,5 +1,4 @@
 use super::{Error, Result};
-use crate::auth_config;
 use crate::pwd::scheme::Scheme;
 use crate::pwd::ContentToHash;
 use hmac::{Hmac, Mac};
@@ -12,8 +11,8 @@ impl Scheme for Scheme01 {
 	fn hash(
 		&self,
 		to_hash: &ContentToHash,
+		key: &[u8],
 	) -> Result<String> {
-		let key = &auth_config().PWD_KEY;
 		hash(
 			key, to_hash,
 		)
@@ -23,8 +22,11 @@ impl Scheme for Scheme01 {
 		&self,
 		to_hash: &ContentToHash,
 		raw_pwd_ref: &str,
+		key: &[u8],
 	) -> Result<()> {
-		let raw_pwd_new = self.hash(to_hash)?;
+		let raw_pwd_new = self.hash(
+			to_hash, key,
+		)?;
 		if raw_pwd_new == raw_pwd_ref {
 			Ok(())
 		} else {
@@ -62,14 +64,14 @@ mod tests {
 	pub type Error = Box<dyn std::error::Error>; // For early tests.
 
 	use super::*;
-	use crate::auth_config;
+	use crate::config::auth_config;
 	use uuid::Uuid;
 
 	#[test]
 	fn test_scheme_01_hash_into_b64u_ok() -> Result<()> {
 		// -- Setup & Fixtures
 		let fx_salt = Uuid::parse_str("f05e8961-d6ad-4086-9e78-a6de065e5453")?;
-		let fx_key = &auth_config().PWD_KEY; // 512 bits = 64 bytes
+		let fx_key = auth_config().PWD_KEYS.active().1; // 512 bits = 64 bytes
 		let fx_to_hash = ContentToHash {
 			content: "hello world".to_string(),
 			salt: fx_salt,
@@ -1,11 +1,9 @@
 use super::{Error, Result};
-use crate::config::auth_config;
 use crate::pwd::scheme::Scheme;
 use argon2::password_hash::SaltString;
 use argon2::{
 	Algorithm, Argon2, Params, PasswordHash, PasswordHasher as _, PasswordVerifier as _, Version,
 };
-use std::sync::OnceLock;
 
 pub struct Scheme02;
 
@@ -13,8 +11,9 @@ impl Scheme for Scheme02 {
 	fn hash(
 		&self,
 		to_hash: &crate::pwd::ContentToHash,
+		key: &[u8],
 	) -> Result<String> {
-		let argon2 = get_argon2();
+		let argon2 = get_argon2(key)?;
 
 		let salt_b64 = SaltString::encode_b64(to_hash.salt.as_bytes()).map_err(|_| Error::Salt)?;
 
@@ -33,8 +32,9 @@ impl Scheme for Scheme02 {
 		&self,
 		to_hash: &crate::pwd::ContentToHash,
 		pwd_ref: &str,
+		key: &[u8],
 	) -> Result<()> {
-		let argon2 = get_argon2();
+		let argon2 = get_argon2(key)?;
 
 		let parsed_hash_ref = PasswordHash::new(pwd_ref).map_err(|_| Error::Hash)?;
 
@@ -47,21 +47,15 @@ impl Scheme for Scheme02 {
 	}
 }
 
-fn get_argon2() -> &'static Argon2<'static> {
-	static INSTANCE: OnceLock<Argon2<'static>> = OnceLock::new();
-
-	INSTANCE.get_or_init(
-		|| {
-			let key = &auth_config().PWD_KEY;
-			Argon2::new_with_secret(
-				key,
-				Algorithm::Argon2id, // Same as Argon2::default()
-				Version::V0x13,      // Same as Argon2::default()
-				Params::default(),
-			)
-			.unwrap() // TODO - needs to fail early
-		},
+/// Note: Cheap to create, the cost is in the hashing.
+fn get_argon2(key: &[u8]) -> Result<Argon2<'_>> {
+	Argon2::new_with_secret(
+		key,
+		Algorithm::Argon2id, // Same as Argon2::default()
+		Version::V0x13,      // Same as Argon2::default()
+		Params::default(),
 	)
+	.map_err(|_| Error::Key)
 }
 
 // region:    --- Tests
@@ -71,6 +65,7 @@ mod tests {
 	pub type Error = Box<dyn std::error::Error>; // For tests.
 
 	use super::*;
+	use crate::config::auth_config;
 	use crate::pwd::ContentToHash;
 	use uuid::Uuid;
 
@@ -85,7 +80,10 @@ mod tests {
 
 		// -- Exec
 		let scheme = Scheme02;
-		let res = scheme.hash(&fx_to_hash)?;
+		let res = scheme.hash(
+			&fx_to_hash,
+			auth_config().PWD_KEYS.active().1,
+		)?;
 
 		// -- Check
 		assert_eq!(
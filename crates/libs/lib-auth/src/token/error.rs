@@ -12,6 +12,16 @@ pub enum Error {
 	SignatureNotMatching,
 	ExpNotIso,
 	Expired,
+
+	// -- JWT
+	JwtEncodeFail,
+	JwtAlgNotSupported,
+	SaltNotMatching,
+
+	// -- Keys
+	KeyMissing,
+	KeyInvalid,
+	KeyUnknown,
 }
 
 // region:    --- Error Boilerplate
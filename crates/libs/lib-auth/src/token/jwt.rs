@@ -0,0 +1,220 @@
+//! JWT web token format (`header.claims.signature`, RFC 7519), signed with HS256 or HS512 and the `TOKEN_KEYS`
+//! key, or with EdDSA and the Ed25519 key (see `keys`), of the header `kid`.
+//!
+//! Notes:
+//!
+//! - The standard claims (`sub`, `exp`, `iat`, `jti`) can be validated by any JWT library,
+//!   with the `TOKEN_KEYS` key, or with the published public key for EdDSA.
+//! - The `sfp` claim is a fingerprint of the user `pwd_salt`, so that, like the custom format,
+//!   the tokens of a user are invalidated when the salt changes.
+
+use crate::token::keys::Ed25519Key;
+use crate::token::{Error, Result, TokenSubject};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use lib_utils::b64::b64u_encode;
+use lib_utils::time::now_utc;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+use uuid::Uuid;
+
+// region:    --- Types
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Claims {
+	/// User id.
+	pub sub: String,
+	/// Tenant id.
+	pub tid: String,
+	/// Expiration time, in seconds since the epoch.
+	pub exp: i64,
+	/// Issued at, in seconds since the epoch.
+	pub iat: i64,
+	/// Token id.
+	pub jti: String,
+	#[serde(default)]
+	pub roles: Vec<String>,
+	/// Fingerprint of the user `pwd_salt`.
+	pub sfp: String,
+	/// Service account, the `sub` being its client id.
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub sa: bool,
+	/// OAuth2 client the user token is issued to (RFC 9068).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub client_id: Option<String>,
+	/// Space-delimited scopes granted to the `client_id` (RFC 9068).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub scope: Option<String>,
+}
+
+impl Claims {
+	/// The granted scopes of a delegated (OAuth2 client) token, None for a full user token.
+	pub fn scopes(&self) -> Option<Vec<&str>> {
+		self.scope
+			.as_deref()
+			.map(|scope| scope.split_whitespace().collect())
+	}
+}
+
+/// A parsed, but not yet validated, JWT.
+#[derive(Debug)]
+pub struct JwtToken {
+	pub alg: Algorithm,
+	pub kid: Option<String>,
+	pub claims: Claims,
+	raw: String,
+}
+
+impl FromStr for JwtToken {
+	type Err = Error;
+
+	fn from_str(token_str: &str) -> std::result::Result<Self, Self::Err> {
+		let header = jsonwebtoken::decode_header(token_str).map_err(|_| Error::InvalidFormat)?;
+
+		// -- Decode the claims only, the signature and expiration are checked by `validate`.
+		let mut validation = Validation::new(header.alg);
+		validation.insecure_disable_signature_validation();
+		validation.validate_exp = false;
+		let claims = decode::<Claims>(
+			token_str,
+			&DecodingKey::from_secret(&[]),
+			&validation,
+		)
+		.map_err(|_| Error::InvalidFormat)?
+		.claims;
+
+		Ok(
+			Self {
+				alg: header.alg,
+				kid: header.kid,
+				claims,
+				raw: token_str.to_string(),
+			},
+		)
+	}
+}
+
+impl Display for JwtToken {
+	fn fmt(
+		&self,
+		f: &mut std::fmt::Formatter<'_>,
+	) -> std::fmt::Result {
+		write!(
+			f,
+			"{}",
+			self.raw
+		)
+	}
+}
+
+// endregion: --- Types
+
+// region:    --- JWT Gen and Validation
+
+/// The key signing a JWT.
+pub(super) enum JwtKey<'a> {
+	/// HS256 or HS512 with a `TOKEN_KEYS` (kid, key).
+	Hmac(Algorithm, &'a str, &'a [u8]),
+	Ed25519(&'a Ed25519Key),
+}
+
+pub(super) fn generate_jwt(
+	subject: &TokenSubject,
+	key: JwtKey,
+	duration_sec: f64,
+) -> Result<JwtToken> {
+	let iat = now_utc().unix_timestamp();
+	let claims = Claims {
+		sub: subject.user_id.to_string(),
+		tid: subject.tenant_id.to_string(),
+		exp: iat + duration_sec.ceil() as i64,
+		iat,
+		jti: Uuid::new_v4().to_string(),
+		roles: subject.roles.to_vec(),
+		sfp: salt_fingerprint(subject.salt),
+		sa: subject.service_account,
+		client_id: subject
+			.grant
+			.as_ref()
+			.map(|grant| grant.client_id.to_string()),
+		scope: subject
+			.grant
+			.as_ref()
+			.map(|grant| grant.scope.join(" ")),
+	};
+
+	let (header, encoding_key) = match key {
+		JwtKey::Hmac(alg, kid, key) => {
+			let mut header = Header::new(alg);
+			header.kid = Some(kid.to_string());
+			(
+				header,
+				EncodingKey::from_secret(key),
+			)
+		},
+		JwtKey::Ed25519(key) => {
+			let mut header = Header::new(Algorithm::EdDSA);
+			header.kid = Some(key.kid.clone());
+			(
+				header,
+				key.encoding_key().clone(),
+			)
+		},
+	};
+	let raw = encode(
+		&header,
+		&claims,
+		&encoding_key,
+	)
+	.map_err(|_| Error::JwtEncodeFail)?;
+
+	Ok(
+		JwtToken {
+			alg: header.alg,
+			kid: header.kid,
+			claims,
+			raw,
+		},
+	)
+}
+
+/// Validates the JWT with the `decoding_key` of its `alg` (and `kid`).
+pub(super) fn validate_jwt(
+	token: &JwtToken,
+	salt: Uuid,
+	decoding_key: &DecodingKey,
+) -> Result<()> {
+	// -- Validate signature and expiration.
+	let mut validation = Validation::new(token.alg);
+	validation.leeway = 0;
+	validation.set_required_spec_claims(&["exp", "sub"]);
+	let claims = decode::<Claims>(
+		&token.raw,
+		decoding_key,
+		&validation,
+	)
+	.map_err(
+		|ex| match ex.kind() {
+			ErrorKind::ExpiredSignature => Error::Expired,
+			_ => Error::SignatureNotMatching,
+		},
+	)?
+	.claims;
+
+	// -- Validate the salt fingerprint.
+	if claims.sfp != salt_fingerprint(salt) {
+		return Err(Error::SaltNotMatching);
+	}
+
+	Ok(())
+}
+
+/// Short, non-reversible, fingerprint of the salt (which must not leak in the token).
+fn salt_fingerprint(salt: Uuid) -> String {
+	let hash = blake3::hash(salt.as_bytes());
+	b64u_encode(&hash.as_bytes()[..16])
+}
+
+// endregion: --- JWT Gen and Validation
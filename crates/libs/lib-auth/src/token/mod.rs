@@ -1,10 +1,18 @@
 // region:    --- Modules
 
 mod error;
+mod jwt;
+mod keys;
+mod refresh;
 
 pub use self::error::{Error, Result};
+pub use self::jwt::{Claims, JwtToken};
+pub use self::keys::{jwks, Ed25519Key, Jwk, Jwks};
+pub use self::refresh::{generate_refresh_token, hash_refresh_token, refresh_token_duration_sec};
 
 use crate::config::auth_config;
+use jsonwebtoken::{Algorithm, DecodingKey};
+use jwt::JwtKey;
 use lib_utils::b64::{b64u_decode_to_string, b64u_encode};
 use lib_utils::time::{now_utc, now_utc_plus_sec_str, parse_utc};
 use std::fmt::Display;
@@ -15,13 +23,15 @@ use uuid::Uuid;
 
 // region:    --- Token Type
 
-/// String format: `ident_b64u.exp_b64u.sign_b64u`
+/// String format: `ident_b64u.exp_b64u.kid.sign_b64u`
+/// (or `ident_b64u.exp_b64u.sign_b64u` for the tokens issued before the `TOKEN_KEYS` ring).
 #[derive(Debug)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct Token {
-	pub ident: String,     // Identifier (username for example).
-	pub exp: String,       // Expiration date in Rfc3339.
-	pub sign_b64u: String, // Signature, base64url encoded.
+	pub ident: String,       // Identifier (username for example).
+	pub exp: String,         // Expiration date in Rfc3339.
+	pub kid: Option<String>, // Signing key id (see `keyring`).
+	pub sign_b64u: String,   // Signature, base64url encoded.
 }
 
 impl FromStr for Token {
@@ -29,12 +39,18 @@ impl FromStr for Token {
 
 	fn from_str(token_str: &str) -> std::result::Result<Self, Self::Err> {
 		let splits: Vec<&str> = token_str.split('.').collect();
-		if splits.len() != 3 {
-			return Err(Error::InvalidFormat);
-		}
-		let (ident_b64u, exp_b64u, sign_b64u) = (
-			splits[0], splits[1], splits[2],
-		);
+		let (ident_b64u, exp_b64u, kid, sign_b64u) = match splits[..] {
+			[ident_b64u, exp_b64u, sign_b64u] => (
+				ident_b64u, exp_b64u, None, sign_b64u,
+			),
+			[ident_b64u, exp_b64u, kid, sign_b64u] => (
+				ident_b64u,
+				exp_b64u,
+				Some(kid.to_string()),
+				sign_b64u,
+			),
+			_ => return Err(Error::InvalidFormat),
+		};
 
 		Ok(
 			Self {
@@ -42,6 +58,8 @@ impl FromStr for Token {
 
 				exp: b64u_decode_to_string(exp_b64u).map_err(|_| Error::CannotDecodeExp)?,
 
+				kid,
+
 				sign_b64u: sign_b64u.to_string(),
 			},
 		)
@@ -55,9 +73,19 @@ impl Display for Token {
 	) -> std::fmt::Result {
 		write!(
 			f,
-			"{}.{}.{}",
+			"{}.{}.",
 			b64u_encode(&self.ident),
 			b64u_encode(&self.exp),
+		)?;
+		if let Some(kid) = &self.kid {
+			write!(
+				f,
+				"{kid}."
+			)?;
+		}
+		write!(
+			f,
+			"{}",
 			self.sign_b64u
 		)
 	}
@@ -65,36 +93,331 @@ impl Display for Token {
 
 // endregion: --- Token Type
 
+// region:    --- Web Token
+
+/// Format of the issued web tokens (`SERVICE_TOKEN_FORMAT`).
+///
+/// Both formats are accepted by `WebToken::from_str` and `validate_web_token`,
+/// so the format can be switched without logging users out.
+///
+/// The custom and HS256/HS512 tokens are signed with the active `TOKEN_KEYS` key, and carry its kid.
+/// Tokens signed with an older key stay valid while the key is in the ring, and are re-issued
+/// with the active key on their next request (see `is_web_token_key_outdated`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenFormat {
+	/// `ident.exp.sign` with a blake3 signature (v1, see `Token`).
+	Custom,
+	/// JWT signed with HS256 (v2, see `JwtToken`).
+	JwtHs256,
+	/// JWT signed with HS512 (v2, see `JwtToken`).
+	JwtHs512,
+	/// JWT signed with EdDSA and the `TOKEN_ED25519_KEY` (v2, see `JwtToken`).
+	JwtEdDsa,
+}
+
+impl FromStr for TokenFormat {
+	type Err = Error;
+
+	fn from_str(format: &str) -> std::result::Result<Self, Self::Err> {
+		match format {
+			"custom" => Ok(Self::Custom),
+			"jwt-hs256" => Ok(Self::JwtHs256),
+			"jwt-hs512" => Ok(Self::JwtHs512),
+			"jwt-eddsa" => Ok(Self::JwtEdDsa),
+			_ => Err(Error::InvalidFormat),
+		}
+	}
+}
+
+/// Where the web token of a request is read from.
+///
+/// Both sources are accepted, `TOKEN_SOURCE_FIRST` is the one read first when both are present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenSource {
+	/// The `auth-token` cookie (browsers).
+	Cookie,
+	/// The `Authorization: Bearer <token>` header (CLI tools, service-to-service calls).
+	Header,
+}
+
+impl FromStr for TokenSource {
+	type Err = Error;
+
+	fn from_str(source: &str) -> std::result::Result<Self, Self::Err> {
+		match source {
+			"cookie" => Ok(Self::Cookie),
+			"header" => Ok(Self::Header),
+			_ => Err(Error::InvalidFormat),
+		}
+	}
+}
+
+/// The user (or service account) a web token is issued for.
+pub struct TokenSubject<'a> {
+	pub user_id: &'a str,
+	pub username: &'a str,
+	pub tenant_id: &'a str,
+	pub roles: &'a [String],
+	pub salt: Uuid,
+	/// A service account (client credentials) rather than a user, flagged by the `sa` claim.
+	pub service_account: bool,
+	/// The OAuth2 client the user delegates to, with the granted scopes (see `lib_core::model::oauth`).
+	pub grant: Option<TokenGrant<'a>>,
+}
+
+/// An OAuth2 grant of a user token, carried by the `client_id` and `scope` claims.
+pub struct TokenGrant<'a> {
+	pub client_id: &'a str,
+	pub scope: &'a [String],
+}
+
+#[derive(Debug)]
+pub enum WebToken {
+	Custom(Token),
+	Jwt(JwtToken),
+}
+
+impl FromStr for WebToken {
+	type Err = Error;
+
+	fn from_str(token_str: &str) -> std::result::Result<Self, Self::Err> {
+		// -- A JWT has three parts and starts with a JSON header, a custom token three or four parts.
+		if token_str.starts_with("eyJ") {
+			if let Ok(jwt) = token_str.parse::<JwtToken>() {
+				return Ok(Self::Jwt(jwt));
+			}
+		}
+		token_str.parse::<Token>().map(Self::Custom)
+	}
+}
+
+impl WebToken {
+	/// Unique id of the token, for revocation (the signature for the custom format).
+	pub fn jti(&self) -> &str {
+		match self {
+			Self::Custom(token) => &token.sign_b64u,
+			Self::Jwt(token) => &token.claims.jti,
+		}
+	}
+
+	/// Issued at, in seconds since the epoch.
+	/// Note: Derived from `exp` and the `TOKEN_DURATION_SEC` for the custom format.
+	pub fn iat(&self) -> Result<i64> {
+		match self {
+			Self::Custom(token) => {
+				let exp = parse_utc(&token.exp).map_err(|_| Error::ExpNotIso)?;
+				Ok(exp.unix_timestamp() - auth_config().TOKEN_DURATION_SEC.ceil() as i64)
+			},
+			Self::Jwt(token) => Ok(token.claims.iat),
+		}
+	}
+}
+
+impl Display for WebToken {
+	fn fmt(
+		&self,
+		f: &mut std::fmt::Formatter<'_>,
+	) -> std::fmt::Result {
+		match self {
+			Self::Custom(token) => token.fmt(f),
+			Self::Jwt(token) => token.fmt(f),
+		}
+	}
+}
+
+// endregion: --- Web Token
+
 // region:    --- Web Token Gen and Validation
 
-pub fn generate_web_token(
-	user: &str,
-	salt: Uuid,
-) -> Result<Token> {
+/// Generates a web token in the configured `TOKEN_FORMAT`.
+///
+/// Note: The service account and OAuth2 client tokens are HS256 JWTs with the `custom` format,
+///       which identifies users by username and has no room for the `sa` flag or the grant.
+pub fn generate_web_token(subject: &TokenSubject) -> Result<WebToken> {
 	let config = &auth_config();
-	generate_token(
-		user,
+	let (kid, key) = config.TOKEN_KEYS.active();
+	let jwt_key = match config.TOKEN_FORMAT {
+		TokenFormat::Custom if subject.service_account || subject.grant.is_some() => JwtKey::Hmac(
+			Algorithm::HS256,
+			kid,
+			key,
+		),
+		TokenFormat::Custom => {
+			return generate_token(
+				subject.username,
+				config.TOKEN_DURATION_SEC,
+				subject.salt,
+				kid,
+				key,
+			)
+			.map(WebToken::Custom);
+		},
+		TokenFormat::JwtHs256 => JwtKey::Hmac(
+			Algorithm::HS256,
+			kid,
+			key,
+		),
+		TokenFormat::JwtHs512 => JwtKey::Hmac(
+			Algorithm::HS512,
+			kid,
+			key,
+		),
+		TokenFormat::JwtEdDsa => JwtKey::Ed25519(
+			config
+				.TOKEN_ED25519_KEY
+				.as_ref()
+				.ok_or(Error::KeyMissing)?,
+		),
+	};
+
+	jwt::generate_jwt(
+		subject,
+		jwt_key,
 		config.TOKEN_DURATION_SEC,
-		salt,
-		&config.TOKEN_KEY,
 	)
+	.map(WebToken::Jwt)
 }
 
+/// Validates a web token of any format.
 pub fn validate_web_token(
-	origin_token: &Token,
+	origin_token: &WebToken,
 	salt: Uuid,
 ) -> Result<()> {
 	let config = &auth_config();
+	match origin_token {
+		WebToken::Custom(token) => validate_token_sign_and_exp(
+			token,
+			salt,
+			config
+				.TOKEN_KEYS
+				.get(token.kid.as_deref())
+				.ok_or(Error::KeyUnknown)?,
+		),
+		WebToken::Jwt(token) => match token.alg {
+			Algorithm::HS256 | Algorithm::HS512 => jwt::validate_jwt(
+				token,
+				salt,
+				&DecodingKey::from_secret(
+					config
+						.TOKEN_KEYS
+						.get(token.kid.as_deref())
+						.ok_or(Error::KeyUnknown)?,
+				),
+			),
+			Algorithm::EdDSA => {
+				let key = config
+					.TOKEN_ED25519_KEY
+					.as_ref()
+					.filter(|key| token.kid.as_deref() == Some(key.kid.as_str()))
+					.ok_or(Error::KeyUnknown)?;
+				jwt::validate_jwt(
+					token,
+					salt,
+					key.decoding_key(),
+				)
+			},
+			_ => Err(Error::JwtAlgNotSupported),
+		},
+	}
+}
+
+/// Lifetime of the web tokens (`TOKEN_DURATION_SEC`).
+pub fn token_duration_sec() -> f64 {
+	auth_config().TOKEN_DURATION_SEC
+}
+
+/// The token source read first (`TOKEN_SOURCE_FIRST`).
+pub fn token_source_first() -> TokenSource {
+	auth_config().TOKEN_SOURCE_FIRST
+}
+
+/// True if the token is not signed with the current signing key (e.g., after a key rotation),
+/// so it should be re-issued.
+pub fn is_web_token_key_outdated(token: &WebToken) -> bool {
+	let config = &auth_config();
+	match token {
+		WebToken::Custom(token) => !config.TOKEN_KEYS.is_active(token.kid.as_deref()),
+		WebToken::Jwt(token) if token.alg == Algorithm::EdDSA => {
+			config
+				.TOKEN_ED25519_KEY
+				.as_ref()
+				.map(|key| key.kid.as_str())
+				!= token.kid.as_deref()
+		},
+		WebToken::Jwt(token) => !config.TOKEN_KEYS.is_active(token.kid.as_deref()),
+	}
+}
+
+/// The JWK Set of the token verification public keys (empty without `TOKEN_ED25519_KEY`).
+pub fn web_token_jwks() -> Jwks {
+	jwks(&auth_config().TOKEN_ED25519_KEY)
+}
+
+// endregion: --- Web Token Gen and Validation
+
+// region:    --- Action Token
+
+/// The purpose of the email verification tokens (see `generate_action_token`).
+pub const ACTION_EMAIL_VERIFICATION: &str = "email-verification";
+/// The purpose of the verification tokens of a new mail (profile update).
+pub const ACTION_MAIL_CHANGE: &str = "mail-change";
+
+/// Generates the signed, expiring token of a user action out of a session (e.g., the link of a mail),
+/// for the `ident` (e.g., the user id) and the `salt` of the user.
+///
+/// The token is a custom `Token`, signed with a key derived from the active `TOKEN_KEYS` key
+/// and the `purpose`, so it is never valid for another purpose, nor as a web token.
+pub fn generate_action_token(
+	purpose: &str,
+	ident: &str,
+	duration_sec: f64,
+	salt: Uuid,
+) -> Result<Token> {
+	let (kid, key) = auth_config().TOKEN_KEYS.active();
+
+	generate_token(
+		ident,
+		duration_sec,
+		salt,
+		kid,
+		&action_key(
+			purpose, key,
+		),
+	)
+}
+
+pub fn validate_action_token(
+	purpose: &str,
+	token: &Token,
+	salt: Uuid,
+) -> Result<()> {
+	let key = auth_config()
+		.TOKEN_KEYS
+		.get(token.kid.as_deref())
+		.ok_or(Error::KeyUnknown)?;
+
 	validate_token_sign_and_exp(
-		origin_token,
+		token,
 		salt,
-		&config.TOKEN_KEY,
-	)?;
+		&action_key(
+			purpose, key,
+		),
+	)
+}
 
-	Ok(())
+fn action_key(
+	purpose: &str,
+	key: &[u8],
+) -> Vec<u8> {
+	let mut hasher = blake3::Hasher::new();
+	hasher.update(b"action-token:");
+	hasher.update(purpose.as_bytes());
+	hasher.update(key);
+
+	hasher.finalize().as_bytes().to_vec()
 }
 
-// endregion: --- Web Token Gen and Validation
+// endregion: --- Action Token
 
 // region:    --- (private) Token Gen and Validation
 
@@ -102,21 +425,28 @@ fn generate_token(
 	ident: &str,
 	duration_sec: f64,
 	salt: Uuid,
+	kid: &str,
 	key: &[u8],
 ) -> Result<Token> {
-	// -- Compute the two first components.
+	// -- Compute the three first components.
 	let ident = ident.to_string();
 	let exp = now_utc_plus_sec_str(duration_sec);
+	let kid = Some(kid.to_string());
 
-	// -- Sign the two first components.
+	// -- Sign the three first components.
 	let sign_b64u = token_sign_into_b64u(
-		&ident, &exp, salt, key,
+		&ident,
+		&exp,
+		kid.as_deref(),
+		salt,
+		key,
 	)?;
 
 	Ok(
 		Token {
 			ident,
 			exp,
+			kid,
 			sign_b64u,
 		},
 	)
@@ -131,6 +461,7 @@ fn validate_token_sign_and_exp(
 	let new_sign_b64u = token_sign_into_b64u(
 		&origin_token.ident,
 		&origin_token.exp,
+		origin_token.kid.as_deref(),
 		salt,
 		key,
 	)?;
@@ -155,14 +486,19 @@ fn validate_token_sign_and_exp(
 fn token_sign_into_b64u(
 	ident: &str,
 	exp: &str,
+	kid: Option<&str>,
 	salt: Uuid,
 	key: &[u8],
 ) -> Result<String> {
-	let content = format!(
+	let mut content = format!(
 		"{}.{}",
 		b64u_encode(ident),
 		b64u_encode(exp)
 	);
+	if let Some(kid) = kid {
+		content.push('.');
+		content.push_str(kid);
+	}
 
 	// -- Create a Black3 Hasher (not from key because blake3 key is fixed length).
 	let mut hasher = blake3::Hasher::new();
@@ -200,6 +536,7 @@ mod tests {
 		let fx_token = Token {
 			ident: "fx-ident-01".to_string(),
 			exp: "2023-05-17T15:30:00Z".to_string(),
+			kid: None,
 			sign_b64u: "some-sign-b64u-encoded".to_string(),
 		};
 
@@ -219,6 +556,7 @@ mod tests {
 		let fx_token = Token {
 			ident: "fx-ident-01".to_string(),
 			exp: "2023-05-17T15:30:00Z".to_string(),
+			kid: None,
 			sign_b64u: "some-sign-b64u-encoded".to_string(),
 		};
 
@@ -234,24 +572,143 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_token_from_str_with_kid_ok() -> Result<()> {
+		// -- Fixtures
+		let fx_token_str = "ZngtaWRlbnQtMDE.MjAyMy0wNS0xN1QxNTozMDowMFo.key-02.some-sign-b64u-encoded";
+
+		// -- Exec
+		let token: Token = fx_token_str.parse()?;
+
+		// -- Check
+		assert_eq!(
+			token.kid.as_deref(),
+			Some("key-02")
+		);
+		assert_eq!(
+			token.to_string(),
+			fx_token_str
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_token_validate_web_token_legacy_without_kid_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_salt = Uuid::new_v4();
+		let ident = "user_one".to_string();
+		let exp = now_utc_plus_sec_str(60.);
+		let legacy_key = auth_config()
+			.TOKEN_KEYS
+			.get(None)
+			.ok_or("Should have a legacy key")?;
+		let sign_b64u = token_sign_into_b64u(
+			&ident, &exp, None, fx_salt, legacy_key,
+		)?;
+		let fx_token = Token {
+			ident,
+			exp,
+			kid: None,
+			sign_b64u,
+		};
+
+		// -- Exec
+		let token: WebToken = fx_token.to_string().parse()?;
+		let res = validate_web_token(
+			&token, fx_salt,
+		);
+
+		// -- Check
+		res?;
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_token_validate_web_token_err_key_unknown() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_salt = Uuid::new_v4();
+		let fx_token = generate_token(
+			"user_one",
+			60.,
+			fx_salt,
+			"retired-key",
+			&[0u8; 64],
+		)?;
+
+		// -- Exec
+		let res = validate_web_token(
+			&WebToken::Custom(fx_token),
+			fx_salt,
+		);
+
+		// -- Check
+		assert!(
+			matches!(
+				res,
+				Err(token::Error::KeyUnknown)
+			),
+			"Should have matched `Err(Error::KeyUnknown)` but was `{res:?}`"
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_web_token_jti_and_iat_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let (token_kid, token_key) = auth_config().TOKEN_KEYS.active();
+		let now = now_utc().unix_timestamp();
+		let fx_token = WebToken::Custom(
+			generate_token(
+				"user_one",
+				auth_config().TOKEN_DURATION_SEC,
+				Uuid::new_v4(),
+				token_kid,
+				token_key,
+			)?,
+		);
+
+		// -- Exec
+		let iat = fx_token.iat()?;
+
+		// -- Check
+		let WebToken::Custom(token) = &fx_token else {
+			return Err("Should be a custom token".into());
+		};
+		assert_eq!(
+			fx_token.jti(),
+			token.sign_b64u
+		);
+		assert!(
+			(now..=now + 1).contains(&iat),
+			"iat {iat} should be about now {now}"
+		);
+
+		Ok(())
+	}
+
 	#[test]
 	fn test_token_validate_web_token_ok() -> Result<()> {
 		// -- Setup & Fixtures
 		let fx_user = "user_one";
 		let fx_salt = Uuid::parse_str("f05e8961-d6ad-4086-9e78-a6de065e5453").unwrap();
 		let fx_duration_sec = 0.02; // 20ms
-		let token_key = &auth_config().TOKEN_KEY;
+		let (token_kid, token_key) = auth_config().TOKEN_KEYS.active();
 		let fx_token = generate_token(
 			fx_user,
 			fx_duration_sec,
 			fx_salt,
+			token_kid,
 			token_key,
 		)?;
 
 		// -- Exec
 		thread::sleep(Duration::from_millis(10));
 		let res = validate_web_token(
-			&fx_token, fx_salt,
+			&WebToken::Custom(fx_token),
+			fx_salt,
 		);
 
 		// -- Check
@@ -266,18 +723,20 @@ mod tests {
 		let fx_user = "user_one";
 		let fx_salt = Uuid::parse_str("f05e8961-d6ad-4086-9e78-a6de065e5453").unwrap();
 		let fx_duration_sec = 0.01; // 10ms
-		let token_key = &auth_config().TOKEN_KEY;
+		let (token_kid, token_key) = auth_config().TOKEN_KEYS.active();
 		let fx_token = generate_token(
 			fx_user,
 			fx_duration_sec,
 			fx_salt,
+			token_kid,
 			token_key,
 		)?;
 
 		// -- Exec
 		thread::sleep(Duration::from_millis(20));
 		let res = validate_web_token(
-			&fx_token, fx_salt,
+			&WebToken::Custom(fx_token),
+			fx_salt,
 		);
 
 		// -- Check
@@ -291,5 +750,134 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn test_action_token_validate_ok_and_err_purpose() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_user_id = "user-id-01";
+		let fx_salt = Uuid::parse_str("f05e8961-d6ad-4086-9e78-a6de065e5453").unwrap();
+		let fx_token = generate_action_token(
+			ACTION_EMAIL_VERIFICATION,
+			fx_user_id,
+			60.,
+			fx_salt,
+		)?;
+		let fx_token: Token = fx_token.to_string().parse()?;
+
+		// -- Exec
+		let res = validate_action_token(
+			ACTION_EMAIL_VERIFICATION,
+			&fx_token,
+			fx_salt,
+		);
+		let res_other_purpose = validate_action_token(
+			"other",
+			&fx_token,
+			fx_salt,
+		);
+		let res_as_web_token = validate_web_token(
+			&WebToken::Custom(fx_token),
+			fx_salt,
+		);
+
+		// -- Check
+		res?;
+		assert!(matches!(
+			res_other_purpose,
+			Err(token::Error::SignatureNotMatching)
+		));
+		assert!(matches!(
+			res_as_web_token,
+			Err(token::Error::SignatureNotMatching)
+		));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_token_jwt_validate_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_salt = Uuid::parse_str("f05e8961-d6ad-4086-9e78-a6de065e5453").unwrap();
+		let fx_roles = vec!["admin".to_string()];
+		let fx_subject = TokenSubject {
+			user_id: "user-id-01",
+			username: "user_one",
+			tenant_id: "tenant-01",
+			roles: &fx_roles,
+			salt: fx_salt,
+			service_account: false,
+			grant: None,
+		};
+		let fx_token = jwt::generate_jwt(
+			&fx_subject,
+			JwtKey::Hmac(
+				Algorithm::HS512,
+				auth_config().TOKEN_KEYS.active().0,
+				auth_config().TOKEN_KEYS.active().1,
+			),
+			60.,
+		)?;
+
+		// -- Exec
+		let token: WebToken = fx_token.to_string().parse()?;
+		validate_web_token(
+			&token, fx_salt,
+		)?;
+
+		// -- Check
+		let WebToken::Jwt(token) = token else {
+			return Err("Should have parsed a JWT".into());
+		};
+		assert_eq!(
+			token.claims,
+			fx_token.claims
+		);
+		assert_eq!(
+			(token.claims.sub.as_str(), token.claims.tid.as_str(), token.claims.roles),
+			("user-id-01", "tenant-01", fx_roles)
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_token_jwt_validate_err_salt_not_matching() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_subject = TokenSubject {
+			user_id: "user-id-01",
+			username: "user_one",
+			tenant_id: "tenant-01",
+			roles: &[],
+			salt: Uuid::new_v4(),
+			service_account: false,
+			grant: None,
+		};
+		let fx_token = jwt::generate_jwt(
+			&fx_subject,
+			JwtKey::Hmac(
+				Algorithm::HS256,
+				auth_config().TOKEN_KEYS.active().0,
+				auth_config().TOKEN_KEYS.active().1,
+			),
+			60.,
+		)?;
+
+		// -- Exec
+		let res = validate_web_token(
+			&WebToken::Jwt(fx_token),
+			Uuid::new_v4(),
+		);
+
+		// -- Check
+		assert!(
+			matches!(
+				res,
+				Err(token::Error::SaltNotMatching)
+			),
+			"Should have matched `Err(Error::SaltNotMatching)` but was `{res:?}`"
+		);
+
+		Ok(())
+	}
 }
 // endregion: --- Tests
@@ -0,0 +1,58 @@
+//! Opaque refresh tokens, exchanged for a new access (web) token and a new refresh token.
+//!
+//! Notes:
+//!
+//! - A refresh token is 256 random bits, base64url encoded. Only its hash is stored
+//!   (see `lib_core::model::refresh_token`), so a database leak does not leak usable tokens.
+//! - The hash does not need a salt or a slow scheme, since the token is random and long.
+
+use crate::config::auth_config;
+use lib_utils::b64::b64u_encode;
+use rand::RngCore;
+
+pub fn generate_refresh_token() -> String {
+	let mut token = [0u8; 32]; // 256 bits
+	rand::thread_rng().fill_bytes(&mut token);
+	b64u_encode(token)
+}
+
+/// The stored (and looked up) form of the refresh token.
+pub fn hash_refresh_token(token: &str) -> String {
+	b64u_encode(blake3::hash(token.as_bytes()).as_bytes())
+}
+
+pub fn refresh_token_duration_sec() -> f64 {
+	auth_config().REFRESH_TOKEN_DURATION_SEC
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+
+	#[test]
+	fn test_refresh_token_generate_and_hash_ok() -> Result<()> {
+		// -- Exec
+		let token_1 = generate_refresh_token();
+		let token_2 = generate_refresh_token();
+
+		// -- Check
+		assert_ne!(
+			token_1, token_2
+		);
+		assert_eq!(
+			hash_refresh_token(&token_1),
+			hash_refresh_token(&token_1)
+		);
+		assert_ne!(
+			hash_refresh_token(&token_1),
+			token_1
+		);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
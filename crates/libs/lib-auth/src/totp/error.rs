@@ -0,0 +1,30 @@
+use serde::Serialize;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Serialize)]
+pub enum Error {
+	// -- Secret Encryption
+	SecretEncInvalidFormat,
+	SecretEncKeyNotFound {
+		kid: String,
+	},
+	SecretEncryptFail,
+	SecretDecryptFail,
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for Error {
+	fn fmt(
+		&self,
+		fmt: &mut core::fmt::Formatter,
+	) -> core::result::Result<(), core::fmt::Error> {
+		write!(
+			fmt,
+			"{self:?}"
+		)
+	}
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error Boilerplate
@@ -0,0 +1,385 @@
+//! TOTP (RFC 6238) second factor: the secrets, their encryption at rest, the codes,
+//! and the single-use recovery codes.
+//!
+//! Notes:
+//!
+//! - The authenticator app defaults: HMAC-SHA1, 6 digits, 30 seconds steps, with one step of clock skew
+//!   accepted each way. A step is only accepted once (see `verify_totp`).
+//! - The secret is stored encrypted (AES-256-GCM) as `#a256gcm@kid#nonce_and_ciphertext_b64u`,
+//!   with a key derived from the `MFA_KEYS` key of `kid` (see `keyring`).
+//! - A recovery code is 80 random bits, base32 encoded (`xxxx-xxxx-xxxx-xxxx`),
+//!   and only stored hashed (like the refresh tokens).
+
+// region:    --- Modules
+
+mod error;
+
+pub use self::error::{Error, Result};
+
+use crate::config::auth_config;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use lib_utils::b64::{b64u_decode, b64u_encode};
+use rand::RngCore;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::hmac as ring_hmac;
+use sha2::Sha256;
+
+// endregion: --- Modules
+
+const SECRET_LEN: usize = 20; // 160 bits (RFC 4226 recommendation)
+const STEP_SEC: i64 = 30;
+const DIGITS: usize = 6;
+const ENC_SCHEME: &str = "a256gcm";
+const RECOVERY_CODE_LEN: usize = 10; // 80 bits
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+// region:    --- Secret
+
+pub fn generate_totp_secret() -> Vec<u8> {
+	let mut secret = vec![0u8; SECRET_LEN];
+	rand::thread_rng().fill_bytes(&mut secret);
+	secret
+}
+
+/// The `otpauth` URI of the secret, for the authenticator apps (e.g., as a QR code).
+pub fn otpauth_uri(
+	account: &str,
+	secret: &[u8],
+) -> String {
+	let issuer = percent_encode(&auth_config().MFA_TOTP_ISSUER);
+	format!(
+		"otpauth://totp/{issuer}:{}?secret={}&issuer={issuer}&algorithm=SHA1&digits={DIGITS}&period={STEP_SEC}",
+		percent_encode(account),
+		secret_b32(secret),
+	)
+}
+
+/// The base32 secret, for a manual entry in the authenticator app.
+pub fn secret_b32(secret: &[u8]) -> String {
+	BASE32_NOPAD.encode(secret)
+}
+
+/// Encrypts the secret with the active `MFA_KEYS` key.
+pub fn encrypt_totp_secret(secret: &[u8]) -> Result<String> {
+	let (kid, key) = auth_config().MFA_KEYS.active();
+
+	let mut nonce = [0u8; NONCE_LEN];
+	rand::thread_rng().fill_bytes(&mut nonce);
+	let mut in_out = secret.to_vec();
+	aead_key(key)?
+		.seal_in_place_append_tag(
+			Nonce::assume_unique_for_key(nonce),
+			Aad::from(kid.as_bytes()),
+			&mut in_out,
+		)
+		.map_err(|_| Error::SecretEncryptFail)?;
+
+	let mut sealed = nonce.to_vec();
+	sealed.extend(in_out);
+
+	Ok(
+		format!(
+			"#{ENC_SCHEME}@{kid}#{}",
+			b64u_encode(sealed)
+		),
+	)
+}
+
+pub fn decrypt_totp_secret(secret_enc: &str) -> Result<Vec<u8>> {
+	let (kid, sealed_b64u) = secret_enc
+		.strip_prefix(&format!("#{ENC_SCHEME}@"))
+		.and_then(|rest| rest.split_once('#'))
+		.ok_or(Error::SecretEncInvalidFormat)?;
+	let key = auth_config()
+		.MFA_KEYS
+		.get(Some(kid))
+		.ok_or_else(|| Error::SecretEncKeyNotFound { kid: kid.to_string() })?;
+
+	let sealed = b64u_decode(sealed_b64u).map_err(|_| Error::SecretEncInvalidFormat)?;
+	if sealed.len() < NONCE_LEN {
+		return Err(Error::SecretEncInvalidFormat);
+	}
+	let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+	let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| Error::SecretEncInvalidFormat)?;
+
+	let mut in_out = ciphertext.to_vec();
+	let secret = aead_key(key)?
+		.open_in_place(
+			nonce,
+			Aad::from(kid.as_bytes()),
+			&mut in_out,
+		)
+		.map_err(|_| Error::SecretDecryptFail)?;
+
+	Ok(secret.to_vec())
+}
+
+/// The AES-256 key derived from a `MFA_KEYS` key (of any length).
+fn aead_key(key: &[u8]) -> Result<LessSafeKey> {
+	let mut mac = Hmac::<Sha256>::new_from_slice(key).map_err(|_| Error::SecretEncryptFail)?;
+	mac.update(b"totp-secret");
+	let derived = mac.finalize().into_bytes();
+
+	let key = UnboundKey::new(
+		&AES_256_GCM,
+		&derived,
+	)
+	.map_err(|_| Error::SecretEncryptFail)?;
+
+	Ok(LessSafeKey::new(key))
+}
+
+// endregion: --- Secret
+
+// region:    --- Code
+
+/// The time step of the code, if valid at `unix_time` and after `last_step` (the last accepted step,
+/// so that a code is never accepted twice).
+pub fn verify_totp(
+	secret: &[u8],
+	code: &str,
+	unix_time: i64,
+	last_step: Option<i64>,
+) -> Option<i64> {
+	let code = code.trim();
+	if code.len() != DIGITS || !code.bytes().all(|b| b.is_ascii_digit()) {
+		return None;
+	}
+
+	let step = unix_time / STEP_SEC;
+	(step - 1..=step + 1)
+		.filter(|step| last_step.is_none_or(|last_step| *step > last_step))
+		.find(|step| totp_code(secret, *step) == code)
+}
+
+/// The code of the time step (HOTP of RFC 4226, with the step as counter).
+pub fn totp_code(
+	secret: &[u8],
+	step: i64,
+) -> String {
+	let key = ring_hmac::Key::new(
+		ring_hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY,
+		secret,
+	);
+	let tag = ring_hmac::sign(
+		&key,
+		&(step as u64).to_be_bytes(),
+	);
+	let hash = tag.as_ref();
+
+	// -- Dynamic truncation.
+	let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+	let binary = u32::from_be_bytes(
+		[
+			hash[offset] & 0x7f,
+			hash[offset + 1],
+			hash[offset + 2],
+			hash[offset + 3],
+		],
+	);
+
+	format!(
+		"{:0width$}",
+		binary % 10u32.pow(DIGITS as u32),
+		width = DIGITS
+	)
+}
+
+// endregion: --- Code
+
+// region:    --- Recovery Codes
+
+/// New recovery codes (`RECOVERY_CODE_COUNT`), to be shown once.
+pub fn generate_recovery_codes() -> Vec<String> {
+	(0..RECOVERY_CODE_COUNT)
+		.map(
+			|_| {
+				let mut code = [0u8; RECOVERY_CODE_LEN];
+				rand::thread_rng().fill_bytes(&mut code);
+				let code = BASE32_NOPAD
+					.encode(&code)
+					.to_lowercase();
+				code.as_bytes()
+					.chunks(4)
+					.map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+					.collect::<Vec<_>>()
+					.join("-")
+			},
+		)
+		.collect()
+}
+
+/// The stored (and looked up) form of the recovery code, whatever its case or separators.
+pub fn hash_recovery_code(code: &str) -> String {
+	let code: String = code
+		.chars()
+		.filter(|c| c.is_ascii_alphanumeric())
+		.map(|c| c.to_ascii_lowercase())
+		.collect();
+
+	b64u_encode(blake3::hash(code.as_bytes()).as_bytes())
+}
+
+// endregion: --- Recovery Codes
+
+/// Percent-encodes all but the unreserved chars (RFC 3986).
+fn percent_encode(value: &str) -> String {
+	let mut encoded = String::with_capacity(value.len());
+	for b in value.bytes() {
+		if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+			encoded.push(b as char);
+		} else {
+			encoded.push_str(&format!("%{b:02X}"));
+		}
+	}
+
+	encoded
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+
+	/// RFC 6238 (appendix B) SHA1 secret.
+	const FX_SECRET: &[u8] = b"12345678901234567890";
+
+	#[test]
+	fn test_totp_code_rfc6238() -> Result<()> {
+		// -- Fixtures
+		// (unix time, last 6 digits of the RFC 8 digits codes)
+		let fx_cases = [
+			(59, "287082"),
+			(1111111109, "081804"),
+			(1234567890, "005924"),
+			(2000000000, "279037"),
+		];
+
+		for (fx_time, fx_code) in fx_cases {
+			// -- Exec
+			let code = totp_code(
+				FX_SECRET,
+				fx_time / STEP_SEC,
+			);
+
+			// -- Check
+			assert_eq!(
+				code, fx_code,
+				"time: {fx_time}"
+			);
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_verify_totp_skew_and_replay() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_time = 1234567890;
+		let fx_step = fx_time / STEP_SEC;
+		let fx_code_prev = totp_code(
+			FX_SECRET,
+			fx_step - 1,
+		);
+
+		// -- Exec & Check
+		assert_eq!(
+			verify_totp(
+				FX_SECRET, "005924", fx_time, None
+			),
+			Some(fx_step)
+		);
+		assert_eq!(
+			verify_totp(
+				FX_SECRET,
+				&fx_code_prev,
+				fx_time,
+				None
+			),
+			Some(fx_step - 1)
+		);
+		assert_eq!(
+			verify_totp(
+				FX_SECRET,
+				"005924",
+				fx_time,
+				Some(fx_step)
+			),
+			None
+		);
+		assert_eq!(
+			verify_totp(
+				FX_SECRET,
+				"005924",
+				fx_time + 3 * STEP_SEC,
+				None
+			),
+			None
+		);
+		assert_eq!(
+			verify_totp(
+				FX_SECRET, "5924", fx_time, None
+			),
+			None
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_totp_secret_encrypt_decrypt_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_secret = generate_totp_secret();
+
+		// -- Exec
+		let secret_enc = encrypt_totp_secret(&fx_secret)?;
+		let secret = decrypt_totp_secret(&secret_enc)?;
+		let res_tampered = decrypt_totp_secret(&secret_enc.replace(
+			'#', "#x",
+		));
+
+		// -- Check
+		assert_eq!(
+			secret, fx_secret
+		);
+		assert!(!secret_enc.contains(&secret_b32(&fx_secret)));
+		assert!(res_tampered.is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_recovery_codes_ok() -> Result<()> {
+		// -- Exec
+		let codes = generate_recovery_codes();
+
+		// -- Check
+		assert_eq!(
+			codes.len(),
+			RECOVERY_CODE_COUNT
+		);
+		assert_eq!(
+			codes[0].len(),
+			19
+		);
+		assert_eq!(
+			hash_recovery_code(&codes[0]),
+			hash_recovery_code(
+				&codes[0]
+					.replace('-', "")
+					.to_uppercase()
+			)
+		);
+		assert_ne!(
+			hash_recovery_code(&codes[0]),
+			hash_recovery_code(&codes[1])
+		);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -1,5 +1,6 @@
 use crate::config::core_config;
 use crate::ctx::Ctx;
+use crate::model::example::ExampleBmc;
 use crate::model::user::{QUser, QUserForCreate, UserBmc};
 use crate::model::{Error, ModelManager, Result};
 use mongodb::Client;
@@ -12,11 +13,17 @@ pub async fn init_dev() -> Result<()> {
 		username: "test".to_string(),
 		pwd_clear: "admin".to_string(),
 	};
+	let mm = ModelManager::new().await?;
 	let _ = UserBmc::create(
 		&Ctx::root_ctx(),
-		&ModelManager::new().await?,
+		&mm,
 		d,
 	)
 	.await?;
+	ExampleBmc::ensure_text_index(
+		&Ctx::root_ctx(),
+		&mm,
+	)
+	.await?;
 	Ok(())
 }
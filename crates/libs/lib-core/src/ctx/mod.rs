@@ -12,8 +12,26 @@ pub struct Ctx {
 	user_id: String,
 	tenant_id: String,
 	conv_id: Option<String>,
+	roles: Vec<String>,
+	/// The `jti` of the web token of the request, if any.
+	token_id: Option<String>,
+	/// The `user_id` is a service account client id (see `ServiceAccountBmc`).
+	service_account: bool,
+	/// The id of the API key of the request, if authenticated by one (see `ApiKeyBmc`).
+	api_key_id: Option<String>,
+	/// The granted scopes of a delegated (OAuth2 client) token, None for a full user ctx.
+	scopes: Option<Vec<String>>,
 }
 
+/// Role of the users administrating the other users (e.g., revoking their sessions).
+pub const ROLE_ADMIN: &str = "admin";
+
+/// Scope granting a delegated ctx the update of the user profile (e.g., its mail).
+pub const SCOPE_PROFILE: &str = "profile";
+/// Scope granting a delegated ctx the management of the user credentials
+/// (e.g., its password, API keys, MFA, and sessions).
+pub const SCOPE_CREDENTIALS: &str = "credentials";
+
 // Constructors.
 impl Ctx {
 	pub fn root_ctx() -> Self {
@@ -21,6 +39,11 @@ impl Ctx {
 			user_id: "0000".to_string(),
 			conv_id: None,
 			tenant_id: "d1UuaOAUBRL2glq1eawbyKHqBgc".to_string(),
+			roles: Vec::new(),
+			token_id: None,
+			service_account: false,
+			api_key_id: None,
+			scopes: None,
 		}
 	}
 	pub fn new(user_id: String) -> Result<Self> {
@@ -32,10 +55,21 @@ impl Ctx {
 					user_id,
 					conv_id: None,
 					tenant_id: "d1UuaOAUBRL2glq1eawbyKHqBgc".to_string(),
+					roles: Vec::new(),
+					token_id: None,
+					service_account: false,
+					api_key_id: None,
+					scopes: None,
 				},
 			)
 		}
 	}
+	/// The ctx of a service account (machine-to-machine caller).
+	pub fn new_service_account(client_id: String) -> Result<Self> {
+		let mut ctx = Self::new(client_id)?;
+		ctx.service_account = true;
+		Ok(ctx)
+	}
 	pub fn add_conv_id(
 		&self,
 		conv_id: String,
@@ -52,6 +86,38 @@ impl Ctx {
 		ctx.tenant_id = tenant_id;
 		ctx
 	}
+	pub fn add_roles(
+		&self,
+		roles: Vec<String>,
+	) -> Ctx {
+		let mut ctx = self.clone();
+		ctx.roles = roles;
+		ctx
+	}
+	pub fn add_token_id(
+		&self,
+		token_id: String,
+	) -> Ctx {
+		let mut ctx = self.clone();
+		ctx.token_id = Some(token_id);
+		ctx
+	}
+	pub fn add_api_key_id(
+		&self,
+		api_key_id: String,
+	) -> Ctx {
+		let mut ctx = self.clone();
+		ctx.api_key_id = Some(api_key_id);
+		ctx
+	}
+	pub fn add_scopes(
+		&self,
+		scopes: Vec<String>,
+	) -> Ctx {
+		let mut ctx = self.clone();
+		ctx.scopes = Some(scopes);
+		ctx
+	}
 }
 
 // Property Accessors.
@@ -65,4 +131,35 @@ impl Ctx {
 	pub fn tenant_id(&self) -> String {
 		self.tenant_id.clone()
 	}
+	pub fn token_id(&self) -> Option<&str> {
+		self.token_id.as_deref()
+	}
+	pub fn is_service_account(&self) -> bool {
+		self.service_account
+	}
+	pub fn api_key_id(&self) -> Option<&str> {
+		self.api_key_id.as_deref()
+	}
+	pub fn is_delegated(&self) -> bool {
+		self.scopes.is_some()
+	}
+	/// True for a full user ctx, or a delegated one granted the scope.
+	pub fn has_scope(
+		&self,
+		scope: &str,
+	) -> bool {
+		match &self.scopes {
+			Some(scopes) => scopes.iter().any(|s| s == scope),
+			None => true,
+		}
+	}
+	pub fn roles(&self) -> &[String] {
+		&self.roles
+	}
+	pub fn has_role(
+		&self,
+		role: &str,
+	) -> bool {
+		self.roles.iter().any(|r| r == role)
+	}
 }
@@ -1,6 +1,8 @@
 pub mod config;
 pub mod ctx;
 pub mod model;
+#[cfg(feature = "with-rpc")]
+pub mod rpc;
 
 // #[cfg(test)] // Commented during early development.
 pub mod _dev_utils;
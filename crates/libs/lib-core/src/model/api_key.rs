@@ -0,0 +1,454 @@
+//! API keys (personal access tokens) of the users, see `lib_auth::api_key`.
+//!
+//! Design:
+//!
+//! - A key carries a name, an optional expiration, and a subset of the roles of its user
+//!   (its privileges). The ctx of a key has the privileges still held by the user.
+//! - Keys are stored by `key_id`, with the `pwd` hash of their secret, and removed on revoke,
+//!   or by a TTL index on `exp` once expired.
+//! - `last_used` is updated at most every `LAST_SEEN_THROTTLE` per instance (see `session`).
+
+use crate::ctx::Ctx;
+use crate::model::validation::{check_length, check_range, Validate, ValidationError, ValidationResult};
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use lib_auth::api_key::{api_key_to_hash, generate_api_key, parse_api_key};
+use lib_auth::pwd::{self, SchemeStatus};
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const TABLE: &str = "ApiKeys";
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+// region:    --- Types
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ApiKeyDoc {
+	/// The `key_id` (simple uuid).
+	#[serde(rename = "_id")]
+	id: String,
+	user_id: String,
+	name: String,
+	key_hash: String,
+	privileges: Vec<String>,
+	ctime: DateTime,
+	exp: Option<DateTime>,
+	last_used: Option<DateTime>,
+}
+
+/// An API key, as listed to its user (without its secret).
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKey {
+	pub id: String,
+	pub name: String,
+	pub privileges: Vec<String>,
+	pub created: String,
+	pub expires: Option<String>,
+	pub last_used: Option<String>,
+}
+
+/// A new API key, with the `key` shown only once.
+#[derive(Debug, Serialize)]
+pub struct ApiKeyCreated {
+	#[serde(flatten)]
+	pub api_key: ApiKey,
+	pub key: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiKeyForCreate {
+	pub name: String,
+	/// None for a key without expiration.
+	pub expires_in_days: Option<u32>,
+	/// The roles granted to the key, a subset of the user roles (none by default).
+	#[serde(default)]
+	pub privileges: Vec<String>,
+}
+
+/// The user and privileges of an authenticated API key.
+#[derive(Debug)]
+pub struct ApiKeyAuth {
+	pub id: String,
+	pub user_id: String,
+	pub privileges: Vec<String>,
+}
+
+impl Validate for ApiKeyForCreate {
+	fn validate(&self) -> ValidationResult {
+		let mut errors = ValidationError::default();
+		check_length(
+			&mut errors,
+			"name",
+			&self.name,
+			Some(1),
+			Some(100),
+		);
+		if let Some(expires_in_days) = self.expires_in_days {
+			check_range(
+				&mut errors,
+				"expires_in_days",
+				&expires_in_days,
+				Some(1.),
+				Some(3650.),
+			);
+		}
+		errors.into_result()
+	}
+}
+
+impl ApiKey {
+	fn from_doc(doc: ApiKeyDoc) -> Result<Self> {
+		let to_rfc3339 = |date: DateTime| {
+			date.try_to_rfc3339_string()
+				.map_err(|_| Error::CrudDocumentError)
+		};
+
+		Ok(
+			ApiKey {
+				created: to_rfc3339(doc.ctime)?,
+				expires: doc.exp.map(to_rfc3339).transpose()?,
+				last_used: doc
+					.last_used
+					.map(to_rfc3339)
+					.transpose()?,
+				id: doc.id,
+				name: doc.name,
+				privileges: doc.privileges,
+			},
+		)
+	}
+}
+
+// endregion: --- Types
+
+// region:    --- ApiKeyBmc
+
+pub struct ApiKeyBmc;
+
+impl ApiKeyBmc {
+	/// Creates an API key for the user, with privileges among the `user_roles`.
+	pub async fn create(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+		user_roles: &[String],
+		api_key_c: ApiKeyForCreate,
+	) -> Result<ApiKeyCreated> {
+		let mut errors = match api_key_c.validate() {
+			Ok(()) => ValidationError::default(),
+			Err(errors) => errors,
+		};
+		for privilege in &api_key_c.privileges {
+			if !user_roles.contains(privilege) {
+				errors.add(
+					"privileges",
+					"not_held",
+					format!("role '{privilege}' is not held by the user"),
+				);
+			}
+		}
+		errors.into_result()?;
+
+		let collection = Self::collection(
+			ctx, mm,
+		);
+		Self::ensure_indexes(&collection).await?;
+
+		let (key_id, key) = generate_api_key();
+		let (_, secret) = parse_api_key(&key).ok_or(Error::CreateError)?;
+		let key_hash = pwd::hash_pwd(
+			api_key_to_hash(
+				key_id, secret,
+			),
+		)
+		.await?;
+
+		let now = DateTime::now();
+		let api_key = ApiKeyDoc {
+			id: key_id.simple().to_string(),
+			user_id: user_id.to_string(),
+			name: api_key_c.name,
+			key_hash,
+			privileges: api_key_c.privileges,
+			ctime: now,
+			exp: api_key_c
+				.expires_in_days
+				.map(|days| DateTime::from_millis(now.timestamp_millis() + i64::from(days) * DAY_MS)),
+			last_used: None,
+		};
+		collection
+			.insert_one(&api_key)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		Ok(
+			ApiKeyCreated {
+				api_key: ApiKey::from_doc(api_key)?,
+				key,
+			},
+		)
+	}
+
+	pub async fn list_for_user(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+	) -> Result<Vec<ApiKey>> {
+		let mut cursor = Self::collection(
+			ctx, mm,
+		)
+		.find(doc! { "user_id": user_id })
+		.sort(doc! { "ctime": -1 })
+		.await
+		.map_err(|_| Error::QueryError)?;
+
+		let mut api_keys = Vec::new();
+		while cursor
+			.advance()
+			.await
+			.map_err(|_| Error::QueryError)?
+		{
+			let api_key = cursor
+				.deserialize_current()
+				.map_err(|_| Error::CrudDocumentError)?;
+			api_keys.push(ApiKey::from_doc(api_key)?);
+		}
+
+		Ok(api_keys)
+	}
+
+	/// Revokes (deletes) the API key `id` of the user, and returns it.
+	pub async fn revoke_for_user(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+		id: &str,
+	) -> Result<ApiKey> {
+		let api_key = Self::collection(
+			ctx, mm,
+		)
+		.find_one_and_delete(doc! { "_id": id, "user_id": user_id })
+		.await
+		.map_err(|_| Error::DeleteError)?
+		.ok_or_else(|| Error::ApiKeyNotFound { id: id.to_string() })?;
+
+		ApiKey::from_doc(api_key)
+	}
+
+	/// Authenticates the API key, and records its use.
+	pub async fn authenticate(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		key: &str,
+	) -> Result<ApiKeyAuth> {
+		let (key_id, secret) = parse_api_key(key).ok_or(Error::ApiKeyInvalid)?;
+		let id = key_id.simple().to_string();
+		let collection = Self::collection(
+			ctx, mm,
+		);
+
+		let api_key = collection
+			.find_one(doc! { "_id": &id })
+			.await
+			.map_err(|_| Error::QueryError)?
+			.ok_or(Error::ApiKeyInvalid)?;
+
+		// Note: The TTL index removes the expired keys, but not right away.
+		if api_key.exp.is_some_and(|exp| exp < DateTime::now()) {
+			return Err(Error::ApiKeyExpired);
+		}
+
+		let scheme_status = pwd::validate_pwd(
+			api_key_to_hash(
+				key_id, secret,
+			),
+			api_key.key_hash,
+		)
+		.await
+		.map_err(|_| Error::ApiKeyInvalid)?;
+
+		// -- Record the use, and re-hash with the current scheme if needed.
+		let mut update = doc! {};
+		if let SchemeStatus::Outdated = scheme_status {
+			let key_hash = pwd::hash_pwd(
+				api_key_to_hash(
+					key_id, secret,
+				),
+			)
+			.await?;
+			update.insert(
+				"key_hash", key_hash,
+			);
+		}
+		if mm
+			.last_seen
+			.should_update(&format!("api_key:{id}"))
+		{
+			update.insert(
+				"last_used",
+				DateTime::now(),
+			);
+		}
+		if !update.is_empty() {
+			collection
+				.update_one(
+					doc! { "_id": &id },
+					doc! { "$set": update },
+				)
+				.await
+				.map_err(|_| Error::UpdateError)?;
+		}
+
+		Ok(
+			ApiKeyAuth {
+				id,
+				user_id: api_key.user_id,
+				privileges: api_key.privileges,
+			},
+		)
+	}
+}
+
+// endregion: --- ApiKeyBmc
+
+// region:    --- (private) Helpers
+
+impl ApiKeyBmc {
+	fn collection(
+		ctx: &Ctx,
+		mm: &ModelManager,
+	) -> Collection<ApiKeyDoc> {
+		mm.client
+			.database(ctx.tenant_id().as_str())
+			.collection(TABLE)
+	}
+
+	async fn ensure_indexes(collection: &Collection<ApiKeyDoc>) -> Result<()> {
+		let indexes = [
+			IndexModel::builder()
+				.keys(doc! { "exp": 1 })
+				.options(
+					IndexOptions::builder()
+						.expire_after(Duration::ZERO)
+						.build(),
+				)
+				.build(),
+			IndexModel::builder()
+				.keys(doc! { "user_id": 1 })
+				.build(),
+		];
+		collection
+			.create_indexes(indexes)
+			.await
+			.map_err(|_| Error::IndexCreateFail)?;
+
+		Ok(())
+	}
+}
+
+// endregion: --- (private) Helpers
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use serial_test::serial;
+	use uuid::Uuid;
+
+	#[serial]
+	#[tokio::test]
+	async fn test_api_key_create_authenticate_revoke() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let ctx = Ctx::root_ctx();
+		let fx_user_id = Uuid::new_v4().to_string();
+		let fx_roles = vec!["reader".to_string()];
+
+		// -- Exec
+		let created = ApiKeyBmc::create(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			&fx_roles,
+			ApiKeyForCreate {
+				name: "ci".to_string(),
+				expires_in_days: Some(30),
+				privileges: fx_roles.clone(),
+			},
+		)
+		.await?;
+		let auth = ApiKeyBmc::authenticate(
+			&ctx,
+			&mm,
+			&created.key,
+		)
+		.await?;
+		ApiKeyBmc::revoke_for_user(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			&created.api_key.id,
+		)
+		.await?;
+		let auth_after = ApiKeyBmc::authenticate(
+			&ctx,
+			&mm,
+			&created.key,
+		)
+		.await;
+
+		// -- Check
+		assert_eq!(
+			auth.user_id,
+			fx_user_id
+		);
+		assert_eq!(
+			auth.privileges,
+			fx_roles
+		);
+		assert!(matches!(
+			auth_after,
+			Err(super::Error::ApiKeyInvalid)
+		));
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_api_key_create_err_privilege_not_held() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let ctx = Ctx::root_ctx();
+
+		// -- Exec
+		let res = ApiKeyBmc::create(
+			&ctx,
+			&mm,
+			"user-01",
+			&[],
+			ApiKeyForCreate {
+				name: "ci".to_string(),
+				expires_in_days: None,
+				privileges: vec!["admin".to_string()],
+			},
+		)
+		.await;
+
+		// -- Check
+		assert!(matches!(
+			res,
+			Err(super::Error::Validation(_))
+		));
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -0,0 +1,390 @@
+//! Attachments are binary files stored in a GridFS bucket of the tenant database.
+//!
+//! Design:
+//!
+//! - The attachment metadata (content type, owner, sha256) is stored in the `metadata`
+//!   sub-document of the GridFS files collection, so deleting the file removes its metadata as well.
+//! - The content is streamed in and out of GridFS (`futures::io::AsyncRead`), it is never fully
+//!   loaded in memory.
+//! - `#[crud]` entities reference attachments by id with the `#[attachment]` field attribute,
+//!   see `IdRefs`.
+//! - An attachment is only visible to its owner (the uploading user), the attachments of the other
+//!   users are not found, to not disclose their existence.
+
+use crate::ctx::Ctx;
+use crate::model::base::IdRefs;
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, Take};
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use mongodb::gridfs::{FilesCollectionDocument, GridFsBucket, GridFsDownloadStream};
+use mongodb::options::GridFsBucketOptions;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const BUCKET: &str = "attachments";
+const UPLOAD_BUF_SIZE: usize = 64 * 1024;
+
+/// The attachment content stream, limited to the requested range.
+pub type AttachmentStream = Take<GridFsDownloadStream>;
+
+// region:    --- Attachment Types
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Attachment {
+	pub id: String,
+	pub filename: String,
+	pub content_type: String,
+	pub size: u64,
+	pub sha256: Option<String>,
+	pub owner_id: Option<String>,
+	pub uploaded: String, // (Rfc3339)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttachmentForUpload {
+	pub filename: String,
+	pub content_type: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AttachmentMeta {
+	content_type: Option<String>,
+	owner_id: Option<String>,
+	sha256: Option<String>,
+}
+
+impl Attachment {
+	fn from_files_doc(
+		id: &str,
+		file: FilesCollectionDocument,
+	) -> Result<Self> {
+		let meta: AttachmentMeta = match file.metadata {
+			Some(metadata) => {
+				mongodb::bson::from_document(metadata).map_err(|_| Error::CrudDocumentError)?
+			},
+			None => AttachmentMeta::default(),
+		};
+
+		Ok(
+			Attachment {
+				id: id.to_string(),
+				filename: file.filename.unwrap_or_default(),
+				content_type: meta
+					.content_type
+					.unwrap_or_else(|| "application/octet-stream".to_string()),
+				size: file.length,
+				sha256: meta.sha256,
+				owner_id: meta.owner_id,
+				uploaded: file
+					.upload_date
+					.try_to_rfc3339_string()
+					.map_err(|_| Error::CrudDocumentError)?,
+			},
+		)
+	}
+}
+
+// endregion: --- Attachment Types
+
+// region:    --- AttachmentBmc
+
+pub struct AttachmentBmc;
+
+impl AttachmentBmc {
+	/// Streams `content` into GridFS and records its metadata.
+	/// The owner of the attachment is the user of the `ctx`.
+	pub async fn upload<R>(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		input: AttachmentForUpload,
+		mut content: R,
+	) -> Result<Attachment>
+	where
+		R: AsyncRead + Unpin,
+	{
+		let metadata = doc! {
+			"content_type": &input.content_type,
+			"owner_id": ctx.user_id(),
+		};
+		let mut upload = Self::bucket(
+			ctx, mm,
+		)
+		.open_upload_stream(&input.filename)
+		.metadata(metadata)
+		.await
+		.map_err(|_| Error::AttachmentUploadFail)?;
+		let file_id = upload.id().clone();
+
+		// -- Stream the content into GridFS, hashing it on the way.
+		let mut hasher = Sha256::new();
+		let mut buf = vec![0u8; UPLOAD_BUF_SIZE];
+		loop {
+			let read = match content.read(&mut buf).await {
+				Ok(0) => break,
+				Ok(read) => read,
+				Err(_) => {
+					let _ = upload.abort().await;
+					return Err(Error::AttachmentUploadFail);
+				},
+			};
+			hasher.update(&buf[..read]);
+			if upload.write_all(&buf[..read]).await.is_err() {
+				let _ = upload.abort().await;
+				return Err(Error::AttachmentUploadFail);
+			}
+		}
+		upload
+			.close()
+			.await
+			.map_err(|_| Error::AttachmentUploadFail)?;
+
+		// -- Record the hash, only known once the content is fully read.
+		let sha256 = format!(
+			"{:x}",
+			hasher.finalize()
+		);
+		mm.client
+			.database(ctx.tenant_id().as_str())
+			.collection::<Document>(&format!("{BUCKET}.files"))
+			.update_one(
+				doc! { "_id": &file_id },
+				doc! { "$set": { "metadata.sha256": sha256 } },
+			)
+			.await
+			.map_err(|_| Error::AttachmentUploadFail)?;
+
+		let id = file_id
+			.as_object_id()
+			.map(|oid| oid.to_hex())
+			.ok_or(Error::ObIdError)?;
+		Self::get(
+			ctx, mm, &id,
+		)
+		.await
+	}
+
+	/// Fails with `AttachmentNotFound` if no attachment `id` owned by the user of the `ctx`.
+	pub async fn get(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: &str,
+	) -> Result<Attachment> {
+		let oid = ObjectId::parse_str(id)
+			.map_err(|_| Error::AttachmentNotFound { id: id.to_string() })?;
+		let file = Self::bucket(
+			ctx, mm,
+		)
+		.find_one(doc! { "_id": oid, "metadata.owner_id": ctx.user_id() })
+		.await
+		.map_err(|_| Error::QueryError)?
+		.ok_or_else(|| Error::AttachmentNotFound { id: id.to_string() })?;
+
+		Attachment::from_files_doc(
+			id, file,
+		)
+	}
+
+	/// Opens the content of the attachment (as returned by `get`, hence owned by the user of the `ctx`).
+	///
+	/// `range` is an inclusive `(start, end)` byte range, already validated against the attachment size.
+	pub async fn open_download(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		attachment: &Attachment,
+		range: Option<(u64, u64)>,
+	) -> Result<AttachmentStream> {
+		let oid = ObjectId::parse_str(&attachment.id).map_err(|_| Error::ObIdError)?;
+		let mut stream = Self::bucket(
+			ctx, mm,
+		)
+		.open_download_stream(Bson::ObjectId(oid))
+		.await
+		.map_err(|_| Error::AttachmentDownloadFail)?;
+
+		let (start, len) = match range {
+			Some((start, end)) => (
+				start,
+				end + 1 - start,
+			),
+			None => (
+				0,
+				attachment.size,
+			),
+		};
+
+		// Note: GridFS download streams cannot seek, so the bytes before the range are skipped.
+		if start > 0 {
+			futures::io::copy(
+				(&mut stream).take(start),
+				&mut futures::io::sink(),
+			)
+			.await
+			.map_err(|_| Error::AttachmentDownloadFail)?;
+		}
+
+		Ok(stream.take(len))
+	}
+
+	/// Deletes the attachment content and metadata. Only the owner can delete an attachment (see `get`).
+	pub async fn delete(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: &str,
+	) -> Result<Attachment> {
+		let attachment = Self::get(
+			ctx, mm, id,
+		)
+		.await?;
+
+		let oid = ObjectId::parse_str(id).map_err(|_| Error::ObIdError)?;
+		Self::bucket(
+			ctx, mm,
+		)
+		.delete(Bson::ObjectId(oid))
+		.await
+		.map_err(|_| Error::DeleteError)?;
+
+		Ok(attachment)
+	}
+
+	/// Fails with `AttachmentNotFound` if one of the referenced attachments does not exist,
+	/// or is not owned by the user of the `ctx` (see `get`).
+	pub async fn validate_refs(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		refs: &impl IdRefs,
+	) -> Result<()> {
+		for id in refs.ref_ids() {
+			Self::get(
+				ctx, mm, id,
+			)
+			.await?;
+		}
+		Ok(())
+	}
+
+	fn bucket(
+		ctx: &Ctx,
+		mm: &ModelManager,
+	) -> GridFsBucket {
+		let options = GridFsBucketOptions::builder()
+			.bucket_name(BUCKET.to_string())
+			.build();
+		mm.client
+			.database(ctx.tenant_id().as_str())
+			.gridfs_bucket(options)
+	}
+}
+
+// endregion: --- AttachmentBmc
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use crate::model;
+	use futures::io::Cursor;
+	use serial_test::serial;
+	use uuid::Uuid;
+
+	#[serial]
+	#[tokio::test]
+	async fn test_attachment_upload_download_delete_across_users() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let fx_owner_ctx = Ctx::new(Uuid::new_v4().to_string())?;
+		let fx_other_ctx = Ctx::new(Uuid::new_v4().to_string())?;
+		let fx_content = b"hello attachment".to_vec();
+		let attachment = AttachmentBmc::upload(
+			&fx_owner_ctx,
+			&mm,
+			AttachmentForUpload {
+				filename: "hello.txt".to_string(),
+				content_type: "text/plain".to_string(),
+			},
+			Cursor::new(fx_content.clone()),
+		)
+		.await?;
+
+		// -- Exec & Check - The owner downloads it.
+		let mut content = Vec::new();
+		AttachmentBmc::open_download(
+			&fx_owner_ctx,
+			&mm,
+			&attachment,
+			Some(
+				(
+					6, 9,
+				),
+			),
+		)
+		.await?
+		.read_to_end(&mut content)
+		.await?;
+		assert_eq!(
+			content,
+			b"atta"
+		);
+		assert_eq!(
+			attachment.owner_id,
+			Some(fx_owner_ctx.user_id())
+		);
+
+		// -- Exec & Check - The other user cannot get, reference, nor delete it.
+		let res_get = AttachmentBmc::get(
+			&fx_other_ctx,
+			&mm,
+			&attachment.id,
+		)
+		.await;
+		let res_refs = AttachmentBmc::validate_refs(
+			&fx_other_ctx,
+			&mm,
+			&vec![attachment.id.clone()],
+		)
+		.await;
+		let res_delete = AttachmentBmc::delete(
+			&fx_other_ctx,
+			&mm,
+			&attachment.id,
+		)
+		.await;
+		assert!(matches!(
+			res_get,
+			Err(model::Error::AttachmentNotFound { .. })
+		));
+		assert!(matches!(
+			res_refs,
+			Err(model::Error::AttachmentNotFound { .. })
+		));
+		assert!(matches!(
+			res_delete,
+			Err(model::Error::AttachmentNotFound { .. })
+		));
+
+		// -- Exec & Check - The owner deletes it.
+		AttachmentBmc::delete(
+			&fx_owner_ctx,
+			&mm,
+			&attachment.id,
+		)
+		.await?;
+		let res_get = AttachmentBmc::get(
+			&fx_owner_ctx,
+			&mm,
+			&attachment.id,
+		)
+		.await;
+		assert!(matches!(
+			res_get,
+			Err(model::Error::AttachmentNotFound { .. })
+		));
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -3,6 +3,7 @@ use crate::model::base::DbBmc;
 use crate::model::ModelManager;
 use crate::model::{Error, Result};
 use futures::stream::TryStreamExt;
+use lib_utils::time::{format_time, now_utc};
 use mongodb::bson::oid::ObjectId;
 use mongodb::bson::{doc, from_document, to_document, Bson, Document};
 use serde::de::DeserializeOwned;
@@ -52,9 +53,10 @@ impl<S: Serialize> Filter<S> {
 	}
 }
 
+/// Converts the string `id` (or `_id`, e.g., of `QUser`) of the filter to the `_id` ObjectId.
 pub fn convert_filter_ids(mut doc: Document) -> Document {
 	for (key, value) in doc.clone().into_iter() {
-		if key == "id" {
+		if key == "id" || key == "_id" {
 			if let Bson::String(s) = value {
 				if let Ok(oid) = ObjectId::parse_str(&s) {
 					doc.remove("id");
@@ -117,6 +119,99 @@ pub fn make_filter_doc(doc: Document) -> Document {
 	)
 }
 
+// region:    --- Entity Options
+
+const OWNER_ID: &str = "owner_id";
+const CID: &str = "cid";
+const CTIME: &str = "ctime";
+const MID: &str = "mid";
+const MTIME: &str = "mtime";
+const DTIME: &str = "dtime";
+
+/// Restricts the filter to the documents visible to the ctx,
+/// i.e., owned by the ctx user (`has_owner_id`) and not deleted (`has_soft_delete`).
+pub fn scope_filter<MC>(
+	ctx: &Ctx,
+	filter: &mut Document,
+) where
+	MC: DbBmc,
+{
+	if MC::has_owner_id() {
+		filter.insert(
+			OWNER_ID,
+			ctx.user_id(),
+		);
+	}
+	if MC::has_soft_delete() {
+		// Note: Matches both a missing and a null `dtime`.
+		filter.insert(
+			DTIME,
+			Bson::Null,
+		);
+	}
+}
+
+fn stamp_create<MC>(
+	ctx: &Ctx,
+	doc: &mut Document,
+) where
+	MC: DbBmc,
+{
+	if MC::has_owner_id() {
+		doc.insert(
+			OWNER_ID,
+			ctx.user_id(),
+		);
+	}
+	if MC::has_timestamps() {
+		let now = format_time(now_utc());
+		doc.insert(
+			CID,
+			ctx.user_id(),
+		);
+		doc.insert(
+			CTIME,
+			now.clone(),
+		);
+		doc.insert(
+			MID,
+			ctx.user_id(),
+		);
+		doc.insert(
+			MTIME, now,
+		);
+	}
+}
+
+/// Sets the modification stamps, and prevents the update from changing the create stamps or owner.
+fn stamp_update<MC>(
+	ctx: &Ctx,
+	set: &mut Document,
+) where
+	MC: DbBmc,
+{
+	if MC::has_owner_id() {
+		set.remove(OWNER_ID);
+	}
+	if MC::has_soft_delete() {
+		set.remove(DTIME);
+	}
+	if MC::has_timestamps() {
+		set.remove(CID);
+		set.remove(CTIME);
+		set.insert(
+			MID,
+			ctx.user_id(),
+		);
+		set.insert(
+			MTIME,
+			format_time(now_utc()),
+		);
+	}
+}
+
+// endregion: --- Entity Options
+
 pub async fn create<MC, D>(
 	ctx: &Ctx,
 	mm: &ModelManager,
@@ -127,11 +222,15 @@ where
 	D: Serialize + Send + Sync + Debug,
 {
 	let q = Query::doc(data);
+	let mut doc = to_document(&q.set).map_err(|_| Error::CrudDocumentError)?;
+	stamp_create::<MC>(
+		ctx, &mut doc,
+	);
 	let collection = mm
 		.client
 		.database(ctx.tenant_id().as_str())
-		.collection::<D>(MC::TABLE);
-	let result = collection.insert_one(q.set).await;
+		.collection::<Document>(MC::TABLE);
+	let result = collection.insert_one(doc).await;
 	let res = result.map_err(|e| Error::CreateError)?;
 	let out = res.inserted_id.as_object_id().to_owned();
 	let st = out.unwrap().to_hex();
@@ -149,13 +248,22 @@ where
 	D: Serialize + Send + Sync,
 {
 	let object_id = ObjectId::parse_str(id).map_err(|_| Error::ObIdError)?;
-	let filter = doc! { "_id": object_id };
-	let update_doc = Update::doc(update);
+	let mut filter = doc! { "_id": object_id };
+	scope_filter::<MC>(
+		ctx,
+		&mut filter,
+	);
+	let mut update_doc = Update::doc(update);
+	if let Ok(set) = update_doc.get_document_mut("$set") {
+		stamp_update::<MC>(
+			ctx, set,
+		);
+	}
 	let doc = update_doc;
 	let collection = mm
 		.client
 		.database(ctx.tenant_id().as_str())
-		.collection::<D>(MC::TABLE);
+		.collection::<Document>(MC::TABLE);
 	collection
 		.update_one(
 			filter, doc,
@@ -174,15 +282,40 @@ where
 	MC: DbBmc,
 	T: Serialize + DeserializeOwned + Send + Sync,
 {
-	let doc = Filter::doc(filter);
+	let mut doc = Filter::doc(filter);
+	scope_filter::<MC>(
+		ctx, &mut doc,
+	);
 	let collection = mm
 		.client
 		.database(ctx.tenant_id().as_str())
-		.collection::<T>(MC::TABLE);
-	collection
-		.delete_one(doc)
-		.await
-		.map_err(|_| Error::DeleteError)?;
+		.collection::<Document>(MC::TABLE);
+
+	if MC::has_soft_delete() {
+		let mut set = doc! { DTIME: format_time(now_utc()) };
+		if MC::has_timestamps() {
+			set.insert(
+				MID,
+				ctx.user_id(),
+			);
+			set.insert(
+				MTIME,
+				format_time(now_utc()),
+			);
+		}
+		collection
+			.update_one(
+				doc,
+				doc! { "$set": set },
+			)
+			.await
+			.map_err(|_| Error::DeleteError)?;
+	} else {
+		collection
+			.delete_one(doc)
+			.await
+			.map_err(|_| Error::DeleteError)?;
+	}
 	Ok(())
 }
 
@@ -195,7 +328,11 @@ where
 	MC: DbBmc,
 	T: Serialize + DeserializeOwned + Send + Sync + Debug,
 {
-	let filter_doc = Filter::doc(filter);
+	let mut filter_doc = Filter::doc(filter);
+	scope_filter::<MC>(
+		ctx,
+		&mut filter_doc,
+	);
 	let collection = mm
 		.client
 		.database(ctx.tenant_id().as_str())
@@ -228,7 +365,10 @@ where
 	MC: DbBmc,
 	T: Serialize + DeserializeOwned + Send + Sync + Default,
 {
-	let doc = Filter::doc(filter);
+	let mut doc = Filter::doc(filter);
+	scope_filter::<MC>(
+		ctx, &mut doc,
+	);
 
 	let collection = mm
 		.client
@@ -497,4 +637,22 @@ mod tests {
 		);
 		Ok(())
 	}
+
+	#[test]
+	fn test_convert_filter_ids_underscore_id() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_oid = ObjectId::new();
+		let fx_filter = doc! { "_id": fx_oid.to_hex(), "name": "Alice" };
+
+		// -- Exec
+		let filter = convert_filter_ids(fx_filter);
+
+		// -- Check
+		assert_eq!(
+			filter,
+			doc! { "name": "Alice", "_id": fx_oid }
+		);
+
+		Ok(())
+	}
 }
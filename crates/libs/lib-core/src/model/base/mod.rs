@@ -1,9 +1,13 @@
 // region:    --- Modules
 
 pub(crate) mod crud_fns;
+pub(crate) mod relation_fns;
+pub(crate) mod search_fns;
 
 // -- Flatten hierarchy for user code.
 pub use crud_fns::*;
+pub use relation_fns::*;
+pub use search_fns::*;
 
 // endregion: --- Modules
 
@@ -17,6 +21,22 @@ pub use crud_fns::*;
 pub trait DbBmc {
 	const TABLE: &'static str;
 
+	/// The fields covered by the full-text search (see `search_fns`).
+	///
+	/// default: none
+	const TEXT_FIELDS: &'static [&'static str] = &[];
+
+	/// The relations of the entity, which can be expanded (see `relation_fns`).
+	///
+	/// default: none
+	const RELATIONS: &'static [Relation] = &[];
+
+	/// The fields exposed when the entity is expanded as a relation of another entity, besides its `_id`
+	/// (an allowlist, so that a new field is not exposed by default, e.g., the user mail).
+	///
+	/// default: none
+	const EXPAND_FIELDS: &'static [&'static str] = &[];
+
 	fn table_ref() -> &'static str {
 		Self::TABLE
 	}
@@ -28,4 +48,98 @@ pub trait DbBmc {
 	fn has_owner_id() -> bool {
 		false
 	}
+
+	/// Specifies if the entity table managed by this BMC
+	/// has the `cid`, `ctime`, `mid`, `mtime` columns, set on create and update.
+	///
+	/// default: false
+	fn has_timestamps() -> bool {
+		false
+	}
+
+	/// Specifies if deleting only sets the `dtime` column of the entity,
+	/// which then is excluded from get, list, update and search.
+	///
+	/// default: false
+	fn has_soft_delete() -> bool {
+		false
+	}
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use crate::ctx::Ctx;
+	use mongodb::bson::{doc, Bson};
+	use proc_mac::crud;
+	use serde::{Deserialize, Serialize};
+
+	#[derive(Debug, Serialize, Deserialize, Default)]
+	#[crud(table = "project_tasks", owner, timestamps, soft_delete)]
+	pub struct ProjectTask {
+		pub id: Option<String>,
+		pub title: Option<String>,
+		#[has_many(crate::model::example::Example)]
+		pub example_ids: Option<Vec<String>>,
+	}
+
+	#[test]
+	fn test_crud_options_ok() -> Result<()> {
+		// -- Exec & Check
+		let task: ProjectTask = ProjectTaskForCreate {
+			title: Some("task".to_string()),
+			..Default::default()
+		}
+		.into();
+		assert_eq!(
+			task.title.as_deref(),
+			Some("task")
+		);
+		assert_eq!(
+			ProjectTaskBmc::TABLE,
+			"project_tasks"
+		);
+		assert_eq!(
+			ProjectTaskBmc::RPC_SUFFIX,
+			"project_task"
+		);
+		assert!(ProjectTaskBmc::has_owner_id());
+		assert!(ProjectTaskBmc::has_timestamps());
+		assert!(ProjectTaskBmc::has_soft_delete());
+		let [relation] = ProjectTaskBmc::RELATIONS else {
+			return Err("Should have one relation".into());
+		};
+		assert_eq!(
+			(relation.field, relation.kind),
+			("example_ids", RelationKind::HasMany)
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scope_filter_owner_and_soft_delete() -> Result<()> {
+		// -- Fixtures
+		let fx_ctx = Ctx::new("user-01".to_string())?;
+		let mut filter = doc! { "title": "task" };
+
+		// -- Exec
+		scope_filter::<ProjectTaskBmc>(
+			&fx_ctx,
+			&mut filter,
+		);
+
+		// -- Check
+		assert_eq!(
+			filter,
+			doc! { "title": "task", "owner_id": "user-01", "dtime": Bson::Null }
+		);
+
+		Ok(())
+	}
 }
+// endregion: --- Tests
@@ -0,0 +1,370 @@
+//! Relations between entities, declared with the `#[belongs_to(..)]` and `#[has_many(..)]`
+//! field attributes of `#[crud]`.
+//!
+//! - A relation field holds the id(s) of the related documents (`String` for `belongs_to`,
+//!   `Vec<String>` for `has_many`, or their `Option`), which must exist on create and update.
+//! - `get_expanded` and `list_expanded` populate the requested relations with a `$lookup`,
+//!   applying the access rules of the related Bmc (see `scope_filter`) and keeping only its `_id`
+//!   and `EXPAND_FIELDS`.
+
+use crate::ctx::Ctx;
+use crate::model::base::{scope_filter, DbBmc, Filter};
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use futures::stream::TryStreamExt;
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, from_document, Bson, Document};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Holds the expanded relations during the aggregation.
+const EXPANDED_FIELD: &str = "_expanded";
+
+// region:    --- Types
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelationKind {
+	BelongsTo,
+	HasMany,
+}
+
+/// A relation of a `DbBmc`, generated by `#[crud]` for the relation fields.
+pub struct Relation {
+	/// The field holding the related id(s), also the name used in `expand`.
+	pub field: &'static str,
+	pub kind: RelationKind,
+	/// The `$lookup` stage of the relation (see `lookup_stage`).
+	pub lookup: fn(&Ctx, &'static str) -> Document,
+}
+
+/// An entity with its expanded relations (field name to the related document(s)).
+#[derive(Debug, Serialize)]
+pub struct Expanded<T: Serialize> {
+	#[serde(flatten)]
+	pub data: T,
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub expanded: HashMap<String, Value>,
+}
+
+/// Implemented by the field types holding related ids
+/// (`String`, `Vec<String>`, and their `Option`).
+pub trait IdRefs {
+	fn ref_ids(&self) -> Vec<&str>;
+}
+
+impl IdRefs for String {
+	fn ref_ids(&self) -> Vec<&str> {
+		vec![self.as_str()]
+	}
+}
+
+impl<T: IdRefs> IdRefs for Vec<T> {
+	fn ref_ids(&self) -> Vec<&str> {
+		self.iter().flat_map(IdRefs::ref_ids).collect()
+	}
+}
+
+impl<T: IdRefs> IdRefs for Option<T> {
+	fn ref_ids(&self) -> Vec<&str> {
+		self.as_ref().map(IdRefs::ref_ids).unwrap_or_default()
+	}
+}
+
+// endregion: --- Types
+
+// region:    --- Relation Functions
+
+/// Fails with `RelationNotFound` if one of the referenced ids is not visible through the related Bmc `MC`.
+pub async fn validate_refs<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	field: &'static str,
+	refs: &impl IdRefs,
+) -> Result<()>
+where
+	MC: DbBmc,
+{
+	let ids = refs.ref_ids();
+	if ids.is_empty() {
+		return Ok(());
+	}
+
+	let not_found = |id: &str| Error::RelationNotFound {
+		entity: MC::TABLE,
+		field,
+		id: id.to_string(),
+	};
+	let mut oids = Vec::with_capacity(ids.len());
+	for id in &ids {
+		oids.push(ObjectId::parse_str(id).map_err(|_| not_found(id))?);
+	}
+
+	let mut filter = doc! { "_id": { "$in": &oids } };
+	scope_filter::<MC>(
+		ctx,
+		&mut filter,
+	);
+	let found: Vec<Document> = mm
+		.client
+		.database(ctx.tenant_id().as_str())
+		.collection::<Document>(MC::TABLE)
+		.find(filter)
+		.projection(doc! { "_id": 1 })
+		.await
+		.map_err(|_| Error::QueryError)?
+		.try_collect()
+		.await
+		.map_err(|_| Error::QueryError)?;
+
+	for (id, oid) in ids.iter().zip(&oids) {
+		if !found.iter().any(|d| d.get_object_id("_id").ok() == Some(*oid)) {
+			return Err(not_found(id));
+		}
+	}
+
+	Ok(())
+}
+
+/// The `$lookup` stage populating the `field` ids with the documents of the related Bmc `MC`.
+pub fn lookup_stage<MC>(
+	ctx: &Ctx,
+	field: &'static str,
+) -> Document
+where
+	MC: DbBmc,
+{
+	let mut scope = Document::new();
+	scope_filter::<MC>(
+		ctx, &mut scope,
+	);
+
+	// -- The ids are stored as hex strings, and a single id is handled as a list of one.
+	let ref_oids = doc! {
+		"$map": {
+			"input": { "$cond": [{ "$isArray": "$$refs" }, "$$refs", ["$$refs"]] },
+			"in": { "$convert": { "input": "$$this", "to": "objectId", "onError": Bson::Null, "onNull": Bson::Null } },
+		}
+	};
+	let mut project = doc! { "_id": 1 };
+	for field in MC::EXPAND_FIELDS {
+		project.insert(
+			*field, 1,
+		);
+	}
+	let pipeline = vec![
+		doc! { "$match": { "$expr": { "$in": ["$_id", ref_oids] } } },
+		doc! { "$match": scope },
+		doc! { "$project": project },
+	];
+
+	doc! {
+		"$lookup": {
+			"from": MC::TABLE,
+			"let": { "refs": { "$ifNull": [format!("${field}"), []] } },
+			"pipeline": pipeline,
+			"as": format!("{EXPANDED_FIELD}.{field}"),
+		}
+	}
+}
+
+pub async fn get_expanded<MC, T>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	filter: T,
+	expand: &[String],
+) -> Result<Expanded<T>>
+where
+	MC: DbBmc,
+	T: Serialize + DeserializeOwned + Send + Sync,
+{
+	expanded_query::<MC, T>(
+		ctx,
+		mm,
+		filter,
+		expand,
+		Some(1),
+	)
+	.await?
+	.pop()
+	.ok_or(Error::ReadError)
+}
+
+pub async fn list_expanded<MC, T>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	filter: T,
+	expand: &[String],
+) -> Result<Vec<Expanded<T>>>
+where
+	MC: DbBmc,
+	T: Serialize + DeserializeOwned + Send + Sync,
+{
+	expanded_query::<MC, T>(
+		ctx, mm, filter, expand, None,
+	)
+	.await
+}
+
+// endregion: --- Relation Functions
+
+// region:    --- (private) Expanded Query
+
+async fn expanded_query<MC, T>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	filter: T,
+	expand: &[String],
+	limit: Option<i64>,
+) -> Result<Vec<Expanded<T>>>
+where
+	MC: DbBmc,
+	T: Serialize + DeserializeOwned + Send + Sync,
+{
+	let relations = expand
+		.iter()
+		.map(
+			|name| {
+				MC::RELATIONS
+					.iter()
+					.find(|relation| relation.field == name)
+					.ok_or_else(|| Error::RelationUnknown { name: name.to_string() })
+			},
+		)
+		.collect::<Result<Vec<_>>>()?;
+
+	let mut filter_doc = Filter::doc(filter);
+	scope_filter::<MC>(
+		ctx,
+		&mut filter_doc,
+	);
+	let mut pipeline = vec![doc! { "$match": filter_doc }];
+	if let Some(limit) = limit {
+		pipeline.push(doc! { "$limit": limit });
+	}
+	for relation in &relations {
+		pipeline.push((relation.lookup)(ctx, relation.field));
+	}
+
+	let docs: Vec<Document> = mm
+		.client
+		.database(ctx.tenant_id().as_str())
+		.collection::<Document>(MC::TABLE)
+		.aggregate(pipeline)
+		.await
+		.map_err(|_| Error::QueryError)?
+		.try_collect()
+		.await
+		.map_err(|_| Error::QueryError)?;
+
+	let mut res = Vec::with_capacity(docs.len());
+	for mut d in docs {
+		let mut expanded_doc = match d.remove(EXPANDED_FIELD) {
+			Some(Bson::Document(expanded_doc)) => expanded_doc,
+			_ => Document::new(),
+		};
+		id_to_hex(&mut d);
+		let data: T = from_document(d).map_err(|_| Error::QueryError)?;
+
+		let mut expanded = HashMap::new();
+		for relation in &relations {
+			let related: Vec<Value> = match expanded_doc.remove(relation.field) {
+				Some(Bson::Array(related)) => related
+					.into_iter()
+					.filter_map(
+						|related| match related {
+							Bson::Document(mut related) => {
+								id_to_hex(&mut related);
+								Some(Bson::Document(related).into_relaxed_extjson())
+							},
+							_ => None,
+						},
+					)
+					.collect(),
+				_ => Vec::new(),
+			};
+			let value = match relation.kind {
+				RelationKind::BelongsTo => related.into_iter().next().unwrap_or(Value::Null),
+				RelationKind::HasMany => Value::Array(related),
+			};
+			expanded.insert(
+				relation.field.to_string(),
+				value,
+			);
+		}
+
+		res.push(Expanded { data, expanded });
+	}
+
+	Ok(res)
+}
+
+fn id_to_hex(d: &mut Document) {
+	if let Some(Bson::ObjectId(oid)) = d.get("_id") {
+		let hex = oid.to_hex();
+		d.insert(
+			"_id", hex,
+		);
+	}
+}
+
+// endregion: --- (private) Expanded Query
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use crate::model::user::UserBmc;
+
+	#[test]
+	fn test_id_refs_ok() -> Result<()> {
+		// -- Fixtures
+		let fx_refs: Option<Vec<String>> = Some(vec!["a".to_string(), "b".to_string()]);
+
+		// -- Exec & Check
+		assert_eq!(
+			fx_refs.ref_ids(),
+			vec!["a", "b"]
+		);
+		assert!(None::<String>.ref_ids().is_empty());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_lookup_stage_projects_expand_fields() -> Result<()> {
+		// -- Exec
+		let stage = lookup_stage::<UserBmc>(
+			&Ctx::root_ctx(),
+			"user_id",
+		);
+
+		// -- Check
+		let lookup = stage.get_document("$lookup")?;
+		assert_eq!(
+			lookup.get_str("from")?,
+			"Users"
+		);
+		assert_eq!(
+			lookup.get_str("as")?,
+			"_expanded.user_id"
+		);
+		let pipeline = lookup.get_array("pipeline")?;
+		let project = pipeline
+			.last()
+			.and_then(Bson::as_document)
+			.ok_or("Should have a pipeline stage")?
+			.get_document("$project")?;
+		assert_eq!(
+			project,
+			&doc! { "_id": 1, "login.username": 1 }
+		);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
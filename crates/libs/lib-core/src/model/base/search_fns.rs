@@ -0,0 +1,471 @@
+//! Full-text search over the `TEXT_FIELDS` of a `DbBmc`.
+//!
+//! - Uses a MongoDB `$text` query when the collection has a text index (see `ensure_text_index`),
+//!   and falls back to case-insensitive regex matching on the text fields otherwise.
+//! - Each hit comes with a relevance score (`textScore` for `$text`, number of matching
+//!   text fields for the regex fallback) and the highlighted text fields (HTML-escaped,
+//!   with the matches wrapped in `<em>`, so they can be rendered as HTML).
+//! - Hits are scoped to the tenant database of the `Ctx`, to the `Ctx` user
+//!   when the Bmc `has_owner_id`, and exclude the deleted documents when it `has_soft_delete`.
+
+use crate::ctx::Ctx;
+use crate::model::base::{scope_filter, DbBmc};
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, from_document, Bson, Document};
+use mongodb::error::ErrorKind;
+use mongodb::options::IndexOptions;
+use mongodb::IndexModel;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const LIST_LIMIT_DEFAULT: i64 = 50;
+const LIST_LIMIT_MAX: i64 = 500;
+
+const TEXT_INDEX_NAME: &str = "text_fields";
+/// MongoDB `IndexNotFound`, returned for `$text` queries without a text index.
+const INDEX_NOT_FOUND_CODE: i32 = 27;
+
+const HIGHLIGHT_START: &str = "<em>";
+const HIGHLIGHT_END: &str = "</em>";
+
+// region:    --- Types
+
+/// Pagination for list-like queries.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ListOptions {
+	pub limit: Option<i64>,
+	pub offset: Option<u64>,
+}
+
+impl ListOptions {
+	pub(crate) fn limit(&self) -> Result<i64> {
+		match self.limit {
+			Some(limit) if limit > LIST_LIMIT_MAX => Err(
+				Error::ListLimitOverMax {
+					max: LIST_LIMIT_MAX,
+					actual: limit,
+				},
+			),
+			Some(limit) if limit > 0 => Ok(limit),
+			_ => Ok(LIST_LIMIT_DEFAULT),
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit<T: Serialize> {
+	pub data: T,
+	pub score: f64,
+	/// Text field name to the field content with the matching terms highlighted.
+	pub highlights: HashMap<String, String>,
+}
+
+// endregion: --- Types
+
+// region:    --- Search Functions
+
+pub async fn search<MC, T>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	query: &str,
+	list_options: ListOptions,
+) -> Result<Vec<SearchHit<T>>>
+where
+	MC: DbBmc,
+	T: Serialize + DeserializeOwned + Send + Sync,
+{
+	let terms = search_terms(query);
+	if terms.is_empty() || MC::TEXT_FIELDS.is_empty() {
+		return Ok(Vec::new());
+	}
+
+	let docs = match text_search::<MC>(
+		ctx,
+		mm,
+		query,
+		&list_options,
+	)
+	.await
+	{
+		Err(Error::SearchNoTextIndex) => {
+			regex_search::<MC>(
+				ctx,
+				mm,
+				&terms,
+				&list_options,
+			)
+			.await?
+		},
+		other => other?,
+	};
+
+	let mut hits = Vec::new();
+	for mut d in docs {
+		let text_score = d.remove("score").and_then(|score| score.as_f64());
+		let highlights = highlight_fields::<MC>(
+			&d, &terms,
+		);
+		let score = text_score.unwrap_or(highlights.len() as f64);
+
+		if let Some(Bson::ObjectId(oid)) = d.get("_id") {
+			d.insert(
+				"_id".to_string(),
+				Bson::String(oid.to_hex()),
+			);
+		}
+		let data: T = from_document(d).map_err(|_| Error::QueryError)?;
+
+		hits.push(
+			SearchHit {
+				data,
+				score,
+				highlights,
+			},
+		);
+	}
+
+	Ok(hits)
+}
+
+/// Creates the text index over the `TEXT_FIELDS` of the Bmc (no-op if already created).
+pub async fn ensure_text_index<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+) -> Result<()>
+where
+	MC: DbBmc,
+{
+	if MC::TEXT_FIELDS.is_empty() {
+		return Ok(());
+	}
+
+	let mut keys = Document::new();
+	for field in MC::TEXT_FIELDS {
+		keys.insert(
+			*field, "text",
+		);
+	}
+	let index = IndexModel::builder()
+		.keys(keys)
+		.options(
+			IndexOptions::builder()
+				.name(TEXT_INDEX_NAME.to_string())
+				.build(),
+		)
+		.build();
+
+	mm.client
+		.database(ctx.tenant_id().as_str())
+		.collection::<Document>(MC::TABLE)
+		.create_index(index)
+		.await
+		.map_err(|_| Error::IndexCreateFail)?;
+
+	Ok(())
+}
+
+// endregion: --- Search Functions
+
+// region:    --- (private) Queries
+
+async fn text_search<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	query: &str,
+	list_options: &ListOptions,
+) -> Result<Vec<Document>>
+where
+	MC: DbBmc,
+{
+	let mut filter = doc! { "$text": { "$search": query } };
+	scope_filter::<MC>(
+		ctx,
+		&mut filter,
+	);
+
+	let score = doc! { "score": { "$meta": "textScore" } };
+	let cursor = mm
+		.client
+		.database(ctx.tenant_id().as_str())
+		.collection::<Document>(MC::TABLE)
+		.find(filter)
+		.projection(score.clone())
+		.sort(score)
+		.skip(list_options.offset.unwrap_or(0))
+		.limit(list_options.limit()?)
+		.await
+		.map_err(
+			|ex| match ex.kind.as_ref() {
+				ErrorKind::Command(cmd_err) if cmd_err.code == INDEX_NOT_FOUND_CODE => {
+					Error::SearchNoTextIndex
+				},
+				_ => Error::QueryError,
+			},
+		)?;
+
+	cursor.try_collect().await.map_err(|_| Error::QueryError)
+}
+
+async fn regex_search<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	terms: &[String],
+	list_options: &ListOptions,
+) -> Result<Vec<Document>>
+where
+	MC: DbBmc,
+{
+	let mut matches = Vec::new();
+	for field in MC::TEXT_FIELDS {
+		for term in terms {
+			matches.push(doc! { *field: { "$regex": escape_regex(term), "$options": "i" } });
+		}
+	}
+	let mut filter = doc! { "$or": matches };
+	scope_filter::<MC>(
+		ctx,
+		&mut filter,
+	);
+
+	let cursor = mm
+		.client
+		.database(ctx.tenant_id().as_str())
+		.collection::<Document>(MC::TABLE)
+		.find(filter)
+		.skip(list_options.offset.unwrap_or(0))
+		.limit(list_options.limit()?)
+		.await
+		.map_err(|_| Error::QueryError)?;
+	let mut docs: Vec<Document> = cursor.try_collect().await.map_err(|_| Error::QueryError)?;
+
+	// -- Best matches first (stable, so equal scores keep the store order).
+	docs.sort_by_key(
+		|d| {
+			std::cmp::Reverse(
+				highlight_fields::<MC>(
+					d, terms,
+				)
+				.len(),
+			)
+		},
+	);
+
+	Ok(docs)
+}
+
+// endregion: --- (private) Queries
+
+// region:    --- (private) Terms & Highlighting
+
+/// Lowercased, deduplicated terms, longest first (so that highlighting prefers the longest match).
+fn search_terms(query: &str) -> Vec<String> {
+	let mut terms: Vec<String> = query
+		.split_whitespace()
+		.map(|term| term.trim_matches('"').to_lowercase())
+		.filter(|term| !term.is_empty())
+		.collect();
+	terms.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+	terms.dedup();
+	terms
+}
+
+pub(crate) fn escape_regex(term: &str) -> String {
+	let mut escaped = String::with_capacity(term.len());
+	for c in term.chars() {
+		if "\\.+*?()|[]{}^$-".contains(c) {
+			escaped.push('\\');
+		}
+		escaped.push(c);
+	}
+	escaped
+}
+
+fn highlight_fields<MC>(
+	d: &Document,
+	terms: &[String],
+) -> HashMap<String, String>
+where
+	MC: DbBmc,
+{
+	let mut highlights = HashMap::new();
+	for field in MC::TEXT_FIELDS {
+		let text = match d.get(*field) {
+			Some(Bson::String(text)) => text.clone(),
+			Some(Bson::Array(values)) => values
+				.iter()
+				.filter_map(|v| v.as_str())
+				.collect::<Vec<_>>()
+				.join(", "),
+			_ => continue,
+		};
+		if let Some(highlighted) = highlight(
+			&text, terms,
+		) {
+			highlights.insert(
+				field.to_string(),
+				highlighted,
+			);
+		}
+	}
+	highlights
+}
+
+/// Wraps the (case-insensitive) occurrences of the terms in `text`, with the text HTML-escaped,
+/// or returns `None` if no term occurs.
+fn highlight(
+	text: &str,
+	terms: &[String],
+) -> Option<String> {
+	let mut highlighted = String::with_capacity(text.len());
+	let mut found = false;
+	let mut idx = 0;
+
+	while idx < text.len() {
+		let rest = &text[idx..];
+		if let Some(len) = terms.iter().find_map(|term| match_len(rest, term)) {
+			highlighted.push_str(HIGHLIGHT_START);
+			push_html_escaped(
+				&mut highlighted,
+				&rest[..len],
+			);
+			highlighted.push_str(HIGHLIGHT_END);
+			idx += len;
+			found = true;
+		} else {
+			let Some(c) = rest.chars().next() else {
+				break;
+			};
+			push_html_escaped(
+				&mut highlighted,
+				&rest[..c.len_utf8()],
+			);
+			idx += c.len_utf8();
+		}
+	}
+
+	found.then_some(highlighted)
+}
+
+/// Appends the `text` to `out`, with the HTML special characters escaped (the stored text is untrusted).
+fn push_html_escaped(
+	out: &mut String,
+	text: &str,
+) {
+	for c in text.chars() {
+		match c {
+			'&' => out.push_str("&amp;"),
+			'<' => out.push_str("&lt;"),
+			'>' => out.push_str("&gt;"),
+			'"' => out.push_str("&quot;"),
+			'\'' => out.push_str("&#39;"),
+			c => out.push(c),
+		}
+	}
+}
+
+/// Byte length of the prefix of `text` matching `term` (case-insensitive), if any.
+fn match_len(
+	text: &str,
+	term: &str,
+) -> Option<usize> {
+	let mut text_chars = text.char_indices();
+	for term_c in term.chars() {
+		let (_, c) = text_chars.next()?;
+		if !c.to_lowercase().eq(term_c.to_lowercase()) {
+			return None;
+		}
+	}
+	Some(
+		text_chars
+			.next()
+			.map(|(idx, _)| idx)
+			.unwrap_or(text.len()),
+	)
+}
+
+// endregion: --- (private) Terms & Highlighting
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+
+	#[test]
+	fn test_search_terms_ok() -> Result<()> {
+		// -- Exec
+		let terms = search_terms("  Rust   rust \"MongoDB\" db ");
+
+		// -- Check
+		assert_eq!(
+			terms,
+			vec!["mongodb", "rust", "db"]
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_highlight_ok() -> Result<()> {
+		// -- Fixtures
+		let fx_terms = search_terms("rust mongo");
+
+		// -- Exec
+		let res = highlight(
+			"Rust with MongoDB, trusted.",
+			&fx_terms,
+		);
+
+		// -- Check
+		assert_eq!(
+			res.as_deref(),
+			Some("<em>Rust</em> with <em>Mongo</em>DB, t<em>rust</em>ed.")
+		);
+		assert_eq!(
+			highlight(
+				"Nothing here",
+				&fx_terms
+			),
+			None
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_highlight_html_escaped() -> Result<()> {
+		// -- Fixtures
+		let fx_terms = search_terms("script");
+
+		// -- Exec
+		let res = highlight(
+			"<script>alert('x & y')</script>",
+			&fx_terms,
+		);
+
+		// -- Check
+		assert_eq!(
+			res.as_deref(),
+			Some("&lt;<em>script</em>&gt;alert(&#39;x &amp; y&#39;)&lt;/<em>script</em>&gt;")
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_escape_regex_ok() -> Result<()> {
+		// -- Exec & Check
+		assert_eq!(
+			escape_regex("c++ (v1.0)"),
+			r"c\+\+ \(v1\.0\)"
+		);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
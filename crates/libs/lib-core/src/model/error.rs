@@ -1,5 +1,7 @@
+use crate::model::user::UserState;
+use crate::model::validation::ValidationError;
 use derive_more::From;
-use lib_auth::pwd;
+use lib_auth::{pwd, totp};
 use serde::Serialize;
 use serde_with::serde_as;
 
@@ -23,6 +25,9 @@ pub enum Error {
 	UserAlreadyExists {
 		username: String,
 	},
+	UserMailAlreadyExists {
+		mail: String,
+	},
 	UniqueViolation {
 		table: String,
 		constraint: String,
@@ -39,6 +44,111 @@ pub enum Error {
 	QueryError,
 	SessionError,
 	CrudSessionError(String),
+	IndexCreateFail,
+
+	// -- Search
+	SearchNoTextIndex,
+
+	// -- Relations
+	RelationNotFound {
+		entity: &'static str,
+		field: &'static str,
+		id: String,
+	},
+	RelationUnknown {
+		name: String,
+	},
+
+	// -- Refresh Tokens
+	RefreshTokenNotFound,
+	RefreshTokenExpired,
+	RefreshTokenRevoked,
+	/// An already rotated token was presented, its family has been revoked.
+	RefreshTokenReused,
+
+	// -- Sessions
+	SessionNotFound {
+		id: String,
+	},
+
+	// -- Api Keys
+	ApiKeyNotFound {
+		id: String,
+	},
+	/// Unknown key, or not matching secret.
+	ApiKeyInvalid,
+	ApiKeyExpired,
+
+	// -- Service Accounts
+	ServiceAccountNotFound {
+		id: String,
+	},
+	/// Unknown client id, or not matching secret.
+	ServiceAccountInvalidCredentials,
+
+	// -- OIDC
+	OidcProviderNotFound {
+		id: String,
+	},
+	/// Unknown, already used, or expired login `state`.
+	OidcLoginStateInvalid,
+
+	// -- OAuth2 Authorization Server
+	OAuthClientNotFound {
+		id: String,
+	},
+	/// Unknown client id, or not matching (or missing) secret.
+	OAuthClientInvalidCredentials,
+	/// Unknown, already answered, or expired consent request.
+	OAuthAuthRequestInvalid,
+	/// Unknown, already used, or expired authorization code, or unknown grant of a refresh token.
+	OAuthGrantInvalid,
+
+	// -- Users
+	UserNotFound {
+		id: String,
+	},
+	/// The user is not `Active` (see `QUserForAuth::check_active`).
+	UserNotActive {
+		id: String,
+		state: UserState,
+	},
+	/// The user has no mail to send it a password link.
+	UserMailMissing {
+		id: String,
+	},
+
+	// -- Self-Service
+	/// The current password of a password change is not matching (or the user has none).
+	UserPwdNotMatching,
+	/// The verified mail is not the pending mail of the user (see `UserBmc::confirm_mail_change`).
+	UserMailChangeNotPending,
+
+	// -- Password Reset
+	/// Unknown, already used, or expired reset token.
+	PwdResetTokenInvalid,
+
+	// -- Login Throttling
+	/// Too many failed logins of the username, or of the client ip.
+	LoginLocked {
+		retry_after_sec: i64,
+	},
+
+	// -- MFA
+	MfaAlreadyEnabled,
+	/// No (confirmed, for a verification) TOTP enrollment.
+	MfaNotEnrolled,
+	/// Not matching, or already used, TOTP or recovery code.
+	MfaCodeInvalid,
+	/// Unknown, expired, or out of attempts login challenge.
+	MfaChallengeInvalid,
+
+	// -- Attachments
+	AttachmentNotFound {
+		id: String,
+	},
+	AttachmentUploadFail,
+	AttachmentDownloadFail,
 
 	// -- ModelManager
 	CantCreateModelManagerProvider(String),
@@ -47,6 +157,10 @@ pub enum Error {
 	// -- Modules
 	#[from]
 	Pwd(pwd::Error),
+	#[from]
+	Totp(totp::Error),
+	#[from]
+	Validation(ValidationError),
 }
 
 // region:    --- Error Boilerplate
@@ -66,3 +180,7 @@ impl core::fmt::Display for Error {
 impl std::error::Error for Error {}
 
 // endregion: --- Error Boilerplate
+
+/// Returned by the `#[crud(rpc)]` generated RPC handlers.
+#[cfg(feature = "with-rpc")]
+impl rpc_router::IntoHandlerError for Error {}
@@ -1,3 +1,4 @@
+use crate::model::user::UserBmc;
 use proc_mac::crud;
 use serde::{Deserialize, Serialize};
 
@@ -5,15 +6,29 @@ use serde::{Deserialize, Serialize};
 /// - ForCreate: All fields (except "id" and those marked with #[skip_create]) as Option types.
 /// - ForUpdate: All fields (except "id" and those marked with #[skip_update]) as Option types.
 /// - Filter: All fields (except those marked with #[skip_filter]) as Option types.
-/// - A BMC struct implementing DbBmc with TABLE set to "<StructName>Bmc".
+/// - A BMC struct implementing DbBmc with TABLE set to "<StructName>Bmc" (or the `table` option).
 /// - CRUD functions in the BMC impl that convert the provided types into the main struct,
 ///   filling missing fields with None.
+/// - Fields marked with #[attachment] hold attachment ids, which must exist (and be owned by the user) on create and update.
+/// - Fields marked with #[text] are covered by the full-text `search` of the BMC.
+/// - Fields marked with #[validate(...)] are validated before create and update.
+/// - Fields marked with #[belongs_to(..)] or #[has_many(..)] reference other entities, and can be expanded.
+/// - Entity options: `#[crud(table = "..", owner, timestamps, soft_delete, rpc, rpc_suffix = "..")]`,
+///   where `rpc` generates and registers the RPC functions of the entity.
 
 #[derive(Debug, Serialize, Deserialize, Default)]
-#[crud]
+#[crud(rpc)]
 pub struct Example {
 	pub id: Option<String>,
+	#[text]
+	#[validate(required, length(min = 1, max = 128))]
 	pub name: Option<String>,
+	#[text]
+	#[validate(length(max = 4096))]
 	pub description: Option<String>,
 	pub skills: Option<Vec<String>>,
+	#[attachment]
+	pub attachments: Option<Vec<String>>,
+	#[belongs_to(User)]
+	pub user_id: Option<String>,
 }
@@ -0,0 +1,412 @@
+//! The failed logins, per username and per client ip, against the password brute force.
+//!
+//! Design:
+//!
+//! - A login is counted under the identifier as sent and, once resolved, the username of its user,
+//!   so the failures with the username and with the mail of a user add up.
+//! - The failures are counted in the root ctx database (shared between the instances, and kept on restart),
+//!   and forgotten `FAILURE_WINDOW` after the last one (TTL on `exp`).
+//! - Past the free failures, each failure locks the login for an exponential backoff
+//!   (`LOCK_BASE`, doubled on each failure, up to `LOCK_MAX`).
+//! - A locked login is rejected before the password check (no password hash spent).
+//! - A successful login resets the failures of its identifiers (not of its ip, shared by the usernames),
+//!   only once past its second factor: the wrong MFA codes are failures of the username too.
+
+use crate::ctx::Ctx;
+use crate::model::user::{normalize_identifier, UserBmc};
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::{IndexOptions, ReturnDocument};
+use mongodb::{Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const TABLE: &str = "LoginThrottles";
+
+/// The failures of a username before it is locked.
+const USERNAME_FREE_FAILURES: i32 = 5;
+/// The failures of a client ip (over all its usernames) before it is locked.
+const IP_FREE_FAILURES: i32 = 20;
+const LOCK_BASE: Duration = Duration::from_secs(30);
+const LOCK_MAX: Duration = Duration::from_secs(60 * 60);
+const FAILURE_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+// region:    --- Types
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LoginThrottleDoc {
+	/// `username:<username>` or `ip:<ip>` (see `LoginThrottleKey`).
+	#[serde(rename = "_id")]
+	id: String,
+	failures: i32,
+	locked_until: Option<DateTime>,
+	exp: DateTime,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LoginThrottleKey<'a> {
+	Username(&'a str),
+	Ip(&'a str),
+}
+
+impl LoginThrottleKey<'_> {
+	fn id(&self) -> String {
+		match self {
+			Self::Username(username) => format!("username:{username}"),
+			Self::Ip(ip) => format!("ip:{ip}"),
+		}
+	}
+
+	fn free_failures(&self) -> i32 {
+		match self {
+			Self::Username(_) => USERNAME_FREE_FAILURES,
+			Self::Ip(_) => IP_FREE_FAILURES,
+		}
+	}
+}
+
+// endregion: --- Types
+
+pub struct LoginThrottleBmc;
+
+impl LoginThrottleBmc {
+	/// Fails with `LoginLocked` if one of the identifiers (username or mail), or the client ip, is locked.
+	pub async fn check(
+		mm: &ModelManager,
+		logins: &[&str],
+		ip: Option<&str>,
+	) -> Result<()> {
+		let ids: Vec<String> = Self::keys(
+			logins, ip,
+		)
+		.iter()
+		.map(|key| key.id())
+		.collect();
+		let now = DateTime::now();
+
+		let mut cursor = Self::collection(mm)
+			.find(doc! { "_id": { "$in": ids }, "locked_until": { "$gt": now } })
+			.await
+			.map_err(|_| Error::QueryError)?;
+		let mut locked_until: Option<DateTime> = None;
+		while cursor
+			.advance()
+			.await
+			.map_err(|_| Error::QueryError)?
+		{
+			let throttle = cursor
+				.deserialize_current()
+				.map_err(|_| Error::ReadError)?;
+			locked_until = locked_until.max(throttle.locked_until);
+		}
+
+		match locked_until {
+			Some(locked_until) => Err(
+				Error::LoginLocked {
+					retry_after_sec: (locked_until.timestamp_millis() - now.timestamp_millis() + 999) / 1000,
+				},
+			),
+			None => Ok(()),
+		}
+	}
+
+	/// Counts a failed login of the identifiers from the client ip, and locks them past their free failures.
+	pub async fn record_failure(
+		mm: &ModelManager,
+		logins: &[&str],
+		ip: Option<&str>,
+	) -> Result<()> {
+		let collection = Self::collection(mm);
+		Self::ensure_indexes(&collection).await?;
+		let now = DateTime::now();
+
+		for key in Self::keys(
+			logins, ip,
+		) {
+			let throttle = collection
+				.find_one_and_update(
+					doc! { "_id": key.id() },
+					doc! {
+						"$inc": { "failures": 1 },
+						"$set": { "exp": add_duration(now, FAILURE_WINDOW) },
+					},
+				)
+				.upsert(true)
+				.return_document(ReturnDocument::After)
+				.await
+				.map_err(|_| Error::UpdateError)?
+				.ok_or(Error::UpdateError)?;
+
+			if let Some(lock) = lock_duration(
+				throttle.failures,
+				key.free_failures(),
+			) {
+				collection
+					.update_one(
+						doc! { "_id": key.id() },
+						doc! { "$set": { "locked_until": add_duration(now, lock) } },
+					)
+					.await
+					.map_err(|_| Error::UpdateError)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Resets the failures of the identifiers, on a successful login.
+	pub async fn record_success(
+		mm: &ModelManager,
+		logins: &[&str],
+	) -> Result<()> {
+		Self::unlock(
+			mm, logins,
+		)
+		.await
+	}
+
+	/// Removes the failures (and locks) of the identifiers, e.g., the username and mail of a user.
+	pub async fn unlock(
+		mm: &ModelManager,
+		logins: &[&str],
+	) -> Result<()> {
+		let ids: Vec<String> = Self::keys(
+			logins, None,
+		)
+		.iter()
+		.map(|key| key.id())
+		.collect();
+		Self::collection(mm)
+			.delete_many(doc! { "_id": { "$in": ids } })
+			.await
+			.map_err(|_| Error::DeleteError)?;
+
+		Ok(())
+	}
+
+	/// Removes the failures (and locks) of the username and mail of the user `id`
+	/// (e.g., on a password reset, or for the admins).
+	pub async fn unlock_user(
+		mm: &ModelManager,
+		id: &String,
+	) -> Result<()> {
+		let profile = UserBmc::get_profile(
+			&Ctx::root_ctx(),
+			mm,
+			id,
+		)
+		.await?;
+		let logins: Vec<String> = std::iter::once(&profile.username)
+			.chain(profile.mail.as_ref())
+			.map(|login| normalize_identifier(login))
+			.collect();
+
+		Self::unlock(
+			mm,
+			&logins
+				.iter()
+				.map(String::as_str)
+				.collect::<Vec<&str>>(),
+		)
+		.await
+	}
+}
+
+// region:    --- (private) Helpers
+
+impl LoginThrottleBmc {
+	/// The keys of the distinct identifiers, then of the client ip.
+	fn keys<'a>(
+		logins: &[&'a str],
+		ip: Option<&'a str>,
+	) -> Vec<LoginThrottleKey<'a>> {
+		let mut keys: Vec<LoginThrottleKey> = Vec::new();
+		for login in logins {
+			if !keys.iter().any(|key| matches!(key, LoginThrottleKey::Username(username) if username == login)) {
+				keys.push(LoginThrottleKey::Username(login));
+			}
+		}
+		keys.extend(ip.map(LoginThrottleKey::Ip));
+
+		keys
+	}
+
+	fn collection(mm: &ModelManager) -> Collection<LoginThrottleDoc> {
+		mm.client
+			.database(Ctx::root_ctx().tenant_id().as_str())
+			.collection(TABLE)
+	}
+
+	async fn ensure_indexes(collection: &Collection<LoginThrottleDoc>) -> Result<()> {
+		collection
+			.create_index(
+				IndexModel::builder()
+					.keys(doc! { "exp": 1 })
+					.options(
+						IndexOptions::builder()
+							.expire_after(Duration::ZERO)
+							.build(),
+					)
+					.build(),
+			)
+			.await
+			.map_err(|_| Error::IndexCreateFail)?;
+
+		Ok(())
+	}
+}
+
+/// The lock after the `failures`-th failure, if past the free failures.
+fn lock_duration(
+	failures: i32,
+	free_failures: i32,
+) -> Option<Duration> {
+	let over = failures - free_failures;
+	if over <= 0 {
+		return None;
+	}
+
+	let lock = LOCK_BASE.saturating_mul(1u32 << (over - 1).min(16));
+	Some(lock.min(LOCK_MAX))
+}
+
+fn add_duration(
+	time: DateTime,
+	duration: Duration,
+) -> DateTime {
+	DateTime::from_millis(time.timestamp_millis() + duration.as_millis() as i64)
+}
+
+// endregion: --- (private) Helpers
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use serial_test::serial;
+	use uuid::Uuid;
+
+	#[test]
+	fn test_lock_duration_backoff() -> Result<()> {
+		// -- Exec & Check
+		assert_eq!(
+			lock_duration(
+				USERNAME_FREE_FAILURES,
+				USERNAME_FREE_FAILURES
+			),
+			None
+		);
+		assert_eq!(
+			lock_duration(
+				USERNAME_FREE_FAILURES + 1,
+				USERNAME_FREE_FAILURES
+			),
+			Some(LOCK_BASE)
+		);
+		assert_eq!(
+			lock_duration(
+				USERNAME_FREE_FAILURES + 3,
+				USERNAME_FREE_FAILURES
+			),
+			Some(LOCK_BASE * 4)
+		);
+		assert_eq!(
+			lock_duration(
+				1000,
+				USERNAME_FREE_FAILURES
+			),
+			Some(LOCK_MAX)
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_login_throttle_keys_distinct() -> Result<()> {
+		// -- Exec
+		let ids: Vec<String> = LoginThrottleBmc::keys(
+			&["one@example.com", "one", "one"],
+			Some("127.0.0.1"),
+		)
+		.iter()
+		.map(|key| key.id())
+		.collect();
+
+		// -- Check
+		assert_eq!(
+			ids,
+			[
+				"username:one@example.com",
+				"username:one",
+				"ip:127.0.0.1"
+			]
+		);
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_login_throttle_lock_unlock() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let fx_username = format!(
+			"test_throttle-{}",
+			Uuid::new_v4().simple()
+		);
+
+		// -- Exec
+		for _ in 0..USERNAME_FREE_FAILURES {
+			LoginThrottleBmc::record_failure(
+				&mm,
+				&[fx_username.as_str()],
+				None,
+			)
+			.await?;
+		}
+		let res_free = LoginThrottleBmc::check(
+			&mm,
+			&[fx_username.as_str()],
+			None,
+		)
+		.await;
+		LoginThrottleBmc::record_failure(
+			&mm,
+			&[fx_username.as_str()],
+			None,
+		)
+		.await?;
+		let res_locked = LoginThrottleBmc::check(
+			&mm,
+			&[fx_username.as_str()],
+			None,
+		)
+		.await;
+		LoginThrottleBmc::unlock(
+			&mm,
+			&[fx_username.as_str()],
+		)
+		.await?;
+		let res_unlocked = LoginThrottleBmc::check(
+			&mm,
+			&[fx_username.as_str()],
+			None,
+		)
+		.await;
+
+		// -- Check
+		assert!(res_free.is_ok());
+		assert!(matches!(
+			res_locked,
+			Err(super::Error::LoginLocked { retry_after_sec }) if retry_after_sec > 0
+		));
+		assert!(res_unlocked.is_ok());
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -0,0 +1,533 @@
+//! TOTP second factor of the users (see `lib_auth::totp`), and the MFA challenges of the logins.
+//!
+//! Design:
+//!
+//! - The MFA of a user is stored in the root ctx database (like the users), keyed by the user id,
+//!   with the encrypted TOTP secret, the last accepted time step (replay protection),
+//!   and the hashed recovery codes.
+//! - Enrolling stores a new secret, only enabled once confirmed with a first code,
+//!   which returns the recovery codes (shown once, each usable once).
+//! - A login of a user with MFA enabled gets a challenge (`MfaChallengeBmc`) instead of a session:
+//!   a random token, only stored hashed, consumed by the code step, and limited in attempts and time.
+//!   The wrong codes also count as failed logins of the challenge `login` (see `LoginThrottleBmc`),
+//!   so new challenges do not give new attempts.
+
+use crate::ctx::Ctx;
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use lib_auth::token::{generate_refresh_token, hash_refresh_token};
+use lib_auth::totp::{
+	decrypt_totp_secret, encrypt_totp_secret, generate_recovery_codes, generate_totp_secret, hash_recovery_code,
+	otpauth_uri, secret_b32, verify_totp,
+};
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::{IndexOptions, ReturnDocument};
+use mongodb::{Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const TABLE: &str = "UserMfa";
+const CHALLENGE_TABLE: &str = "MfaChallenges";
+
+/// The time for the user to enter the code, after the password.
+const CHALLENGE_DURATION: Duration = Duration::from_secs(5 * 60);
+/// The codes tried on a challenge, before it is dropped (a new login is needed).
+const CHALLENGE_MAX_ATTEMPTS: i32 = 5;
+
+// region:    --- Types
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UserMfaDoc {
+	/// The user id.
+	#[serde(rename = "_id")]
+	id: String,
+	totp_secret_enc: String,
+	/// False until the enrollment is confirmed with a first code.
+	enabled: bool,
+	/// The last accepted TOTP time step.
+	last_step: Option<i64>,
+	recovery_code_hashes: Vec<String>,
+	ctime: DateTime,
+}
+
+/// A new TOTP enrollment, to register in the authenticator app.
+#[derive(Debug, Serialize)]
+pub struct TotpEnrollment {
+	/// Base32, for a manual entry.
+	pub secret: String,
+	pub otpauth_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpConfirm {
+	/// The first code of the authenticator app.
+	pub code: String,
+}
+
+/// The recovery codes of a confirmed enrollment, shown only once.
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodes {
+	pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MfaChallengeDoc {
+	/// The challenge token hash.
+	#[serde(rename = "_id")]
+	id: String,
+	user_id: String,
+	login: String,
+	device_id: Option<String>,
+	attempts: i32,
+	exp: DateTime,
+}
+
+/// The pending login of a challenge.
+#[derive(Debug)]
+pub struct MfaChallenge {
+	pub user_id: String,
+	/// The username (or mail) of the password step, the key of its login throttle.
+	pub login: String,
+	pub device_id: Option<String>,
+}
+
+// endregion: --- Types
+
+// region:    --- MfaBmc
+
+pub struct MfaBmc;
+
+impl MfaBmc {
+	/// Starts (or restarts) the TOTP enrollment of the user.
+	pub async fn enroll(
+		mm: &ModelManager,
+		user_id: &str,
+		account: &str,
+	) -> Result<TotpEnrollment> {
+		let collection = Self::collection(mm);
+		if Self::is_enabled(
+			mm, user_id,
+		)
+		.await?
+		{
+			return Err(Error::MfaAlreadyEnabled);
+		}
+
+		let secret = generate_totp_secret();
+		let mfa = UserMfaDoc {
+			id: user_id.to_string(),
+			totp_secret_enc: encrypt_totp_secret(&secret)?,
+			enabled: false,
+			last_step: None,
+			recovery_code_hashes: Vec::new(),
+			ctime: DateTime::now(),
+		};
+		collection
+			.replace_one(
+				doc! { "_id": user_id, "enabled": false },
+				&mfa,
+			)
+			.upsert(true)
+			.await
+			.map_err(|_| Error::UpdateError)?;
+
+		Ok(
+			TotpEnrollment {
+				secret: secret_b32(&secret),
+				otpauth_uri: otpauth_uri(
+					account, &secret,
+				),
+			},
+		)
+	}
+
+	/// Confirms the enrollment with a first code, enables the MFA, and returns the recovery codes.
+	pub async fn confirm(
+		mm: &ModelManager,
+		user_id: &str,
+		code: &str,
+	) -> Result<RecoveryCodes> {
+		let collection = Self::collection(mm);
+		let mfa = collection
+			.find_one(doc! { "_id": user_id })
+			.await
+			.map_err(|_| Error::QueryError)?
+			.ok_or(Error::MfaNotEnrolled)?;
+		if mfa.enabled {
+			return Err(Error::MfaAlreadyEnabled);
+		}
+
+		let secret = decrypt_totp_secret(&mfa.totp_secret_enc)?;
+		let step = verify_totp(
+			&secret,
+			code,
+			DateTime::now().timestamp_millis() / 1000,
+			None,
+		)
+		.ok_or(Error::MfaCodeInvalid)?;
+
+		let recovery_codes = generate_recovery_codes();
+		let recovery_code_hashes: Vec<String> = recovery_codes
+			.iter()
+			.map(|code| hash_recovery_code(code))
+			.collect();
+		collection
+			.update_one(
+				doc! { "_id": user_id, "enabled": false },
+				doc! { "$set": {
+					"enabled": true,
+					"last_step": step,
+					"recovery_code_hashes": recovery_code_hashes,
+				} },
+			)
+			.await
+			.map_err(|_| Error::UpdateError)?;
+
+		Ok(RecoveryCodes { recovery_codes })
+	}
+
+	pub async fn is_enabled(
+		mm: &ModelManager,
+		user_id: &str,
+	) -> Result<bool> {
+		let mfa = Self::collection(mm)
+			.find_one(doc! { "_id": user_id, "enabled": true })
+			.await
+			.map_err(|_| Error::QueryError)?;
+
+		Ok(mfa.is_some())
+	}
+
+	/// Verifies a TOTP code, or else a recovery code (consumed).
+	pub async fn verify(
+		mm: &ModelManager,
+		user_id: &str,
+		code: &str,
+	) -> Result<()> {
+		let collection = Self::collection(mm);
+		let mfa = collection
+			.find_one(doc! { "_id": user_id, "enabled": true })
+			.await
+			.map_err(|_| Error::QueryError)?
+			.ok_or(Error::MfaNotEnrolled)?;
+
+		// -- TOTP code.
+		// Note: The step is only accepted if still after the last one (concurrent logins).
+		let secret = decrypt_totp_secret(&mfa.totp_secret_enc)?;
+		if let Some(step) = verify_totp(
+			&secret,
+			code,
+			DateTime::now().timestamp_millis() / 1000,
+			mfa.last_step,
+		) {
+			let res = collection
+				.update_one(
+					doc! {
+						"_id": user_id,
+						"$or": [{ "last_step": null }, { "last_step": { "$lt": step } }],
+					},
+					doc! { "$set": { "last_step": step } },
+				)
+				.await
+				.map_err(|_| Error::UpdateError)?;
+			if res.modified_count == 1 {
+				return Ok(());
+			}
+		}
+
+		// -- Recovery code.
+		let res = collection
+			.update_one(
+				doc! { "_id": user_id, "recovery_code_hashes": hash_recovery_code(code) },
+				doc! { "$pull": { "recovery_code_hashes": hash_recovery_code(code) } },
+			)
+			.await
+			.map_err(|_| Error::UpdateError)?;
+		if res.modified_count == 1 {
+			return Ok(());
+		}
+
+		Err(Error::MfaCodeInvalid)
+	}
+
+	/// Removes the MFA of the user (e.g., lost device and recovery codes), for the admins.
+	pub async fn reset(
+		mm: &ModelManager,
+		user_id: &str,
+	) -> Result<()> {
+		let res = Self::collection(mm)
+			.delete_one(doc! { "_id": user_id })
+			.await
+			.map_err(|_| Error::DeleteError)?;
+		if res.deleted_count == 0 {
+			return Err(Error::MfaNotEnrolled);
+		}
+
+		Ok(())
+	}
+}
+
+// endregion: --- MfaBmc
+
+// region:    --- MfaChallengeBmc
+
+pub struct MfaChallengeBmc;
+
+impl MfaChallengeBmc {
+	/// Starts the challenge of a login, and returns its token (clear, to be sent to the client only).
+	pub async fn create(
+		mm: &ModelManager,
+		user_id: &str,
+		login: &str,
+		device_id: Option<String>,
+	) -> Result<String> {
+		let collection = Self::collection(mm);
+		Self::ensure_indexes(&collection).await?;
+
+		let token = generate_refresh_token();
+		collection
+			.insert_one(
+				MfaChallengeDoc {
+					id: hash_refresh_token(&token),
+					user_id: user_id.to_string(),
+					login: login.to_string(),
+					device_id,
+					attempts: 0,
+					exp: DateTime::from_millis(
+						DateTime::now().timestamp_millis() + CHALLENGE_DURATION.as_millis() as i64,
+					),
+				},
+			)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		Ok(token)
+	}
+
+	/// Counts a code attempt on the challenge, and returns its pending login.
+	pub async fn attempt(
+		mm: &ModelManager,
+		token: &str,
+	) -> Result<MfaChallenge> {
+		let challenge = Self::collection(mm)
+			.find_one_and_update(
+				doc! {
+					"_id": hash_refresh_token(token),
+					"attempts": { "$lt": CHALLENGE_MAX_ATTEMPTS },
+					"exp": { "$gt": DateTime::now() },
+				},
+				doc! { "$inc": { "attempts": 1 } },
+			)
+			.return_document(ReturnDocument::After)
+			.await
+			.map_err(|_| Error::UpdateError)?
+			.ok_or(Error::MfaChallengeInvalid)?;
+
+		Ok(
+			MfaChallenge {
+				user_id: challenge.user_id,
+				login: challenge.login,
+				device_id: challenge.device_id,
+			},
+		)
+	}
+
+	/// Consumes the challenge once its code is verified (a challenge only starts one session).
+	pub async fn consume(
+		mm: &ModelManager,
+		token: &str,
+	) -> Result<()> {
+		Self::collection(mm)
+			.find_one_and_delete(doc! { "_id": hash_refresh_token(token) })
+			.await
+			.map_err(|_| Error::DeleteError)?
+			.ok_or(Error::MfaChallengeInvalid)?;
+
+		Ok(())
+	}
+}
+
+// endregion: --- MfaChallengeBmc
+
+// region:    --- (private) Helpers
+
+impl MfaBmc {
+	fn collection(mm: &ModelManager) -> Collection<UserMfaDoc> {
+		root_collection(
+			mm, TABLE,
+		)
+	}
+}
+
+impl MfaChallengeBmc {
+	fn collection(mm: &ModelManager) -> Collection<MfaChallengeDoc> {
+		root_collection(
+			mm,
+			CHALLENGE_TABLE,
+		)
+	}
+
+	async fn ensure_indexes(collection: &Collection<MfaChallengeDoc>) -> Result<()> {
+		collection
+			.create_index(
+				IndexModel::builder()
+					.keys(doc! { "exp": 1 })
+					.options(
+						IndexOptions::builder()
+							.expire_after(Duration::ZERO)
+							.build(),
+					)
+					.build(),
+			)
+			.await
+			.map_err(|_| Error::IndexCreateFail)?;
+
+		Ok(())
+	}
+}
+
+fn root_collection<T: Send + Sync>(
+	mm: &ModelManager,
+	table: &str,
+) -> Collection<T> {
+	mm.client
+		.database(Ctx::root_ctx().tenant_id().as_str())
+		.collection(table)
+}
+
+// endregion: --- (private) Helpers
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use data_encoding::BASE32_NOPAD;
+	use lib_auth::totp::totp_code;
+	use serial_test::serial;
+	use uuid::Uuid;
+
+	fn fx_current_code(secret_b32: &str) -> Result<String> {
+		let secret = BASE32_NOPAD.decode(secret_b32.as_bytes())?;
+		Ok(
+			totp_code(
+				&secret,
+				DateTime::now().timestamp_millis() / 1000 / 30,
+			),
+		)
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_mfa_enroll_confirm_verify() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let fx_user_id = Uuid::new_v4().simple().to_string();
+
+		// -- Exec
+		let enrollment = MfaBmc::enroll(
+			&mm,
+			&fx_user_id,
+			"demo1",
+		)
+		.await?;
+		let enabled_before = MfaBmc::is_enabled(
+			&mm,
+			&fx_user_id,
+		)
+		.await?;
+		let fx_code = fx_current_code(&enrollment.secret)?;
+		let recovery = MfaBmc::confirm(
+			&mm,
+			&fx_user_id,
+			&fx_code,
+		)
+		.await?;
+		// The confirmation code is already used.
+		let res_replay = MfaBmc::verify(
+			&mm,
+			&fx_user_id,
+			&fx_code,
+		)
+		.await;
+		let res_recovery = MfaBmc::verify(
+			&mm,
+			&fx_user_id,
+			&recovery.recovery_codes[0],
+		)
+		.await;
+		let res_recovery_again = MfaBmc::verify(
+			&mm,
+			&fx_user_id,
+			&recovery.recovery_codes[0],
+		)
+		.await;
+
+		// -- Check
+		assert!(!enabled_before);
+		assert!(enrollment
+			.otpauth_uri
+			.starts_with("otpauth://totp/"));
+		assert!(MfaBmc::is_enabled(
+			&mm,
+			&fx_user_id
+		)
+		.await?);
+		assert!(matches!(
+			res_replay,
+			Err(super::Error::MfaCodeInvalid)
+		));
+		assert!(res_recovery.is_ok());
+		assert!(matches!(
+			res_recovery_again,
+			Err(super::Error::MfaCodeInvalid)
+		));
+
+		// -- Clean
+		MfaBmc::reset(
+			&mm,
+			&fx_user_id,
+		)
+		.await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_mfa_challenge_attempts() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let token = MfaChallengeBmc::create(
+			&mm, "user-01", "demo1", None,
+		)
+		.await?;
+
+		// -- Exec
+		for _ in 0..CHALLENGE_MAX_ATTEMPTS {
+			let challenge = MfaChallengeBmc::attempt(
+				&mm, &token,
+			)
+			.await?;
+			assert_eq!(
+				challenge.login,
+				"demo1"
+			);
+		}
+		let res_over = MfaChallengeBmc::attempt(
+			&mm, &token,
+		)
+		.await;
+
+		// -- Check
+		assert!(matches!(
+			res_over,
+			Err(super::Error::MfaChallengeInvalid)
+		));
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
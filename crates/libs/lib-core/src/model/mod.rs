@@ -24,15 +24,31 @@ mod base;
 mod error;
 mod store;
 
+pub mod api_key;
+pub mod attachment;
 pub mod example;
+pub mod login_throttle;
+pub mod mfa;
+pub mod oauth;
+pub mod oidc;
+pub mod pwd_reset;
+pub mod refresh_token;
+pub mod service_account;
+pub mod session;
+pub mod token_revocation;
 pub mod user;
+pub mod validation;
 
+pub use self::base::{Expanded, ListOptions, Relation, RelationKind, SearchHit};
 pub use self::error::{Error, Result};
 use mongodb::{Client, ClientSession};
 use std::cell::RefCell;
 use std::rc::Rc;
 
 use crate::model::store::new_client;
+use crate::model::session::LastSeenCache;
+use crate::model::token_revocation::RevocationCache;
+use std::sync::Arc;
 
 // endregion: --- Modules
 
@@ -42,6 +58,8 @@ use crate::model::store::new_client;
 #[derive(Debug, Clone)]
 pub struct ModelManager {
 	client: Client,
+	revocations: Arc<RevocationCache>,
+	last_seen: Arc<LastSeenCache>,
 	// session: Rc<RefCell<Option<ClientSession>>>, // Have to think about sessions, clone is needed
 }
 
@@ -55,6 +73,8 @@ impl ModelManager {
 		Ok(
 			ModelManager {
 				client,
+				revocations: Arc::default(),
+				last_seen: Arc::default(),
 				// session: Rc::new(RefCell::new(None)),
 			},
 		)
@@ -0,0 +1,1019 @@
+//! The client apps of the OAuth2 authorization server, and the state of their authorizations.
+//!
+//! Design:
+//!
+//! - A client app belongs to a tenant (managed by its admins), and is stored in the root ctx database
+//!   with its `tenant_id` (like the service accounts), since the endpoints only know the `client_id`.
+//! - A confidential client authenticates with its `client_secret` (a `pwd` hash, see
+//!   `lib_auth::client_credentials`), a public client (SPA, mobile) has none and must use PKCE.
+//! - The redirect uris are registered, and matched exactly (no prefix or wildcard).
+//! - A scope is the name of a privilege (role): the client is registered with the scopes it may request,
+//!   and its tokens only carry the privileges of the user within the granted scopes.
+//!   The account management (profile, credentials) is refused to these tokens, unless granted
+//!   the `SCOPE_PROFILE` or `SCOPE_CREDENTIALS` scope (see `lib_rpc_core::require_account_access`).
+//! - A pending consent (`OAuthAuthRequestBmc`) and an authorization code (`OAuthCodeBmc`) are consumed once,
+//!   or removed by a TTL index on `exp`. The codes are only stored hashed.
+//! - The consents are remembered per user and client (`OAuthConsentBmc`), the first-party clients
+//!   never ask for it.
+//! - The refresh tokens of a client are a token family of the `oauth:<client_id>` device
+//!   (see `RefreshTokenBmc`), with the granted scopes kept by family (`OAuthGrantBmc`).
+
+use crate::ctx::Ctx;
+use crate::model::validation::{check_length, check_url, Validate, ValidationError, ValidationResult};
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use lib_auth::client_credentials::{client_secret_to_hash, generate_app_client_id, generate_client_secret};
+use lib_auth::oauth::{generate_authorization_code, hash_authorization_code};
+use lib_auth::pwd::{self, SchemeStatus};
+use lib_auth::token::refresh_token_duration_sec;
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+const CLIENT_TABLE: &str = "OAuthClients";
+const AUTH_REQUEST_TABLE: &str = "OAuthAuthRequests";
+const CODE_TABLE: &str = "OAuthCodes";
+const CONSENT_TABLE: &str = "OAuthConsents";
+const GRANT_TABLE: &str = "OAuthGrants";
+
+/// The time for the user to answer the consent page.
+const AUTH_REQUEST_DURATION: Duration = Duration::from_secs(10 * 60);
+/// The time for the client to exchange the code (RFC 6749 recommends 10 minutes at most).
+const CODE_DURATION: Duration = Duration::from_secs(60);
+
+/// The device prefix of the refresh token families of the clients.
+pub const OAUTH_DEVICE_PREFIX: &str = "oauth:";
+
+/// The refresh token device of a client (see `RefreshTokenBmc`).
+pub fn oauth_device_id(client_id: &str) -> String {
+	format!("{OAUTH_DEVICE_PREFIX}{client_id}")
+}
+
+// region:    --- Types
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OAuthClientDoc {
+	/// The `client_id`.
+	#[serde(rename = "_id")]
+	id: String,
+	tenant_id: String,
+	name: String,
+	/// None for a public client.
+	secret_hash: Option<String>,
+	redirect_uris: Vec<String>,
+	scopes: Vec<String>,
+	first_party: bool,
+	ctime: DateTime,
+}
+
+/// A client app, as listed to the admins (without its secret).
+#[derive(Debug, Clone, Serialize)]
+pub struct OAuthClient {
+	pub client_id: String,
+	pub name: String,
+	pub public: bool,
+	pub redirect_uris: Vec<String>,
+	pub scopes: Vec<String>,
+	pub first_party: bool,
+	pub created: String,
+}
+
+/// A client app with its `client_secret` (confidential clients only), shown only once on create.
+#[derive(Debug, Serialize)]
+pub struct OAuthClientWithSecret {
+	#[serde(flatten)]
+	pub client: OAuthClient,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OAuthClientForCreate {
+	pub name: String,
+	/// A public client has no secret, and must use PKCE.
+	#[serde(default)]
+	pub public: bool,
+	pub redirect_uris: Vec<String>,
+	/// The scopes (privilege names) the client may request.
+	#[serde(default)]
+	pub scopes: Vec<String>,
+	/// An app of the gateway owner, authorized without the consent page.
+	#[serde(default)]
+	pub first_party: bool,
+}
+
+/// A client app, for the authorization and token endpoints.
+#[derive(Debug)]
+pub struct OAuthClientForAuth {
+	pub client_id: String,
+	pub tenant_id: String,
+	pub name: String,
+	pub public: bool,
+	pub redirect_uris: Vec<String>,
+	pub scopes: Vec<String>,
+	pub first_party: bool,
+}
+
+/// What a user authorizes a client to, from the authorization request to the tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthAuthorization {
+	pub user_id: String,
+	pub client_id: String,
+	pub redirect_uri: String,
+	pub scope: Vec<String>,
+	/// The `S256` PKCE challenge, if any.
+	pub code_challenge: Option<String>,
+}
+
+/// An authorization request waiting for the consent of the user.
+#[derive(Debug)]
+pub struct OAuthAuthRequest {
+	pub authorization: OAuthAuthorization,
+	/// The client `state`, returned as is with the code.
+	pub state: Option<String>,
+}
+
+/// The grant of a refresh token family.
+#[derive(Debug)]
+pub struct OAuthGrant {
+	pub client_id: String,
+	pub user_id: String,
+	pub scope: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OAuthAuthRequestDoc {
+	/// Simple uuid, the `request_id` of the consent form.
+	#[serde(rename = "_id")]
+	id: String,
+	authorization: OAuthAuthorization,
+	state: Option<String>,
+	exp: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OAuthCodeDoc {
+	/// The code hash.
+	#[serde(rename = "_id")]
+	id: String,
+	authorization: OAuthAuthorization,
+	exp: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OAuthConsentDoc {
+	/// `{user_id}|{client_id}`.
+	#[serde(rename = "_id")]
+	id: String,
+	user_id: String,
+	client_id: String,
+	scope: Vec<String>,
+	mtime: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OAuthGrantDoc {
+	/// The refresh token family id.
+	#[serde(rename = "_id")]
+	id: String,
+	client_id: String,
+	user_id: String,
+	scope: Vec<String>,
+	exp: DateTime,
+}
+
+impl Validate for OAuthClientForCreate {
+	fn validate(&self) -> ValidationResult {
+		let mut errors = ValidationError::default();
+		check_length(
+			&mut errors,
+			"name",
+			&self.name,
+			Some(1),
+			Some(100),
+		);
+		check_length(
+			&mut errors,
+			"redirect_uris",
+			&self.redirect_uris,
+			Some(1),
+			Some(10),
+		);
+		for redirect_uri in &self.redirect_uris {
+			check_url(
+				&mut errors,
+				"redirect_uris",
+				redirect_uri,
+			);
+			// Note: RFC 6749 (section 3.1.2), the redirect uri must not have a fragment.
+			if redirect_uri.contains('#') {
+				errors.add(
+					"redirect_uris",
+					"fragment",
+					"must not have a fragment",
+				);
+			}
+		}
+		check_length(
+			&mut errors,
+			"scopes",
+			&self.scopes,
+			None,
+			Some(50),
+		);
+		errors.into_result()
+	}
+}
+
+impl OAuthClient {
+	fn from_doc(doc: OAuthClientDoc) -> Result<Self> {
+		Ok(
+			OAuthClient {
+				created: doc
+					.ctime
+					.try_to_rfc3339_string()
+					.map_err(|_| Error::CrudDocumentError)?,
+				client_id: doc.id,
+				name: doc.name,
+				public: doc.secret_hash.is_none(),
+				redirect_uris: doc.redirect_uris,
+				scopes: doc.scopes,
+				first_party: doc.first_party,
+			},
+		)
+	}
+}
+
+impl From<OAuthClientDoc> for OAuthClientForAuth {
+	fn from(doc: OAuthClientDoc) -> Self {
+		OAuthClientForAuth {
+			client_id: doc.id,
+			tenant_id: doc.tenant_id,
+			name: doc.name,
+			public: doc.secret_hash.is_none(),
+			redirect_uris: doc.redirect_uris,
+			scopes: doc.scopes,
+			first_party: doc.first_party,
+		}
+	}
+}
+
+impl OAuthClientForAuth {
+	/// Note: Exact match, as registered.
+	pub fn has_redirect_uri(
+		&self,
+		redirect_uri: &str,
+	) -> bool {
+		self.redirect_uris
+			.iter()
+			.any(|uri| uri == redirect_uri)
+	}
+
+	/// The scopes to grant for the requested ones (all the client scopes if none),
+	/// or None if one is not a scope of the client.
+	pub fn resolve_scope(
+		&self,
+		requested: &[String],
+	) -> Option<Vec<String>> {
+		if requested.is_empty() {
+			return Some(self.scopes.clone());
+		}
+
+		requested
+			.iter()
+			.all(|scope| self.scopes.contains(scope))
+			.then(|| requested.to_vec())
+	}
+}
+
+// endregion: --- Types
+
+// region:    --- OAuthClientBmc
+
+pub struct OAuthClientBmc;
+
+impl OAuthClientBmc {
+	/// Creates a client app in the ctx tenant.
+	pub async fn create(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		client_c: OAuthClientForCreate,
+	) -> Result<OAuthClientWithSecret> {
+		client_c.validate()?;
+
+		let collection = Self::collection(mm);
+		Self::ensure_indexes(&collection).await?;
+
+		let client_id = generate_app_client_id();
+		let client_secret = (!client_c.public).then(generate_client_secret);
+		let secret_hash = match &client_secret {
+			Some(client_secret) => {
+				Some(
+					Self::hash_secret(
+						&client_id,
+						client_secret,
+					)
+					.await?,
+				)
+			},
+			None => None,
+		};
+
+		let client = OAuthClientDoc {
+			id: client_id,
+			tenant_id: ctx.tenant_id(),
+			name: client_c.name,
+			secret_hash,
+			redirect_uris: client_c.redirect_uris,
+			scopes: client_c.scopes,
+			first_party: client_c.first_party,
+			ctime: DateTime::now(),
+		};
+		collection
+			.insert_one(&client)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		Ok(
+			OAuthClientWithSecret {
+				client: OAuthClient::from_doc(client)?,
+				client_secret,
+			},
+		)
+	}
+
+	/// The client apps of the ctx tenant.
+	pub async fn list(
+		ctx: &Ctx,
+		mm: &ModelManager,
+	) -> Result<Vec<OAuthClient>> {
+		let mut cursor = Self::collection(mm)
+			.find(doc! { "tenant_id": ctx.tenant_id() })
+			.sort(doc! { "ctime": -1 })
+			.await
+			.map_err(|_| Error::QueryError)?;
+
+		let mut clients = Vec::new();
+		while cursor
+			.advance()
+			.await
+			.map_err(|_| Error::QueryError)?
+		{
+			let client = cursor
+				.deserialize_current()
+				.map_err(|_| Error::CrudDocumentError)?;
+			clients.push(OAuthClient::from_doc(client)?);
+		}
+
+		Ok(clients)
+	}
+
+	/// Deletes the client app and its consents.
+	///
+	/// Note: Its refresh tokens can no longer be used (the client cannot authenticate),
+	///       its access tokens stay valid until they expire.
+	pub async fn delete(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		client_id: &str,
+	) -> Result<OAuthClient> {
+		let client = Self::collection(mm)
+			.find_one_and_delete(doc! { "_id": client_id, "tenant_id": ctx.tenant_id() })
+			.await
+			.map_err(|_| Error::DeleteError)?
+			.ok_or_else(|| Error::OAuthClientNotFound { id: client_id.to_string() })?;
+
+		OAuthConsentBmc::collection(mm)
+			.delete_many(doc! { "client_id": client_id })
+			.await
+			.map_err(|_| Error::DeleteError)?;
+
+		OAuthClient::from_doc(client)
+	}
+
+	/// The client app of an authorization request (of any tenant).
+	pub async fn get_for_auth(
+		mm: &ModelManager,
+		client_id: &str,
+	) -> Result<OAuthClientForAuth> {
+		let client = Self::collection(mm)
+			.find_one(doc! { "_id": client_id })
+			.await
+			.map_err(|_| Error::QueryError)?
+			.ok_or_else(|| Error::OAuthClientNotFound { id: client_id.to_string() })?;
+
+		Ok(client.into())
+	}
+
+	/// Authenticates the client of a token request: with its secret if confidential,
+	/// without any if public.
+	pub async fn authenticate(
+		mm: &ModelManager,
+		client_id: &str,
+		client_secret: Option<&str>,
+	) -> Result<OAuthClientForAuth> {
+		let collection = Self::collection(mm);
+		let client = collection
+			.find_one(doc! { "_id": client_id })
+			.await
+			.map_err(|_| Error::QueryError)?
+			.ok_or(Error::OAuthClientInvalidCredentials)?;
+
+		let (secret_hash, client_secret) = match (&client.secret_hash, client_secret) {
+			(None, None) => return Ok(client.into()),
+			(Some(secret_hash), Some(client_secret)) => (secret_hash.clone(), client_secret),
+			_ => return Err(Error::OAuthClientInvalidCredentials),
+		};
+		let to_hash = client_secret_to_hash(
+			client_id,
+			client_secret,
+		)
+		.ok_or(Error::OAuthClientInvalidCredentials)?;
+		let scheme_status = pwd::validate_pwd(
+			to_hash,
+			secret_hash,
+		)
+		.await
+		.map_err(|_| Error::OAuthClientInvalidCredentials)?;
+
+		// -- Re-hash with the current scheme if needed.
+		if let SchemeStatus::Outdated = scheme_status {
+			let secret_hash = Self::hash_secret(
+				client_id,
+				client_secret,
+			)
+			.await?;
+			collection
+				.update_one(
+					doc! { "_id": client_id },
+					doc! { "$set": { "secret_hash": secret_hash } },
+				)
+				.await
+				.map_err(|_| Error::UpdateError)?;
+		}
+
+		Ok(client.into())
+	}
+}
+
+// endregion: --- OAuthClientBmc
+
+// region:    --- OAuthAuthRequestBmc
+
+pub struct OAuthAuthRequestBmc;
+
+impl OAuthAuthRequestBmc {
+	/// Records the request until the consent of the user, and returns its id.
+	pub async fn create(
+		mm: &ModelManager,
+		auth_request: OAuthAuthRequest,
+	) -> Result<String> {
+		let collection = Self::collection(mm);
+		ensure_exp_index(&collection).await?;
+
+		let id = Uuid::new_v4().simple().to_string();
+		collection
+			.insert_one(
+				OAuthAuthRequestDoc {
+					id: id.clone(),
+					authorization: auth_request.authorization,
+					state: auth_request.state,
+					exp: exp_in(AUTH_REQUEST_DURATION),
+				},
+			)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		Ok(id)
+	}
+
+	/// Consumes the pending request of the user (a consent is only answered once).
+	pub async fn take(
+		mm: &ModelManager,
+		id: &str,
+		user_id: &str,
+	) -> Result<OAuthAuthRequest> {
+		let auth_request = Self::collection(mm)
+			.find_one_and_delete(doc! { "_id": id, "authorization.user_id": user_id })
+			.await
+			.map_err(|_| Error::DeleteError)?
+			.ok_or(Error::OAuthAuthRequestInvalid)?;
+
+		// Note: The TTL index removes the expired requests, but not right away.
+		if auth_request.exp < DateTime::now() {
+			return Err(Error::OAuthAuthRequestInvalid);
+		}
+
+		Ok(
+			OAuthAuthRequest {
+				authorization: auth_request.authorization,
+				state: auth_request.state,
+			},
+		)
+	}
+}
+
+// endregion: --- OAuthAuthRequestBmc
+
+// region:    --- OAuthCodeBmc
+
+pub struct OAuthCodeBmc;
+
+impl OAuthCodeBmc {
+	/// Issues an authorization code for the authorization, and returns it (clear).
+	pub async fn create(
+		mm: &ModelManager,
+		authorization: OAuthAuthorization,
+	) -> Result<String> {
+		let collection = Self::collection(mm);
+		ensure_exp_index(&collection).await?;
+
+		let code = generate_authorization_code();
+		collection
+			.insert_one(
+				OAuthCodeDoc {
+					id: hash_authorization_code(&code),
+					authorization,
+					exp: exp_in(CODE_DURATION),
+				},
+			)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		Ok(code)
+	}
+
+	/// Consumes the code (a code is only exchanged once).
+	pub async fn take(
+		mm: &ModelManager,
+		code: &str,
+	) -> Result<OAuthAuthorization> {
+		let code = Self::collection(mm)
+			.find_one_and_delete(doc! { "_id": hash_authorization_code(code) })
+			.await
+			.map_err(|_| Error::DeleteError)?
+			.ok_or(Error::OAuthGrantInvalid)?;
+
+		if code.exp < DateTime::now() {
+			return Err(Error::OAuthGrantInvalid);
+		}
+
+		Ok(code.authorization)
+	}
+}
+
+// endregion: --- OAuthCodeBmc
+
+// region:    --- OAuthConsentBmc
+
+pub struct OAuthConsentBmc;
+
+impl OAuthConsentBmc {
+	/// True if the user already consented to all the scopes for the client.
+	pub async fn covers(
+		mm: &ModelManager,
+		user_id: &str,
+		client_id: &str,
+		scope: &[String],
+	) -> Result<bool> {
+		let consent = Self::collection(mm)
+			.find_one(doc! { "_id": consent_id(user_id, client_id) })
+			.await
+			.map_err(|_| Error::QueryError)?;
+
+		Ok(consent.is_some_and(|consent| scope.iter().all(|s| consent.scope.contains(s))))
+	}
+
+	/// Adds the scopes to the consent of the user for the client.
+	pub async fn grant(
+		mm: &ModelManager,
+		user_id: &str,
+		client_id: &str,
+		scope: &[String],
+	) -> Result<()> {
+		Self::collection(mm)
+			.update_one(
+				doc! { "_id": consent_id(user_id, client_id) },
+				doc! {
+					"$set": { "user_id": user_id, "client_id": client_id, "mtime": DateTime::now() },
+					"$addToSet": { "scope": { "$each": scope } },
+				},
+			)
+			.upsert(true)
+			.await
+			.map_err(|_| Error::UpdateError)?;
+
+		Ok(())
+	}
+}
+
+// endregion: --- OAuthConsentBmc
+
+// region:    --- OAuthGrantBmc
+
+pub struct OAuthGrantBmc;
+
+impl OAuthGrantBmc {
+	/// Records the grant of a new refresh token family of the client.
+	pub async fn create(
+		mm: &ModelManager,
+		family_id: &str,
+		authorization: &OAuthAuthorization,
+	) -> Result<()> {
+		let collection = Self::collection(mm);
+		ensure_exp_index(&collection).await?;
+
+		collection
+			.insert_one(
+				OAuthGrantDoc {
+					id: family_id.to_string(),
+					client_id: authorization
+						.client_id
+						.clone(),
+					user_id: authorization.user_id.clone(),
+					scope: authorization.scope.clone(),
+					exp: exp_in(Duration::from_secs_f64(refresh_token_duration_sec())),
+				},
+			)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		Ok(())
+	}
+
+	/// The grant of a refreshed token family, its expiration extended like its refresh token.
+	pub async fn refresh(
+		mm: &ModelManager,
+		family_id: &str,
+	) -> Result<OAuthGrant> {
+		let grant = Self::collection(mm)
+			.find_one_and_update(
+				doc! { "_id": family_id },
+				doc! { "$set": { "exp": exp_in(Duration::from_secs_f64(refresh_token_duration_sec())) } },
+			)
+			.await
+			.map_err(|_| Error::UpdateError)?
+			.ok_or(Error::OAuthGrantInvalid)?;
+
+		Ok(
+			OAuthGrant {
+				client_id: grant.client_id,
+				user_id: grant.user_id,
+				scope: grant.scope,
+			},
+		)
+	}
+}
+
+// endregion: --- OAuthGrantBmc
+
+// region:    --- (private) Helpers
+
+impl OAuthClientBmc {
+	fn collection(mm: &ModelManager) -> Collection<OAuthClientDoc> {
+		root_collection(
+			mm,
+			CLIENT_TABLE,
+		)
+	}
+
+	async fn hash_secret(
+		client_id: &str,
+		client_secret: &str,
+	) -> Result<String> {
+		let to_hash = client_secret_to_hash(
+			client_id,
+			client_secret,
+		)
+		.ok_or(Error::OAuthClientInvalidCredentials)?;
+
+		Ok(pwd::hash_pwd(to_hash).await?)
+	}
+
+	async fn ensure_indexes(collection: &Collection<OAuthClientDoc>) -> Result<()> {
+		collection
+			.create_index(
+				IndexModel::builder()
+					.keys(doc! { "tenant_id": 1 })
+					.build(),
+			)
+			.await
+			.map_err(|_| Error::IndexCreateFail)?;
+
+		Ok(())
+	}
+}
+
+impl OAuthAuthRequestBmc {
+	fn collection(mm: &ModelManager) -> Collection<OAuthAuthRequestDoc> {
+		root_collection(
+			mm,
+			AUTH_REQUEST_TABLE,
+		)
+	}
+}
+
+impl OAuthCodeBmc {
+	fn collection(mm: &ModelManager) -> Collection<OAuthCodeDoc> {
+		root_collection(
+			mm, CODE_TABLE,
+		)
+	}
+}
+
+impl OAuthConsentBmc {
+	fn collection(mm: &ModelManager) -> Collection<OAuthConsentDoc> {
+		root_collection(
+			mm,
+			CONSENT_TABLE,
+		)
+	}
+}
+
+impl OAuthGrantBmc {
+	fn collection(mm: &ModelManager) -> Collection<OAuthGrantDoc> {
+		root_collection(
+			mm,
+			GRANT_TABLE,
+		)
+	}
+}
+
+fn consent_id(
+	user_id: &str,
+	client_id: &str,
+) -> String {
+	format!("{user_id}|{client_id}")
+}
+
+fn exp_in(duration: Duration) -> DateTime {
+	DateTime::from_millis(DateTime::now().timestamp_millis() + duration.as_millis() as i64)
+}
+
+async fn ensure_exp_index<T: Send + Sync>(collection: &Collection<T>) -> Result<()> {
+	collection
+		.create_index(
+			IndexModel::builder()
+				.keys(doc! { "exp": 1 })
+				.options(
+					IndexOptions::builder()
+						.expire_after(Duration::ZERO)
+						.build(),
+				)
+				.build(),
+		)
+		.await
+		.map_err(|_| Error::IndexCreateFail)?;
+
+	Ok(())
+}
+
+fn root_collection<T: Send + Sync>(
+	mm: &ModelManager,
+	table: &str,
+) -> Collection<T> {
+	mm.client
+		.database(Ctx::root_ctx().tenant_id().as_str())
+		.collection(table)
+}
+
+// endregion: --- (private) Helpers
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use serial_test::serial;
+
+	fn fx_client(scopes: &[&str]) -> OAuthClientForAuth {
+		OAuthClientForAuth {
+			client_id: "app_01".to_string(),
+			tenant_id: "tenant-01".to_string(),
+			name: "App 01".to_string(),
+			public: true,
+			redirect_uris: vec!["https://app.example.com/callback".to_string()],
+			scopes: scopes
+				.iter()
+				.map(|scope| scope.to_string())
+				.collect(),
+			first_party: false,
+		}
+	}
+
+	fn fx_authorization(client_id: &str) -> OAuthAuthorization {
+		OAuthAuthorization {
+			user_id: "user-01".to_string(),
+			client_id: client_id.to_string(),
+			redirect_uri: "https://app.example.com/callback".to_string(),
+			scope: vec!["read".to_string()],
+			code_challenge: None,
+		}
+	}
+
+	#[test]
+	fn test_oauth_client_resolve_scope() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_client = fx_client(&["read", "write"]);
+
+		// -- Exec & Check
+		assert_eq!(
+			fx_client.resolve_scope(&[]),
+			Some(vec!["read".to_string(), "write".to_string()])
+		);
+		assert_eq!(
+			fx_client.resolve_scope(&["write".to_string()]),
+			Some(vec!["write".to_string()])
+		);
+		assert_eq!(
+			fx_client.resolve_scope(&["admin".to_string()]),
+			None
+		);
+		assert!(fx_client.has_redirect_uri("https://app.example.com/callback"));
+		assert!(!fx_client.has_redirect_uri("https://app.example.com/callback/other"));
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_oauth_client_authenticate() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let ctx = Ctx::root_ctx();
+		let fx_client_c = |public| OAuthClientForCreate {
+			name: "App 01".to_string(),
+			public,
+			redirect_uris: vec!["https://app.example.com/callback".to_string()],
+			scopes: vec!["read".to_string()],
+			first_party: false,
+		};
+		let confidential = OAuthClientBmc::create(
+			&ctx,
+			&mm,
+			fx_client_c(false),
+		)
+		.await?;
+		let public = OAuthClientBmc::create(
+			&ctx,
+			&mm,
+			fx_client_c(true),
+		)
+		.await?;
+		let confidential_id = &confidential
+			.client
+			.client_id;
+		let public_id = &public.client.client_id;
+
+		// -- Exec
+		let res_confidential = OAuthClientBmc::authenticate(
+			&mm,
+			confidential_id,
+			confidential
+				.client_secret
+				.as_deref(),
+		)
+		.await;
+		let res_no_secret = OAuthClientBmc::authenticate(
+			&mm,
+			confidential_id,
+			None,
+		)
+		.await;
+		let res_public = OAuthClientBmc::authenticate(
+			&mm, public_id, None,
+		)
+		.await;
+		let res_public_secret = OAuthClientBmc::authenticate(
+			&mm,
+			public_id,
+			Some("secret"),
+		)
+		.await;
+
+		// -- Check
+		assert!(public.client_secret.is_none());
+		assert!(res_confidential.is_ok());
+		assert!(matches!(
+			res_no_secret,
+			Err(super::Error::OAuthClientInvalidCredentials)
+		));
+		assert!(res_public?.public);
+		assert!(matches!(
+			res_public_secret,
+			Err(super::Error::OAuthClientInvalidCredentials)
+		));
+
+		// -- Clean
+		OAuthClientBmc::delete(
+			&ctx,
+			&mm,
+			confidential_id,
+		)
+		.await?;
+		OAuthClientBmc::delete(
+			&ctx, &mm, public_id,
+		)
+		.await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_oauth_code_take_once() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let code = OAuthCodeBmc::create(
+			&mm,
+			fx_authorization("app_01"),
+		)
+		.await?;
+
+		// -- Exec
+		let authorization = OAuthCodeBmc::take(
+			&mm, &code,
+		)
+		.await?;
+		let res_again = OAuthCodeBmc::take(
+			&mm, &code,
+		)
+		.await;
+
+		// -- Check
+		assert_eq!(
+			authorization.client_id,
+			"app_01"
+		);
+		assert!(matches!(
+			res_again,
+			Err(super::Error::OAuthGrantInvalid)
+		));
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_oauth_consent_covers() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let fx_user_id = Uuid::new_v4().simple().to_string();
+		let fx_scope = |scopes: &[&str]| -> Vec<String> {
+			scopes
+				.iter()
+				.map(|scope| scope.to_string())
+				.collect()
+		};
+
+		// -- Exec
+		let covers_before = OAuthConsentBmc::covers(
+			&mm,
+			&fx_user_id,
+			"app_01",
+			&fx_scope(&["read"]),
+		)
+		.await?;
+		OAuthConsentBmc::grant(
+			&mm,
+			&fx_user_id,
+			"app_01",
+			&fx_scope(&["read"]),
+		)
+		.await?;
+		OAuthConsentBmc::grant(
+			&mm,
+			&fx_user_id,
+			"app_01",
+			&fx_scope(&["write"]),
+		)
+		.await?;
+		let covers_both = OAuthConsentBmc::covers(
+			&mm,
+			&fx_user_id,
+			"app_01",
+			&fx_scope(&["read", "write"]),
+		)
+		.await?;
+		let covers_other = OAuthConsentBmc::covers(
+			&mm,
+			&fx_user_id,
+			"app_01",
+			&fx_scope(&["admin"]),
+		)
+		.await?;
+
+		// -- Check
+		assert!(!covers_before);
+		assert!(covers_both);
+		assert!(!covers_other);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
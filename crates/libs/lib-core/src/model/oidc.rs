@@ -0,0 +1,672 @@
+//! OpenID Connect identity providers of the tenants, and the identities they link to the users.
+//!
+//! Design:
+//!
+//! - A provider belongs to a tenant (managed by its admins), and is stored in the root ctx database
+//!   with its `tenant_id` (like the service accounts), since the login only knows the provider id.
+//! - The `client_secret` is stored as is, since it is sent to the provider on each code exchange.
+//! - A pending login (`OidcLoginBmc`) is keyed by its `state`, holds the `nonce` and the PKCE verifier,
+//!   and is consumed once by the callback (or removed by a TTL index on `exp`).
+//! - An identity (`provider_id`, `sub`) is linked to one user, on its first login: to the user with
+//!   its verified email (as mail or username), or to a new user without password (just-in-time provisioning).
+
+use crate::ctx::Ctx;
+use crate::model::user::{QUserForCreateExternal, UserBmc};
+use crate::model::validation::{check_length, check_url, Validate, ValidationError, ValidationResult};
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use lib_auth::oidc::IdTokenClaims;
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+const PROVIDER_TABLE: &str = "OidcProviders";
+const LOGIN_TABLE: &str = "OidcLogins";
+const IDENTITY_TABLE: &str = "OidcIdentities";
+
+/// The time for the user to authenticate with the provider.
+pub const LOGIN_DURATION: Duration = Duration::from_secs(10 * 60);
+
+// region:    --- Types
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OidcProviderDoc {
+	/// Simple uuid.
+	#[serde(rename = "_id")]
+	id: String,
+	tenant_id: String,
+	name: String,
+	issuer: String,
+	client_id: String,
+	client_secret: String,
+	scopes: Vec<String>,
+	redirect_uri: String,
+	ctime: DateTime,
+}
+
+/// A provider, as listed to the admins (without its secret).
+#[derive(Debug, Clone, Serialize)]
+pub struct OidcProvider {
+	pub id: String,
+	pub name: String,
+	pub issuer: String,
+	pub client_id: String,
+	pub scopes: Vec<String>,
+	pub redirect_uri: String,
+	pub created: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcProviderForCreate {
+	pub name: String,
+	/// The issuer url, its discovery document being at `{issuer}/.well-known/openid-configuration`.
+	pub issuer: String,
+	pub client_id: String,
+	pub client_secret: String,
+	/// `openid` is always requested.
+	#[serde(default = "default_scopes")]
+	pub scopes: Vec<String>,
+	/// The `/api/oidc/callback` url, as registered with the provider.
+	pub redirect_uri: String,
+}
+
+/// A provider, for a login.
+pub struct OidcProviderForAuth {
+	pub id: String,
+	pub tenant_id: String,
+	pub issuer: String,
+	pub client_id: String,
+	pub client_secret: String,
+	pub scopes: Vec<String>,
+	pub redirect_uri: String,
+}
+
+/// A pending login, from the authorization request to the callback.
+#[derive(Debug)]
+pub struct OidcLogin {
+	pub state: String,
+	pub provider_id: String,
+	pub nonce: String,
+	pub code_verifier: String,
+	/// The local path to redirect to once logged in.
+	pub return_to: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OidcLoginDoc {
+	/// The `state`.
+	#[serde(rename = "_id")]
+	id: String,
+	provider_id: String,
+	nonce: String,
+	code_verifier: String,
+	return_to: String,
+	exp: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OidcIdentityDoc {
+	/// `{provider_id}|{sub}`.
+	#[serde(rename = "_id")]
+	id: String,
+	provider_id: String,
+	sub: String,
+	user_id: String,
+	ctime: DateTime,
+}
+
+fn default_scopes() -> Vec<String> {
+	["openid", "email", "profile"]
+		.map(String::from)
+		.to_vec()
+}
+
+impl Validate for OidcProviderForCreate {
+	fn validate(&self) -> ValidationResult {
+		let mut errors = ValidationError::default();
+		check_length(
+			&mut errors,
+			"name",
+			&self.name,
+			Some(1),
+			Some(100),
+		);
+		check_url(
+			&mut errors,
+			"issuer",
+			&self.issuer,
+		);
+		check_length(
+			&mut errors,
+			"client_id",
+			&self.client_id,
+			Some(1),
+			Some(255),
+		);
+		check_length(
+			&mut errors,
+			"client_secret",
+			&self.client_secret,
+			Some(1),
+			Some(1024),
+		);
+		check_url(
+			&mut errors,
+			"redirect_uri",
+			&self.redirect_uri,
+		);
+		errors.into_result()
+	}
+}
+
+impl OidcProvider {
+	fn from_doc(doc: OidcProviderDoc) -> Result<Self> {
+		Ok(
+			OidcProvider {
+				created: doc
+					.ctime
+					.try_to_rfc3339_string()
+					.map_err(|_| Error::CrudDocumentError)?,
+				id: doc.id,
+				name: doc.name,
+				issuer: doc.issuer,
+				client_id: doc.client_id,
+				scopes: doc.scopes,
+				redirect_uri: doc.redirect_uri,
+			},
+		)
+	}
+}
+
+impl From<OidcProviderDoc> for OidcProviderForAuth {
+	fn from(doc: OidcProviderDoc) -> Self {
+		OidcProviderForAuth {
+			id: doc.id,
+			tenant_id: doc.tenant_id,
+			issuer: doc.issuer,
+			client_id: doc.client_id,
+			client_secret: doc.client_secret,
+			scopes: doc.scopes,
+			redirect_uri: doc.redirect_uri,
+		}
+	}
+}
+
+impl std::fmt::Debug for OidcProviderForAuth {
+	fn fmt(
+		&self,
+		f: &mut std::fmt::Formatter<'_>,
+	) -> std::fmt::Result {
+		// Note: Never print the client secret.
+		f.debug_struct("OidcProviderForAuth")
+			.field(
+				"id", &self.id,
+			)
+			.field(
+				"tenant_id",
+				&self.tenant_id,
+			)
+			.field(
+				"issuer",
+				&self.issuer,
+			)
+			.field(
+				"client_id",
+				&self.client_id,
+			)
+			.finish()
+	}
+}
+
+// endregion: --- Types
+
+// region:    --- OidcProviderBmc
+
+pub struct OidcProviderBmc;
+
+impl OidcProviderBmc {
+	/// Creates a provider in the ctx tenant.
+	pub async fn create(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		provider_c: OidcProviderForCreate,
+	) -> Result<OidcProvider> {
+		provider_c.validate()?;
+
+		let collection = Self::collection(mm);
+		Self::ensure_indexes(&collection).await?;
+
+		let provider = OidcProviderDoc {
+			id: Uuid::new_v4().simple().to_string(),
+			tenant_id: ctx.tenant_id(),
+			name: provider_c.name,
+			issuer: provider_c.issuer,
+			client_id: provider_c.client_id,
+			client_secret: provider_c.client_secret,
+			scopes: provider_c.scopes,
+			redirect_uri: provider_c.redirect_uri,
+			ctime: DateTime::now(),
+		};
+		collection
+			.insert_one(&provider)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		OidcProvider::from_doc(provider)
+	}
+
+	/// The providers of the ctx tenant.
+	pub async fn list(
+		ctx: &Ctx,
+		mm: &ModelManager,
+	) -> Result<Vec<OidcProvider>> {
+		let mut cursor = Self::collection(mm)
+			.find(doc! { "tenant_id": ctx.tenant_id() })
+			.sort(doc! { "ctime": -1 })
+			.await
+			.map_err(|_| Error::QueryError)?;
+
+		let mut providers = Vec::new();
+		while cursor
+			.advance()
+			.await
+			.map_err(|_| Error::QueryError)?
+		{
+			let provider = cursor
+				.deserialize_current()
+				.map_err(|_| Error::CrudDocumentError)?;
+			providers.push(OidcProvider::from_doc(provider)?);
+		}
+
+		Ok(providers)
+	}
+
+	/// Note: The identities of the provider stay linked, in case it is configured again.
+	pub async fn delete(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: &str,
+	) -> Result<OidcProvider> {
+		let provider = Self::collection(mm)
+			.find_one_and_delete(doc! { "_id": id, "tenant_id": ctx.tenant_id() })
+			.await
+			.map_err(|_| Error::DeleteError)?
+			.ok_or_else(|| Error::OidcProviderNotFound { id: id.to_string() })?;
+
+		OidcProvider::from_doc(provider)
+	}
+
+	/// The provider of a login (of any tenant).
+	pub async fn get_for_auth(
+		mm: &ModelManager,
+		id: &str,
+	) -> Result<OidcProviderForAuth> {
+		let provider = Self::collection(mm)
+			.find_one(doc! { "_id": id })
+			.await
+			.map_err(|_| Error::QueryError)?
+			.ok_or_else(|| Error::OidcProviderNotFound { id: id.to_string() })?;
+
+		Ok(provider.into())
+	}
+}
+
+// endregion: --- OidcProviderBmc
+
+// region:    --- OidcLoginBmc
+
+pub struct OidcLoginBmc;
+
+impl OidcLoginBmc {
+	pub async fn create(
+		mm: &ModelManager,
+		login: OidcLogin,
+	) -> Result<()> {
+		let collection = Self::collection(mm);
+		Self::ensure_indexes(&collection).await?;
+
+		let login = OidcLoginDoc {
+			id: login.state,
+			provider_id: login.provider_id,
+			nonce: login.nonce,
+			code_verifier: login.code_verifier,
+			return_to: login.return_to,
+			exp: DateTime::from_millis(DateTime::now().timestamp_millis() + LOGIN_DURATION.as_millis() as i64),
+		};
+		collection
+			.insert_one(&login)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		Ok(())
+	}
+
+	/// Consumes the pending login of the `state` (a callback can only be answered once).
+	pub async fn take(
+		mm: &ModelManager,
+		state: &str,
+	) -> Result<OidcLogin> {
+		let login = Self::collection(mm)
+			.find_one_and_delete(doc! { "_id": state })
+			.await
+			.map_err(|_| Error::DeleteError)?
+			.ok_or(Error::OidcLoginStateInvalid)?;
+
+		// Note: The TTL index removes the expired logins, but not right away.
+		if login.exp < DateTime::now() {
+			return Err(Error::OidcLoginStateInvalid);
+		}
+
+		Ok(
+			OidcLogin {
+				state: login.id,
+				provider_id: login.provider_id,
+				nonce: login.nonce,
+				code_verifier: login.code_verifier,
+				return_to: login.return_to,
+			},
+		)
+	}
+}
+
+// endregion: --- OidcLoginBmc
+
+// region:    --- OidcIdentityBmc
+
+pub struct OidcIdentityBmc;
+
+impl OidcIdentityBmc {
+	/// The user of the identity of the ID token, linked (or provisioned) on its first login.
+	pub async fn link_or_provision(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		provider_id: &str,
+		claims: &IdTokenClaims,
+	) -> Result<String> {
+		let collection = Self::collection(mm);
+		let id = format!(
+			"{provider_id}|{}",
+			claims.sub
+		);
+
+		// -- Already linked.
+		if let Some(identity) = collection
+			.find_one(doc! { "_id": &id })
+			.await
+			.map_err(|_| Error::QueryError)?
+		{
+			return Ok(identity.user_id);
+		}
+
+		// -- Link to the user of the verified email, or provision one.
+		// Note: An unverified email is never trusted, as anyone could claim it at their provider.
+		let verified_email = claims
+			.email
+			.as_deref()
+			.filter(|_| claims.email_verified);
+		let email_user_id = match verified_email {
+			Some(email) => {
+				UserBmc::find_id_by_email(
+					ctx, mm, email,
+				)
+				.await?
+			},
+			None => None,
+		};
+		let user_id = match email_user_id {
+			Some(user_id) => user_id,
+			None => {
+				UserBmc::create_external(
+					ctx,
+					mm,
+					QUserForCreateExternal {
+						username: verified_email.map(String::from).unwrap_or_else(|| id.clone()),
+						name: claims.given_name.clone(),
+						surname: claims.family_name.clone(),
+						mail: verified_email.map(String::from),
+					},
+				)
+				.await?
+			},
+		};
+
+		collection
+			.insert_one(
+				OidcIdentityDoc {
+					id,
+					provider_id: provider_id.to_string(),
+					sub: claims.sub.clone(),
+					user_id: user_id.clone(),
+					ctime: DateTime::now(),
+				},
+			)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		Ok(user_id)
+	}
+}
+
+// endregion: --- OidcIdentityBmc
+
+// region:    --- (private) Helpers
+
+impl OidcProviderBmc {
+	fn collection(mm: &ModelManager) -> Collection<OidcProviderDoc> {
+		root_collection(
+			mm,
+			PROVIDER_TABLE,
+		)
+	}
+
+	async fn ensure_indexes(collection: &Collection<OidcProviderDoc>) -> Result<()> {
+		collection
+			.create_index(
+				IndexModel::builder()
+					.keys(doc! { "tenant_id": 1 })
+					.build(),
+			)
+			.await
+			.map_err(|_| Error::IndexCreateFail)?;
+
+		Ok(())
+	}
+}
+
+impl OidcLoginBmc {
+	fn collection(mm: &ModelManager) -> Collection<OidcLoginDoc> {
+		root_collection(
+			mm,
+			LOGIN_TABLE,
+		)
+	}
+
+	async fn ensure_indexes(collection: &Collection<OidcLoginDoc>) -> Result<()> {
+		collection
+			.create_index(
+				IndexModel::builder()
+					.keys(doc! { "exp": 1 })
+					.options(
+						IndexOptions::builder()
+							.expire_after(Duration::ZERO)
+							.build(),
+					)
+					.build(),
+			)
+			.await
+			.map_err(|_| Error::IndexCreateFail)?;
+
+		Ok(())
+	}
+}
+
+impl OidcIdentityBmc {
+	fn collection(mm: &ModelManager) -> Collection<OidcIdentityDoc> {
+		root_collection(
+			mm,
+			IDENTITY_TABLE,
+		)
+	}
+}
+
+fn root_collection<T: Send + Sync>(
+	mm: &ModelManager,
+	table: &str,
+) -> Collection<T> {
+	mm.client
+		.database(Ctx::root_ctx().tenant_id().as_str())
+		.collection(table)
+}
+
+// endregion: --- (private) Helpers
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use serial_test::serial;
+
+	fn fx_claims(
+		sub: &str,
+		email: &str,
+		email_verified: bool,
+	) -> IdTokenClaims {
+		IdTokenClaims {
+			sub: sub.to_string(),
+			nonce: None,
+			email: Some(email.to_string()),
+			email_verified,
+			name: None,
+			given_name: Some("One".to_string()),
+			family_name: None,
+			preferred_username: None,
+		}
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_oidc_login_take_once() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let fx_state = Uuid::new_v4().to_string();
+		OidcLoginBmc::create(
+			&mm,
+			OidcLogin {
+				state: fx_state.clone(),
+				provider_id: "provider-01".to_string(),
+				nonce: "nonce-01".to_string(),
+				code_verifier: "verifier-01".to_string(),
+				return_to: "/".to_string(),
+			},
+		)
+		.await?;
+
+		// -- Exec
+		let login = OidcLoginBmc::take(
+			&mm, &fx_state,
+		)
+		.await?;
+		let res_again = OidcLoginBmc::take(
+			&mm, &fx_state,
+		)
+		.await;
+
+		// -- Check
+		assert_eq!(
+			login.nonce,
+			"nonce-01"
+		);
+		assert!(matches!(
+			res_again,
+			Err(super::Error::OidcLoginStateInvalid)
+		));
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_oidc_identity_link_or_provision() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let ctx = Ctx::root_ctx();
+		let fx_provider_id = Uuid::new_v4().simple().to_string();
+		let fx_email = format!(
+			"{}@example.com",
+			Uuid::new_v4().simple()
+		);
+
+		// -- Exec
+		let user_id = OidcIdentityBmc::link_or_provision(
+			&ctx,
+			&mm,
+			&fx_provider_id,
+			&fx_claims(
+				"sub-01", &fx_email, true,
+			),
+		)
+		.await?;
+		let user_id_again = OidcIdentityBmc::link_or_provision(
+			&ctx,
+			&mm,
+			&fx_provider_id,
+			&fx_claims(
+				"sub-01", &fx_email, true,
+			),
+		)
+		.await?;
+		let user_id_other_sub = OidcIdentityBmc::link_or_provision(
+			&ctx,
+			&mm,
+			&fx_provider_id,
+			&fx_claims(
+				"sub-02", &fx_email, true,
+			),
+		)
+		.await?;
+		let user_id_unverified = OidcIdentityBmc::link_or_provision(
+			&ctx,
+			&mm,
+			&fx_provider_id,
+			&fx_claims(
+				"sub-03", &fx_email, false,
+			),
+		)
+		.await?;
+		let user_unverified = UserBmc::get(
+			&ctx,
+			&mm,
+			&user_id_unverified,
+		)
+		.await?;
+
+		// -- Check
+		assert_eq!(
+			user_id,
+			user_id_again
+		);
+		assert_eq!(
+			user_id,
+			user_id_other_sub
+		);
+		assert_ne!(
+			user_id,
+			user_id_unverified
+		);
+		assert!(
+			user_unverified
+				.metadata
+				.and_then(|meta| meta.mail)
+				.is_none()
+		);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
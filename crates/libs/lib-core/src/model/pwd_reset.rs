@@ -0,0 +1,208 @@
+//! The password reset tokens, sent by mail to the users who forgot their password.
+//!
+//! Design:
+//!
+//! - A token is random, only stored hashed (like the refresh tokens), in the root ctx database.
+//! - It is short-lived (`RESET_DURATION`, TTL on `exp`), and single-use (`take`).
+//! - The same token invites the users created by an admin to choose their password,
+//!   for longer (`INVITE_DURATION`, see `create_invite`).
+//! - A user has at most one pending token, a new request replaces it.
+
+use crate::ctx::Ctx;
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use lib_auth::token::{generate_refresh_token, hash_refresh_token};
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const TABLE: &str = "PwdResetTokens";
+
+const RESET_DURATION: Duration = Duration::from_secs(30 * 60);
+const INVITE_DURATION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PwdResetDoc {
+	/// The token hash.
+	#[serde(rename = "_id")]
+	id: String,
+	user_id: String,
+	exp: DateTime,
+}
+
+pub struct PwdResetBmc;
+
+impl PwdResetBmc {
+	/// Returns the new token of the user (clear, to be sent by mail only).
+	pub async fn create(
+		mm: &ModelManager,
+		user_id: &str,
+	) -> Result<String> {
+		Self::create_for(
+			mm,
+			user_id,
+			RESET_DURATION,
+		)
+		.await
+	}
+
+	/// Same as `create`, valid for `INVITE_DURATION`.
+	pub async fn create_invite(
+		mm: &ModelManager,
+		user_id: &str,
+	) -> Result<String> {
+		Self::create_for(
+			mm,
+			user_id,
+			INVITE_DURATION,
+		)
+		.await
+	}
+
+	/// The user of the token, without consuming it (e.g., to check the new password first).
+	pub async fn get_user_id(
+		mm: &ModelManager,
+		token: &str,
+	) -> Result<String> {
+		let reset = Self::collection(mm)
+			.find_one(doc! { "_id": hash_refresh_token(token), "exp": { "$gt": DateTime::now() } })
+			.await
+			.map_err(|_| Error::QueryError)?
+			.ok_or(Error::PwdResetTokenInvalid)?;
+
+		Ok(reset.user_id)
+	}
+
+	/// Consumes the token, and returns its user.
+	pub async fn take(
+		mm: &ModelManager,
+		token: &str,
+	) -> Result<String> {
+		let reset = Self::collection(mm)
+			.find_one_and_delete(doc! { "_id": hash_refresh_token(token), "exp": { "$gt": DateTime::now() } })
+			.await
+			.map_err(|_| Error::DeleteError)?
+			.ok_or(Error::PwdResetTokenInvalid)?;
+
+		Ok(reset.user_id)
+	}
+}
+
+// region:    --- (private) Helpers
+
+impl PwdResetBmc {
+	async fn create_for(
+		mm: &ModelManager,
+		user_id: &str,
+		duration: Duration,
+	) -> Result<String> {
+		let collection = Self::collection(mm);
+		Self::ensure_indexes(&collection).await?;
+
+		collection
+			.delete_many(doc! { "user_id": user_id })
+			.await
+			.map_err(|_| Error::DeleteError)?;
+
+		let token = generate_refresh_token();
+		collection
+			.insert_one(
+				PwdResetDoc {
+					id: hash_refresh_token(&token),
+					user_id: user_id.to_string(),
+					exp: DateTime::from_millis(
+						DateTime::now().timestamp_millis() + duration.as_millis() as i64,
+					),
+				},
+			)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		Ok(token)
+	}
+
+	fn collection(mm: &ModelManager) -> Collection<PwdResetDoc> {
+		mm.client
+			.database(Ctx::root_ctx().tenant_id().as_str())
+			.collection(TABLE)
+	}
+
+	async fn ensure_indexes(collection: &Collection<PwdResetDoc>) -> Result<()> {
+		collection
+			.create_index(
+				IndexModel::builder()
+					.keys(doc! { "exp": 1 })
+					.options(
+						IndexOptions::builder()
+							.expire_after(Duration::ZERO)
+							.build(),
+					)
+					.build(),
+			)
+			.await
+			.map_err(|_| Error::IndexCreateFail)?;
+
+		Ok(())
+	}
+}
+
+// endregion: --- (private) Helpers
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use serial_test::serial;
+
+	#[serial]
+	#[tokio::test]
+	async fn test_pwd_reset_single_use() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let fx_user_id = "test_pwd_reset-user-01";
+		let token_replaced = PwdResetBmc::create(
+			&mm, fx_user_id,
+		)
+		.await?;
+		let token = PwdResetBmc::create(
+			&mm, fx_user_id,
+		)
+		.await?;
+
+		// -- Exec
+		let res_replaced = PwdResetBmc::get_user_id(
+			&mm,
+			&token_replaced,
+		)
+		.await;
+		let user_id = PwdResetBmc::take(
+			&mm, &token,
+		)
+		.await?;
+		let res_again = PwdResetBmc::take(
+			&mm, &token,
+		)
+		.await;
+
+		// -- Check
+		assert!(matches!(
+			res_replaced,
+			Err(super::Error::PwdResetTokenInvalid)
+		));
+		assert_eq!(
+			user_id, fx_user_id
+		);
+		assert!(matches!(
+			res_again,
+			Err(super::Error::PwdResetTokenInvalid)
+		));
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
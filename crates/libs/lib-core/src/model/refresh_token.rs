@@ -0,0 +1,485 @@
+//! Refresh tokens, stored hashed per user and device.
+//!
+//! Design:
+//!
+//! - A login starts a token family for the user device (replacing the previous family of that device).
+//! - Each refresh rotates the token: the presented token is marked `rotated`,
+//!   and a new token of the same family is issued.
+//! - Presenting an already rotated token means it was stolen (or replayed), so the whole family
+//!   is revoked, logging out both the attacker and the user.
+//! - Tokens are looked up by their unique `token_hash`, and expired documents are removed
+//!   by a TTL index on `exp`.
+
+use crate::ctx::Ctx;
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use lib_auth::token::{generate_refresh_token, hash_refresh_token, refresh_token_duration_sec};
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+
+const TABLE: &str = "RefreshTokens";
+
+// region:    --- Types
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RefreshTokenDoc {
+	user_id: String,
+	device_id: String,
+	family_id: String,
+	token_hash: String,
+	rotated: bool,
+	revoked: bool,
+	ctime: DateTime,
+	exp: DateTime,
+}
+
+/// A newly issued refresh token (on login or refresh).
+#[derive(Debug)]
+pub struct IssuedRefreshToken {
+	pub user_id: String,
+	/// The device of the family (`oauth:<client_id>` for an OAuth2 client, see `oauth`).
+	pub device_id: String,
+	/// The token family, one per login (see `SessionBmc`).
+	pub family_id: String,
+	/// The new refresh token (clear, to be sent to the client only).
+	pub refresh_token: String,
+}
+
+/// The family of a refresh token.
+#[derive(Debug)]
+pub struct RefreshTokenFamily {
+	pub user_id: String,
+	pub device_id: String,
+	pub family_id: String,
+}
+
+// endregion: --- Types
+
+// region:    --- RefreshTokenBmc
+
+pub struct RefreshTokenBmc;
+
+impl RefreshTokenBmc {
+	/// Starts a new token family for the user device, and returns its first refresh token.
+	pub async fn create(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+		device_id: &str,
+	) -> Result<IssuedRefreshToken> {
+		let collection = Self::collection(
+			ctx, mm,
+		);
+		Self::ensure_indexes(&collection).await?;
+
+		// -- One family per user device.
+		collection
+			.update_many(
+				doc! { "user_id": user_id, "device_id": device_id, "revoked": false },
+				doc! { "$set": { "revoked": true } },
+			)
+			.await
+			.map_err(|_| Error::UpdateError)?;
+
+		let family_id = Uuid::new_v4().to_string();
+		let refresh_token = Self::insert(
+			&collection,
+			user_id,
+			device_id,
+			&family_id,
+		)
+		.await?;
+
+		Ok(
+			IssuedRefreshToken {
+				user_id: user_id.to_string(),
+				device_id: device_id.to_string(),
+				family_id,
+				refresh_token,
+			},
+		)
+	}
+
+	/// Rotates the refresh token, see the module doc for the reuse detection.
+	pub async fn rotate(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		refresh_token: &str,
+	) -> Result<IssuedRefreshToken> {
+		let collection = Self::collection(
+			ctx, mm,
+		);
+		let token_hash = hash_refresh_token(refresh_token);
+
+		// -- Mark as rotated, atomically, so that two concurrent uses cannot both succeed.
+		let token = collection
+			.find_one_and_update(
+				doc! { "token_hash": &token_hash, "rotated": false, "revoked": false },
+				doc! { "$set": { "rotated": true } },
+			)
+			.await
+			.map_err(|_| Error::UpdateError)?;
+
+		let Some(token) = token else {
+			return Err(
+				Self::rotate_fail(
+					&collection,
+					&token_hash,
+				)
+				.await,
+			);
+		};
+
+		if token.exp < DateTime::now() {
+			return Err(Error::RefreshTokenExpired);
+		}
+
+		let new_token = Self::insert(
+			&collection,
+			&token.user_id,
+			&token.device_id,
+			&token.family_id,
+		)
+		.await?;
+
+		Ok(
+			IssuedRefreshToken {
+				user_id: token.user_id,
+				device_id: token.device_id,
+				family_id: token.family_id,
+				refresh_token: new_token,
+			},
+		)
+	}
+
+	/// Revokes the family of the refresh token (e.g., on logoff). No-op if the token is unknown.
+	pub async fn revoke_family(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		refresh_token: &str,
+	) -> Result<()> {
+		let collection = Self::collection(
+			ctx, mm,
+		);
+		let token = collection
+			.find_one(doc! { "token_hash": hash_refresh_token(refresh_token) })
+			.await
+			.map_err(|_| Error::QueryError)?;
+
+		if let Some(token) = token {
+			Self::revoke_family_id_in(
+				&collection,
+				&token.family_id,
+			)
+			.await?;
+		}
+
+		Ok(())
+	}
+
+	/// The family of a refresh token, if known (without rotating it).
+	pub async fn get_family(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		refresh_token: &str,
+	) -> Result<Option<RefreshTokenFamily>> {
+		let token = Self::collection(
+			ctx, mm,
+		)
+		.find_one(doc! { "token_hash": hash_refresh_token(refresh_token) })
+		.await
+		.map_err(|_| Error::QueryError)?;
+
+		Ok(
+			token.map(
+				|token| RefreshTokenFamily {
+					user_id: token.user_id,
+					device_id: token.device_id,
+					family_id: token.family_id,
+				},
+			),
+		)
+	}
+
+	/// Revokes a token family (e.g., a session of `SessionBmc`).
+	pub async fn revoke_family_id(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		family_id: &str,
+	) -> Result<()> {
+		Self::revoke_family_id_in(
+			&Self::collection(
+				ctx, mm,
+			),
+			family_id,
+		)
+		.await
+	}
+
+	/// The families of the user with a usable (not rotated, revoked or expired) refresh token.
+	pub async fn active_family_ids(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+	) -> Result<Vec<String>> {
+		let family_ids = Self::collection(
+			ctx, mm,
+		)
+		.distinct(
+			"family_id",
+			doc! { "user_id": user_id, "rotated": false, "revoked": false, "exp": { "$gt": DateTime::now() } },
+		)
+		.await
+		.map_err(|_| Error::QueryError)?;
+
+		Ok(
+			family_ids
+				.into_iter()
+				.filter_map(|family_id| family_id.as_str().map(str::to_string))
+				.collect(),
+		)
+	}
+
+	/// Revokes all the refresh tokens of the user (e.g., on password change).
+	pub async fn revoke_all_for_user(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+	) -> Result<()> {
+		Self::collection(
+			ctx, mm,
+		)
+		.update_many(
+			doc! { "user_id": user_id, "revoked": false },
+			doc! { "$set": { "revoked": true } },
+		)
+		.await
+		.map_err(|_| Error::UpdateError)?;
+
+		Ok(())
+	}
+
+	/// Revokes all the refresh tokens of the user, but the ones of the family `family_id`
+	/// (e.g., the session changing the password).
+	pub async fn revoke_all_except(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+		family_id: &str,
+	) -> Result<()> {
+		Self::collection(
+			ctx, mm,
+		)
+		.update_many(
+			doc! { "user_id": user_id, "family_id": { "$ne": family_id }, "revoked": false },
+			doc! { "$set": { "revoked": true } },
+		)
+		.await
+		.map_err(|_| Error::UpdateError)?;
+
+		Ok(())
+	}
+}
+
+// endregion: --- RefreshTokenBmc
+
+// region:    --- (private) Helpers
+
+impl RefreshTokenBmc {
+	fn collection(
+		ctx: &Ctx,
+		mm: &ModelManager,
+	) -> Collection<RefreshTokenDoc> {
+		mm.client
+			.database(ctx.tenant_id().as_str())
+			.collection(TABLE)
+	}
+
+	async fn insert(
+		collection: &Collection<RefreshTokenDoc>,
+		user_id: &str,
+		device_id: &str,
+		family_id: &str,
+	) -> Result<String> {
+		let refresh_token = generate_refresh_token();
+		let now = DateTime::now();
+		let exp = DateTime::from_millis(
+			now.timestamp_millis() + (refresh_token_duration_sec() * 1000.) as i64,
+		);
+
+		collection
+			.insert_one(
+				RefreshTokenDoc {
+					user_id: user_id.to_string(),
+					device_id: device_id.to_string(),
+					family_id: family_id.to_string(),
+					token_hash: hash_refresh_token(&refresh_token),
+					rotated: false,
+					revoked: false,
+					ctime: now,
+					exp,
+				},
+			)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		Ok(refresh_token)
+	}
+
+	/// Why the rotation failed. Revokes the family if the token was already rotated (reuse).
+	async fn rotate_fail(
+		collection: &Collection<RefreshTokenDoc>,
+		token_hash: &str,
+	) -> Error {
+		let token = match collection
+			.find_one(doc! { "token_hash": token_hash })
+			.await
+		{
+			Ok(Some(token)) => token,
+			Ok(None) => return Error::RefreshTokenNotFound,
+			Err(_) => return Error::QueryError,
+		};
+
+		if token.revoked {
+			return Error::RefreshTokenRevoked;
+		}
+
+		warn!(
+			"refresh token reuse detected - user_id: {}, family_id: {}",
+			token.user_id, token.family_id
+		);
+		match Self::revoke_family_id_in(
+			collection,
+			&token.family_id,
+		)
+		.await
+		{
+			Ok(()) => Error::RefreshTokenReused,
+			Err(ex) => ex,
+		}
+	}
+
+	async fn revoke_family_id_in(
+		collection: &Collection<RefreshTokenDoc>,
+		family_id: &str,
+	) -> Result<()> {
+		collection
+			.update_many(
+				doc! { "family_id": family_id },
+				doc! { "$set": { "revoked": true } },
+			)
+			.await
+			.map_err(|_| Error::UpdateError)?;
+
+		Ok(())
+	}
+
+	async fn ensure_indexes(collection: &Collection<RefreshTokenDoc>) -> Result<()> {
+		let indexes = [
+			IndexModel::builder()
+				.keys(doc! { "exp": 1 })
+				.options(
+					IndexOptions::builder()
+						.expire_after(Duration::ZERO)
+						.build(),
+				)
+				.build(),
+			IndexModel::builder()
+				.keys(doc! { "token_hash": 1 })
+				.options(
+					IndexOptions::builder()
+						.unique(true)
+						.build(),
+				)
+				.build(),
+		];
+		collection
+			.create_indexes(indexes)
+			.await
+			.map_err(|_| Error::IndexCreateFail)?;
+
+		Ok(())
+	}
+}
+
+// endregion: --- (private) Helpers
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use crate::model;
+	use serial_test::serial;
+
+	#[serial]
+	#[tokio::test]
+	async fn test_refresh_token_rotate_and_reuse() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let ctx = Ctx::root_ctx();
+		let fx_user_id = Uuid::new_v4().to_string();
+		let token_1 = RefreshTokenBmc::create(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			"device-01",
+		)
+		.await?
+		.refresh_token;
+
+		// -- Exec
+		let refreshed = RefreshTokenBmc::rotate(
+			&ctx, &mm, &token_1,
+		)
+		.await?;
+		let reuse_res = RefreshTokenBmc::rotate(
+			&ctx, &mm, &token_1,
+		)
+		.await;
+		let after_reuse_res = RefreshTokenBmc::rotate(
+			&ctx,
+			&mm,
+			&refreshed.refresh_token,
+		)
+		.await;
+
+		// -- Check
+		assert_eq!(
+			refreshed.user_id,
+			fx_user_id
+		);
+		assert!(
+			matches!(
+				reuse_res,
+				Err(model::Error::RefreshTokenReused)
+			),
+			"Should have matched `Err(Error::RefreshTokenReused)` but was `{reuse_res:?}`"
+		);
+		assert!(
+			matches!(
+				after_reuse_res,
+				Err(model::Error::RefreshTokenRevoked)
+			),
+			"Should have matched `Err(Error::RefreshTokenRevoked)` but was `{after_reuse_res:?}`"
+		);
+
+		// -- Clean
+		RefreshTokenBmc::revoke_all_for_user(
+			&ctx,
+			&mm,
+			&fx_user_id,
+		)
+		.await?;
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
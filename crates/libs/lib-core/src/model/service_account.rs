@@ -0,0 +1,436 @@
+//! Service accounts, the identities of the machine-to-machine callers (other backend services).
+//!
+//! Design:
+//!
+//! - A service account belongs to a tenant, has role assignments, and no password: it authenticates
+//!   with its client credentials (see `lib_auth::client_credentials`) on the OAuth2
+//!   client-credentials token endpoint.
+//! - Service accounts are stored in the root ctx database (like the users), with their `tenant_id`,
+//!   since the token endpoint only knows the `client_id`.
+//! - Their tokens carry the `token_salt`, renewed with the secret, so rotating the secret
+//!   invalidates the issued tokens.
+
+use crate::ctx::Ctx;
+use crate::model::validation::{check_length, Validate, ValidationError, ValidationResult};
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use lib_auth::client_credentials::{client_secret_to_hash, generate_client_credentials, generate_client_secret};
+use lib_auth::pwd::{self, SchemeStatus};
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::ReturnDocument;
+use mongodb::{Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const TABLE: &str = "ServiceAccounts";
+
+// region:    --- Types
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ServiceAccountDoc {
+	/// The `client_id`.
+	#[serde(rename = "_id")]
+	id: String,
+	tenant_id: String,
+	name: String,
+	secret_hash: String,
+	/// Uuid string.
+	token_salt: String,
+	roles: Vec<String>,
+	ctime: DateTime,
+}
+
+/// A service account, as listed to the admins (without its secret).
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceAccount {
+	pub client_id: String,
+	pub name: String,
+	pub roles: Vec<String>,
+	pub created: String,
+}
+
+/// A service account with its new `client_secret`, shown only once (on create and rotate).
+#[derive(Debug, Serialize)]
+pub struct ServiceAccountWithSecret {
+	#[serde(flatten)]
+	pub service_account: ServiceAccount,
+	pub client_secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceAccountForCreate {
+	pub name: String,
+	#[serde(default)]
+	pub roles: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceAccountForUpdate {
+	pub roles: Vec<String>,
+}
+
+/// The service account of a token, or of client credentials.
+#[derive(Debug)]
+pub struct ServiceAccountForAuth {
+	pub client_id: String,
+	pub tenant_id: String,
+	pub roles: Vec<String>,
+	pub token_salt: Uuid,
+}
+
+impl Validate for ServiceAccountForCreate {
+	fn validate(&self) -> ValidationResult {
+		let mut errors = ValidationError::default();
+		check_length(
+			&mut errors,
+			"name",
+			&self.name,
+			Some(1),
+			Some(100),
+		);
+		errors.into_result()
+	}
+}
+
+impl ServiceAccount {
+	fn from_doc(doc: ServiceAccountDoc) -> Result<Self> {
+		Ok(
+			ServiceAccount {
+				created: doc
+					.ctime
+					.try_to_rfc3339_string()
+					.map_err(|_| Error::CrudDocumentError)?,
+				client_id: doc.id,
+				name: doc.name,
+				roles: doc.roles,
+			},
+		)
+	}
+}
+
+impl TryFrom<ServiceAccountDoc> for ServiceAccountForAuth {
+	type Error = Error;
+
+	fn try_from(doc: ServiceAccountDoc) -> Result<Self> {
+		Ok(
+			ServiceAccountForAuth {
+				token_salt: Uuid::parse_str(&doc.token_salt).map_err(|_| Error::CrudDocumentError)?,
+				client_id: doc.id,
+				tenant_id: doc.tenant_id,
+				roles: doc.roles,
+			},
+		)
+	}
+}
+
+// endregion: --- Types
+
+// region:    --- ServiceAccountBmc
+
+pub struct ServiceAccountBmc;
+
+impl ServiceAccountBmc {
+	/// Creates a service account in the ctx tenant.
+	pub async fn create(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		service_account_c: ServiceAccountForCreate,
+	) -> Result<ServiceAccountWithSecret> {
+		service_account_c.validate()?;
+
+		let collection = Self::collection(mm);
+		Self::ensure_indexes(&collection).await?;
+
+		let (client_id, client_secret) = generate_client_credentials();
+		let service_account = ServiceAccountDoc {
+			secret_hash: Self::hash_secret(
+				&client_id,
+				&client_secret,
+			)
+			.await?,
+			id: client_id,
+			tenant_id: ctx.tenant_id(),
+			name: service_account_c.name,
+			token_salt: Uuid::new_v4().to_string(),
+			roles: service_account_c.roles,
+			ctime: DateTime::now(),
+		};
+		collection
+			.insert_one(&service_account)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		Ok(
+			ServiceAccountWithSecret {
+				service_account: ServiceAccount::from_doc(service_account)?,
+				client_secret,
+			},
+		)
+	}
+
+	/// The service accounts of the ctx tenant.
+	pub async fn list(
+		ctx: &Ctx,
+		mm: &ModelManager,
+	) -> Result<Vec<ServiceAccount>> {
+		let mut cursor = Self::collection(mm)
+			.find(doc! { "tenant_id": ctx.tenant_id() })
+			.sort(doc! { "ctime": -1 })
+			.await
+			.map_err(|_| Error::QueryError)?;
+
+		let mut service_accounts = Vec::new();
+		while cursor
+			.advance()
+			.await
+			.map_err(|_| Error::QueryError)?
+		{
+			let service_account = cursor
+				.deserialize_current()
+				.map_err(|_| Error::CrudDocumentError)?;
+			service_accounts.push(ServiceAccount::from_doc(service_account)?);
+		}
+
+		Ok(service_accounts)
+	}
+
+	/// Replaces the role assignments of the service account.
+	/// Note: Takes effect on the next token of the service account.
+	pub async fn update_roles(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		client_id: &str,
+		roles: Vec<String>,
+	) -> Result<ServiceAccount> {
+		let service_account = Self::collection(mm)
+			.find_one_and_update(
+				doc! { "_id": client_id, "tenant_id": ctx.tenant_id() },
+				doc! { "$set": { "roles": roles } },
+			)
+			.return_document(ReturnDocument::After)
+			.await
+			.map_err(|_| Error::UpdateError)?
+			.ok_or_else(|| Error::ServiceAccountNotFound { id: client_id.to_string() })?;
+
+		ServiceAccount::from_doc(service_account)
+	}
+
+	/// Issues a new secret, and invalidates the tokens of the previous one.
+	pub async fn rotate_secret(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		client_id: &str,
+	) -> Result<ServiceAccountWithSecret> {
+		let client_secret = generate_client_secret();
+		let secret_hash = Self::hash_secret(
+			client_id,
+			&client_secret,
+		)
+		.await?;
+
+		let service_account = Self::collection(mm)
+			.find_one_and_update(
+				doc! { "_id": client_id, "tenant_id": ctx.tenant_id() },
+				doc! { "$set": { "secret_hash": secret_hash, "token_salt": Uuid::new_v4().to_string() } },
+			)
+			.return_document(ReturnDocument::After)
+			.await
+			.map_err(|_| Error::UpdateError)?
+			.ok_or_else(|| Error::ServiceAccountNotFound { id: client_id.to_string() })?;
+
+		Ok(
+			ServiceAccountWithSecret {
+				service_account: ServiceAccount::from_doc(service_account)?,
+				client_secret,
+			},
+		)
+	}
+
+	pub async fn delete(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		client_id: &str,
+	) -> Result<ServiceAccount> {
+		let service_account = Self::collection(mm)
+			.find_one_and_delete(doc! { "_id": client_id, "tenant_id": ctx.tenant_id() })
+			.await
+			.map_err(|_| Error::DeleteError)?
+			.ok_or_else(|| Error::ServiceAccountNotFound { id: client_id.to_string() })?;
+
+		ServiceAccount::from_doc(service_account)
+	}
+
+	/// Authenticates the client credentials (OAuth2 client-credentials grant).
+	pub async fn authenticate(
+		mm: &ModelManager,
+		client_id: &str,
+		client_secret: &str,
+	) -> Result<ServiceAccountForAuth> {
+		let to_hash = client_secret_to_hash(
+			client_id,
+			client_secret,
+		)
+		.ok_or(Error::ServiceAccountInvalidCredentials)?;
+		let collection = Self::collection(mm);
+
+		let service_account = collection
+			.find_one(doc! { "_id": client_id })
+			.await
+			.map_err(|_| Error::QueryError)?
+			.ok_or(Error::ServiceAccountInvalidCredentials)?;
+
+		let scheme_status = pwd::validate_pwd(
+			to_hash,
+			service_account.secret_hash.clone(),
+		)
+		.await
+		.map_err(|_| Error::ServiceAccountInvalidCredentials)?;
+
+		// -- Re-hash with the current scheme if needed.
+		if let SchemeStatus::Outdated = scheme_status {
+			let secret_hash = Self::hash_secret(
+				client_id,
+				client_secret,
+			)
+			.await?;
+			collection
+				.update_one(
+					doc! { "_id": client_id },
+					doc! { "$set": { "secret_hash": secret_hash } },
+				)
+				.await
+				.map_err(|_| Error::UpdateError)?;
+		}
+
+		service_account.try_into()
+	}
+
+	/// The service account of a token `sub` (see `Ctx::new_service_account`).
+	pub async fn get_for_auth(
+		mm: &ModelManager,
+		client_id: &str,
+	) -> Result<ServiceAccountForAuth> {
+		let service_account = Self::collection(mm)
+			.find_one(doc! { "_id": client_id })
+			.await
+			.map_err(|_| Error::QueryError)?
+			.ok_or_else(|| Error::ServiceAccountNotFound { id: client_id.to_string() })?;
+
+		service_account.try_into()
+	}
+}
+
+// endregion: --- ServiceAccountBmc
+
+// region:    --- (private) Helpers
+
+impl ServiceAccountBmc {
+	fn collection(mm: &ModelManager) -> Collection<ServiceAccountDoc> {
+		mm.client
+			.database(Ctx::root_ctx().tenant_id().as_str())
+			.collection(TABLE)
+	}
+
+	async fn hash_secret(
+		client_id: &str,
+		client_secret: &str,
+	) -> Result<String> {
+		let to_hash = client_secret_to_hash(
+			client_id,
+			client_secret,
+		)
+		.ok_or(Error::ServiceAccountInvalidCredentials)?;
+
+		Ok(pwd::hash_pwd(to_hash).await?)
+	}
+
+	async fn ensure_indexes(collection: &Collection<ServiceAccountDoc>) -> Result<()> {
+		collection
+			.create_index(
+				IndexModel::builder()
+					.keys(doc! { "tenant_id": 1 })
+					.build(),
+			)
+			.await
+			.map_err(|_| Error::IndexCreateFail)?;
+
+		Ok(())
+	}
+}
+
+// endregion: --- (private) Helpers
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use serial_test::serial;
+
+	#[serial]
+	#[tokio::test]
+	async fn test_service_account_authenticate_and_rotate() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let ctx = Ctx::root_ctx();
+		let created = ServiceAccountBmc::create(
+			&ctx,
+			&mm,
+			ServiceAccountForCreate {
+				name: "billing-service".to_string(),
+				roles: vec!["billing".to_string()],
+			},
+		)
+		.await?;
+		let client_id = &created.service_account.client_id;
+
+		// -- Exec
+		let auth = ServiceAccountBmc::authenticate(
+			&mm,
+			client_id,
+			&created.client_secret,
+		)
+		.await?;
+		let rotated = ServiceAccountBmc::rotate_secret(
+			&ctx, &mm, client_id,
+		)
+		.await?;
+		let auth_old_secret = ServiceAccountBmc::authenticate(
+			&mm,
+			client_id,
+			&created.client_secret,
+		)
+		.await;
+		let auth_new = ServiceAccountBmc::authenticate(
+			&mm,
+			client_id,
+			&rotated.client_secret,
+		)
+		.await?;
+
+		// -- Check
+		assert_eq!(
+			auth.roles,
+			vec!["billing".to_string()]
+		);
+		assert!(matches!(
+			auth_old_secret,
+			Err(super::Error::ServiceAccountInvalidCredentials)
+		));
+		assert_ne!(
+			auth_new.token_salt,
+			auth.token_salt
+		);
+
+		// -- Clean
+		ServiceAccountBmc::delete(
+			&ctx, &mm, client_id,
+		)
+		.await?;
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
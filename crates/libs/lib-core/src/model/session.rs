@@ -0,0 +1,605 @@
+//! The session inventory of the users (one session per login).
+//!
+//! Design:
+//!
+//! - A session is identified by its refresh token family (see `RefreshTokenBmc`), and tracks
+//!   the `jti` of its current web token, updated on each refresh.
+//! - A session is listed as long as its family has a usable refresh token,
+//!   and removed by a TTL index on `exp` (the refresh token expiration).
+//! - `last_seen` is updated by the auth middleware, at most every `LAST_SEEN_THROTTLE`
+//!   per instance, to avoid a write per request.
+//! - Revoking a session revokes its refresh token family and its current web token.
+
+use crate::ctx::Ctx;
+use crate::model::refresh_token::RefreshTokenBmc;
+use crate::model::token_revocation::TokenRevocationBmc;
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use lib_auth::token::refresh_token_duration_sec;
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const TABLE: &str = "Sessions";
+
+const LAST_SEEN_THROTTLE: Duration = Duration::from_secs(60);
+/// Past this size, the stale entries are pruned.
+const LAST_SEEN_PRUNE_LEN: usize = 10_000;
+
+// region:    --- Types
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+	Password,
+	/// A password, then a TOTP or recovery code (see `mfa`).
+	PasswordMfa,
+	Oidc,
+	/// A grant to an OAuth2 client app (see `oauth`).
+	OAuth,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionDoc {
+	/// The refresh token family id.
+	#[serde(rename = "_id")]
+	id: String,
+	user_id: String,
+	jti: String,
+	ctime: DateTime,
+	last_seen: DateTime,
+	ip: Option<String>,
+	user_agent: Option<String>,
+	auth_method: AuthMethod,
+	exp: DateTime,
+}
+
+/// A session, as listed to its user.
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+	pub id: String,
+	pub created: String,
+	pub last_seen: String,
+	pub ip: Option<String>,
+	pub user_agent: Option<String>,
+	pub auth_method: AuthMethod,
+	/// True for the session of the request.
+	pub current: bool,
+}
+
+pub struct SessionForCreate {
+	pub user_id: String,
+	/// The refresh token family of the login.
+	pub family_id: String,
+	/// The `jti` of the issued web token.
+	pub jti: String,
+	pub ip: Option<String>,
+	pub user_agent: Option<String>,
+	pub auth_method: AuthMethod,
+}
+
+impl Session {
+	fn from_doc(
+		doc: SessionDoc,
+		current_jti: Option<&str>,
+	) -> Result<Self> {
+		Ok(
+			Session {
+				current: current_jti == Some(doc.jti.as_str()),
+				id: doc.id,
+				created: doc
+					.ctime
+					.try_to_rfc3339_string()
+					.map_err(|_| Error::CrudDocumentError)?,
+				last_seen: doc
+					.last_seen
+					.try_to_rfc3339_string()
+					.map_err(|_| Error::CrudDocumentError)?,
+				ip: doc.ip,
+				user_agent: doc.user_agent,
+				auth_method: doc.auth_method,
+			},
+		)
+	}
+}
+
+// endregion: --- Types
+
+// region:    --- LastSeenCache
+
+/// The last `last_seen` update per `jti` (or API key), held by the `ModelManager`.
+#[derive(Debug, Default)]
+pub(in crate::model) struct LastSeenCache {
+	entries: Mutex<HashMap<String, Instant>>,
+}
+
+impl LastSeenCache {
+	/// True if the `key` (e.g., a `jti`) was not seen within `LAST_SEEN_THROTTLE` (and records it as seen).
+	pub(in crate::model) fn should_update(
+		&self,
+		key: &str,
+	) -> bool {
+		let Ok(mut entries) = self.entries.lock() else {
+			return true;
+		};
+		if entries
+			.get(key)
+			.is_some_and(|at| at.elapsed() < LAST_SEEN_THROTTLE)
+		{
+			return false;
+		}
+		if entries.len() >= LAST_SEEN_PRUNE_LEN {
+			entries.retain(|_, at| at.elapsed() < LAST_SEEN_THROTTLE);
+		}
+		entries.insert(
+			key.to_string(),
+			Instant::now(),
+		);
+
+		true
+	}
+}
+
+// endregion: --- LastSeenCache
+
+// region:    --- SessionBmc
+
+pub struct SessionBmc;
+
+impl SessionBmc {
+	pub async fn create(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		session_c: SessionForCreate,
+	) -> Result<String> {
+		let collection = Self::collection(
+			ctx, mm,
+		);
+		Self::ensure_indexes(&collection).await?;
+
+		let now = DateTime::now();
+		let session = SessionDoc {
+			id: session_c.family_id,
+			user_id: session_c.user_id,
+			jti: session_c.jti,
+			ctime: now,
+			last_seen: now,
+			ip: session_c.ip,
+			user_agent: session_c.user_agent,
+			auth_method: session_c.auth_method,
+			exp: Self::exp(),
+		};
+		collection
+			.insert_one(&session)
+			.await
+			.map_err(|_| Error::CreateError)?;
+
+		Ok(session.id)
+	}
+
+	/// Records the web token issued by a refresh of the session.
+	pub async fn update_jti(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		family_id: &str,
+		jti: &str,
+	) -> Result<()> {
+		let now = DateTime::now();
+		Self::collection(
+			ctx, mm,
+		)
+		.update_one(
+			doc! { "_id": family_id },
+			doc! { "$set": { "jti": jti, "last_seen": now, "exp": Self::exp() } },
+		)
+		.await
+		.map_err(|_| Error::UpdateError)?;
+
+		Ok(())
+	}
+
+	/// Records the web token re-issued in place of the web token `jti` (e.g., on a password change).
+	pub async fn replace_jti(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		jti: &str,
+		new_jti: &str,
+	) -> Result<()> {
+		Self::collection(
+			ctx, mm,
+		)
+		.update_one(
+			doc! { "jti": jti },
+			doc! { "$set": { "jti": new_jti, "last_seen": DateTime::now() } },
+		)
+		.await
+		.map_err(|_| Error::UpdateError)?;
+
+		Ok(())
+	}
+
+	/// Updates the `last_seen` of the session of the web token `jti` (throttled).
+	pub async fn touch(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		jti: &str,
+	) -> Result<()> {
+		if !mm.last_seen.should_update(jti) {
+			return Ok(());
+		}
+
+		Self::collection(
+			ctx, mm,
+		)
+		.update_one(
+			doc! { "jti": jti },
+			doc! { "$set": { "last_seen": DateTime::now() } },
+		)
+		.await
+		.map_err(|_| Error::UpdateError)?;
+
+		Ok(())
+	}
+
+	/// The active sessions of the user, most recently seen first.
+	pub async fn list_for_user(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+		current_jti: Option<&str>,
+	) -> Result<Vec<Session>> {
+		let family_ids = RefreshTokenBmc::active_family_ids(
+			ctx, mm, user_id,
+		)
+		.await?;
+
+		let mut cursor = Self::collection(
+			ctx, mm,
+		)
+		.find(doc! { "user_id": user_id, "_id": { "$in": family_ids } })
+		.sort(doc! { "last_seen": -1 })
+		.await
+		.map_err(|_| Error::QueryError)?;
+
+		let mut sessions = Vec::new();
+		while cursor
+			.advance()
+			.await
+			.map_err(|_| Error::QueryError)?
+		{
+			let session = cursor
+				.deserialize_current()
+				.map_err(|_| Error::CrudDocumentError)?;
+			sessions.push(
+				Session::from_doc(
+					session,
+					current_jti,
+				)?,
+			);
+		}
+
+		Ok(sessions)
+	}
+
+	/// Revokes the session `id` of the user, and returns it.
+	pub async fn revoke_for_user(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+		id: &str,
+	) -> Result<Session> {
+		let session = Self::collection(
+			ctx, mm,
+		)
+		.find_one_and_delete(doc! { "_id": id, "user_id": user_id })
+		.await
+		.map_err(|_| Error::DeleteError)?
+		.ok_or_else(|| Error::SessionNotFound { id: id.to_string() })?;
+
+		RefreshTokenBmc::revoke_family_id(
+			ctx, mm, id,
+		)
+		.await?;
+		TokenRevocationBmc::revoke_token(
+			ctx,
+			mm,
+			user_id,
+			&session.jti,
+		)
+		.await?;
+
+		Session::from_doc(
+			session, None,
+		)
+	}
+
+	/// The `jti` of the current web token of each session of the user.
+	pub(in crate::model) async fn current_jtis(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+	) -> Result<Vec<String>> {
+		let jtis = Self::collection(
+			ctx, mm,
+		)
+		.distinct(
+			"jti",
+			doc! { "user_id": user_id },
+		)
+		.await
+		.map_err(|_| Error::QueryError)?;
+
+		Ok(
+			jtis
+				.into_iter()
+				.filter_map(|jti| jti.as_str().map(str::to_string))
+				.collect(),
+		)
+	}
+
+	/// Revokes the sessions of the user, but the one of the web token `jti` (if any),
+	/// e.g., on a password change.
+	///
+	/// Note: Their web tokens are not revoked, as the password change already invalidates them (new salt).
+	pub async fn revoke_others_for_user(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+		jti: Option<&str>,
+	) -> Result<()> {
+		let collection = Self::collection(
+			ctx, mm,
+		);
+		let current = match jti {
+			Some(jti) => collection
+				.find_one(doc! { "user_id": user_id, "jti": jti })
+				.await
+				.map_err(|_| Error::QueryError)?,
+			None => None,
+		};
+
+		match &current {
+			Some(current) => {
+				RefreshTokenBmc::revoke_all_except(
+					ctx,
+					mm,
+					user_id,
+					&current.id,
+				)
+				.await?;
+				collection
+					.delete_many(doc! { "user_id": user_id, "_id": { "$ne": &current.id } })
+					.await
+					.map_err(|_| Error::DeleteError)?;
+			},
+			None => {
+				RefreshTokenBmc::revoke_all_for_user(
+					ctx, mm, user_id,
+				)
+				.await?;
+				collection
+					.delete_many(doc! { "user_id": user_id })
+					.await
+					.map_err(|_| Error::DeleteError)?;
+			},
+		}
+
+		Ok(())
+	}
+}
+
+// endregion: --- SessionBmc
+
+// region:    --- (private) Helpers
+
+impl SessionBmc {
+	fn collection(
+		ctx: &Ctx,
+		mm: &ModelManager,
+	) -> Collection<SessionDoc> {
+		mm.client
+			.database(ctx.tenant_id().as_str())
+			.collection(TABLE)
+	}
+
+	/// The session ends with its refresh token family.
+	fn exp() -> DateTime {
+		DateTime::from_millis(
+			DateTime::now().timestamp_millis() + (refresh_token_duration_sec() * 1000.) as i64,
+		)
+	}
+
+	async fn ensure_indexes(collection: &Collection<SessionDoc>) -> Result<()> {
+		let indexes = [
+			IndexModel::builder()
+				.keys(doc! { "exp": 1 })
+				.options(
+					IndexOptions::builder()
+						.expire_after(Duration::ZERO)
+						.build(),
+				)
+				.build(),
+			IndexModel::builder()
+				.keys(doc! { "jti": 1 })
+				.build(),
+			IndexModel::builder()
+				.keys(doc! { "user_id": 1 })
+				.build(),
+		];
+		collection
+			.create_indexes(indexes)
+			.await
+			.map_err(|_| Error::IndexCreateFail)?;
+
+		Ok(())
+	}
+}
+
+// endregion: --- (private) Helpers
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use serial_test::serial;
+	use uuid::Uuid;
+
+	#[test]
+	fn test_last_seen_cache_throttle_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let cache = LastSeenCache::default();
+
+		// -- Exec
+		let first = cache.should_update("jti-01");
+		let second = cache.should_update("jti-01");
+		let other = cache.should_update("jti-02");
+
+		// -- Check
+		assert!(first);
+		assert!(!second);
+		assert!(other);
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_session_list_and_revoke() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let ctx = Ctx::root_ctx();
+		let fx_user_id = Uuid::new_v4().to_string();
+		let refresh = RefreshTokenBmc::create(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			"device-01",
+		)
+		.await?;
+		let session_id = SessionBmc::create(
+			&ctx,
+			&mm,
+			SessionForCreate {
+				user_id: fx_user_id.clone(),
+				family_id: refresh.family_id,
+				jti: "jti-01".to_string(),
+				ip: Some("127.0.0.1".to_string()),
+				user_agent: None,
+				auth_method: AuthMethod::Password,
+			},
+		)
+		.await?;
+
+		// -- Exec
+		let sessions = SessionBmc::list_for_user(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			Some("jti-01"),
+		)
+		.await?;
+		SessionBmc::revoke_for_user(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			&session_id,
+		)
+		.await?;
+		let sessions_after = SessionBmc::list_for_user(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			None,
+		)
+		.await?;
+
+		// -- Check
+		assert_eq!(
+			sessions.len(),
+			1
+		);
+		assert!(sessions[0].current);
+		assert!(sessions_after.is_empty());
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_session_revoke_others() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let ctx = Ctx::root_ctx();
+		let fx_user_id = Uuid::new_v4().to_string();
+		let mut refresh_tokens = Vec::new();
+		for (fx_device_id, fx_jti) in [("device-01", "jti-01"), ("device-02", "jti-02")] {
+			let refresh = RefreshTokenBmc::create(
+				&ctx,
+				&mm,
+				&fx_user_id,
+				fx_device_id,
+			)
+			.await?;
+			SessionBmc::create(
+				&ctx,
+				&mm,
+				SessionForCreate {
+					user_id: fx_user_id.clone(),
+					family_id: refresh.family_id.clone(),
+					jti: fx_jti.to_string(),
+					ip: None,
+					user_agent: None,
+					auth_method: AuthMethod::Password,
+				},
+			)
+			.await?;
+			refresh_tokens.push(refresh.refresh_token);
+		}
+
+		// -- Exec
+		SessionBmc::revoke_others_for_user(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			Some("jti-01"),
+		)
+		.await?;
+		let res_current = RefreshTokenBmc::rotate(
+			&ctx,
+			&mm,
+			&refresh_tokens[0],
+		)
+		.await;
+		let res_other = RefreshTokenBmc::rotate(
+			&ctx,
+			&mm,
+			&refresh_tokens[1],
+		)
+		.await;
+		let sessions = SessionBmc::list_for_user(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			None,
+		)
+		.await?;
+
+		// -- Check
+		assert!(res_current.is_ok());
+		assert!(res_other.is_err());
+		assert_eq!(
+			sessions.len(),
+			1
+		);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -0,0 +1,367 @@
+//! Server-side revocation of the web tokens, before their `exp`.
+//!
+//! Design:
+//!
+//! - A single token is revoked by its `jti` (see `WebToken::jti`).
+//! - All the tokens of a user are revoked with a cutoff: the tokens issued before that second
+//!   are rejected, and the ones of that second by their `jti` (the current tokens of its sessions),
+//!   so a token issued right after is accepted. The refresh tokens of the user are revoked as well.
+//! - Revocations are only needed until the revoked tokens expire, so they are removed by
+//!   a TTL index on `exp` (now + `TOKEN_DURATION_SEC`).
+//! - Lookups are cached in the `ModelManager` for `CACHE_TTL`, so a revocation made by another
+//!   instance takes effect within `CACHE_TTL`, and immediately on the revoking instance.
+
+use crate::ctx::Ctx;
+use crate::model::refresh_token::RefreshTokenBmc;
+use crate::model::session::SessionBmc;
+use crate::model::ModelManager;
+use crate::model::{Error, Result};
+use lib_auth::token::token_duration_sec;
+use mongodb::bson::{doc, DateTime, Document};
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, IndexModel};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const TABLE: &str = "TokenRevocations";
+
+const CACHE_TTL: Duration = Duration::from_secs(10);
+/// Past this size, the stale entries are pruned.
+const CACHE_PRUNE_LEN: usize = 10_000;
+
+const KIND_TOKEN: &str = "token";
+const KIND_USER: &str = "user";
+
+// region:    --- RevocationCache
+
+/// The in-process cache of the revocation lookups, held by the `ModelManager`.
+#[derive(Debug, Default)]
+pub(in crate::model) struct RevocationCache {
+	/// jti -> entry
+	entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+	user_id: String,
+	revoked: bool,
+	at: Instant,
+}
+
+impl RevocationCache {
+	fn get(
+		&self,
+		jti: &str,
+	) -> Option<bool> {
+		let entries = self.entries.lock().ok()?;
+		entries
+			.get(jti)
+			.filter(|entry| entry.at.elapsed() < CACHE_TTL)
+			.map(|entry| entry.revoked)
+	}
+
+	fn insert(
+		&self,
+		jti: &str,
+		user_id: &str,
+		revoked: bool,
+	) {
+		let Ok(mut entries) = self.entries.lock() else {
+			return;
+		};
+		if entries.len() >= CACHE_PRUNE_LEN {
+			entries.retain(|_, entry| entry.at.elapsed() < CACHE_TTL);
+		}
+		entries.insert(
+			jti.to_string(),
+			CacheEntry {
+				user_id: user_id.to_string(),
+				revoked,
+				at: Instant::now(),
+			},
+		);
+	}
+
+	fn remove_user(
+		&self,
+		user_id: &str,
+	) {
+		if let Ok(mut entries) = self.entries.lock() {
+			entries.retain(|_, entry| entry.user_id != user_id);
+		}
+	}
+}
+
+// endregion: --- RevocationCache
+
+// region:    --- TokenRevocationBmc
+
+pub struct TokenRevocationBmc;
+
+impl TokenRevocationBmc {
+	/// Revokes a single token (e.g., the current session).
+	pub async fn revoke_token(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+		jti: &str,
+	) -> Result<()> {
+		let collection = Self::collection(
+			ctx, mm,
+		);
+		Self::ensure_indexes(&collection).await?;
+
+		collection
+			.insert_one(
+				doc! { "kind": KIND_TOKEN, "jti": jti, "user_id": user_id, "exp": Self::exp() },
+			)
+			.await
+			.map_err(|_| Error::CreateError)?;
+		mm.revocations.insert(
+			jti, user_id, true,
+		);
+
+		Ok(())
+	}
+
+	/// Revokes all the current tokens and refresh tokens of the user ("log out everywhere").
+	pub async fn revoke_all_for_user(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+	) -> Result<()> {
+		let collection = Self::collection(
+			ctx, mm,
+		);
+		Self::ensure_indexes(&collection).await?;
+
+		let now = DateTime::now();
+		collection
+			.update_one(
+				doc! { "kind": KIND_USER, "user_id": user_id },
+				doc! { "$set": { "revoked_before": now.timestamp_millis() / 1000, "exp": Self::exp() } },
+			)
+			.upsert(true)
+			.await
+			.map_err(|_| Error::UpdateError)?;
+		mm.revocations.remove_user(user_id);
+
+		// -- The tokens of the cutoff second (not rejected by the cutoff).
+		for jti in SessionBmc::current_jtis(
+			ctx, mm, user_id,
+		)
+		.await?
+		{
+			Self::revoke_token(
+				ctx, mm, user_id, &jti,
+			)
+			.await?;
+		}
+
+		RefreshTokenBmc::revoke_all_for_user(
+			ctx, mm, user_id,
+		)
+		.await
+	}
+
+	/// True if the token `jti`, of `user_id` and issued at `iat` (seconds since the epoch), is revoked.
+	pub async fn is_revoked(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		user_id: &str,
+		jti: &str,
+		iat: i64,
+	) -> Result<bool> {
+		if let Some(revoked) = mm.revocations.get(jti) {
+			return Ok(revoked);
+		}
+
+		let filter = doc! {
+			"$or": [
+				{ "kind": KIND_TOKEN, "jti": jti },
+				{ "kind": KIND_USER, "user_id": user_id, "revoked_before": { "$gt": iat } },
+			]
+		};
+		let revoked = Self::collection(
+			ctx, mm,
+		)
+		.find_one(filter)
+		.await
+		.map_err(|_| Error::QueryError)?
+		.is_some();
+		mm.revocations.insert(
+			jti, user_id, revoked,
+		);
+
+		Ok(revoked)
+	}
+}
+
+// endregion: --- TokenRevocationBmc
+
+// region:    --- (private) Helpers
+
+impl TokenRevocationBmc {
+	fn collection(
+		ctx: &Ctx,
+		mm: &ModelManager,
+	) -> Collection<Document> {
+		mm.client
+			.database(ctx.tenant_id().as_str())
+			.collection(TABLE)
+	}
+
+	/// The revoked tokens are expired by then.
+	fn exp() -> DateTime {
+		DateTime::from_millis(
+			DateTime::now().timestamp_millis() + (token_duration_sec() * 1000.) as i64,
+		)
+	}
+
+	async fn ensure_indexes(collection: &Collection<Document>) -> Result<()> {
+		let indexes = [
+			IndexModel::builder()
+				.keys(doc! { "exp": 1 })
+				.options(
+					IndexOptions::builder()
+						.expire_after(Duration::ZERO)
+						.build(),
+				)
+				.build(),
+			IndexModel::builder()
+				.keys(doc! { "jti": 1 })
+				.build(),
+			IndexModel::builder()
+				.keys(doc! { "user_id": 1 })
+				.build(),
+		];
+		collection
+			.create_indexes(indexes)
+			.await
+			.map_err(|_| Error::IndexCreateFail)?;
+
+		Ok(())
+	}
+}
+
+// endregion: --- (private) Helpers
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use crate::model::session::{AuthMethod, SessionForCreate};
+	use serial_test::serial;
+	use uuid::Uuid;
+
+	#[test]
+	fn test_revocation_cache_remove_user_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let cache = RevocationCache::default();
+		cache.insert(
+			"jti-01", "user-01", false,
+		);
+		cache.insert(
+			"jti-02", "user-02", true,
+		);
+
+		// -- Exec
+		cache.remove_user("user-01");
+
+		// -- Check
+		assert_eq!(
+			cache.get("jti-01"),
+			None
+		);
+		assert_eq!(
+			cache.get("jti-02"),
+			Some(true)
+		);
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_token_revocation_revoke_all_for_user() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = ModelManager::new().await?;
+		let ctx = Ctx::root_ctx();
+		let fx_user_id = Uuid::new_v4().to_string();
+		let fx_iat = DateTime::now().timestamp_millis() / 1000;
+		let refresh = RefreshTokenBmc::create(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			"device-01",
+		)
+		.await?;
+		SessionBmc::create(
+			&ctx,
+			&mm,
+			SessionForCreate {
+				user_id: fx_user_id.clone(),
+				family_id: refresh.family_id,
+				jti: "jti-01".to_string(),
+				ip: None,
+				user_agent: None,
+				auth_method: AuthMethod::Password,
+			},
+		)
+		.await?;
+
+		// -- Exec
+		let revoked_before = TokenRevocationBmc::is_revoked(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			"jti-01",
+			fx_iat,
+		)
+		.await?;
+		TokenRevocationBmc::revoke_all_for_user(
+			&ctx,
+			&mm,
+			&fx_user_id,
+		)
+		.await?;
+		let revoked_after = TokenRevocationBmc::is_revoked(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			"jti-01",
+			fx_iat,
+		)
+		.await?;
+		let revoked_earlier = TokenRevocationBmc::is_revoked(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			"jti-02",
+			fx_iat - 1,
+		)
+		.await?;
+		// Note: Issued after the revocation, within its second.
+		let revoked_next = TokenRevocationBmc::is_revoked(
+			&ctx,
+			&mm,
+			&fx_user_id,
+			"jti-03",
+			DateTime::now().timestamp_millis() / 1000,
+		)
+		.await?;
+
+		// -- Check
+		assert!(!revoked_before);
+		assert!(revoked_after);
+		assert!(revoked_earlier);
+		assert!(!revoked_next);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
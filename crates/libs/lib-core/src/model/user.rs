@@ -1,14 +1,48 @@
 use crate::ctx::Ctx;
-use crate::model::base::{create, delete, get, list, update, DbBmc};
-use crate::model::ModelManager;
+use crate::model::base::{create, delete, escape_regex, get, list, update, DbBmc};
+use crate::model::token_revocation::TokenRevocationBmc;
+use crate::model::validation::{
+	check_email, check_length, check_pwd_policy, check_required, Validate, ValidationError, ValidationResult,
+};
 use crate::model::{Error, Result};
+use crate::model::{ListOptions, ModelManager};
+use futures::stream::TryStreamExt;
 use lib_auth::pwd;
 use lib_auth::pwd::ContentToHash;
-use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::bson::{doc, from_document, oid::ObjectId, Bson, Document};
+use mongodb::error::ErrorKind;
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, IndexModel};
 use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::collections::HashMap;
 use time::Date;
 use uuid::Uuid;
 
+/// The unique indexes of the identifiers, with their default names (see `UserBmc::normalize_identifiers`).
+const MAIL_INDEX: &str = "metadata.mail_1";
+const USERNAME_INDEX: &str = "login.username_1";
+const DUPLICATE_KEY_CODE: i32 = 11000;
+
+/// The username or mail as stored and matched (trimmed and lowercased), so the logins are case-insensitive.
+pub fn normalize_identifier(identifier: &str) -> String {
+	identifier.trim().to_lowercase()
+}
+
+/// The account state of a user, only the `Active` users can authenticate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UserState {
+	#[default]
+	Active,
+	/// Deactivated by an admin.
+	Disabled,
+	/// Self-registered, until its mail is verified (see `UserBmc::register`).
+	PendingVerification,
+	/// Locked by an admin (e.g., suspected compromise), unlike the temporary lock of the failed logins.
+	Locked,
+}
+
 /// --- Q-User Types
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -16,9 +50,12 @@ pub struct QUser {
 	#[serde(rename = "_id")]
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub id: Option<String>,
+	/// Mirrors `state` (`Active` or not), for the users stored before it.
 	pub active: Option<bool>,
+	pub state: Option<UserState>,
 	pub login: Option<QUserLogin>,
 	pub metadata: Option<QUserMeta>,
+	pub roles: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -34,6 +71,9 @@ pub struct QUserMeta {
 	pub surname: Option<String>,
 	pub birth: Option<Date>,
 	pub mail: Option<String>,
+	/// The new mail of a profile update, until verified (see `UserBmc::confirm_mail_change`).
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub mail_pending: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -42,19 +82,313 @@ pub struct QUserForCreate {
 	pub pwd_clear: String,
 }
 
+/// A self-registered user (see `UserBmc::register`), not active until its mail is verified.
+#[derive(Debug, Deserialize)]
+pub struct QUserForRegister {
+	pub username: String,
+	pub pwd_clear: String,
+	pub mail: String,
+}
+
+impl Validate for QUserForRegister {
+	fn validate(&self) -> ValidationResult {
+		let mut errors = ValidationError::default();
+		check_length(
+			&mut errors,
+			"username",
+			&self.username,
+			Some(3),
+			Some(64),
+		);
+		check_pwd_policy(
+			&mut errors,
+			"pwd",
+			&self.pwd_clear,
+			&self.username,
+		);
+		check_email(
+			&mut errors,
+			"mail",
+			&self.mail,
+		);
+		errors.into_result()
+	}
+}
+
+/// A user provisioned by an external identity provider (see `oidc`), without password.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct QUserForCreateExternal {
+	pub username: String,
+	pub name: Option<String>,
+	pub surname: Option<String>,
+	pub mail: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct QUserForLogin {
 	pub id: String,
 	pub username: String,
+	pub state: UserState,
 	pub pwd: Option<String>,
 	pub pwd_salt: Uuid,
+	pub roles: Vec<String>,
+}
+
+/// The `$set` of a password update (see `UserBmc::update_pwd`), or removal (see `UserBmc::clear_pwd`).
+#[derive(Serialize)]
+struct QUserPwdUpdate {
+	#[serde(rename = "login.pw")]
+	pw: Option<String>,
+	#[serde(rename = "login.pwd_salt")]
+	pwd_salt: Uuid,
+}
+
+/// The admin view of a user (see `UserBmc::get_view`), without its password hash and salt.
+#[derive(Debug, Serialize)]
+pub struct QUserView {
+	pub id: String,
+	pub username: String,
+	pub state: UserState,
+	/// False for the users without password (e.g., external, invited, or forced to reset it).
+	pub has_pwd: bool,
+	pub roles: Vec<String>,
+	pub name: Option<String>,
+	pub surname: Option<String>,
+	pub birth: Option<Date>,
+	pub mail: Option<String>,
+}
+
+/// The filter of the admin user list (see `UserBmc::list_views`), all the given fields must match.
+#[derive(Debug, Deserialize, Default)]
+pub struct QUserFilter {
+	/// Part of the username (case-insensitive).
+	pub username: Option<String>,
+	/// Part of the mail (case-insensitive).
+	pub mail: Option<String>,
+	pub state: Option<UserState>,
+	/// Users with this role.
+	pub role: Option<String>,
+}
+
+impl QUserFilter {
+	fn into_doc(self) -> Document {
+		let mut filter = Document::new();
+		if let Some(username) = self.username {
+			filter.insert(
+				"login.username",
+				doc! { "$regex": escape_regex(&username), "$options": "i" },
+			);
+		}
+		if let Some(mail) = self.mail {
+			filter.insert(
+				"metadata.mail",
+				doc! { "$regex": escape_regex(&mail), "$options": "i" },
+			);
+		}
+		// Note: The users stored before `state` only have the `active` flag (see `QUser::state`).
+		match self.state {
+			Some(UserState::Active) => {
+				filter.insert(
+					"active",
+					doc! { "$ne": false },
+				);
+			},
+			Some(state) => {
+				filter.insert(
+					"state",
+					state.as_str(),
+				);
+			},
+			None => (),
+		}
+		if let Some(role) = self.role {
+			filter.insert(
+				"roles", role,
+			);
+		}
+		filter
+	}
+}
+
+/// A user created by an admin (see `UserBmc::create_by_admin`), with an initial password,
+/// or without (to be invited by mail to choose it).
+#[derive(Debug, Deserialize)]
+pub struct QUserForAdminCreate {
+	pub username: String,
+	pub pwd_clear: Option<String>,
+	pub mail: Option<String>,
+	pub name: Option<String>,
+	pub surname: Option<String>,
+	#[serde(default)]
+	pub roles: Vec<String>,
+}
+
+impl Validate for QUserForAdminCreate {
+	fn validate(&self) -> ValidationResult {
+		let mut errors = ValidationError::default();
+		check_length(
+			&mut errors,
+			"username",
+			&self.username,
+			Some(3),
+			Some(64),
+		);
+		match &self.pwd_clear {
+			Some(pwd_clear) => check_pwd_policy(
+				&mut errors,
+				"pwd",
+				pwd_clear,
+				&self.username,
+			),
+			// The invitation is sent by mail.
+			None => check_required(
+				&mut errors,
+				"mail",
+				&self.mail,
+			),
+		}
+		if let Some(mail) = &self.mail {
+			check_email(
+				&mut errors,
+				"mail",
+				mail,
+			);
+		}
+		errors.into_result()
+	}
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QUserRolesForUpdate {
+	pub roles: Vec<String>,
+}
+
+/// The self-service view of a user (see `UserBmc::get_profile`), without its `login`.
+#[derive(Debug, Serialize)]
+pub struct QUserProfile {
+	pub id: String,
+	pub username: String,
+	pub name: Option<String>,
+	pub surname: Option<String>,
+	pub birth: Option<Date>,
+	pub mail: Option<String>,
+	/// The new mail, until verified (see `UserBmc::confirm_mail_change`).
+	pub mail_pending: Option<String>,
+}
+
+/// The profile fields to update, the missing ones are kept (see `UserBmc::update_profile`).
+///
+/// Note: Serialized as the `$set` of the update, hence the `metadata.` field names.
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct QUserProfileForUpdate {
+	#[serde(rename(serialize = "metadata.name"))]
+	pub name: Option<String>,
+	#[serde(rename(serialize = "metadata.surname"))]
+	pub surname: Option<String>,
+	#[serde(rename(serialize = "metadata.birth"))]
+	pub birth: Option<Date>,
+	/// The new mail, only stored as pending until verified (see `UserBmc::confirm_mail_change`).
+	#[serde(rename(serialize = "metadata.mail_pending"))]
+	pub mail: Option<String>,
+}
+
+impl QUserProfileForUpdate {
+	fn is_empty(&self) -> bool {
+		self.name.is_none() && self.surname.is_none() && self.birth.is_none() && self.mail.is_none()
+	}
+}
+
+impl Validate for QUserProfileForUpdate {
+	fn validate(&self) -> ValidationResult {
+		let mut errors = ValidationError::default();
+		if let Some(name) = &self.name {
+			check_length(
+				&mut errors,
+				"name",
+				name,
+				Some(1),
+				Some(100),
+			);
+		}
+		if let Some(surname) = &self.surname {
+			check_length(
+				&mut errors,
+				"surname",
+				surname,
+				Some(1),
+				Some(100),
+			);
+		}
+		if let Some(mail) = &self.mail {
+			check_email(
+				&mut errors,
+				"mail",
+				mail,
+			);
+		}
+		errors.into_result()
+	}
+}
+
+/// A password change of a logged-in user (see `UserBmc::change_pwd`).
+#[derive(Debug, Deserialize)]
+pub struct QUserPwdChange {
+	pub pwd_current: String,
+	pub pwd_new: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QUserStateForUpdate {
+	pub state: UserState,
+}
+
+/// The outcome of the normalization of the stored usernames and mails (see `UserBmc::normalize_identifiers`).
+#[derive(Debug, Serialize, Default)]
+pub struct QUserNormalizeReport {
+	/// The number of users with their username or mail updated.
+	pub normalized: usize,
+	/// The users left as is, as their normalized username or mail is the one of another user
+	/// (to be renamed before running the normalization again).
+	pub conflicts: Vec<String>,
+	/// The unique indexes not created yet, as duplicate usernames (or mails) remain.
+	pub indexes_pending: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct QUserForAuth {
 	pub id: String,
 	pub username: String,
+	pub state: UserState,
 	pub pwd_salt: Uuid,
+	pub roles: Vec<String>,
+}
+
+impl QUserForAuth {
+	/// Fails with `UserNotActive` unless the user is `Active`, before authenticating it
+	/// (or issuing it a token).
+	pub fn check_active(&self) -> Result<()> {
+		match self.state {
+			UserState::Active => Ok(()),
+			state => Err(
+				Error::UserNotActive {
+					id: self.id.clone(),
+					state,
+				},
+			),
+		}
+	}
+}
+
+impl UserState {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Active => "active",
+			Self::Disabled => "disabled",
+			Self::PendingVerification => "pending_verification",
+			Self::Locked => "locked",
+		}
+	}
 }
 impl QUser {
 	pub fn filter_id(id_string: &String) -> Result<Self> {
@@ -62,26 +396,53 @@ impl QUser {
 			QUser {
 				id: Some(id_string.to_string()),
 				active: None,
+				state: None,
 				login: None,
 				metadata: None,
+				roles: None,
 			},
 		)
 	}
 
 	pub fn filter_username(username: String) -> Result<Self> {
+		Ok(Self::filter_username_as_stored(normalize_identifier(&username)))
+	}
+
+	pub fn filter_mail(mail: String) -> Result<Self> {
+		Ok(Self::filter_mail_as_stored(normalize_identifier(&mail)))
+	}
+
+	/// The filter of the `username` as is, e.g., of a user not normalized yet (see `UserBmc::normalize_identifiers`).
+	fn filter_username_as_stored(username: String) -> Self {
 		let login = QUserLogin {
 			username: Some(username),
 			pw: None,
 			pwd_salt: None,
 		};
-		Ok(
-			QUser {
-				id: None,
-				active: None,
-				login: Some(login),
-				metadata: None,
-			},
-		)
+		QUser {
+			id: None,
+			active: None,
+			state: None,
+			login: Some(login),
+			metadata: None,
+			roles: None,
+		}
+	}
+
+	/// The filter of the `mail` as is, e.g., of a user not normalized yet (see `UserBmc::normalize_identifiers`).
+	fn filter_mail_as_stored(mail: String) -> Self {
+		let metadata = QUserMeta {
+			mail: Some(mail),
+			..Default::default()
+		};
+		QUser {
+			id: None,
+			active: None,
+			state: None,
+			login: None,
+			metadata: Some(metadata),
+			roles: None,
+		}
 	}
 
 	pub async fn create_user(userc: QUserForCreate) -> Result<Self> {
@@ -95,7 +456,7 @@ impl QUser {
 		.await?;
 
 		let login = QUserLogin {
-			username: Some(userc.username),
+			username: Some(normalize_identifier(&userc.username)),
 			pw: Some(pwd),
 			pwd_salt: Some(salt),
 		};
@@ -104,8 +465,124 @@ impl QUser {
 			QUser {
 				id: None,
 				active: Some(true),
+				state: Some(UserState::Active),
 				login: Some(login),
 				metadata: None,
+				roles: None,
+			},
+		)
+	}
+
+	pub async fn create_registered_user(userc: QUserForRegister) -> Result<Self> {
+		let QUserForRegister {
+			username,
+			pwd_clear,
+			mail,
+		} = userc;
+		let mut user = Self::create_user(QUserForCreate { username, pwd_clear }).await?;
+		user.active = Some(false);
+		user.state = Some(UserState::PendingVerification);
+		user.metadata = Some(
+			QUserMeta {
+				mail: Some(normalize_identifier(&mail)),
+				..Default::default()
+			},
+		);
+
+		Ok(user)
+	}
+
+	/// Note: The `pwd_salt` is still set, as it salts the tokens of the user.
+	pub fn create_external_user(userc: QUserForCreateExternal) -> Self {
+		let login = QUserLogin {
+			username: Some(normalize_identifier(&userc.username)),
+			pw: None,
+			pwd_salt: Some(Uuid::new_v4()),
+		};
+		let metadata = QUserMeta {
+			name: userc.name,
+			surname: userc.surname,
+			birth: None,
+			mail: userc.mail.as_deref().map(normalize_identifier),
+			mail_pending: None,
+		};
+
+		QUser {
+			id: None,
+			active: Some(true),
+			state: Some(UserState::Active),
+			login: Some(login),
+			metadata: Some(metadata),
+			roles: None,
+		}
+	}
+
+	/// Note: The users stored before `state` are `Disabled` if not `active`.
+	pub fn state(&self) -> UserState {
+		match (self.state, self.active) {
+			(Some(state), _) => state,
+			(None, Some(false)) => UserState::Disabled,
+			(None, _) => UserState::Active,
+		}
+	}
+
+	fn into_for_auth(self) -> Result<QUserForAuth> {
+		let state = self.state();
+		let id = self.id.ok_or(Error::ReadError)?.to_string();
+		let login = self.login.ok_or(Error::ReadError)?;
+		let username = login.username.ok_or(Error::ReadError)?;
+		let pwd_salt = login.pwd_salt.ok_or(Error::ReadError)?;
+
+		Ok(
+			QUserForAuth {
+				id,
+				username,
+				state,
+				pwd_salt,
+				roles: self.roles.unwrap_or_default(),
+			},
+		)
+	}
+
+	fn into_view(self) -> Result<QUserView> {
+		let state = self.state();
+		let id = self.id.ok_or(Error::ReadError)?.to_string();
+		let login = self.login.ok_or(Error::ReadError)?;
+		let username = login.username.ok_or(Error::ReadError)?;
+		let metadata = self.metadata.unwrap_or_default();
+
+		Ok(
+			QUserView {
+				id,
+				username,
+				state,
+				has_pwd: login.pw.is_some(),
+				roles: self.roles.unwrap_or_default(),
+				name: metadata.name,
+				surname: metadata.surname,
+				birth: metadata.birth,
+				mail: metadata.mail,
+			},
+		)
+	}
+
+	fn into_profile(self) -> Result<QUserProfile> {
+		let id = self.id.ok_or(Error::ReadError)?.to_string();
+		let username = self
+			.login
+			.and_then(|login| login.username)
+			.ok_or(Error::ReadError)?;
+		let metadata = self.metadata.unwrap_or_default();
+
+		Ok(
+			QUserProfile {
+				id,
+				username,
+				name: metadata.name,
+				surname: metadata.surname,
+				birth: metadata.birth,
+				mail: metadata.mail,
+				mail_pending: metadata.mail_pending,
 			},
 		)
 	}
@@ -127,6 +604,8 @@ pub struct UserBmc;
 
 impl DbBmc for UserBmc {
 	const TABLE: &'static str = "Users";
+	/// Only the public fields of the users are expanded (see `QUserView` for the admin view).
+	const EXPAND_FIELDS: &'static [&'static str] = &["login.username"];
 }
 
 impl UserBmc {
@@ -143,49 +622,402 @@ impl UserBmc {
 		Ok(id)
 	}
 
-	pub async fn update_pwd(
+	/// Creates a self-registered user, `PendingVerification` until its mail is verified (see `QUserForRegister`).
+	pub async fn register(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		input: QUserForRegister,
+	) -> Result<String> {
+		input.validate()?;
+		Self::check_not_taken(
+			ctx,
+			mm,
+			&input.username,
+			Some(&input.mail),
+		)
+		.await?;
+
+		let new_user = QUser::create_registered_user(input).await?;
+		create::<UserBmc, QUser>(
+			ctx, mm, new_user,
+		)
+		.await
+	}
+
+	/// Creates an active user for an admin, without password if none given (see `QUserForAdminCreate`).
+	pub async fn create_by_admin(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		input: QUserForAdminCreate,
+	) -> Result<String> {
+		input.validate()?;
+		Self::check_not_taken(
+			ctx,
+			mm,
+			&input.username,
+			input.mail.as_deref(),
+		)
+		.await?;
+
+		let QUserForAdminCreate {
+			username,
+			pwd_clear,
+			mail,
+			name,
+			surname,
+			roles,
+		} = input;
+		let mut new_user = match pwd_clear {
+			Some(pwd_clear) => QUser::create_user(QUserForCreate { username, pwd_clear }).await?,
+			None => QUser::create_external_user(
+				QUserForCreateExternal {
+					username,
+					..Default::default()
+				},
+			),
+		};
+		new_user.metadata = Some(
+			QUserMeta {
+				name,
+				surname,
+				birth: None,
+				mail: mail.as_deref().map(normalize_identifier),
+				mail_pending: None,
+			},
+		);
+		new_user.roles = Some(roles);
+
+		create::<UserBmc, QUser>(
+			ctx, mm, new_user,
+		)
+		.await
+	}
+
+	/// Sets the account state of the user. Leaving `Active` revokes all its tokens and refresh tokens
+	/// (its next requests are rejected anyway, see `QUserForAuth::check_active`).
+	pub async fn set_state(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: &String,
+		state: UserState,
+	) -> Result<()> {
+		update::<UserBmc, Document>(
+			ctx,
+			mm,
+			id,
+			doc! { "state": state.as_str(), "active": state == UserState::Active },
+		)
+		.await?;
+
+		if state != UserState::Active {
+			TokenRevocationBmc::revoke_all_for_user(
+				ctx, mm, id,
+			)
+			.await?;
+		}
+
+		Ok(())
+	}
+
+	/// Replaces the roles of the user.
+	pub async fn set_roles(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: &String,
+		roles: Vec<String>,
+	) -> Result<()> {
+		update::<UserBmc, Document>(
+			ctx,
+			mm,
+			id,
+			doc! { "roles": roles },
+		)
+		.await
+	}
+
+	pub async fn create_external(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		input: QUserForCreateExternal,
+	) -> Result<String> {
+		let new_user = QUser::create_external_user(input);
+		create::<UserBmc, QUser>(
+			ctx, mm, new_user,
+		)
+		.await
+	}
+
+	/// Hashes the new password (with the `DEFAULT_SCHEME`) with a new salt, which invalidates all
+	/// the web tokens of the user (salted with the previous one). Returns the new salt.
+	pub async fn update_pwd(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: &String,
+		pwd: &str,
+	) -> Result<Uuid> {
+		let pwd_salt = Uuid::new_v4();
+		let pw = pwd::hash_pwd(
+			ContentToHash {
+				content: pwd.to_string(),
+				salt: pwd_salt,
+			},
+		)
+		.await?;
+
+		update::<UserBmc, QUserPwdUpdate>(
+			ctx,
+			mm,
+			id,
+			QUserPwdUpdate {
+				pw: Some(pw),
+				pwd_salt,
+			},
+		)
+		.await?;
+
+		Ok(pwd_salt)
+	}
+
+	/// Removes the password of the user, until set again (e.g., by a password reset),
+	/// and rotates its salt, which invalidates all its web tokens.
+	pub async fn clear_pwd(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: &String,
+	) -> Result<()> {
+		update::<UserBmc, QUserPwdUpdate>(
+			ctx,
+			mm,
+			id,
+			QUserPwdUpdate {
+				pw: None,
+				pwd_salt: Uuid::new_v4(),
+			},
+		)
+		.await
+	}
+
+	/// Changes the password of the user, once its current one checked (see `update_pwd`).
+	/// Returns the new salt.
+	pub async fn change_pwd(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: &String,
+		input: QUserPwdChange,
+	) -> Result<Uuid> {
+		let user = Self::get(
+			ctx, mm, id,
+		)
+		.await?;
+		let login = user.login.ok_or(Error::ReadError)?;
+		let username = login.username.ok_or(Error::ReadError)?;
+
+		// -- Check the current password (none for the external users).
+		let (Some(pwd_ref), Some(salt)) = (login.pw, login.pwd_salt) else {
+			return Err(Error::UserPwdNotMatching);
+		};
+		pwd::validate_pwd(
+			ContentToHash {
+				salt,
+				content: input.pwd_current,
+			},
+			pwd_ref,
+		)
+		.await
+		.map_err(|_| Error::UserPwdNotMatching)?;
+
+		Self::validate_new_pwd(
+			&username,
+			&input.pwd_new,
+		)?;
+		Self::update_pwd(
+			ctx,
+			mm,
+			id,
+			&input.pwd_new,
+		)
+		.await
+	}
+
+	/// Checks a new password of the user against the password policy (see `check_pwd_policy`).
+	pub fn validate_new_pwd(
+		username: &str,
+		pwd: &str,
+	) -> Result<()> {
+		let mut errors = ValidationError::default();
+		check_pwd_policy(
+			&mut errors,
+			"pwd",
+			pwd,
+			username,
+		);
+
+		Ok(errors.into_result()?)
+	}
+
+	pub async fn get(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: &String,
+	) -> Result<QUser> {
+		let filter = QUser::filter_id(id)?;
+		get::<UserBmc, QUser>(
+			ctx, mm, filter,
+		)
+		.await
+	}
+
+	/// Fails with `UserNotFound` if no user `id`.
+	pub async fn get_view(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: &String,
+	) -> Result<QUserView> {
+		let user = Self::get(
+			ctx, mm, id,
+		)
+		.await
+		.map_err(
+			|ex| match ex {
+				Error::ReadError => Error::UserNotFound { id: id.to_string() },
+				ex => ex,
+			},
+		)?;
+
+		user.into_view()
+	}
+
+	/// The users matching the filter, by username.
+	pub async fn list_views(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		filter: Option<QUserFilter>,
+		list_options: ListOptions,
+	) -> Result<Vec<QUserView>> {
+		let filter = filter.unwrap_or_default().into_doc();
+
+		let docs: Vec<Document> = mm
+			.client
+			.database(ctx.tenant_id().as_str())
+			.collection::<Document>(Self::TABLE)
+			.find(filter)
+			.sort(doc! { "login.username": 1 })
+			.skip(list_options.offset.unwrap_or(0))
+			.limit(list_options.limit()?)
+			.await
+			.map_err(|_| Error::QueryError)?
+			.try_collect()
+			.await
+			.map_err(|_| Error::QueryError)?;
+
+		let mut users = Vec::new();
+		for mut d in docs {
+			if let Some(Bson::ObjectId(oid)) = d.get("_id") {
+				d.insert(
+					"_id".to_string(),
+					Bson::String(oid.to_hex()),
+				);
+			}
+			let user: QUser = from_document(d).map_err(|_| Error::QueryError)?;
+			users.push(user.into_view()?);
+		}
+
+		Ok(users)
+	}
+
+	pub async fn get_profile(
 		ctx: &Ctx,
 		mm: &ModelManager,
 		id: &String,
-		pwd: &str,
-	) -> Result<()> {
+	) -> Result<QUserProfile> {
 		let user = Self::get(
 			ctx, mm, id,
 		)
 		.await?;
-		if let Some(login) = &user.login {
-			let salt = login.pwd_salt.ok_or(Error::UpdateError)?;
-			let hashed_pwd = pwd::hash_pwd(
-				ContentToHash {
-					content: pwd.to_string(),
-					salt,
-				},
+
+		user.into_profile()
+	}
+
+	/// Updates the given profile fields of the user, and returns its updated profile.
+	///
+	/// Note: A new mail is only stored as `mail_pending`, and applied once verified
+	///       (see `confirm_mail_change`).
+	pub async fn update_profile(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: &String,
+		mut input: QUserProfileForUpdate,
+	) -> Result<QUserProfile> {
+		input.validate()?;
+
+		if let Some(mail) = input.mail.take().as_deref().map(normalize_identifier) {
+			let profile = Self::get_profile(
+				ctx, mm, id,
 			)
 			.await?;
-			let updated_user = user.update_pwd(hashed_pwd)?;
-			update::<UserBmc, QUser>(
-				ctx,
-				mm,
-				id,
-				updated_user,
+			if profile.mail.as_ref() != Some(&mail) {
+				let mail_user_id = Self::find_id_by_email(
+					ctx, mm, &mail,
+				)
+				.await?;
+				if mail_user_id.is_some_and(|mail_user_id| &mail_user_id != id) {
+					return Err(Error::UserMailAlreadyExists { mail });
+				}
+				input.mail = Some(mail);
+			}
+		}
+
+		if !input.is_empty() {
+			update::<UserBmc, QUserProfileForUpdate>(
+				ctx, mm, id, input,
 			)
 			.await?;
-			Ok(())
-		} else {
-			Err(Error::UpdateError)
 		}
+
+		Self::get_profile(
+			ctx, mm, id,
+		)
+		.await
 	}
 
-	pub async fn get(
+	/// Applies the pending `mail` of the user (see `update_profile`), once verified.
+	///
+	/// Note: Fails with `UserMailChangeNotPending` if the mail is no longer the pending one
+	///       (e.g., replaced by a later change, or already applied).
+	pub async fn confirm_mail_change(
 		ctx: &Ctx,
 		mm: &ModelManager,
 		id: &String,
-	) -> Result<QUser> {
-		let filter = QUser::filter_id(id)?;
-		get::<UserBmc, QUser>(
-			ctx, mm, filter,
+		mail: &str,
+	) -> Result<()> {
+		let mail = normalize_identifier(mail);
+		let mail_user_id = Self::find_id_by_email(
+			ctx, mm, &mail,
 		)
-		.await
+		.await?;
+		if mail_user_id.is_some_and(|mail_user_id| &mail_user_id != id) {
+			return Err(Error::UserMailAlreadyExists { mail });
+		}
+
+		let object_id = ObjectId::parse_str(id).map_err(|_| Error::ObIdError)?;
+		let res = mm
+			.client
+			.database(ctx.tenant_id().as_str())
+			.collection::<Document>(Self::TABLE)
+			.update_one(
+				doc! { "_id": object_id, "metadata.mail_pending": &mail },
+				doc! {
+					"$set": { "metadata.mail": &mail },
+					"$unset": { "metadata.mail_pending": "" },
+				},
+			)
+			.await
+			.map_err(|_| Error::UpdateError)?;
+		if res.matched_count == 0 {
+			return Err(Error::UserMailChangeNotPending);
+		}
+
+		Ok(())
 	}
 
 	pub async fn get_user_by_name(
@@ -193,28 +1025,61 @@ impl UserBmc {
 		mm: &ModelManager,
 		username: &String,
 	) -> Result<QUser> {
-		let filter = QUser::filter_username(username.clone())?;
+		let mut filters = vec![QUser::filter_username(username.clone())?];
+		if normalize_identifier(username) != *username {
+			filters.push(QUser::filter_username_as_stored(username.clone()));
+		}
 		println!(
 			"{:?}",
-			filter
+			filters
 		);
-		get::<UserBmc, QUser>(
-			ctx, mm, filter,
+		Self::get_first(
+			ctx, mm, filters,
 		)
 		.await
 	}
 
+	/// The id of the user with the `email` as mail or username (case-insensitive), if any.
+	pub async fn find_id_by_email(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		email: &str,
+	) -> Result<Option<String>> {
+		let email = normalize_identifier(email);
+		let user = mm
+			.client
+			.database(ctx.tenant_id().as_str())
+			.collection::<Document>(Self::TABLE)
+			.find_one(doc! { "$or": [{ "metadata.mail": &email }, { "login.username": &email }] })
+			.await
+			.map_err(|_| Error::QueryError)?;
+
+		Ok(user.and_then(|user| user.get("_id").and_then(id_string)))
+	}
+
+	/// The user logging in with the `identifier` as username or mail (case-insensitive).
+	///
+	/// Note: The username is matched first, it cannot be the mail of another user (see `check_not_taken`).
 	pub async fn get_user_for_login(
 		ctx: &Ctx,
 		mm: &ModelManager,
-		username: &String,
+		identifier: &String,
 	) -> Result<QUserForLogin> {
-		let filter = QUser::filter_username(username.clone())?;
-		let user = get::<UserBmc, QUser>(
-			ctx, mm, filter,
+		// Note: The identifier as is matches the users not normalized yet (see `normalize_identifiers`).
+		let mut filters = vec![
+			QUser::filter_username(identifier.clone())?,
+			QUser::filter_mail(identifier.clone())?,
+		];
+		if normalize_identifier(identifier) != *identifier {
+			filters.push(QUser::filter_username_as_stored(identifier.clone()));
+			filters.push(QUser::filter_mail_as_stored(identifier.clone()));
+		}
+		let user = Self::get_first(
+			ctx, mm, filters,
 		)
 		.await?;
 
+		let state = user.state();
 		let id = user.id.ok_or(Error::ReadError)?.to_string();
 		let login = user.login.ok_or(Error::ReadError)?;
 		let username = login.username.ok_or(Error::ReadError)?;
@@ -224,8 +1089,10 @@ impl UserBmc {
 		let out = QUserForLogin {
 			id,
 			username,
+			state,
 			pwd: Some(pwd),
 			pwd_salt,
+			roles: user.roles.unwrap_or_default(),
 		};
 		Ok(out)
 	}
@@ -235,23 +1102,32 @@ impl UserBmc {
 		mm: &ModelManager,
 		username: &String,
 	) -> Result<QUserForAuth> {
-		let filter = QUser::filter_username(username.clone())?;
-		let user = get::<UserBmc, QUser>(
-			ctx, mm, filter,
+		// Note: The username as is matches the users not normalized yet (see `normalize_identifiers`),
+		//       e.g., the ident of their custom web tokens.
+		let mut filters = vec![QUser::filter_username(username.clone())?];
+		if normalize_identifier(username) != *username {
+			filters.push(QUser::filter_username_as_stored(username.clone()));
+		}
+		let user = Self::get_first(
+			ctx, mm, filters,
 		)
 		.await?;
 
-		let id = user.id.ok_or(Error::ReadError)?.to_string();
-		let login = user.login.ok_or(Error::ReadError)?;
-		let username = login.username.ok_or(Error::ReadError)?;
-		let pwd_salt = login.pwd_salt.ok_or(Error::ReadError)?;
+		user.into_for_auth()
+	}
 
-		let out = QUserForAuth {
-			id,
-			username,
-			pwd_salt,
-		};
-		Ok(out)
+	/// Same as `get_user_for_auth`, by user id (e.g., the `sub` of a JWT).
+	pub async fn get_user_for_auth_by_id(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: &String,
+	) -> Result<QUserForAuth> {
+		let user = Self::get(
+			ctx, mm, id,
+		)
+		.await?;
+
+		user.into_for_auth()
 	}
 
 	pub async fn list(
@@ -263,8 +1139,10 @@ impl UserBmc {
 			QUser {
 				id: None,
 				active: None,
+				state: None,
 				login: None,
 				metadata: None,
+				roles: None,
 			},
 		);
 		list::<UserBmc, QUser>(
@@ -284,12 +1162,283 @@ impl UserBmc {
 		)
 		.await
 	}
+
+	/// Normalizes the usernames and mails of the users stored before (see `normalize_identifier`),
+	/// then creates the unique mail and username indexes.
+	///
+	/// Fails with `IndexCreateFail` if an index cannot be created for another reason than duplicates.
+	///
+	/// Note: Run at the server startup (and idempotent), the users left as conflicts are still matched
+	///       by their username or mail as stored (see `get_user_for_login`).
+	///
+	/// Note: The users whose normalized username or mail is also the one of another user are left as is,
+	/// and reported as conflicts (see `QUserNormalizeReport`).
+	pub async fn normalize_identifiers(
+		ctx: &Ctx,
+		mm: &ModelManager,
+	) -> Result<QUserNormalizeReport> {
+		let collection = mm
+			.client
+			.database(ctx.tenant_id().as_str())
+			.collection::<Document>(Self::TABLE);
+		let docs: Vec<Document> = collection
+			.find(doc! {})
+			.projection(doc! { "login.username": 1, "metadata.mail": 1 })
+			.await
+			.map_err(|_| Error::QueryError)?
+			.try_collect()
+			.await
+			.map_err(|_| Error::QueryError)?;
+
+		// -- Group the users by normalized identifier (as both the usernames and mails identify them at login).
+		let mut users = Vec::new();
+		let mut owners: HashMap<String, Vec<Bson>> = HashMap::new();
+		for d in docs {
+			let Some(id) = d.get("_id").cloned() else {
+				continue;
+			};
+			let username = d
+				.get_document("login")
+				.ok()
+				.and_then(|login| login.get_str("username").ok())
+				.map(str::to_string);
+			let mail = d
+				.get_document("metadata")
+				.ok()
+				.and_then(|metadata| metadata.get_str("mail").ok())
+				.map(str::to_string);
+			for identifier in [&username, &mail].into_iter().flatten() {
+				let owner_ids = owners.entry(normalize_identifier(identifier)).or_default();
+				if !owner_ids.contains(&id) {
+					owner_ids.push(id.clone());
+				}
+			}
+			users.push(
+				(
+					id, username, mail,
+				),
+			);
+		}
+
+		// -- Normalize the users without conflict.
+		let mut report = QUserNormalizeReport::default();
+		for (id, username, mail) in users {
+			let conflict = [&username, &mail].into_iter().flatten().any(
+				|identifier| {
+					owners
+						.get(&normalize_identifier(identifier))
+						.is_some_and(|owner_ids| owner_ids.len() > 1)
+				},
+			);
+			if conflict {
+				report.conflicts.extend(id_string(&id));
+				continue;
+			}
+
+			let mut set = Document::new();
+			for (field, value) in [
+				(
+					"login.username",
+					username,
+				),
+				(
+					"metadata.mail",
+					mail,
+				),
+			] {
+				let Some(value) = value else {
+					continue;
+				};
+				let normalized = normalize_identifier(&value);
+				if normalized != value {
+					set.insert(
+						field, normalized,
+					);
+				}
+			}
+			if set.is_empty() {
+				continue;
+			}
+			collection
+				.update_one(
+					doc! { "_id": id },
+					doc! { "$set": set },
+				)
+				.await
+				.map_err(|_| Error::UpdateError)?;
+			report.normalized += 1;
+		}
+
+		// -- Enforce the unique mails and usernames (pending while conflicting ones remain).
+		report.indexes_pending = Self::ensure_indexes(&collection).await?;
+
+		Ok(report)
+	}
+}
+
+// region:    --- (private) Helpers
+
+impl UserBmc {
+	/// The user of the first matching filter (e.g., by username, then by mail).
+	async fn get_first(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		filters: Vec<QUser>,
+	) -> Result<QUser> {
+		for filter in filters {
+			match get::<UserBmc, QUser>(
+				ctx, mm, filter,
+			)
+			.await
+			{
+				Err(Error::ReadError) => continue,
+				res => return res,
+			}
+		}
+
+		Err(Error::ReadError)
+	}
+
+	/// Fails with `UserAlreadyExists` (or `UserMailAlreadyExists`) if the username (or mail) is taken,
+	/// as the username or mail of a user (both identify it at login).
+	async fn check_not_taken(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		username: &str,
+		mail: Option<&str>,
+	) -> Result<()> {
+		let username_taken = Self::find_id_by_email(
+			ctx, mm, username,
+		)
+		.await?
+		.is_some();
+		if username_taken {
+			return Err(
+				Error::UserAlreadyExists {
+					username: username.to_string(),
+				},
+			);
+		}
+
+		if let Some(mail) = mail {
+			if Self::find_id_by_email(
+				ctx, mm, mail,
+			)
+			.await?
+			.is_some()
+			{
+				return Err(
+					Error::UserMailAlreadyExists {
+						mail: mail.to_string(),
+					},
+				);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// The unique mail and username indexes (both stored normalized), and returns the names
+	/// of the ones not created because of duplicates.
+	///
+	/// Note: Only the users with a mail (or username) are in its index. Created once, by `normalize_identifiers`
+	///       at the server startup. The non-unique username index created before is replaced, and kept
+	///       while duplicate usernames remain (for the logins).
+	async fn ensure_indexes(collection: &Collection<Document>) -> Result<Vec<String>> {
+		let mut indexes_pending = Vec::new();
+
+		// -- Mails
+		if !Self::create_unique_index(
+			collection,
+			MAIL_INDEX,
+			"metadata.mail",
+		)
+		.await?
+		{
+			indexes_pending.push(MAIL_INDEX.to_string());
+		}
+
+		// -- Usernames
+		let legacy_username_index = collection
+			.list_indexes()
+			.await
+			.map_err(|_| Error::IndexCreateFail)?
+			.try_collect::<Vec<IndexModel>>()
+			.await
+			.map_err(|_| Error::IndexCreateFail)?
+			.into_iter()
+			.filter_map(|index| index.options)
+			.any(|options| options.name.as_deref() == Some(USERNAME_INDEX) && options.unique != Some(true));
+		if legacy_username_index {
+			collection
+				.drop_index(USERNAME_INDEX)
+				.await
+				.map_err(|_| Error::IndexCreateFail)?;
+		}
+		if !Self::create_unique_index(
+			collection,
+			USERNAME_INDEX,
+			"login.username",
+		)
+		.await?
+		{
+			indexes_pending.push(USERNAME_INDEX.to_string());
+			collection
+				.create_index(
+					IndexModel::builder()
+						.keys(doc! { "login.username": 1 })
+						.build(),
+				)
+				.await
+				.map_err(|_| Error::IndexCreateFail)?;
+		}
+
+		Ok(indexes_pending)
+	}
+
+	/// False if not created, as the `field` has duplicates.
+	async fn create_unique_index(
+		collection: &Collection<Document>,
+		name: &str,
+		field: &str,
+	) -> Result<bool> {
+		let index = IndexModel::builder()
+			.keys(doc! { field: 1 })
+			.options(
+				IndexOptions::builder()
+					.name(name.to_string())
+					.unique(true)
+					.partial_filter_expression(doc! { field: { "$type": "string" } })
+					.build(),
+			)
+			.build();
+
+		match collection.create_index(index).await {
+			Ok(_) => Ok(true),
+			Err(ex) => match ex.kind.as_ref() {
+				ErrorKind::Command(cmd_err) if cmd_err.code == DUPLICATE_KEY_CODE => Ok(false),
+				_ => Err(Error::IndexCreateFail),
+			},
+		}
+	}
+}
+
+/// The string id of a user `_id` (an ObjectId, or a string for the users created with one).
+fn id_string(id: &Bson) -> Option<String> {
+	match id {
+		Bson::ObjectId(oid) => Some(oid.to_hex()),
+		Bson::String(id) => Some(id.clone()),
+		_ => None,
+	}
 }
 
+// endregion: --- (private) Helpers
+
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use crate::ctx::Ctx;
+	use crate::model::base::Filter;
 	use crate::model::error::Error;
 	use crate::model::ModelManager;
 	use mongodb::bson::{doc, Document};
@@ -315,6 +1464,164 @@ mod tests {
 		const TABLE: &'static str = "dummy_collection_for_testing";
 	}
 
+	#[tokio::test]
+	async fn test_user_for_auth_by_jwt_sub() -> core::result::Result<(), Box<dyn std::error::Error>> {
+		use lib_auth::token::{generate_web_token, validate_web_token, TokenSubject, WebToken};
+
+		// -- Setup & Fixtures
+		let ctx = Ctx::root_ctx();
+		let mm = ModelManager::new().await?;
+		let fx_username = format!(
+			"test_jwt_sub-{}",
+			Uuid::new_v4()
+		);
+		let fx_id = UserBmc::create(
+			&ctx,
+			&mm,
+			QUserForCreate {
+				username: fx_username.clone(),
+				pwd_clear: "welcome".to_string(),
+			},
+		)
+		.await?;
+		let fx_user = UserBmc::get_user_for_auth(
+			&ctx,
+			&mm,
+			&fx_username,
+		)
+		.await?;
+		let fx_token = generate_web_token(
+			&TokenSubject {
+				user_id: &fx_user.id,
+				username: &fx_user.username,
+				tenant_id: &ctx.tenant_id(),
+				roles: &fx_user.roles,
+				salt: fx_user.pwd_salt,
+				service_account: false,
+				grant: None,
+			},
+		)?;
+
+		// -- Exec
+		let WebToken::Jwt(token) = fx_token.to_string().parse::<WebToken>()? else {
+			return Err("not a jwt (see SERVICE_TOKEN_FORMAT)".into());
+		};
+		let user = UserBmc::get_user_for_auth_by_id(
+			&ctx,
+			&mm,
+			&token.claims.sub,
+		)
+		.await?;
+
+		// -- Check
+		validate_web_token(
+			&WebToken::Jwt(token),
+			user.pwd_salt,
+		)?;
+		assert_eq!(
+			user.id,
+			fx_id
+		);
+		assert_eq!(
+			user.username,
+			fx_username
+		);
+
+		// -- Clean
+		UserBmc::delete(
+			&ctx, &mm, &fx_id,
+		)
+		.await?;
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_user_mail_change_pending() -> core::result::Result<(), Box<dyn std::error::Error>> {
+		// -- Setup & Fixtures
+		let ctx = Ctx::root_ctx();
+		let mm = ModelManager::new().await?;
+		let fx_username = format!(
+			"test_mail_change-{}",
+			Uuid::new_v4()
+		);
+		let fx_mail = format!("{fx_username}@example.com");
+		let fx_id = UserBmc::create(
+			&ctx,
+			&mm,
+			QUserForCreate {
+				username: fx_username.clone(),
+				pwd_clear: "welcome".to_string(),
+			},
+		)
+		.await?;
+
+		// -- Exec
+		let profile_pending = UserBmc::update_profile(
+			&ctx,
+			&mm,
+			&fx_id,
+			QUserProfileForUpdate {
+				mail: Some(fx_mail.to_uppercase()),
+				..Default::default()
+			},
+		)
+		.await?;
+		let res_other_mail = UserBmc::confirm_mail_change(
+			&ctx,
+			&mm,
+			&fx_id,
+			"other@example.com",
+		)
+		.await;
+		UserBmc::confirm_mail_change(
+			&ctx, &mm, &fx_id, &fx_mail,
+		)
+		.await?;
+		let res_again = UserBmc::confirm_mail_change(
+			&ctx, &mm, &fx_id, &fx_mail,
+		)
+		.await;
+		let profile = UserBmc::get_profile(
+			&ctx, &mm, &fx_id,
+		)
+		.await?;
+
+		// -- Check
+		assert_eq!(
+			profile_pending.mail,
+			None
+		);
+		assert_eq!(
+			profile_pending.mail_pending,
+			Some(fx_mail.clone())
+		);
+		assert!(matches!(
+			res_other_mail,
+			Err(Error::UserMailChangeNotPending)
+		));
+		assert!(matches!(
+			res_again,
+			Err(Error::UserMailChangeNotPending)
+		));
+		assert_eq!(
+			profile.mail,
+			Some(fx_mail)
+		);
+		assert_eq!(
+			profile.mail_pending,
+			None
+		);
+
+		// -- Clean
+		UserBmc::delete(
+			&ctx, &mm, &fx_id,
+		)
+		.await?;
+
+		Ok(())
+	}
+
 	/// Hilfsfunktion, um die Collection zu leeren
 	async fn clear_collection(
 		mm: &ModelManager,
@@ -552,4 +1859,131 @@ mod tests {
 		);
 		Ok(())
 	}
+
+	#[test]
+	fn test_user_profile_for_update_set_doc() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_update = QUserProfileForUpdate {
+			name: Some("Jen".to_string()),
+			mail: Some("jen@example.com".to_string()),
+			..Default::default()
+		};
+		let fx_update_invalid = QUserProfileForUpdate {
+			mail: Some("not-a-mail".to_string()),
+			..Default::default()
+		};
+
+		// -- Exec
+		let set_doc = mongodb::bson::to_document(&fx_update).map_err(|_| Error::CrudDocumentError)?;
+
+		// -- Check
+		assert_eq!(
+			set_doc,
+			doc! { "metadata.name": "Jen", "metadata.mail_pending": "jen@example.com" }
+		);
+		assert!(fx_update.validate().is_ok());
+		assert!(fx_update_invalid.validate().is_err());
+		assert!(QUserProfileForUpdate::default().is_empty());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_user_filter_into_doc() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_filter = QUserFilter {
+			username: Some("j.doe".to_string()),
+			state: Some(UserState::Active),
+			role: Some("admin".to_string()),
+			..Default::default()
+		};
+
+		// -- Exec
+		let filter = fx_filter.into_doc();
+
+		// -- Check
+		assert_eq!(
+			filter,
+			doc! {
+				"login.username": { "$regex": "j\\.doe", "$options": "i" },
+				"active": { "$ne": false },
+				"roles": "admin",
+			}
+		);
+		assert_eq!(
+			QUserFilter::default().into_doc(),
+			doc! {}
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_user_state_and_check_active() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_legacy_inactive = QUser {
+			active: Some(false),
+			..Default::default()
+		};
+		let fx_pending = QUser {
+			active: Some(false),
+			state: Some(UserState::PendingVerification),
+			..Default::default()
+		};
+		let fx_locked = QUserForAuth {
+			state: UserState::Locked,
+			..Default::default()
+		};
+
+		// -- Exec & Check
+		assert_eq!(
+			QUser::default().state(),
+			UserState::Active
+		);
+		assert_eq!(
+			fx_legacy_inactive.state(),
+			UserState::Disabled
+		);
+		assert_eq!(
+			fx_pending.state(),
+			UserState::PendingVerification
+		);
+		assert!(QUserForAuth::default().check_active().is_ok());
+		assert!(matches!(
+			fx_locked.check_active(),
+			Err(Error::UserNotActive {
+				state: UserState::Locked,
+				..
+			})
+		));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_user_login_filters_normalized() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_username = " J.Doe ".to_string();
+		let fx_mail = "J.Doe@Example.COM".to_string();
+
+		// -- Exec
+		let username_filter = Filter::doc(QUser::filter_username(fx_username)?);
+		let mail_filter = Filter::doc(QUser::filter_mail(fx_mail)?);
+
+		// -- Check
+		assert_eq!(
+			username_filter,
+			doc! { "login.username": "j.doe" }
+		);
+		assert_eq!(
+			mail_filter,
+			doc! { "metadata.mail": "j.doe@example.com" }
+		);
+		assert_eq!(
+			Filter::doc(QUser::filter_username_as_stored(" J.Doe ".to_string())),
+			doc! { "login.username": " J.Doe " }
+		);
+
+		Ok(())
+	}
 }
@@ -0,0 +1,401 @@
+//! Field validation for the `#[crud]` ForCreate/ForUpdate types.
+//!
+//! The `#[validate(...)]` field attributes of a `#[crud]` entity generate a `Validate` implementation
+//! calling the `check_...` functions below. All the failing fields are collected in one `ValidationError`.
+//!
+//! Supported rules:
+//!
+//! - `required` - the field must be present (create only, updates are partial).
+//! - `length(min = .., max = ..)` - number of chars of a string, or of items of a list.
+//! - `email` - simple email address format.
+//! - `regex = "..."` - must match the regex (checked at compile time with `lazy_regex`).
+//! - `range(min = .., max = ..)` - numeric bounds, inclusive.
+//!
+//! The hand-written types (e.g., `QUserForRegister`) call them directly, as does `check_pwd_policy`.
+
+use lazy_regex::regex_is_match;
+use serde::Serialize;
+
+pub type ValidationResult = core::result::Result<(), ValidationError>;
+
+pub const PWD_MIN_LEN: usize = 10;
+pub const PWD_MAX_LEN: usize = 128;
+
+/// Implemented by the `#[crud]` generated ForCreate and ForUpdate types.
+pub trait Validate {
+	fn validate(&self) -> ValidationResult;
+}
+
+// region:    --- ValidationError
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ValidationError {
+	pub fields: Vec<FieldError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+	pub field: &'static str,
+	pub code: &'static str,
+	pub message: String,
+}
+
+impl ValidationError {
+	pub fn add(
+		&mut self,
+		field: &'static str,
+		code: &'static str,
+		message: impl Into<String>,
+	) {
+		self.fields.push(
+			FieldError {
+				field,
+				code,
+				message: message.into(),
+			},
+		);
+	}
+
+	pub fn into_result(self) -> ValidationResult {
+		if self.fields.is_empty() {
+			Ok(())
+		} else {
+			Err(self)
+		}
+	}
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for ValidationError {
+	fn fmt(
+		&self,
+		fmt: &mut core::fmt::Formatter,
+	) -> core::result::Result<(), core::fmt::Error> {
+		write!(
+			fmt,
+			"{self:?}"
+		)
+	}
+}
+
+impl std::error::Error for ValidationError {}
+// endregion: --- Error Boilerplate
+
+// endregion: --- ValidationError
+
+// region:    --- Rule Checks
+
+/// Values with a length (strings in chars, lists in items).
+pub trait ValidateLength {
+	fn validate_len(&self) -> usize;
+}
+
+impl ValidateLength for String {
+	fn validate_len(&self) -> usize {
+		self.chars().count()
+	}
+}
+
+impl<T> ValidateLength for Vec<T> {
+	fn validate_len(&self) -> usize {
+		self.len()
+	}
+}
+
+/// Numeric values, compared as `f64`.
+pub trait ValidateRange {
+	fn validate_f64(&self) -> f64;
+}
+
+macro_rules! impl_validate_range {
+	($($ty:ty),*) => {
+		$(
+			impl ValidateRange for $ty {
+				fn validate_f64(&self) -> f64 {
+					*self as f64
+				}
+			}
+		)*
+	};
+}
+
+impl_validate_range!(i8, i16, i32, i64, u8, u16, u32, u64, usize, f32, f64);
+
+pub fn check_required<T>(
+	errors: &mut ValidationError,
+	field: &'static str,
+	value: &Option<T>,
+) {
+	if value.is_none() {
+		errors.add(
+			field,
+			"required",
+			"is required",
+		);
+	}
+}
+
+pub fn check_length(
+	errors: &mut ValidationError,
+	field: &'static str,
+	value: &impl ValidateLength,
+	min: Option<usize>,
+	max: Option<usize>,
+) {
+	let len = value.validate_len();
+	if min.is_some_and(|min| len < min) || max.is_some_and(|max| len > max) {
+		errors.add(
+			field,
+			"length",
+			bounds_message(
+				"length", min, max,
+			),
+		);
+	}
+}
+
+pub fn check_range(
+	errors: &mut ValidationError,
+	field: &'static str,
+	value: &impl ValidateRange,
+	min: Option<f64>,
+	max: Option<f64>,
+) {
+	let value = value.validate_f64();
+	if min.is_some_and(|min| value < min) || max.is_some_and(|max| value > max) {
+		errors.add(
+			field,
+			"range",
+			bounds_message(
+				"value", min, max,
+			),
+		);
+	}
+}
+
+pub fn check_email(
+	errors: &mut ValidationError,
+	field: &'static str,
+	value: &str,
+) {
+	if !regex_is_match!(
+		r"^[^\s@]+@[^\s@]+\.[^\s@]+$",
+		value
+	) {
+		errors.add(
+			field,
+			"email",
+			"must be a valid email address",
+		);
+	}
+}
+
+/// An `https` url (or `http` on the loopback, for development and tests).
+pub fn check_url(
+	errors: &mut ValidationError,
+	field: &'static str,
+	value: &str,
+) {
+	let is_url = value.starts_with("https://")
+		|| value.starts_with("http://localhost")
+		|| value.starts_with("http://127.0.0.1");
+	if !is_url || value.len() > 2048 {
+		errors.add(
+			field,
+			"url",
+			"must be an https url",
+		);
+	}
+}
+
+/// The password policy: `PWD_MIN_LEN` to `PWD_MAX_LEN` chars, with letters and digits,
+/// and not containing the username.
+pub fn check_pwd_policy(
+	errors: &mut ValidationError,
+	field: &'static str,
+	pwd: &str,
+	username: &str,
+) {
+	let len = pwd.chars().count();
+	if !(PWD_MIN_LEN..=PWD_MAX_LEN).contains(&len) {
+		errors.add(
+			field,
+			"pwd_length",
+			bounds_message(
+				"length",
+				Some(PWD_MIN_LEN),
+				Some(PWD_MAX_LEN),
+			),
+		);
+	}
+	if !pwd.chars().any(char::is_alphabetic) || !pwd.chars().any(|c| c.is_ascii_digit()) {
+		errors.add(
+			field,
+			"pwd_weak",
+			"must contain letters and digits",
+		);
+	}
+	if username.chars().count() >= 3
+		&& pwd
+			.to_lowercase()
+			.contains(&username.to_lowercase())
+	{
+		errors.add(
+			field,
+			"pwd_contains_username",
+			"must not contain the username",
+		);
+	}
+}
+
+/// Note: The regex itself is matched by the generated code, so that it is compiled once per field.
+pub fn check_regex(
+	errors: &mut ValidationError,
+	field: &'static str,
+	is_match: bool,
+) {
+	if !is_match {
+		errors.add(
+			field,
+			"regex",
+			"has an invalid format",
+		);
+	}
+}
+
+fn bounds_message<T: std::fmt::Display>(
+	what: &str,
+	min: Option<T>,
+	max: Option<T>,
+) -> String {
+	match (min, max) {
+		(Some(min), Some(max)) => format!("{what} must be between {min} and {max}"),
+		(Some(min), None) => format!("{what} must be at least {min}"),
+		(None, Some(max)) => format!("{what} must be at most {max}"),
+		(None, None) => format!("{what} is invalid"),
+	}
+}
+
+// endregion: --- Rule Checks
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use crate::model::example::{ExampleForCreate, ExampleForUpdate};
+
+	#[test]
+	fn test_validate_for_create_err_fields() -> Result<()> {
+		// -- Fixtures
+		let fx_example = ExampleForCreate {
+			description: Some("x".repeat(5000)),
+			..Default::default()
+		};
+
+		// -- Exec
+		let res = fx_example.validate();
+
+		// -- Check
+		let Err(err) = res else {
+			return Err("Should have failed validation".into());
+		};
+		let failing: Vec<_> = err.fields.iter().map(|f| (f.field, f.code)).collect();
+		assert_eq!(
+			failing,
+			vec![
+				("name", "required"),
+				("description", "length")
+			]
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_validate_for_update_ok_partial() -> Result<()> {
+		// -- Fixtures
+		let fx_example = ExampleForUpdate {
+			description: Some("new description".to_string()),
+			..Default::default()
+		};
+
+		// -- Exec & Check
+		fx_example.validate()?;
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_check_email_and_range() -> Result<()> {
+		// -- Exec
+		let mut errors = ValidationError::default();
+		check_email(
+			&mut errors,
+			"mail",
+			"jane@example.com",
+		);
+		check_email(
+			&mut errors,
+			"mail_bad",
+			"jane@",
+		);
+		check_range(
+			&mut errors,
+			"age",
+			&-1i32,
+			Some(0.),
+			Some(150.),
+		);
+
+		// -- Check
+		let failing: Vec<_> = errors.fields.iter().map(|f| f.field).collect();
+		assert_eq!(
+			failing,
+			vec![
+				"mail_bad", "age"
+			]
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_check_pwd_policy() -> Result<()> {
+		// -- Exec
+		let mut errors = ValidationError::default();
+		check_pwd_policy(
+			&mut errors,
+			"pwd_ok",
+			"correct-horse-42",
+			"demo1",
+		);
+		check_pwd_policy(
+			&mut errors,
+			"pwd_short",
+			"abc1",
+			"demo1",
+		);
+		check_pwd_policy(
+			&mut errors,
+			"pwd_username",
+			"my-Demo1-password",
+			"demo1",
+		);
+
+		// -- Check
+		let failing: Vec<_> = errors.fields.iter().map(|f| (f.field, f.code)).collect();
+		assert_eq!(
+			failing,
+			vec![
+				("pwd_short", "pwd_length"),
+				("pwd_username", "pwd_contains_username")
+			]
+		);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -0,0 +1,21 @@
+//! RPC support of the model layer (only with the `with-rpc` feature).
+//!
+//! Design:
+//!
+//! - The typed RPC params and result live here (re-exported by `lib_rpc_core`),
+//!   so that the `#[crud(rpc)]` entities of this crate can generate their RPC handlers.
+//! - Each `#[crud(rpc)]` entity registers its `rpc_router_builder` in the `RpcRouterEntry`
+//!   registry, and `all_crud_rpc_router_builder` merges them all, so that adding
+//!   an entity to the RPC API is a single annotated struct.
+
+// region:    --- Modules
+
+mod registry;
+mod rpc_params;
+mod rpc_result;
+
+pub use registry::*;
+pub use rpc_params::*;
+pub use rpc_result::*;
+
+// endregion: --- Modules
@@ -0,0 +1,59 @@
+use rpc_router::{Router, RouterBuilder};
+
+/// Registry entry of a `#[crud(rpc)]` entity, submitted by the macro.
+pub struct RpcRouterEntry {
+	/// Name of the entity struct (e.g., `"Example"`).
+	pub entity: &'static str,
+	pub router_builder: fn() -> RouterBuilder,
+}
+
+inventory::collect!(RpcRouterEntry);
+
+/// The `RouterBuilder` with the RPC functions of all the `#[crud(rpc)]` entities.
+///
+/// Note: The entities are merged by name, so the result does not depend on the link order.
+pub fn all_crud_rpc_router_builder() -> RouterBuilder {
+	let mut entries: Vec<&RpcRouterEntry> = inventory::iter::<RpcRouterEntry>().collect();
+	entries.sort_by_key(|entry| entry.entity);
+
+	entries
+		.into_iter()
+		.fold(
+			Router::builder(),
+			|builder, entry| builder.extend((entry.router_builder)()),
+		)
+}
+
+/// Names of the registered `#[crud(rpc)]` entities, sorted.
+pub fn crud_rpc_entities() -> Vec<&'static str> {
+	let mut entities: Vec<&'static str> = inventory::iter::<RpcRouterEntry>()
+		.map(|entry| entry.entity)
+		.collect();
+	entities.sort_unstable();
+	entities
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+
+	#[test]
+	fn test_crud_rpc_entities_registered() -> Result<()> {
+		// -- Exec
+		let entities = crud_rpc_entities();
+		let _router = all_crud_rpc_router_builder().build();
+
+		// -- Check
+		assert!(
+			entities.contains(&"Example"),
+			"entities: {entities:?}"
+		);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
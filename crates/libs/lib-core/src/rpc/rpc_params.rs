@@ -7,10 +7,11 @@
 //! `IntoParams` or `IntoDefaultRpcParams` are implemented to ensure these Params conform to the
 //! `RpcRouter` (i.e., `rpc::router`) model.
 
+use crate::model::ListOptions;
 use rpc_router::{IntoDefaultRpcParams, IntoParams};
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
-use serde_with::{serde_as, OneOrMany};
+use serde_with::serde_as;
 
 /// Params structure for any RPC Create call.
 #[derive(Deserialize)]
@@ -36,6 +37,14 @@ pub struct ParamsIded {
 }
 impl IntoParams for ParamsIded {}
 
+/// Params structure for any RPC Get call, with the relations to expand.
+#[derive(Deserialize)]
+pub struct ParamsGet {
+	pub id: String,
+	pub expand: Option<Vec<String>>,
+}
+impl IntoParams for ParamsGet {}
+
 /// Params structure for any RPC List call.
 #[serde_as]
 #[derive(Deserialize, Default)]
@@ -45,6 +54,19 @@ where
 {
 	#[serde_as(deserialize_as = "Option<_>")]
 	pub filter: Option<F>,
+	/// The relations to expand (only for the `#[crud(rpc)]` entities).
+	pub expand: Option<Vec<String>>,
+	/// The page to list (only for the hand-written rpcs, e.g., `list_users`).
+	pub list_options: Option<ListOptions>,
 }
 
 impl<D> IntoDefaultRpcParams for ParamsList<D> where D: DeserializeOwned + Send + Default {}
+
+/// Params structure for any RPC Search call.
+#[derive(Deserialize)]
+pub struct ParamsSearch {
+	pub query: String,
+	pub list_options: Option<ListOptions>,
+}
+
+impl IntoParams for ParamsSearch {}
@@ -1,4 +1,4 @@
-//! The `lib_rpc::response` module normalizes the JSON-RPC `.result` format for various
+//! The `lib_core::rpc::rpc_result` module normalizes the JSON-RPC `.result` format for various
 //! JSON-RPC APIs.
 //!
 //! The primary type is the simple DataRpcResult, which contains only a `data` property.
@@ -0,0 +1,36 @@
+use crate::MailSender;
+use lib_utils::envs::{get_env, get_env_parse};
+use std::sync::OnceLock;
+
+pub fn mail_config() -> &'static MailConfig {
+	static INSTANCE: OnceLock<MailConfig> = OnceLock::new();
+
+	INSTANCE.get_or_init(
+		|| {
+			MailConfig::load_from_env()
+				.unwrap_or_else(|ex| panic!("FATAL - WHILE LOADING CONF - Cause: {ex:?}"))
+		},
+	)
+}
+
+#[allow(non_snake_case)]
+pub struct MailConfig {
+	/// The sender of the mails (see `MailSender`).
+	pub MAIL_SENDER: MailSender,
+	/// The `From` of the mails (e.g., `Gateway <no-reply@example.com>`).
+	pub MAIL_FROM: String,
+	/// The base url of the links in the mails (see `mail_link`).
+	pub MAIL_LINK_BASE_URL: String,
+}
+
+impl MailConfig {
+	fn load_from_env() -> lib_utils::envs::Result<MailConfig> {
+		Ok(
+			MailConfig {
+				MAIL_SENDER: get_env_parse("SERVICE_MAIL_URL")?,
+				MAIL_FROM: get_env("SERVICE_MAIL_FROM")?,
+				MAIL_LINK_BASE_URL: get_env("SERVICE_MAIL_LINK_BASE_URL")?,
+			},
+		)
+	}
+}
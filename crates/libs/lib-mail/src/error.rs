@@ -0,0 +1,33 @@
+use serde::Serialize;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Serialize)]
+pub enum Error {
+	/// Not a `smtp(s)://`, `file://` or `memory:` url (see `MailSender`).
+	MailUrlInvalid,
+	MailAddressInvalid {
+		address: String,
+	},
+	MailBuildFail(String),
+
+	// -- Senders
+	SmtpSendFail(String),
+	FileWriteFail(String),
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for Error {
+	fn fmt(
+		&self,
+		fmt: &mut core::fmt::Formatter,
+	) -> core::result::Result<(), core::fmt::Error> {
+		write!(
+			fmt,
+			"{self:?}"
+		)
+	}
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error Boilerplate
@@ -0,0 +1,46 @@
+use crate::{Error, Mail, Result};
+use lib_utils::time::{format_time, now_utc};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Writes the mails as `.eml` files in a directory, for the development (nothing is sent).
+pub struct FileMailSender {
+	dir: PathBuf,
+}
+
+impl FileMailSender {
+	pub fn new(dir: impl Into<PathBuf>) -> Self {
+		Self { dir: dir.into() }
+	}
+
+	pub(crate) async fn send(
+		&self,
+		from: &str,
+		mail: Mail,
+	) -> Result<()> {
+		let Mail { to, subject, body } = mail;
+		let now = now_utc();
+		let content = format!(
+			"Date: {}\r\nFrom: {from}\r\nTo: {to}\r\nSubject: {subject}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{body}\r\n",
+			format_time(now)
+		);
+
+		tokio::fs::create_dir_all(&self.dir)
+			.await
+			.map_err(|ex| Error::FileWriteFail(ex.to_string()))?;
+		let file = self.dir.join(
+			format!(
+				"{}-{}.eml",
+				now.unix_timestamp(),
+				Uuid::new_v4().simple()
+			),
+		);
+		tokio::fs::write(
+			&file, content,
+		)
+		.await
+		.map_err(|ex| Error::FileWriteFail(ex.to_string()))?;
+
+		Ok(())
+	}
+}
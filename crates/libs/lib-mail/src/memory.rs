@@ -0,0 +1,30 @@
+use crate::Mail;
+use std::sync::Mutex;
+
+/// Keeps the mails in memory, for the tests (see `take_sent`).
+#[derive(Default)]
+pub struct MemoryMailSender {
+	sent: Mutex<Vec<Mail>>,
+}
+
+impl MemoryMailSender {
+	pub(crate) fn send(
+		&self,
+		mail: Mail,
+	) {
+		self.sent
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+			.push(mail);
+	}
+
+	/// The mails sent since the last call.
+	pub fn take_sent(&self) -> Vec<Mail> {
+		std::mem::take(
+			&mut *self
+				.sent
+				.lock()
+				.unwrap_or_else(|poisoned| poisoned.into_inner()),
+		)
+	}
+}
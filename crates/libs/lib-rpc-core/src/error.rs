@@ -24,9 +24,25 @@ pub type Result<T> = core::result::Result<T, Error>;
 #[serde_as]
 #[derive(Debug, From, Serialize, RpcHandlerError)]
 pub enum Error {
+	// -- Access
+	/// The ctx lacks the role required by the rpc (see `require_role`).
+	RoleMissing {
+		role: String,
+	},
+	/// The account management rpcs are not allowed to an API key ctx (see `require_account_access`).
+	ApiKeyNotAllowed,
+	/// The delegated ctx was not granted the scope (see `require_account_access`).
+	ScopeMissing {
+		scope: String,
+	},
+
 	// -- App Libs
 	#[from]
 	Model(lib_core::model::Error),
+	#[from]
+	Token(lib_auth::token::Error),
+	#[from]
+	Mail(lib_mail::Error),
 
 	// -- External Modules
 	#[from]
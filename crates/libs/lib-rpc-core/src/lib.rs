@@ -1,12 +1,15 @@
 // region:    --- Modules
 
 mod error;
-mod rpc_params;
-mod rpc_result;
 mod utils;
 
 pub use self::error::{Error, Result};
-pub use rpc_params::*;
+pub use self::utils::{require_account_access, require_role};
+// -- The typed params and result are shared with the `#[crud(rpc)]` entities of lib-core.
+pub use lib_core::rpc::{
+	all_crud_rpc_router_builder, crud_rpc_entities, DataRpcResult, ParamsForCreate, ParamsForUpdate,
+	ParamsGet, ParamsIded, ParamsList, ParamsSearch,
+};
 
 pub mod prelude;
 // endregion: --- Modules
@@ -1,11 +1,10 @@
 //! This is a prelude for all .._rpc modules to avoid redundant imports.
 //! NOTE: This is only for the `rpcs` module and sub-modules.
 
-pub use crate::generate_common_rpc_fns;
-pub use crate::rpc_result::DataRpcResult;
+pub use crate::{require_account_access, require_role};
+pub use crate::DataRpcResult;
 pub use crate::Result;
-pub use crate::{ParamsForCreate, ParamsForUpdate, ParamsIded, ParamsList};
-pub use lib_core::ctx::Ctx;
-pub use lib_core::model::ModelManager;
-pub use paste::paste;
+pub use crate::{ParamsForCreate, ParamsForUpdate, ParamsGet, ParamsIded, ParamsList, ParamsSearch};
+pub use lib_core::ctx::{Ctx, ROLE_ADMIN, SCOPE_CREDENTIALS, SCOPE_PROFILE};
+pub use lib_core::model::{Expanded, ModelManager, SearchHit};
 pub use rpc_router::{router_builder, RouterBuilder};
@@ -0,0 +1,85 @@
+use crate::{Error, Result};
+use lib_core::ctx::Ctx;
+
+/// Fails before managing the credentials or identity of the user (e.g., its password, mail,
+/// or API keys) with:
+///
+/// - `Error::ApiKeyNotAllowed` if the ctx comes from an API key.
+/// - `Error::ScopeMissing` if the ctx is delegated (OAuth2 client token) without the `scope`
+///   (e.g., `SCOPE_PROFILE`, `SCOPE_CREDENTIALS`).
+///
+/// Note: A leaked API key or client token must not be turned into a permanent control of the account.
+pub fn require_account_access(
+	ctx: &Ctx,
+	scope: &str,
+) -> Result<()> {
+	if ctx.api_key_id().is_some() {
+		return Err(Error::ApiKeyNotAllowed);
+	}
+	if !ctx.has_scope(scope) {
+		return Err(Error::ScopeMissing { scope: scope.to_string() });
+	}
+
+	Ok(())
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use lib_core::ctx::{SCOPE_CREDENTIALS, SCOPE_PROFILE};
+
+	#[test]
+	fn test_require_account_access_api_key_err() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_ctx = Ctx::new("user-01".to_string())?;
+		let fx_api_key_ctx = fx_ctx.add_api_key_id("key-01".to_string());
+
+		// -- Exec & Check
+		assert!(
+			require_account_access(
+				&fx_ctx,
+				SCOPE_CREDENTIALS
+			)
+			.is_ok()
+		);
+		assert!(
+			matches!(
+				require_account_access(
+					&fx_api_key_ctx,
+					SCOPE_CREDENTIALS
+				),
+				Err(crate::Error::ApiKeyNotAllowed)
+			)
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_require_account_access_delegated() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_ctx = Ctx::new("user-01".to_string())?.add_scopes(vec![SCOPE_PROFILE.to_string()]);
+
+		// -- Exec & Check
+		assert!(
+			require_account_access(
+				&fx_ctx,
+				SCOPE_PROFILE
+			)
+			.is_ok()
+		);
+		assert!(
+			matches!(
+				require_account_access(&fx_ctx, SCOPE_CREDENTIALS),
+				Err(crate::Error::ScopeMissing { scope }) if scope == SCOPE_CREDENTIALS
+			)
+		);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -1,5 +1,9 @@
 // region:    --- Modules
 
-mod macro_utils;
+mod access;
+mod role;
+
+pub use self::access::require_account_access;
+pub use self::role::require_role;
 
 // endregion: --- Modules
@@ -0,0 +1,14 @@
+use crate::{Error, Result};
+use lib_core::ctx::Ctx;
+
+/// Fails with `Error::RoleMissing` unless the ctx has the `role` (e.g., `ROLE_ADMIN`).
+pub fn require_role(
+	ctx: &Ctx,
+	role: &str,
+) -> Result<()> {
+	if ctx.has_role(role) {
+		Ok(())
+	} else {
+		Err(Error::RoleMissing { role: role.to_string() })
+	}
+}
@@ -17,6 +17,15 @@ pub fn b64u_decode_to_string(b64u: &str) -> Result<String> {
 		.ok_or(Error::FailToB64uDecode)
 }
 
+/// Standard (padded) base64, e.g., of the HTTP Basic auth credentials.
+pub fn b64_decode_to_string(b64: &str) -> Result<String> {
+	general_purpose::STANDARD
+		.decode(b64)
+		.ok()
+		.and_then(|r| String::from_utf8(r).ok())
+		.ok_or(Error::FailToB64Decode)
+}
+
 // region:    --- Error
 
 pub type Result<T> = core::result::Result<T, Error>;
@@ -24,6 +33,7 @@ pub type Result<T> = core::result::Result<T, Error>;
 #[derive(Debug)]
 pub enum Error {
 	FailToB64uDecode,
+	FailToB64Decode,
 }
 
 // region:    --- Error Boilerplate
@@ -1,9 +1,12 @@
 use crate::middleware;
+use axum::extract::multipart::MultipartError;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use derive_more::From;
-use lib_auth::{pwd, token};
+use lib_auth::{oidc, pwd, token};
 use lib_core::model;
+use lib_core::model::user::UserState;
+use lib_core::model::validation::ValidationError;
 use serde::Serialize;
 use serde_json::Value;
 use serde_with::{serde_as, DisplayFromStr};
@@ -24,6 +27,77 @@ pub enum Error {
 	LoginFailPwdNotMatching {
 		user_id: String,
 	},
+	/// Disabled, locked, or self-registered with its mail not verified yet (see `UserState`).
+	LoginFailUserNotActive {
+		user_id: String,
+		state: UserState,
+	},
+
+	// -- Register
+	/// Invalid, expired, or not matching (e.g., password changed) verification token.
+	EmailVerifyTokenInvalid,
+
+	// -- Token
+	RefreshTokenNotInRequest,
+	/// The refresh token of an OAuth2 client, presented to `/api/token/refresh`.
+	RefreshTokenOfOAuthClient,
+	TokenRevokeNotAdmin {
+		user_id: String,
+	},
+
+	// -- OAuth
+	OAuthUnsupportedGrantType {
+		grant_type: String,
+	},
+	OAuthClientCredentialsMissing,
+	OAuthClientUnknown {
+		client_id: String,
+	},
+	OAuthRequestParamMissing {
+		name: &'static str,
+	},
+	/// Not a registered redirect uri of the client (never redirected to).
+	OAuthRedirectUriInvalid,
+	/// The code (or refresh token) was issued to another client, or for another redirect uri.
+	OAuthGrantNotMatching,
+	/// Missing or not matching PKCE `code_verifier`.
+	OAuthCodeVerifierInvalid,
+	OAuthRefreshTokenInvalid,
+	/// An API key, a delegated token, or a service account cannot authorize a client app.
+	OAuthAuthorizeCtxNotAllowed,
+
+	// -- OIDC
+	OidcCallbackError {
+		error: String,
+		error_description: Option<String>,
+	},
+	OidcCallbackCodeMissing,
+	/// The `state` is not the one of the login started by this browser (see `OIDC_STATE`).
+	OidcStateCookieNotMatching,
+	OidcUrlInvalid {
+		url: String,
+	},
+	/// The discovery document is not the one of the configured issuer.
+	OidcIssuerNotMatching {
+		issuer: String,
+	},
+	OidcTokenResponseNoIdToken,
+	#[from]
+	Oidc(oidc::Error),
+
+	// -- Http Client
+	HttpClientFail {
+		url: String,
+		cause: String,
+	},
+
+	// -- Files
+	FileUploadNoFileField,
+	FileRangeNotSatisfiable {
+		size: u64,
+	},
+	#[from]
+	FileMultipart(#[serde_as(as = "DisplayFromStr")] MultipartError),
 
 	// -- CtxExtError
 	#[from]
@@ -41,6 +115,8 @@ pub enum Error {
 	Token(token::Error),
 	#[from]
 	Rpc(lib_rpc_core::Error),
+	#[from]
+	Mail(lib_mail::Error),
 
 	// -- RpcError (deconstructed from rpc_router::Error)
 	// Simple mapping for the RpcRequestParsingError. It will have the eventual id, method context.
@@ -87,6 +163,10 @@ impl From<rpc_router::CallError> for Error {
 				if let Some(lib_rpc_error) = rpc_handler_error.remove::<lib_rpc_core::Error>() {
 					Error::RpcLibRpc(lib_rpc_error)
 				}
+				// the `#[crud(rpc)]` handlers return the model error directly.
+				else if let Some(model_error) = rpc_handler_error.remove::<model::Error>() {
+					Error::RpcLibRpc(lib_rpc_core::Error::Model(model_error))
+				}
 				// report the unhandled error for debugging and completing code.
 				else {
 					let type_name = rpc_handler_error.type_name();
@@ -150,12 +230,56 @@ impl Error {
 
 		match self {
 			// -- Login
-			LoginFailUsernameNotFound
-			| LoginFailUserHasNoPwd { .. }
-			| LoginFailPwdNotMatching { .. } => (
+			LoginFailUsernameNotFound | LoginFailUserHasNoPwd { .. } | LoginFailPwdNotMatching { .. } => (
 				StatusCode::FORBIDDEN,
 				ClientError::LOGIN_FAIL,
 			),
+			// Note: Only answered to the valid credentials (or tokens) of the user.
+			LoginFailUserNotActive { state, .. }
+			| CtxExt(middleware::mw_auth::CtxExtError::UserNotActive(state))
+			| Model(model::Error::UserNotActive { state, .. }) => (
+				StatusCode::FORBIDDEN,
+				ClientError::from_user_state(*state),
+			),
+			Model(model::Error::LoginLocked { retry_after_sec }) => (
+				StatusCode::TOO_MANY_REQUESTS,
+				ClientError::LOGIN_LOCKED {
+					retry_after_sec: *retry_after_sec,
+				},
+			),
+
+			// -- Register & Password Reset
+			EmailVerifyTokenInvalid => (
+				StatusCode::BAD_REQUEST,
+				ClientError::EMAIL_VERIFY_TOKEN_INVALID,
+			),
+			Model(model::Error::PwdResetTokenInvalid) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::PWD_RESET_TOKEN_INVALID,
+			),
+			Model(model::Error::UserAlreadyExists { .. } | model::Error::UserMailAlreadyExists { .. })
+			| RpcLibRpc(lib_rpc_core::Error::Model(
+				model::Error::UserAlreadyExists { .. } | model::Error::UserMailAlreadyExists { .. },
+			)) => (
+				StatusCode::CONFLICT,
+				ClientError::USER_ALREADY_EXISTS,
+			),
+
+			// -- Users
+			RpcLibRpc(lib_rpc_core::Error::Model(model::Error::UserNotFound { id })) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID(format!("user '{id}' not found")),
+			),
+			RpcLibRpc(lib_rpc_core::Error::Model(model::Error::UserMailMissing { id })) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID(format!("user '{id}' has no mail")),
+			),
+
+			// -- Self-Service
+			RpcLibRpc(lib_rpc_core::Error::Model(model::Error::UserPwdNotMatching)) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID("current pwd not matching".to_string()),
+			),
 
 			// -- Auth
 			CtxExt(_) => (
@@ -163,13 +287,168 @@ impl Error {
 				ClientError::NO_AUTH,
 			),
 
+			// -- Token
+			RefreshTokenNotInRequest
+			| RefreshTokenOfOAuthClient
+			| Model(
+				model::Error::RefreshTokenNotFound
+				| model::Error::RefreshTokenExpired
+				| model::Error::RefreshTokenRevoked
+				| model::Error::RefreshTokenReused,
+			) => (
+				StatusCode::FORBIDDEN,
+				ClientError::NO_AUTH,
+			),
+			TokenRevokeNotAdmin { .. } | OAuthAuthorizeCtxNotAllowed => (
+				StatusCode::FORBIDDEN,
+				ClientError::ACCESS_DENIED,
+			),
+
+			// -- OAuth
+			OAuthUnsupportedGrantType { .. } => (
+				StatusCode::BAD_REQUEST,
+				ClientError::OAUTH_UNSUPPORTED_GRANT_TYPE,
+			),
+			OAuthClientCredentialsMissing
+			| Model(model::Error::ServiceAccountInvalidCredentials | model::Error::OAuthClientInvalidCredentials) => (
+				StatusCode::UNAUTHORIZED,
+				ClientError::OAUTH_INVALID_CLIENT,
+			),
+			OAuthClientUnknown { client_id } => (
+				StatusCode::BAD_REQUEST,
+				ClientError::OAUTH_INVALID_REQUEST(format!("unknown client '{client_id}'")),
+			),
+			OAuthRequestParamMissing { name } => (
+				StatusCode::BAD_REQUEST,
+				ClientError::OAUTH_INVALID_REQUEST(format!("'{name}' missing")),
+			),
+			OAuthRedirectUriInvalid => (
+				StatusCode::BAD_REQUEST,
+				ClientError::OAUTH_INVALID_REQUEST("redirect_uri not registered".to_string()),
+			),
+			Model(model::Error::OAuthAuthRequestInvalid) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::OAUTH_INVALID_REQUEST("unknown or expired authorization request".to_string()),
+			),
+			OAuthGrantNotMatching
+			| OAuthCodeVerifierInvalid
+			| OAuthRefreshTokenInvalid
+			| Model(model::Error::OAuthGrantInvalid) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::OAUTH_INVALID_GRANT,
+			),
+			Model(model::Error::OAuthClientNotFound { id })
+			| RpcLibRpc(lib_rpc_core::Error::Model(model::Error::OAuthClientNotFound { id })) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID(format!("oauth client '{id}' not found")),
+			),
+
+			// -- OIDC
+			OidcCallbackError { .. }
+			| OidcCallbackCodeMissing
+			| OidcStateCookieNotMatching
+			| Oidc(_)
+			| Model(model::Error::OidcLoginStateInvalid) => (
+				StatusCode::FORBIDDEN,
+				ClientError::LOGIN_FAIL,
+			),
+			OidcUrlInvalid { .. } | OidcIssuerNotMatching { .. } | OidcTokenResponseNoIdToken | HttpClientFail { .. } => (
+				StatusCode::BAD_GATEWAY,
+				ClientError::OIDC_PROVIDER_FAIL,
+			),
+			Model(model::Error::OidcProviderNotFound { id })
+			| RpcLibRpc(lib_rpc_core::Error::Model(model::Error::OidcProviderNotFound { id })) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID(format!("oidc provider '{id}' not found")),
+			),
+
+			// -- MFA
+			Model(model::Error::MfaCodeInvalid | model::Error::MfaChallengeInvalid | model::Error::MfaNotEnrolled) => (
+				StatusCode::FORBIDDEN,
+				ClientError::LOGIN_FAIL,
+			),
+			RpcLibRpc(lib_rpc_core::Error::Model(model::Error::MfaAlreadyEnabled)) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID("mfa already enabled".to_string()),
+			),
+			RpcLibRpc(lib_rpc_core::Error::Model(model::Error::MfaNotEnrolled)) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID("mfa not enrolled".to_string()),
+			),
+			RpcLibRpc(lib_rpc_core::Error::Model(model::Error::MfaCodeInvalid)) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID("mfa code invalid".to_string()),
+			),
+
+			// -- Files
+			FileUploadNoFileField | FileMultipart(_) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::FILE_UPLOAD_INVALID,
+			),
+			FileRangeNotSatisfiable { .. } => (
+				StatusCode::RANGE_NOT_SATISFIABLE,
+				ClientError::FILE_RANGE_NOT_SATISFIABLE,
+			),
+
 			// -- Model
 			Model(model::Error::EntityNotFound { entity, id }) => (
 				StatusCode::BAD_REQUEST,
 				ClientError::ENTITY_NOT_FOUND { entity, id: *id },
 			),
+			Model(model::Error::AttachmentNotFound { .. }) => (
+				StatusCode::NOT_FOUND,
+				ClientError::FILE_NOT_FOUND,
+			),
+
+			// -- Sessions
+			Model(model::Error::SessionNotFound { id })
+			| RpcLibRpc(lib_rpc_core::Error::Model(model::Error::SessionNotFound { id })) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID(format!("session '{id}' not found")),
+			),
+
+			// -- Api Keys
+			Model(model::Error::ApiKeyNotFound { id })
+			| RpcLibRpc(lib_rpc_core::Error::Model(model::Error::ApiKeyNotFound { id })) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID(format!("api key '{id}' not found")),
+			),
+
+			// -- Service Accounts
+			Model(model::Error::ServiceAccountNotFound { id })
+			| RpcLibRpc(lib_rpc_core::Error::Model(model::Error::ServiceAccountNotFound { id })) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID(format!("service account '{id}' not found")),
+			),
+
+			// -- Relations
+			Model(model::Error::RelationNotFound { entity, field, id })
+			| RpcLibRpc(lib_rpc_core::Error::Model(model::Error::RelationNotFound { entity, field, id })) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID(format!("'{field}' references a missing {entity} '{id}'")),
+			),
+			Model(model::Error::RelationUnknown { name })
+			| RpcLibRpc(lib_rpc_core::Error::Model(model::Error::RelationUnknown { name })) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_INVALID(format!("unknown relation '{name}' to expand")),
+			),
+
+			// -- Validation
+			Model(model::Error::Validation(validation_err))
+			| RpcLibRpc(lib_rpc_core::Error::Model(model::Error::Validation(validation_err))) => (
+				StatusCode::BAD_REQUEST,
+				ClientError::RPC_PARAMS_VALIDATION_FAIL(validation_err.clone()),
+			),
 
 			// -- Rpc
+			RpcLibRpc(
+				lib_rpc_core::Error::RoleMissing { .. }
+				| lib_rpc_core::Error::ApiKeyNotAllowed
+				| lib_rpc_core::Error::ScopeMissing { .. },
+			) => (
+				StatusCode::FORBIDDEN,
+				ClientError::ACCESS_DENIED,
+			),
 			RpcRequestParsing(req_parsing_err) => (
 				StatusCode::BAD_REQUEST,
 				ClientError::RPC_REQUEST_INVALID(req_parsing_err.to_string()),
@@ -214,13 +493,48 @@ impl Error {
 #[allow(non_camel_case_types)]
 pub enum ClientError {
 	LOGIN_FAIL,
+	/// Also sent as the `Retry-After` header (see `mw_reponse_map`).
+	LOGIN_LOCKED { retry_after_sec: i64 },
+	USER_ALREADY_EXISTS,
+	EMAIL_VERIFY_TOKEN_INVALID,
+	PWD_RESET_TOKEN_INVALID,
+	ACCOUNT_DISABLED,
+	ACCOUNT_LOCKED,
+	ACCOUNT_NOT_VERIFIED,
 	NO_AUTH,
 	ENTITY_NOT_FOUND { entity: &'static str, id: i64 },
+	ACCESS_DENIED,
+
+	OAUTH_INVALID_CLIENT,
+	OAUTH_INVALID_GRANT,
+	OAUTH_INVALID_REQUEST(String),
+	OAUTH_UNSUPPORTED_GRANT_TYPE,
+
+	OIDC_PROVIDER_FAIL,
+
+	FILE_NOT_FOUND,
+	FILE_UPLOAD_INVALID,
+	FILE_RANGE_NOT_SATISFIABLE,
 
 	RPC_REQUEST_INVALID(String),
 	RPC_REQUEST_METHOD_UNKNOWN(String),
 	RPC_PARAMS_INVALID(String),
+	// Note: Same client message as `RPC_PARAMS_INVALID`, with the failing fields as detail.
+	#[serde(rename = "RPC_PARAMS_INVALID")]
+	RPC_PARAMS_VALIDATION_FAIL(ValidationError),
 
 	SERVICE_ERROR,
 }
+
+impl ClientError {
+	/// The rejection of a not `Active` user.
+	fn from_user_state(state: UserState) -> Self {
+		match state {
+			UserState::Disabled => Self::ACCOUNT_DISABLED,
+			UserState::Locked => Self::ACCOUNT_LOCKED,
+			UserState::PendingVerification => Self::ACCOUNT_NOT_VERIFIED,
+			UserState::Active => Self::NO_AUTH,
+		}
+	}
+}
 // endregion: --- Client Error
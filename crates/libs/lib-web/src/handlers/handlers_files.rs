@@ -0,0 +1,200 @@
+use crate::error::{Error, Result};
+use crate::middleware::mw_auth::CtxW;
+use crate::utils::range::parse_range;
+use axum::body::Body;
+use axum::extract::{Multipart, Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use futures::TryStreamExt;
+use lib_core::model::attachment::{AttachmentBmc, AttachmentForUpload};
+use lib_core::model::ModelManager;
+use serde_json::{json, Value};
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+use tokio_util::io::ReaderStream;
+use tracing::debug;
+
+/// The multipart field holding the file content.
+const FILE_FIELD: &str = "file";
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+// region:    --- Upload
+pub async fn api_file_upload_handler(
+	State(mm): State<ModelManager>,
+	ctx: CtxW,
+	mut multipart: Multipart,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_file_upload_handler",
+		"HANDLER"
+	);
+	let ctx = ctx.0;
+
+	while let Some(field) = multipart.next_field().await? {
+		if field.name() != Some(FILE_FIELD) {
+			continue;
+		}
+
+		let input = AttachmentForUpload {
+			filename: field.file_name().unwrap_or(FILE_FIELD).to_string(),
+			content_type: field
+				.content_type()
+				.unwrap_or(DEFAULT_CONTENT_TYPE)
+				.to_string(),
+		};
+		let content = field.map_err(std::io::Error::other).into_async_read();
+
+		let attachment = AttachmentBmc::upload(
+			&ctx, &mm, input, content,
+		)
+		.await?;
+
+		// Create the success body.
+		let body = Json(
+			json!({
+				"result": {
+					"data": attachment
+				}
+			}),
+		);
+
+		return Ok(body);
+	}
+
+	Err(Error::FileUploadNoFileField)
+}
+// endregion: --- Upload
+
+// region:    --- Download
+pub async fn api_file_download_handler(
+	State(mm): State<ModelManager>,
+	ctx: CtxW,
+	Path(id): Path<String>,
+	headers: HeaderMap,
+) -> Result<Response> {
+	debug!(
+		"{:<12} - api_file_download_handler",
+		"HANDLER"
+	);
+	let ctx = ctx.0;
+
+	let attachment = AttachmentBmc::get(
+		&ctx, &mm, &id,
+	)
+	.await?;
+
+	let range = match headers.get(header::RANGE).and_then(|v| v.to_str().ok()) {
+		Some(range) => parse_range(
+			range,
+			attachment.size,
+		)
+		.map_err(
+			|_| Error::FileRangeNotSatisfiable {
+				size: attachment.size,
+			},
+		)?,
+		None => None,
+	};
+
+	let stream = AttachmentBmc::open_download(
+		&ctx,
+		&mm,
+		&attachment,
+		range,
+	)
+	.await?;
+	let body = Body::from_stream(ReaderStream::new(stream.compat()));
+
+	let (status, content_length, content_range) = match range {
+		Some((start, end)) => (
+			StatusCode::PARTIAL_CONTENT,
+			end + 1 - start,
+			Some(
+				format!(
+					"bytes {start}-{end}/{}",
+					attachment.size
+				),
+			),
+		),
+		None => (
+			StatusCode::OK,
+			attachment.size,
+			None,
+		),
+	};
+
+	let mut res = (
+		status,
+		[
+			(
+				header::CONTENT_TYPE,
+				attachment.content_type.clone(),
+			),
+			(
+				header::CONTENT_LENGTH,
+				content_length.to_string(),
+			),
+			(
+				header::ACCEPT_RANGES,
+				"bytes".to_string(),
+			),
+			(
+				header::CONTENT_DISPOSITION,
+				format!(
+					"attachment; filename=\"{}\"",
+					header_safe_filename(&attachment.filename)
+				),
+			),
+		],
+		body,
+	)
+		.into_response();
+
+	if let Some(content_range) = content_range.and_then(|v| v.parse().ok()) {
+		res.headers_mut().insert(
+			header::CONTENT_RANGE,
+			content_range,
+		);
+	}
+
+	Ok(res)
+}
+
+/// Keeps only the characters allowed in a quoted `Content-Disposition` filename.
+fn header_safe_filename(filename: &str) -> String {
+	filename
+		.chars()
+		.filter(|c| c.is_ascii() && !c.is_ascii_control() && *c != '"' && *c != '\\')
+		.collect()
+}
+// endregion: --- Download
+
+// region:    --- Delete
+pub async fn api_file_delete_handler(
+	State(mm): State<ModelManager>,
+	ctx: CtxW,
+	Path(id): Path<String>,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_file_delete_handler",
+		"HANDLER"
+	);
+	let ctx = ctx.0;
+
+	let attachment = AttachmentBmc::delete(
+		&ctx, &mm, &id,
+	)
+	.await?;
+
+	// Create the success body.
+	let body = Json(
+		json!({
+			"result": {
+				"data": attachment
+			}
+		}),
+	);
+
+	Ok(body)
+}
+// endregion: --- Delete
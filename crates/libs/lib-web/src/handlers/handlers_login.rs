@@ -1,11 +1,21 @@
 use crate::error::{Error, Result};
+use crate::handlers::handlers_token::revoke_current_session;
+use crate::middleware::mw_auth::CtxW;
+use crate::utils::client_info::ClientInfo;
 use crate::utils::token;
 use axum::extract::State;
 use axum::Json;
 use lib_auth::pwd::{self, ContentToHash, SchemeStatus};
+use lib_auth::token::{generate_web_token, TokenSubject, WebToken};
 use lib_core::ctx::Ctx;
-use lib_core::model::user::{QUser, QUserForLogin, UserBmc};
-use lib_core::model::ModelManager;
+use lib_core::model::login_throttle::LoginThrottleBmc;
+use lib_core::model::mfa::{MfaBmc, MfaChallengeBmc};
+use lib_core::model::refresh_token::{IssuedRefreshToken, RefreshTokenBmc};
+use lib_core::model::session::{AuthMethod, SessionBmc, SessionForCreate};
+use lib_core::model::user::{
+	normalize_identifier, QUser, QUserForAuth, QUserForLogin, UserBmc, UserState,
+};
+use lib_core::model::{self, ModelManager};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use tower_cookies::Cookies;
@@ -15,6 +25,7 @@ use tracing::debug;
 pub async fn api_login_handler(
 	State(mm): State<ModelManager>,
 	cookies: Cookies,
+	client: ClientInfo,
 	Json(payload): Json<LoginPayload>,
 ) -> Result<Json<Value>> {
 	debug!(
@@ -22,17 +33,198 @@ pub async fn api_login_handler(
 		"HANDLER"
 	);
 
+	let tokens = match login(
+		&mm, client, payload,
+	)
+	.await?
+	{
+		LoginStep::Tokens(tokens) => *tokens,
+		LoginStep::MfaRequired { mfa_token } => return Ok(mfa_required_body(&mfa_token)),
+	};
+
+	// -- Set the token cookies.
+	token::add_token_cookie(
+		&cookies,
+		&tokens.web_token,
+	);
+	token::set_refresh_token_cookie(
+		&cookies,
+		tokens.refresh_token,
+	);
+
+	// Create the success body.
+	let body = Json(
+		json!({
+			"result": {
+				"success": true
+			}
+		}),
+	);
+
+	Ok(body)
+}
+
+/// The code step of a login of a user with MFA enabled (see `api_login_handler`).
+pub async fn api_login_mfa_handler(
+	State(mm): State<ModelManager>,
+	cookies: Cookies,
+	client: ClientInfo,
+	Json(payload): Json<LoginMfaPayload>,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_login_mfa_handler",
+		"HANDLER"
+	);
+
+	let tokens = login_mfa(
+		&mm, client, payload,
+	)
+	.await?;
+
+	// -- Set the token cookies.
+	token::add_token_cookie(
+		&cookies,
+		&tokens.web_token,
+	);
+	token::set_refresh_token_cookie(
+		&cookies,
+		tokens.refresh_token,
+	);
+
+	// Create the success body.
+	let body = Json(
+		json!({
+			"result": {
+				"success": true
+			}
+		}),
+	);
+
+	Ok(body)
+}
+
+/// The tokens of a successful login, set as cookies (`/api/login`) or returned in the body (`/api/token`).
+pub(crate) struct LoginTokens {
+	pub web_token: WebToken,
+	pub refresh_token: String,
+}
+
+/// The outcome of the password step of a login.
+pub(crate) enum LoginStep {
+	Tokens(Box<LoginTokens>),
+	/// The user has MFA enabled, the code is to be sent with the challenge token (see `login_mfa`).
+	MfaRequired { mfa_token: String },
+}
+
+/// Validates the credentials, and issues the web token and the refresh token of a new session,
+/// or, for a user with MFA enabled, starts the challenge of the code step.
+///
+/// Note: The failed logins lock the username (or mail) as sent, the username of its user, and the client ip
+///       for a while (see `LoginThrottleBmc`), and their failures are only reset once past the second factor.
+pub(crate) async fn login(
+	mm: &ModelManager,
+	client: ClientInfo,
+	payload: LoginPayload,
+) -> Result<LoginStep> {
 	let LoginPayload {
-		username,
+		username: identifier,
 		pwd: pwd_clear,
+		device_id,
 	} = payload;
-	let root_ctx = Ctx::root_ctx();
+	let username = normalize_identifier(&identifier);
+	let ip = client.ip.clone();
 
-	// -- Get the user.
-	let user: QUserForLogin = UserBmc::get_user_for_login(
-		&root_ctx, &mm, &username,
+	// -- Get the user, so its failures add up over its username and mail.
+	// Note: With the identifier as sent, for the users not normalized yet (see `UserBmc::get_user_for_login`).
+	let user = match UserBmc::get_user_for_login(
+		&Ctx::root_ctx(),
+		mm,
+		&identifier,
+	)
+	.await
+	{
+		Ok(user) => Some(user),
+		Err(model::Error::ReadError) => None,
+		Err(ex) => return Err(ex.into()),
+	};
+	let user_login = user
+		.as_ref()
+		.map(|user| normalize_identifier(&user.username));
+	let logins: Vec<&str> = std::iter::once(username.as_str())
+		.chain(user_login.as_deref())
+		.collect();
+
+	// -- Reject the locked logins (before any password hash).
+	LoginThrottleBmc::check(
+		mm,
+		&logins,
+		ip.as_deref(),
 	)
 	.await?;
+
+	// -- Validate the credentials.
+	let user = match check_credentials(
+		mm, user, pwd_clear,
+	)
+	.await
+	{
+		Ok(user) => user,
+		Err(
+			ex @ (Error::LoginFailUsernameNotFound
+			| Error::LoginFailUserHasNoPwd { .. }
+			| Error::LoginFailPwdNotMatching { .. }),
+		) => {
+			LoginThrottleBmc::record_failure(
+				mm,
+				&logins,
+				ip.as_deref(),
+			)
+			.await?;
+			return Err(ex);
+		},
+		Err(ex) => return Err(ex),
+	};
+
+	// -- Challenge the second factor.
+	if MfaBmc::is_enabled(
+		mm, &user.id,
+	)
+	.await?
+	{
+		let mfa_token = MfaChallengeBmc::create(
+			mm, &user.id, &username, device_id,
+		)
+		.await?;
+		return Ok(LoginStep::MfaRequired { mfa_token });
+	}
+	LoginThrottleBmc::record_success(
+		mm, &logins,
+	)
+	.await?;
+
+	let tokens = issue_login_tokens(
+		mm,
+		client,
+		&user,
+		device_id.as_deref(),
+		AuthMethod::Password,
+	)
+	.await?;
+
+	Ok(LoginStep::Tokens(Box::new(tokens)))
+}
+
+/// Validates the password of the user of the username or mail, if any (and upgrades its scheme if outdated).
+async fn check_credentials(
+	mm: &ModelManager,
+	user: Option<QUserForLogin>,
+	pwd_clear: String,
+) -> Result<QUserForAuth> {
+	let root_ctx = Ctx::root_ctx();
+
+	let Some(user) = user else {
+		return Err(Error::LoginFailUsernameNotFound);
+	};
 	let user_id = user.id;
 
 	// -- Validate the password.
@@ -62,42 +254,228 @@ pub async fn api_login_handler(
 	)?;
 
 	// -- Update password scheme if needed
+	// Note: The password is re-hashed with a new salt, the one of the issued web token.
+	let mut pwd_salt = user.pwd_salt;
 	if let SchemeStatus::Outdated = scheme_status {
 		debug!("pwd encrypt scheme outdated, upgrading.");
-		UserBmc::update_pwd(
-			&root_ctx, &mm, &user_id, &pwd_clear,
+		pwd_salt = UserBmc::update_pwd(
+			&root_ctx, mm, &user_id, &pwd_clear,
 		)
 		.await?;
 	}
 
-	// -- Set web token.
-	token::set_token_cookie(
-		&cookies,
-		&user.username,
-		user.pwd_salt,
-	)?;
+	// -- Reject the not active users (e.g., disabled, or with their mail not verified yet).
+	if user.state != UserState::Active {
+		return Err(
+			Error::LoginFailUserNotActive {
+				user_id,
+				state: user.state,
+			},
+		);
+	}
 
-	// Create the success body.
-	let body = Json(
+	Ok(
+		QUserForAuth {
+			id: user_id,
+			username: user.username,
+			state: user.state,
+			pwd_salt,
+			roles: user.roles,
+		},
+	)
+}
+
+/// Verifies the TOTP (or recovery) code of the login challenge, and issues the tokens of the new session.
+///
+/// Note: The wrong codes are failed logins of the challenge username (or mail), of the user username,
+///       and of the client ip, so the lock of the password step also limits the codes over the challenges.
+pub(crate) async fn login_mfa(
+	mm: &ModelManager,
+	client: ClientInfo,
+	payload: LoginMfaPayload,
+) -> Result<LoginTokens> {
+	let LoginMfaPayload { mfa_token, code } = payload;
+	let root_ctx = Ctx::root_ctx();
+	let ip = client.ip.clone();
+
+	// -- Verify the code (the challenge attempts are limited, and the login locked).
+	let challenge = MfaChallengeBmc::attempt(
+		mm, &mfa_token,
+	)
+	.await?;
+	let user = UserBmc::get_user_for_auth_by_id(
+		&root_ctx,
+		mm,
+		&challenge.user_id,
+	)
+	.await?;
+	let user_login = normalize_identifier(&user.username);
+	let logins = [challenge.login.as_str(), user_login.as_str()];
+	LoginThrottleBmc::check(
+		mm,
+		&logins,
+		ip.as_deref(),
+	)
+	.await?;
+	match MfaBmc::verify(
+		mm,
+		&challenge.user_id,
+		&code,
+	)
+	.await
+	{
+		Ok(()) => (),
+		Err(ex @ model::Error::MfaCodeInvalid) => {
+			LoginThrottleBmc::record_failure(
+				mm,
+				&logins,
+				ip.as_deref(),
+			)
+			.await?;
+			return Err(ex.into());
+		},
+		Err(ex) => return Err(ex.into()),
+	}
+	MfaChallengeBmc::consume(
+		mm, &mfa_token,
+	)
+	.await?;
+	LoginThrottleBmc::record_success(
+		mm, &logins,
+	)
+	.await?;
+
+	// -- Issue the tokens (unless the user was deactivated meanwhile).
+	user.check_active()?;
+
+	issue_login_tokens(
+		mm,
+		client,
+		&user,
+		challenge.device_id.as_deref(),
+		AuthMethod::PasswordMfa,
+	)
+	.await
+}
+
+/// The body of a password step answered with an MFA challenge.
+pub(crate) fn mfa_required_body(mfa_token: &str) -> Json<Value> {
+	Json(
 		json!({
 			"result": {
-				"success": true
+				"success": false,
+				"mfa_required": true,
+				"mfa_token": mfa_token,
 			}
 		}),
-	);
+	)
+}
 
-	Ok(body)
+/// Issues the web token of the authenticated user, and starts its session.
+async fn issue_login_tokens(
+	mm: &ModelManager,
+	client: ClientInfo,
+	user: &QUserForAuth,
+	device_id: Option<&str>,
+	auth_method: AuthMethod,
+) -> Result<LoginTokens> {
+	// -- Issue web token.
+	let web_token = generate_web_token(
+		&TokenSubject {
+			user_id: &user.id,
+			username: &user.username,
+			tenant_id: &Ctx::root_ctx().tenant_id(),
+			roles: &user.roles,
+			salt: user.pwd_salt,
+			service_account: false,
+			grant: None,
+		},
+	)?;
+
+	// -- Start the session.
+	let refresh_token = create_session(
+		mm,
+		client,
+		&user.id,
+		&web_token,
+		device_id,
+		auth_method,
+	)
+	.await?
+	.refresh_token;
+
+	Ok(
+		LoginTokens {
+			web_token,
+			refresh_token,
+		},
+	)
+}
+
+/// Starts the session of an issued web token: a refresh token family for the device, and its
+/// session record. Returns the issued refresh token (and its family).
+pub(crate) async fn create_session(
+	mm: &ModelManager,
+	client: ClientInfo,
+	user_id: &str,
+	web_token: &WebToken,
+	device_id: Option<&str>,
+	auth_method: AuthMethod,
+) -> Result<IssuedRefreshToken> {
+	let root_ctx = Ctx::root_ctx();
+
+	// -- Start a refresh token family for the device.
+	let refresh_token = RefreshTokenBmc::create(
+		&root_ctx,
+		mm,
+		user_id,
+		device_id.unwrap_or(DEFAULT_DEVICE_ID),
+	)
+	.await?;
+
+	// -- Record the session.
+	SessionBmc::create(
+		&root_ctx,
+		mm,
+		SessionForCreate {
+			user_id: user_id.to_string(),
+			family_id: refresh_token.family_id.clone(),
+			jti: web_token.jti().to_string(),
+			ip: client.ip,
+			user_agent: client.user_agent,
+			auth_method,
+		},
+	)
+	.await?;
+
+	Ok(refresh_token)
 }
 
 #[derive(Debug, Deserialize)]
 pub struct LoginPayload {
+	/// The username or the mail of the user (case-insensitive).
 	username: String,
 	pwd: String,
+	/// Identifies the client device, for its refresh token family.
+	device_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginMfaPayload {
+	/// The challenge token of the password step.
+	mfa_token: String,
+	/// A TOTP code, or a recovery code.
+	code: String,
 }
+
+/// The device of the logins without `device_id`.
+const DEFAULT_DEVICE_ID: &str = "default";
 // endregion: --- Login
 
 // region:    --- Logoff
 pub async fn api_logoff_handler(
+	State(mm): State<ModelManager>,
+	ctx: Result<CtxW>,
 	cookies: Cookies,
 	Json(payload): Json<LogoffPayload>,
 ) -> Result<Json<Value>> {
@@ -108,7 +486,13 @@ pub async fn api_logoff_handler(
 	let should_logoff = payload.logoff;
 
 	if should_logoff {
-		token::remove_token_cookie(&cookies)?;
+		// Note: Without a valid token, only the refresh token (if any) is left to revoke.
+		revoke_current_session(
+			&mm,
+			ctx.ok().map(|ctx| ctx.0).as_ref(),
+			&cookies,
+		)
+		.await?;
 	}
 
 	// Create the success body.
@@ -0,0 +1,1164 @@
+//! The OAuth2 authorization server (see `lib_core::model::oauth` for the client apps and their grants).
+//!
+//! Endpoints:
+//!
+//! - `/api/oauth/token` (RFC 6749): the `client_credentials` grant of the service accounts,
+//!   and the `authorization_code` and `refresh_token` grants of the client apps.
+//! - `/api/oauth/authorize` (GET, logged in user): validates the request, and answers with the code
+//!   redirect if the user already consented (or the client is first-party), or else with the consent page,
+//!   which posts the decision back to `/api/oauth/authorize` (POST).
+//! - `/api/oauth/revoke` (RFC 7009) and `/api/oauth/introspect` (RFC 7662), for the client apps.
+//!
+//! Notes:
+//!
+//! - The tokens of a client app are user tokens carrying the `client_id` and the granted `scope`,
+//!   and only grant the user roles within that scope (see `mw_auth`).
+//! - Until the `redirect_uri` is validated, the authorize errors are answered to the user,
+//!   never redirected to the client (RFC 6749, section 4.1.2.1).
+//! - Only a user logged in with its own credentials authorizes a client (see `is_user_session`).
+
+use crate::error::{Error, Result};
+use crate::handlers::handlers_login::create_session;
+use crate::middleware::mw_auth::{check_not_revoked, CtxW};
+use crate::utils::client_info::ClientInfo;
+use axum::extract::{Query, State};
+use axum::http::header::{AUTHORIZATION, CACHE_CONTROL};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::{Form, Json};
+use lib_auth::oauth::{parse_scope, verify_pkce, PKCE_METHOD_S256};
+use lib_auth::token::{
+	self, generate_web_token, token_duration_sec, validate_web_token, Claims, TokenGrant, TokenSubject, WebToken,
+};
+use lib_core::ctx::Ctx;
+use lib_core::model::oauth::{
+	oauth_device_id, OAuthAuthRequest, OAuthAuthRequestBmc, OAuthAuthorization, OAuthClientBmc, OAuthClientForAuth,
+	OAuthCodeBmc, OAuthConsentBmc, OAuthGrantBmc,
+};
+use lib_core::model::refresh_token::RefreshTokenBmc;
+use lib_core::model::service_account::ServiceAccountBmc;
+use lib_core::model::session::{AuthMethod, SessionBmc};
+use lib_core::model::token_revocation::TokenRevocationBmc;
+use lib_core::model::user::{QUserForAuth, UserBmc};
+use lib_core::model::{self, ModelManager};
+use lib_utils::b64::b64_decode_to_string;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::debug;
+use url::Url;
+
+const GRANT_CLIENT_CREDENTIALS: &str = "client_credentials";
+const GRANT_AUTHORIZATION_CODE: &str = "authorization_code";
+const GRANT_REFRESH_TOKEN: &str = "refresh_token";
+const RESPONSE_TYPE_CODE: &str = "code";
+const DECISION_ALLOW: &str = "allow";
+
+// region:    --- Token
+
+/// The OAuth2 token endpoint (RFC 6749).
+///
+/// The client credentials are read from the `Authorization: Basic` header,
+/// or else from the `client_id`/`client_secret` form fields (`client_id` only for a public client).
+pub async fn api_oauth_token_handler(
+	State(mm): State<ModelManager>,
+	client: ClientInfo,
+	headers: HeaderMap,
+	Form(payload): Form<OAuthTokenPayload>,
+) -> Result<impl IntoResponse> {
+	debug!(
+		"{:<12} - api_oauth_token_handler",
+		"HANDLER"
+	);
+
+	let credentials = client_credentials(
+		&headers,
+		payload.client_id.as_deref(),
+		payload.client_secret.as_deref(),
+	);
+	let body = match payload.grant_type.as_str() {
+		GRANT_CLIENT_CREDENTIALS => {
+			client_credentials_grant(
+				&mm,
+				credentials,
+			)
+			.await?
+		},
+		GRANT_AUTHORIZATION_CODE => {
+			authorization_code_grant(
+				&mm,
+				client,
+				credentials,
+				&payload,
+			)
+			.await?
+		},
+		GRANT_REFRESH_TOKEN => {
+			refresh_token_grant(
+				&mm,
+				credentials,
+				&payload,
+			)
+			.await?
+		},
+		_ => {
+			return Err(
+				Error::OAuthUnsupportedGrantType {
+					grant_type: payload.grant_type,
+				},
+			)
+		},
+	};
+
+	// Note: The OAuth2 token response is not wrapped in `result`, and must not be cached.
+	Ok(
+		(
+			[(CACHE_CONTROL, "no-store")],
+			body,
+		),
+	)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OAuthTokenPayload {
+	grant_type: String,
+	client_id: Option<String>,
+	client_secret: Option<String>,
+	/// `authorization_code` grant.
+	code: Option<String>,
+	redirect_uri: Option<String>,
+	code_verifier: Option<String>,
+	/// `refresh_token` grant.
+	refresh_token: Option<String>,
+}
+
+/// The service account access token, for its client credentials.
+async fn client_credentials_grant(
+	mm: &ModelManager,
+	credentials: Option<(String, Option<String>)>,
+) -> Result<Json<Value>> {
+	let Some((client_id, Some(client_secret))) = credentials else {
+		return Err(Error::OAuthClientCredentialsMissing);
+	};
+
+	// -- Authenticate the service account.
+	let service_account = ServiceAccountBmc::authenticate(
+		mm,
+		&client_id,
+		&client_secret,
+	)
+	.await?;
+
+	// -- Issue the access token.
+	let web_token = generate_web_token(
+		&TokenSubject {
+			user_id: &service_account.client_id,
+			username: &service_account.client_id,
+			tenant_id: &service_account.tenant_id,
+			roles: &service_account.roles,
+			salt: service_account.token_salt,
+			service_account: true,
+			grant: None,
+		},
+	)?;
+
+	Ok(
+		Json(
+			json!({
+				"access_token": web_token.to_string(),
+				"token_type": "Bearer",
+				"expires_in": token_duration_sec() as i64,
+			}),
+		),
+	)
+}
+
+/// The client app tokens, for its authorization code (consumed once).
+async fn authorization_code_grant(
+	mm: &ModelManager,
+	client: ClientInfo,
+	credentials: Option<(String, Option<String>)>,
+	payload: &OAuthTokenPayload,
+) -> Result<Json<Value>> {
+	let (client_id, client_secret) = credentials.ok_or(Error::OAuthClientCredentialsMissing)?;
+	let oauth_client = OAuthClientBmc::authenticate(
+		mm,
+		&client_id,
+		client_secret.as_deref(),
+	)
+	.await?;
+	let code = payload
+		.code
+		.as_deref()
+		.ok_or(Error::OAuthRequestParamMissing { name: "code" })?;
+
+	// -- Consume the code, and check it is the one of the client.
+	// Note: Consumed first, so that a code is never exchanged twice (even on a failed check).
+	let authorization = OAuthCodeBmc::take(
+		mm, code,
+	)
+	.await?;
+	if authorization.client_id != oauth_client.client_id
+		|| payload.redirect_uri.as_deref() != Some(authorization.redirect_uri.as_str())
+	{
+		return Err(Error::OAuthGrantNotMatching);
+	}
+
+	// -- Check the PKCE verifier.
+	// Note: A verifier without challenge is rejected too (PKCE downgrade).
+	match (
+		&authorization.code_challenge,
+		payload.code_verifier.as_deref(),
+	) {
+		(Some(code_challenge), Some(code_verifier)) if verify_pkce(
+			code_verifier,
+			code_challenge,
+		) => {},
+		(None, None) => {},
+		_ => return Err(Error::OAuthCodeVerifierInvalid),
+	}
+
+	// -- Issue the tokens, and start the session of the client.
+	// Note: The grant of a user deactivated since is no longer valid.
+	let user = UserBmc::get_user_for_auth_by_id(
+		&Ctx::root_ctx(),
+		mm,
+		&authorization.user_id,
+	)
+	.await?;
+	user.check_active()
+		.map_err(|_| model::Error::OAuthGrantInvalid)?;
+	let web_token = client_web_token(
+		&user,
+		&authorization.client_id,
+		&authorization.scope,
+	)?;
+	let refresh_token = create_session(
+		mm,
+		client,
+		&user.id,
+		&web_token,
+		Some(&oauth_device_id(&authorization.client_id)),
+		AuthMethod::OAuth,
+	)
+	.await?;
+	OAuthGrantBmc::create(
+		mm,
+		&refresh_token.family_id,
+		&authorization,
+	)
+	.await?;
+
+	Ok(
+		client_tokens_body(
+			&web_token,
+			&refresh_token.refresh_token,
+			&authorization.scope,
+		),
+	)
+}
+
+/// The new client app tokens, for its (rotated) refresh token.
+async fn refresh_token_grant(
+	mm: &ModelManager,
+	credentials: Option<(String, Option<String>)>,
+	payload: &OAuthTokenPayload,
+) -> Result<Json<Value>> {
+	let (client_id, client_secret) = credentials.ok_or(Error::OAuthClientCredentialsMissing)?;
+	let oauth_client = OAuthClientBmc::authenticate(
+		mm,
+		&client_id,
+		client_secret.as_deref(),
+	)
+	.await?;
+	let refresh_token = payload
+		.refresh_token
+		.as_deref()
+		.ok_or(Error::OAuthRequestParamMissing { name: "refresh_token" })?;
+	let root_ctx = Ctx::root_ctx();
+
+	// -- Check the token family is the one of the client, before rotating it.
+	let family = RefreshTokenBmc::get_family(
+		&root_ctx,
+		mm,
+		refresh_token,
+	)
+	.await?
+	.ok_or(Error::OAuthRefreshTokenInvalid)?;
+	if family.device_id != oauth_device_id(&oauth_client.client_id) {
+		return Err(Error::OAuthGrantNotMatching);
+	}
+
+	// -- Rotate the refresh token.
+	let refreshed = RefreshTokenBmc::rotate(
+		&root_ctx,
+		mm,
+		refresh_token,
+	)
+	.await
+	.map_err(
+		|ex| match ex {
+			model::Error::RefreshTokenNotFound
+			| model::Error::RefreshTokenExpired
+			| model::Error::RefreshTokenRevoked
+			| model::Error::RefreshTokenReused => Error::OAuthRefreshTokenInvalid,
+			ex => ex.into(),
+		},
+	)?;
+	let grant = OAuthGrantBmc::refresh(
+		mm,
+		&refreshed.family_id,
+	)
+	.await?;
+
+	// -- Issue the new access token.
+	let user = UserBmc::get_user_for_auth_by_id(
+		&root_ctx,
+		mm,
+		&grant.user_id,
+	)
+	.await?;
+	user.check_active()
+		.map_err(|_| model::Error::OAuthGrantInvalid)?;
+	let web_token = client_web_token(
+		&user,
+		&grant.client_id,
+		&grant.scope,
+	)?;
+	SessionBmc::update_jti(
+		&root_ctx,
+		mm,
+		&refreshed.family_id,
+		web_token.jti(),
+	)
+	.await?;
+
+	Ok(
+		client_tokens_body(
+			&web_token,
+			&refreshed.refresh_token,
+			&grant.scope,
+		),
+	)
+}
+
+/// The access token of the user for the client, with the user roles within the scope.
+fn client_web_token(
+	user: &QUserForAuth,
+	client_id: &str,
+	scope: &[String],
+) -> token::Result<WebToken> {
+	let roles: Vec<String> = user
+		.roles
+		.iter()
+		.filter(|role| scope.contains(role))
+		.cloned()
+		.collect();
+
+	generate_web_token(
+		&TokenSubject {
+			user_id: &user.id,
+			username: &user.username,
+			tenant_id: &Ctx::root_ctx().tenant_id(),
+			roles: &roles,
+			salt: user.pwd_salt,
+			service_account: false,
+			grant: Some(
+				TokenGrant {
+					client_id,
+					scope,
+				},
+			),
+		},
+	)
+}
+
+fn client_tokens_body(
+	web_token: &WebToken,
+	refresh_token: &str,
+	scope: &[String],
+) -> Json<Value> {
+	Json(
+		json!({
+			"access_token": web_token.to_string(),
+			"token_type": "Bearer",
+			"expires_in": token_duration_sec() as i64,
+			"refresh_token": refresh_token,
+			"scope": scope.join(" "),
+		}),
+	)
+}
+
+/// The `(client_id, client_secret)` of the `Authorization: Basic` header, or else of the form fields.
+fn client_credentials(
+	headers: &HeaderMap,
+	client_id: Option<&str>,
+	client_secret: Option<&str>,
+) -> Option<(String, Option<String>)> {
+	basic_credentials(headers)
+		.map(|(client_id, client_secret)| (client_id, Some(client_secret)))
+		.or_else(|| client_id.map(|client_id| (client_id.to_string(), client_secret.map(String::from))))
+}
+
+/// The `(client_id, client_secret)` of the `Authorization: Basic` header.
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+	let (scheme, credentials) = headers
+		.get(AUTHORIZATION)?
+		.to_str()
+		.ok()?
+		.split_once(' ')?;
+	if !scheme.eq_ignore_ascii_case("Basic") {
+		return None;
+	}
+
+	let credentials = b64_decode_to_string(credentials.trim()).ok()?;
+	let (client_id, client_secret) = credentials.split_once(':')?;
+
+	Some(
+		(
+			client_id.to_string(),
+			client_secret.to_string(),
+		),
+	)
+}
+
+// endregion: --- Token
+
+// region:    --- Authorize
+
+/// The authorization request of a client app, for the logged in user.
+pub async fn api_oauth_authorize_handler(
+	State(mm): State<ModelManager>,
+	ctx: CtxW,
+	Query(params): Query<OAuthAuthorizeParams>,
+) -> Result<Response> {
+	debug!(
+		"{:<12} - api_oauth_authorize_handler",
+		"HANDLER"
+	);
+	if !is_user_session(&ctx.0) {
+		return Err(Error::OAuthAuthorizeCtxNotAllowed);
+	}
+
+	// -- Validate the client and its redirect uri (answered to the user on error).
+	let client_id = params
+		.client_id
+		.as_deref()
+		.ok_or(Error::OAuthRequestParamMissing { name: "client_id" })?;
+	let oauth_client = OAuthClientBmc::get_for_auth(
+		&mm, client_id,
+	)
+	.await
+	.map_err(
+		|ex| match ex {
+			model::Error::OAuthClientNotFound { id } => Error::OAuthClientUnknown { client_id: id },
+			ex => ex.into(),
+		},
+	)?;
+	let redirect_uri = params
+		.redirect_uri
+		.as_deref()
+		.ok_or(Error::OAuthRequestParamMissing { name: "redirect_uri" })?;
+	if !oauth_client.has_redirect_uri(redirect_uri) {
+		return Err(Error::OAuthRedirectUriInvalid);
+	}
+
+	// -- Validate the request (redirected to the client on error).
+	let state = params.state.as_deref();
+	let scope = match check_authorize_params(
+		&oauth_client,
+		&params,
+	) {
+		Ok(scope) => scope,
+		Err((error, error_description)) => {
+			let redirect = redirect_to_client(
+				redirect_uri,
+				&[
+					("error", error),
+					("error_description", error_description),
+				],
+				state,
+			)
+			.ok_or(Error::OAuthRedirectUriInvalid)?;
+			return Ok(redirect.into_response());
+		},
+	};
+	let authorization = OAuthAuthorization {
+		user_id: ctx.0.user_id(),
+		client_id: oauth_client.client_id.clone(),
+		redirect_uri: redirect_uri.to_string(),
+		scope,
+		code_challenge: params.code_challenge.clone(),
+	};
+
+	// -- Issue the code if already consented, or else ask the user.
+	let consented = oauth_client.first_party
+		|| OAuthConsentBmc::covers(
+			&mm,
+			&authorization.user_id,
+			&authorization.client_id,
+			&authorization.scope,
+		)
+		.await?;
+	if consented {
+		let redirect = redirect_with_code(
+			&mm,
+			authorization,
+			state,
+		)
+		.await?;
+		return Ok(redirect.into_response());
+	}
+
+	let scope = authorization.scope.clone();
+	let request_id = OAuthAuthRequestBmc::create(
+		&mm,
+		OAuthAuthRequest {
+			authorization,
+			state: params.state,
+		},
+	)
+	.await?;
+
+	Ok(
+		consent_page(
+			&oauth_client.name,
+			&scope,
+			&request_id,
+		)
+		.into_response(),
+	)
+}
+
+/// The decision of the user on the consent page.
+///
+/// Note: The `request_id` is random, of the user, and consumed once, which protects the form from CSRF.
+pub async fn api_oauth_authorize_decision_handler(
+	State(mm): State<ModelManager>,
+	ctx: CtxW,
+	Form(payload): Form<OAuthDecisionPayload>,
+) -> Result<Redirect> {
+	debug!(
+		"{:<12} - api_oauth_authorize_decision_handler",
+		"HANDLER"
+	);
+	if !is_user_session(&ctx.0) {
+		return Err(Error::OAuthAuthorizeCtxNotAllowed);
+	}
+
+	let OAuthAuthRequest {
+		authorization,
+		state,
+	} = OAuthAuthRequestBmc::take(
+		&mm,
+		&payload.request_id,
+		&ctx.0.user_id(),
+	)
+	.await?;
+
+	if payload.decision != DECISION_ALLOW {
+		return redirect_to_client(
+			&authorization.redirect_uri,
+			&[("error", "access_denied")],
+			state.as_deref(),
+		)
+		.ok_or(Error::OAuthRedirectUriInvalid);
+	}
+
+	OAuthConsentBmc::grant(
+		&mm,
+		&authorization.user_id,
+		&authorization.client_id,
+		&authorization.scope,
+	)
+	.await?;
+
+	redirect_with_code(
+		&mm,
+		authorization,
+		state.as_deref(),
+	)
+	.await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OAuthAuthorizeParams {
+	response_type: Option<String>,
+	client_id: Option<String>,
+	redirect_uri: Option<String>,
+	scope: Option<String>,
+	state: Option<String>,
+	code_challenge: Option<String>,
+	code_challenge_method: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OAuthDecisionPayload {
+	request_id: String,
+	/// `allow`, or anything else to deny.
+	decision: String,
+}
+
+/// False for an API key, a delegated (OAuth2 client) token, or a service account, which cannot
+/// authorize a client, as the code of the authorization exchanges for a token with all the user privileges.
+fn is_user_session(ctx: &Ctx) -> bool {
+	ctx.api_key_id().is_none() && !ctx.is_delegated() && !ctx.is_service_account()
+}
+
+/// The granted scope of a valid request, or else the `(error, error_description)` for the client.
+fn check_authorize_params(
+	oauth_client: &OAuthClientForAuth,
+	params: &OAuthAuthorizeParams,
+) -> core::result::Result<Vec<String>, (&'static str, &'static str)> {
+	if params.response_type.as_deref() != Some(RESPONSE_TYPE_CODE) {
+		return Err(
+			(
+				"unsupported_response_type",
+				"only the code response type is supported",
+			),
+		);
+	}
+
+	// -- PKCE (required for the public clients, S256 only).
+	match (
+		&params.code_challenge,
+		params
+			.code_challenge_method
+			.as_deref(),
+	) {
+		(Some(_), Some(PKCE_METHOD_S256)) => {},
+		(Some(_), _) => {
+			return Err(
+				(
+					"invalid_request",
+					"only the S256 code_challenge_method is supported",
+				),
+			)
+		},
+		(None, _) if oauth_client.public => {
+			return Err(
+				(
+					"invalid_request",
+					"code_challenge required for a public client",
+				),
+			)
+		},
+		(None, _) => {},
+	}
+
+	// -- Scope.
+	let requested = parse_scope(
+		params
+			.scope
+			.as_deref()
+			.unwrap_or_default(),
+	);
+	oauth_client
+		.resolve_scope(&requested)
+		.ok_or(
+			(
+				"invalid_scope",
+				"scope not allowed for the client",
+			),
+		)
+}
+
+async fn redirect_with_code(
+	mm: &ModelManager,
+	authorization: OAuthAuthorization,
+	state: Option<&str>,
+) -> Result<Redirect> {
+	let redirect_uri = authorization
+		.redirect_uri
+		.clone();
+	let code = OAuthCodeBmc::create(
+		mm,
+		authorization,
+	)
+	.await?;
+
+	redirect_to_client(
+		&redirect_uri,
+		&[("code", &code)],
+		state,
+	)
+	.ok_or(Error::OAuthRedirectUriInvalid)
+}
+
+/// The redirect to the (registered) redirect uri, with the response params and the client `state`.
+fn redirect_to_client(
+	redirect_uri: &str,
+	params: &[(&str, &str)],
+	state: Option<&str>,
+) -> Option<Redirect> {
+	let mut url = Url::parse(redirect_uri).ok()?;
+	{
+		let mut query = url.query_pairs_mut();
+		query.extend_pairs(params);
+		if let Some(state) = state {
+			query.append_pair(
+				"state", state,
+			);
+		}
+	}
+
+	Some(Redirect::to(url.as_str()))
+}
+
+fn consent_page(
+	client_name: &str,
+	scope: &[String],
+	request_id: &str,
+) -> Html<String> {
+	let scope_items: String = scope
+		.iter()
+		.map(|scope| format!("<li>{}</li>", html_escape(scope)))
+		.collect();
+
+	Html(
+		format!(
+			r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>Authorize {client_name}</title></head>
+<body>
+<h1>Authorize {client_name}</h1>
+<p>{client_name} requests access to your account, with the privileges:</p>
+<ul>{scope_items}</ul>
+<form method="post" action="/api/oauth/authorize">
+<input type="hidden" name="request_id" value="{request_id}">
+<button type="submit" name="decision" value="allow">Allow</button>
+<button type="submit" name="decision" value="deny">Deny</button>
+</form>
+</body>
+</html>"#,
+			client_name = html_escape(client_name),
+			request_id = html_escape(request_id),
+		),
+	)
+}
+
+fn html_escape(value: &str) -> String {
+	let mut escaped = String::with_capacity(value.len());
+	for c in value.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&#39;"),
+			c => escaped.push(c),
+		}
+	}
+
+	escaped
+}
+
+// endregion: --- Authorize
+
+// region:    --- Revoke & Introspect
+
+/// Revokes a token of the client app (RFC 7009): a refresh token revokes its whole session.
+///
+/// Note: Always `200 OK` for an authenticated client, even for an unknown (or other client) token.
+pub async fn api_oauth_revoke_handler(
+	State(mm): State<ModelManager>,
+	headers: HeaderMap,
+	Form(payload): Form<OAuthTokenParams>,
+) -> Result<StatusCode> {
+	debug!(
+		"{:<12} - api_oauth_revoke_handler",
+		"HANDLER"
+	);
+
+	let (client_id, client_secret) = client_credentials(
+		&headers,
+		payload.client_id.as_deref(),
+		payload.client_secret.as_deref(),
+	)
+	.ok_or(Error::OAuthClientCredentialsMissing)?;
+	let oauth_client = OAuthClientBmc::authenticate(
+		&mm,
+		&client_id,
+		client_secret.as_deref(),
+	)
+	.await?;
+	let root_ctx = Ctx::root_ctx();
+
+	// -- Refresh token.
+	let family = RefreshTokenBmc::get_family(
+		&root_ctx,
+		&mm,
+		&payload.token,
+	)
+	.await?;
+	if let Some(family) = family {
+		if family.device_id == oauth_device_id(&oauth_client.client_id) {
+			match SessionBmc::revoke_for_user(
+				&root_ctx,
+				&mm,
+				&family.user_id,
+				&family.family_id,
+			)
+			.await
+			{
+				Ok(_) => {},
+				Err(model::Error::SessionNotFound { .. }) => {
+					RefreshTokenBmc::revoke_family_id(
+						&root_ctx,
+						&mm,
+						&family.family_id,
+					)
+					.await?
+				},
+				Err(ex) => return Err(ex.into()),
+			}
+		}
+		return Ok(StatusCode::OK);
+	}
+
+	// -- Access token.
+	if let Some(access_token) = client_access_token(
+		&mm,
+		&payload.token,
+	)
+	.await
+	{
+		if access_token.claims.client_id.as_deref() == Some(oauth_client.client_id.as_str()) {
+			TokenRevocationBmc::revoke_token(
+				&root_ctx,
+				&mm,
+				&access_token.claims.sub,
+				&access_token.claims.jti,
+			)
+			.await?;
+		}
+	}
+
+	Ok(StatusCode::OK)
+}
+
+/// The state of an access token issued to a client app (RFC 7662), for the confidential clients
+/// (e.g., the resource servers).
+///
+/// Note: Only the access tokens are introspected, any other token is `{"active": false}`.
+pub async fn api_oauth_introspect_handler(
+	State(mm): State<ModelManager>,
+	headers: HeaderMap,
+	Form(payload): Form<OAuthTokenParams>,
+) -> Result<impl IntoResponse> {
+	debug!(
+		"{:<12} - api_oauth_introspect_handler",
+		"HANDLER"
+	);
+
+	let Some((client_id, Some(client_secret))) = client_credentials(
+		&headers,
+		payload.client_id.as_deref(),
+		payload.client_secret.as_deref(),
+	) else {
+		return Err(Error::OAuthClientCredentialsMissing);
+	};
+	OAuthClientBmc::authenticate(
+		&mm,
+		&client_id,
+		Some(&client_secret),
+	)
+	.await?;
+
+	let mut body = json!({ "active": false });
+	if let Some(access_token) = client_access_token(
+		&mm,
+		&payload.token,
+	)
+	.await
+	{
+		let ClientAccessToken {
+			token,
+			claims,
+			username,
+		} = access_token;
+		if check_not_revoked(
+			&mm,
+			&claims.sub,
+			&token,
+		)
+		.await
+		.is_ok()
+		{
+			body = json!({
+				"active": true,
+				"scope": claims.scope,
+				"client_id": claims.client_id,
+				"username": username,
+				"token_type": "Bearer",
+				"exp": claims.exp,
+				"iat": claims.iat,
+				"sub": claims.sub,
+				"jti": claims.jti,
+			});
+		}
+	}
+
+	Ok(
+		(
+			[(CACHE_CONTROL, "no-store")],
+			Json(body),
+		),
+	)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OAuthTokenParams {
+	token: String,
+	client_id: Option<String>,
+	client_secret: Option<String>,
+}
+
+/// A valid (but maybe revoked) access token issued to a client app.
+struct ClientAccessToken {
+	token: WebToken,
+	claims: Claims,
+	username: String,
+}
+
+async fn client_access_token(
+	mm: &ModelManager,
+	token: &str,
+) -> Option<ClientAccessToken> {
+	let token: WebToken = token.parse().ok()?;
+	let claims = match &token {
+		WebToken::Jwt(jwt) if jwt.claims.client_id.is_some() => jwt.claims.clone(),
+		_ => return None,
+	};
+
+	let user = UserBmc::get_user_for_auth_by_id(
+		&Ctx::root_ctx(),
+		mm,
+		&claims.sub,
+	)
+	.await
+	.ok()?;
+	user.check_active().ok()?;
+	validate_web_token(
+		&token,
+		user.pwd_salt,
+	)
+	.ok()?;
+
+	Some(
+		ClientAccessToken {
+			token,
+			claims,
+			username: user.username,
+		},
+	)
+}
+
+// endregion: --- Revoke & Introspect
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+
+	fn fx_client(public: bool) -> OAuthClientForAuth {
+		OAuthClientForAuth {
+			client_id: "app_01".to_string(),
+			tenant_id: "tenant-01".to_string(),
+			name: "App 01".to_string(),
+			public,
+			redirect_uris: vec!["https://app.example.com/callback".to_string()],
+			scopes: vec!["read".to_string(), "write".to_string()],
+			first_party: false,
+		}
+	}
+
+	fn fx_params(
+		scope: Option<&str>,
+		code_challenge_method: Option<&str>,
+	) -> OAuthAuthorizeParams {
+		OAuthAuthorizeParams {
+			response_type: Some("code".to_string()),
+			client_id: Some("app_01".to_string()),
+			redirect_uri: Some("https://app.example.com/callback".to_string()),
+			scope: scope.map(String::from),
+			state: None,
+			code_challenge: code_challenge_method.map(|_| "challenge-01".to_string()),
+			code_challenge_method: code_challenge_method.map(String::from),
+		}
+	}
+
+	#[test]
+	fn test_basic_credentials_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let mut headers = HeaderMap::new();
+		// "sa_01:secret-01"
+		headers.insert(
+			AUTHORIZATION,
+			"Basic c2FfMDE6c2VjcmV0LTAx".parse()?,
+		);
+
+		// -- Exec
+		let credentials = basic_credentials(&headers);
+
+		// -- Check
+		assert_eq!(
+			credentials,
+			Some(("sa_01".to_string(), "secret-01".to_string()))
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_check_authorize_params() -> Result<()> {
+		// -- Exec & Check
+		assert_eq!(
+			check_authorize_params(
+				&fx_client(false),
+				&fx_params(
+					Some("write"),
+					None
+				),
+			),
+			Ok(vec!["write".to_string()])
+		);
+		assert_eq!(
+			check_authorize_params(
+				&fx_client(true),
+				&fx_params(
+					None,
+					Some("S256")
+				),
+			),
+			Ok(vec!["read".to_string(), "write".to_string()])
+		);
+		assert!(matches!(
+			check_authorize_params(
+				&fx_client(true),
+				&fx_params(
+					None, None
+				),
+			),
+			Err(("invalid_request", _))
+		));
+		assert!(matches!(
+			check_authorize_params(
+				&fx_client(true),
+				&fx_params(
+					None,
+					Some("plain")
+				),
+			),
+			Err(("invalid_request", _))
+		));
+		assert!(matches!(
+			check_authorize_params(
+				&fx_client(false),
+				&fx_params(
+					Some("read admin"),
+					None
+				),
+			),
+			Err(("invalid_scope", _))
+		));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_redirect_to_client_ok() -> Result<()> {
+		// -- Exec
+		let redirect = redirect_to_client(
+			"https://app.example.com/callback?app=1",
+			&[("code", "code-01")],
+			Some("a b"),
+		)
+		.ok_or("no redirect")?
+		.into_response();
+
+		// -- Check
+		assert_eq!(
+			redirect.headers()["location"],
+			"https://app.example.com/callback?app=1&code=code-01&state=a+b"
+		);
+
+		Ok(())
+	}
+
+	/// Checks that both the authorize and the decision handlers refuse the ctx.
+	async fn check_authorize_ctx_not_allowed(fx_ctx: Ctx) -> Result<()> {
+		let mm = ModelManager::new().await?;
+
+		// -- Exec
+		let res_authorize = api_oauth_authorize_handler(
+			State(mm.clone()),
+			CtxW(fx_ctx.clone()),
+			Query(
+				fx_params(
+					None,
+					Some(PKCE_METHOD_S256),
+				),
+			),
+		)
+		.await;
+		let res_decision = api_oauth_authorize_decision_handler(
+			State(mm),
+			CtxW(fx_ctx),
+			Form(
+				OAuthDecisionPayload {
+					request_id: "request-01".to_string(),
+					decision: DECISION_ALLOW.to_string(),
+				},
+			),
+		)
+		.await;
+
+		// -- Check
+		assert!(matches!(
+			res_authorize,
+			Err(crate::Error::OAuthAuthorizeCtxNotAllowed)
+		));
+		assert!(matches!(
+			res_decision,
+			Err(crate::Error::OAuthAuthorizeCtxNotAllowed)
+		));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_authorize_api_key_ctx_err() -> Result<()> {
+		let fx_ctx = Ctx::new("user-01".to_string())?.add_api_key_id("key-01".to_string());
+
+		check_authorize_ctx_not_allowed(fx_ctx).await
+	}
+
+	#[tokio::test]
+	async fn test_authorize_delegated_ctx_err() -> Result<()> {
+		let fx_ctx = Ctx::new("user-01".to_string())?.add_scopes(vec!["read".to_string()]);
+
+		check_authorize_ctx_not_allowed(fx_ctx).await
+	}
+
+	#[tokio::test]
+	async fn test_authorize_service_account_ctx_err() -> Result<()> {
+		let fx_ctx = Ctx::new_service_account("sa_01".to_string())?;
+
+		check_authorize_ctx_not_allowed(fx_ctx).await
+	}
+
+	#[test]
+	fn test_html_escape_ok() -> Result<()> {
+		// -- Exec & Check
+		assert_eq!(
+			html_escape(r#"<App "One" & 'Two'>"#),
+			"&lt;App &quot;One&quot; &amp; &#39;Two&#39;&gt;"
+		);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
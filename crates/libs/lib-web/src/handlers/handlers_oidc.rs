@@ -0,0 +1,314 @@
+//! OpenID Connect login with the identity providers of the tenants (see `OidcProviderBmc`).
+//!
+//! Flow:
+//!
+//! 1. `/api/oidc/login/{provider_id}` records a pending login (`state`, `nonce`, PKCE verifier),
+//!    sets the `state` in the `OIDC_STATE` cookie, and redirects to the provider authorization endpoint.
+//! 2. `/api/oidc/callback` checks the `state` against the cookie (so a callback started by another
+//!    browser cannot log this one in), consumes the pending login of the `state`, exchanges the code for the ID token,
+//!    validates it, links (or provisions) the user, and starts a session like `/api/login` (cookies),
+//!    before redirecting to the `return_to` path.
+
+use crate::error::{Error, Result};
+use crate::handlers::handlers_login::create_session;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::{oidc, token};
+use axum::extract::{Path, Query, State};
+use axum::response::Redirect;
+use lib_auth::oidc::{generate_pkce, generate_state};
+use lib_auth::token::TokenSubject;
+use lib_core::ctx::Ctx;
+use lib_core::model::oidc::{OidcIdentityBmc, OidcLogin, OidcLoginBmc, OidcProviderBmc, LOGIN_DURATION};
+use lib_core::model::session::AuthMethod;
+use lib_core::model::user::UserBmc;
+use lib_core::model::ModelManager;
+use serde::Deserialize;
+use tower_cookies::cookie::{time, SameSite};
+use tower_cookies::{Cookie, Cookies};
+use tracing::debug;
+
+// region:    --- Login
+
+/// The `state` of the login started by the browser, for the callback only.
+const OIDC_STATE: &str = "oidc-state";
+const CALLBACK_PATH: &str = "/api/oidc/callback";
+
+pub async fn api_oidc_login_handler(
+	State(mm): State<ModelManager>,
+	cookies: Cookies,
+	Path(provider_id): Path<String>,
+	Query(params): Query<OidcLoginParams>,
+) -> Result<Redirect> {
+	debug!(
+		"{:<12} - api_oidc_login_handler",
+		"HANDLER"
+	);
+
+	let provider = OidcProviderBmc::get_for_auth(
+		&mm,
+		&provider_id,
+	)
+	.await?;
+	let metadata = oidc::discover(&provider.issuer).await?;
+
+	// -- Record the pending login.
+	let pkce = generate_pkce();
+	let login = OidcLogin {
+		state: generate_state(),
+		provider_id: provider.id.clone(),
+		nonce: generate_state(),
+		code_verifier: pkce.verifier,
+		return_to: local_return_to(params.return_to.as_deref()).to_string(),
+	};
+	let authorization_url = oidc::authorization_url(
+		&metadata,
+		&provider,
+		&login.state,
+		&login.nonce,
+		&pkce.challenge,
+	);
+	add_state_cookie(
+		&cookies,
+		login.state.clone(),
+	);
+	OidcLoginBmc::create(
+		&mm, login,
+	)
+	.await?;
+
+	Ok(Redirect::to(authorization_url.as_str()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcLoginParams {
+	/// The local path to redirect to once logged in (`/` by default).
+	return_to: Option<String>,
+}
+
+/// Only the local paths are followed, to not be an open redirect.
+fn local_return_to(return_to: Option<&str>) -> &str {
+	match return_to {
+		Some(path) if path.starts_with('/') && !path.starts_with("//") && !path.starts_with("/\\") => path,
+		_ => "/",
+	}
+}
+
+// endregion: --- Login
+
+// region:    --- Callback
+
+pub async fn api_oidc_callback_handler(
+	State(mm): State<ModelManager>,
+	cookies: Cookies,
+	client: ClientInfo,
+	Query(params): Query<OidcCallbackParams>,
+) -> Result<Redirect> {
+	debug!(
+		"{:<12} - api_oidc_callback_handler",
+		"HANDLER"
+	);
+
+	if !state_cookie_matches(
+		&cookies,
+		&params.state,
+	) {
+		return Err(Error::OidcStateCookieNotMatching);
+	}
+	remove_state_cookie(&cookies);
+
+	// Note: Consumed first, so that a `state` is never answered twice (even on a provider error).
+	let login = OidcLoginBmc::take(
+		&mm,
+		&params.state,
+	)
+	.await?;
+	if let Some(error) = params.error {
+		return Err(
+			Error::OidcCallbackError {
+				error,
+				error_description: params.error_description,
+			},
+		);
+	}
+	let code = params
+		.code
+		.ok_or(Error::OidcCallbackCodeMissing)?;
+
+	// -- Exchange the code, and validate the ID token.
+	let provider = OidcProviderBmc::get_for_auth(
+		&mm,
+		&login.provider_id,
+	)
+	.await?;
+	let metadata = oidc::discover(&provider.issuer).await?;
+	let id_token = oidc::exchange_code(
+		&metadata,
+		&provider,
+		&code,
+		&login.code_verifier,
+	)
+	.await?;
+	let claims = oidc::validate_id_token(
+		&metadata,
+		&provider,
+		&id_token,
+		&login.nonce,
+	)
+	.await?;
+
+	// -- Get the linked (or provisioned) user.
+	let root_ctx = Ctx::root_ctx();
+	let user_id = OidcIdentityBmc::link_or_provision(
+		&root_ctx,
+		&mm,
+		&provider.id,
+		&claims,
+	)
+	.await?;
+	let user = UserBmc::get_user_for_auth_by_id(
+		&root_ctx, &mm, &user_id,
+	)
+	.await?;
+	user.check_active()?;
+
+	// -- Start the session, as `/api/login`.
+	let web_token = token::set_token_cookie(
+		&cookies,
+		&TokenSubject {
+			user_id: &user.id,
+			username: &user.username,
+			tenant_id: &root_ctx.tenant_id(),
+			roles: &user.roles,
+			salt: user.pwd_salt,
+			service_account: false,
+			grant: None,
+		},
+	)?;
+	let refresh_token = create_session(
+		&mm,
+		client,
+		&user.id,
+		&web_token,
+		None,
+		AuthMethod::Oidc,
+	)
+	.await?;
+	token::set_refresh_token_cookie(
+		&cookies,
+		refresh_token.refresh_token,
+	);
+
+	Ok(Redirect::to(&login.return_to))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackParams {
+	state: String,
+	code: Option<String>,
+	/// Set by the provider when the authorization failed (e.g., `access_denied`).
+	error: Option<String>,
+	error_description: Option<String>,
+}
+
+// endregion: --- Callback
+
+// region:    --- State Cookie
+
+/// Note: `SameSite=Lax`, to be sent on the top-level redirect from the provider.
+fn add_state_cookie(
+	cookies: &Cookies,
+	state: String,
+) {
+	let mut cookie = Cookie::new(
+		OIDC_STATE, state,
+	);
+	cookie.set_http_only(true);
+	cookie.set_same_site(SameSite::Lax);
+	cookie.set_path(CALLBACK_PATH);
+	cookie.set_max_age(time::Duration::seconds(LOGIN_DURATION.as_secs() as i64));
+
+	cookies.add(cookie);
+}
+
+fn remove_state_cookie(cookies: &Cookies) {
+	let mut cookie = Cookie::from(OIDC_STATE);
+	cookie.set_path(CALLBACK_PATH);
+
+	cookies.remove(cookie);
+}
+
+fn state_cookie_matches(
+	cookies: &Cookies,
+	state: &str,
+) -> bool {
+	cookies
+		.get(OIDC_STATE)
+		.is_some_and(|cookie| cookie.value() == state)
+}
+
+// endregion: --- State Cookie
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+
+	#[test]
+	fn test_local_return_to_ok() -> Result<()> {
+		// -- Fixtures
+		let fx_cases = [
+			(Some("/app/home?tab=1"), "/app/home?tab=1"),
+			(None, "/"),
+			(Some("https://evil.example.com"), "/"),
+			(Some("//evil.example.com"), "/"),
+			(Some("/\\evil.example.com"), "/"),
+		];
+
+		for (fx_return_to, fx_path) in fx_cases {
+			// -- Exec
+			let path = local_return_to(fx_return_to);
+
+			// -- Check
+			assert_eq!(
+				path, fx_path,
+				"return_to: {fx_return_to:?}"
+			);
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_state_cookie_matches() -> Result<()> {
+		// -- Setup & Fixtures
+		let cookies = Cookies::default();
+		let fx_state = generate_state();
+
+		// -- Exec
+		let matches_none = state_cookie_matches(
+			&cookies, &fx_state,
+		);
+		add_state_cookie(
+			&cookies,
+			fx_state.clone(),
+		);
+		let matches_same = state_cookie_matches(
+			&cookies, &fx_state,
+		);
+		let matches_other = state_cookie_matches(
+			&cookies,
+			&generate_state(),
+		);
+
+		// -- Check
+		assert!(!matches_none);
+		assert!(matches_same);
+		assert!(!matches_other);
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -0,0 +1,197 @@
+//! The "forgot password" flow: a reset token sent by mail, exchanged for a new password
+//! (see `PwdResetBmc`).
+//!
+//! Notes:
+//!
+//! - The forgot request answers the same whether the user exists or not, and its mail is sent
+//!   in the background (so the response time does not tell either).
+//! - The reset re-hashes the password with a new salt (see `UserBmc::update_pwd`), and revokes all
+//!   the tokens and refresh tokens of the user.
+
+use crate::error::Result;
+use axum::extract::State;
+use axum::Json;
+use lib_core::ctx::Ctx;
+use lib_core::model::login_throttle::LoginThrottleBmc;
+use lib_core::model::pwd_reset::PwdResetBmc;
+use lib_core::model::token_revocation::TokenRevocationBmc;
+use lib_core::model::user::UserBmc;
+use lib_core::model::ModelManager;
+use lib_mail::{mail_link, send_mail, Mail};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+/// The page of the web app (web folder) posting the token and the new password to `/api/pwd/reset`.
+const PWD_RESET_PAGE_PATH: &str = "/reset-pwd";
+
+// region:    --- Forgot
+
+pub async fn api_pwd_forgot_handler(
+	State(mm): State<ModelManager>,
+	Json(payload): Json<PwdForgotPayload>,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_pwd_forgot_handler",
+		"HANDLER"
+	);
+
+	tokio::spawn(
+		async move {
+			if let Err(ex) = send_pwd_reset_mail(
+				&mm,
+				payload.mail,
+			)
+			.await
+			{
+				warn!("pwd reset mail not sent - {ex:?}");
+			}
+		},
+	);
+
+	// Create the success body (whether the user exists or not).
+	let body = Json(
+		json!({
+			"result": {
+				"success": true
+			}
+		}),
+	);
+
+	Ok(body)
+}
+
+async fn send_pwd_reset_mail(
+	mm: &ModelManager,
+	mail: String,
+) -> Result<()> {
+	let Some(user_id) = UserBmc::find_id_by_email(
+		&Ctx::root_ctx(),
+		mm,
+		&mail,
+	)
+	.await?
+	else {
+		return Ok(());
+	};
+
+	let token = PwdResetBmc::create(
+		mm, &user_id,
+	)
+	.await?;
+	send_mail(
+		pwd_link_mail(
+			mail, &token, false,
+		),
+	)
+	.await?;
+
+	Ok(())
+}
+
+/// The mail with the link to choose a password: a reset, or the invitation of a user created
+/// by an admin (see `PwdResetBmc::create_invite`).
+pub fn pwd_link_mail(
+	to: String,
+	token: &str,
+	invite: bool,
+) -> Mail {
+	let link = mail_link(&format!("{PWD_RESET_PAGE_PATH}?token={token}"));
+
+	let (subject, body) = if invite {
+		(
+			"Your new account",
+			format!(
+				"Hello,\n\nAn account was created for you. Open this link to choose your password (valid for 7 days):\n\n{link}\n"
+			),
+		)
+	} else {
+		(
+			"Reset your password",
+			format!(
+				"Hello,\n\nA password reset was requested for your account. Open this link to choose a new password (valid for 30 minutes):\n\n{link}\n\nIf you did not request it, you can ignore this mail.\n"
+			),
+		)
+	};
+
+	Mail {
+		to,
+		subject: subject.to_string(),
+		body,
+	}
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PwdForgotPayload {
+	mail: String,
+}
+
+// endregion: --- Forgot
+
+// region:    --- Reset
+
+pub async fn api_pwd_reset_handler(
+	State(mm): State<ModelManager>,
+	Json(payload): Json<PwdResetPayload>,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_pwd_reset_handler",
+		"HANDLER"
+	);
+
+	let PwdResetPayload { token, pwd } = payload;
+	let root_ctx = Ctx::root_ctx();
+
+	// -- Check the new password (before consuming the token, to try another one).
+	let user_id = PwdResetBmc::get_user_id(
+		&mm, &token,
+	)
+	.await?;
+	let user = UserBmc::get_user_for_auth_by_id(
+		&root_ctx, &mm, &user_id,
+	)
+	.await?;
+	UserBmc::validate_new_pwd(
+		&user.username,
+		&pwd,
+	)?;
+
+	// -- Reset the password.
+	let user_id = PwdResetBmc::take(
+		&mm, &token,
+	)
+	.await?;
+	UserBmc::update_pwd(
+		&root_ctx, &mm, &user_id, &pwd,
+	)
+	.await?;
+
+	// -- Log out everywhere, and lift the lockout of the failed logins.
+	TokenRevocationBmc::revoke_all_for_user(
+		&root_ctx, &mm, &user_id,
+	)
+	.await?;
+	LoginThrottleBmc::unlock_user(
+		&mm, &user_id,
+	)
+	.await?;
+
+	// Create the success body.
+	let body = Json(
+		json!({
+			"result": {
+				"success": true
+			}
+		}),
+	);
+
+	Ok(body)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PwdResetPayload {
+	token: String,
+	pwd: String,
+}
+
+// endregion: --- Reset
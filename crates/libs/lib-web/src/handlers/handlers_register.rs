@@ -0,0 +1,283 @@
+//! Self-service registration, with the verification of the mail (see `UserBmc::register`).
+//!
+//! The registered user is inactive (cannot log in) until the link of the verification mail is opened.
+//! The link carries a signed, expiring action token of the user (see `generate_action_token`).
+//!
+//! The new mail of a profile update is verified the same way, before it is applied
+//! (see `UserBmc::confirm_mail_change`).
+
+use crate::error::{Error, Result};
+use axum::extract::{Query, State};
+use axum::Json;
+use lib_auth::token::{
+	generate_action_token, validate_action_token, Token, ACTION_EMAIL_VERIFICATION,
+	ACTION_MAIL_CHANGE,
+};
+use lib_core::ctx::Ctx;
+use lib_core::model::user::{QUserForAuth, QUserForRegister, UserBmc, UserState};
+use lib_core::model::{self, ModelManager};
+use lib_mail::{mail_link, send_mail, Mail};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::debug;
+
+/// The validity of the verification links.
+const EMAIL_VERIFICATION_DURATION_SEC: f64 = 24. * 60. * 60.; // 24 hours
+
+// region:    --- Register
+
+pub async fn api_register_handler(
+	State(mm): State<ModelManager>,
+	Json(payload): Json<RegisterPayload>,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_register_handler",
+		"HANDLER"
+	);
+
+	let RegisterPayload {
+		username,
+		pwd,
+		mail,
+	} = payload;
+	let root_ctx = Ctx::root_ctx();
+
+	let user_id = UserBmc::register(
+		&root_ctx,
+		&mm,
+		QUserForRegister {
+			username,
+			pwd_clear: pwd,
+			mail: mail.clone(),
+		},
+	)
+	.await?;
+
+	// -- Send the verification mail.
+	// Note: Without the mail, the user could never be activated, so it is removed (to register again).
+	if let Err(ex) = send_verification_mail(
+		&mm, &user_id, mail,
+	)
+	.await
+	{
+		UserBmc::delete(
+			&root_ctx, &mm, &user_id,
+		)
+		.await?;
+		return Err(ex);
+	}
+
+	// Create the success body.
+	let body = Json(
+		json!({
+			"result": {
+				"success": true
+			}
+		}),
+	);
+
+	Ok(body)
+}
+
+async fn send_verification_mail(
+	mm: &ModelManager,
+	user_id: &String,
+	mail: String,
+) -> Result<()> {
+	let user = UserBmc::get_user_for_auth_by_id(
+		&Ctx::root_ctx(),
+		mm,
+		user_id,
+	)
+	.await?;
+	let token = generate_action_token(
+		ACTION_EMAIL_VERIFICATION,
+		&user.id,
+		EMAIL_VERIFICATION_DURATION_SEC,
+		user.pwd_salt,
+	)?;
+	let link = mail_link(&format!("/api/register/verify?token={token}"));
+
+	send_mail(
+		Mail {
+			to: mail,
+			subject: "Verify your email".to_string(),
+			body: format!(
+				"Hello {},\n\nPlease verify your email by opening this link (valid for 24 hours):\n\n{link}\n",
+				user.username
+			),
+		},
+	)
+	.await?;
+
+	Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterPayload {
+	username: String,
+	pwd: String,
+	mail: String,
+}
+
+// endregion: --- Register
+
+// region:    --- Verify
+
+/// Activates the user of the verification link.
+pub async fn api_register_verify_handler(
+	State(mm): State<ModelManager>,
+	Query(params): Query<VerifyParams>,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_register_verify_handler",
+		"HANDLER"
+	);
+
+	let token: Token = params
+		.token
+		.parse()
+		.map_err(|_| Error::EmailVerifyTokenInvalid)?;
+	let root_ctx = Ctx::root_ctx();
+
+	// -- Validate the token (with the salt of its user).
+	let user = UserBmc::get_user_for_auth_by_id(
+		&root_ctx,
+		&mm,
+		&token.ident,
+	)
+	.await
+	.map_err(|_| Error::EmailVerifyTokenInvalid)?;
+	validate_action_token(
+		ACTION_EMAIL_VERIFICATION,
+		&token,
+		user.pwd_salt,
+	)
+	.map_err(|_| Error::EmailVerifyTokenInvalid)?;
+
+	// -- Activate the user (only if still pending, not e.g. disabled meanwhile).
+	match user.state {
+		UserState::PendingVerification => {
+			UserBmc::set_state(
+				&root_ctx,
+				&mm,
+				&user.id,
+				UserState::Active,
+			)
+			.await?
+		},
+		UserState::Active => (),
+		_ => return Err(Error::EmailVerifyTokenInvalid),
+	}
+
+	// Create the success body.
+	let body = Json(
+		json!({
+			"result": {
+				"verified": true
+			}
+		}),
+	);
+
+	Ok(body)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyParams {
+	token: String,
+}
+
+// endregion: --- Verify
+
+// region:    --- Mail Change
+
+/// The mail with the verification link of the new `mail` of the user (see `UserBmc::update_profile`).
+///
+/// Note: The token ident is `<user_id>:<mail>`, so the link only confirms the mail it was sent to.
+pub fn mail_change_mail(
+	user: &QUserForAuth,
+	mail: String,
+) -> lib_auth::token::Result<Mail> {
+	let token = generate_action_token(
+		ACTION_MAIL_CHANGE,
+		&format!(
+			"{}:{mail}",
+			user.id
+		),
+		EMAIL_VERIFICATION_DURATION_SEC,
+		user.pwd_salt,
+	)?;
+	let link = mail_link(&format!("/api/profile/mail/verify?token={token}"));
+
+	Ok(
+		Mail {
+			to: mail,
+			subject: "Verify your new email".to_string(),
+			body: format!(
+				"Hello {},\n\nPlease verify your new email by opening this link (valid for 24 hours):\n\n{link}\n\nIf you did not request it, you can ignore this mail.\n",
+				user.username
+			),
+		},
+	)
+}
+
+/// Applies the new mail of the verification link.
+pub async fn api_mail_change_verify_handler(
+	State(mm): State<ModelManager>,
+	Query(params): Query<VerifyParams>,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_mail_change_verify_handler",
+		"HANDLER"
+	);
+
+	let token: Token = params
+		.token
+		.parse()
+		.map_err(|_| Error::EmailVerifyTokenInvalid)?;
+	let (user_id, mail) = token
+		.ident
+		.split_once(':')
+		.ok_or(Error::EmailVerifyTokenInvalid)?;
+	let root_ctx = Ctx::root_ctx();
+
+	// -- Validate the token (with the salt of its user).
+	let user = UserBmc::get_user_for_auth_by_id(
+		&root_ctx,
+		&mm,
+		&user_id.to_string(),
+	)
+	.await
+	.map_err(|_| Error::EmailVerifyTokenInvalid)?;
+	validate_action_token(
+		ACTION_MAIL_CHANGE,
+		&token,
+		user.pwd_salt,
+	)
+	.map_err(|_| Error::EmailVerifyTokenInvalid)?;
+
+	// -- Apply the mail (only if still the pending one).
+	UserBmc::confirm_mail_change(
+		&root_ctx, &mm, &user.id, mail,
+	)
+	.await
+	.map_err(
+		|ex| match ex {
+			model::Error::UserMailChangeNotPending => Error::EmailVerifyTokenInvalid,
+			ex => ex.into(),
+		},
+	)?;
+
+	// Create the success body.
+	let body = Json(
+		json!({
+			"result": {
+				"verified": true
+			}
+		}),
+	);
+
+	Ok(body)
+}
+
+// endregion: --- Mail Change
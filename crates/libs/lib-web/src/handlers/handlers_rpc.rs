@@ -1,10 +1,13 @@
 use crate::middleware::mw_auth::CtxW;
+use crate::utils::token::{add_token_cookie, AUTH_TOKEN};
 use axum::extract::State;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use rpc_router::resources_builder;
+use lib_auth::token::{generate_web_token, TokenSubject, WebToken};
+use rpc_router::{resources_builder, RpcResource};
 use serde_json::{json, Value};
 use std::sync::Arc;
+use tower_cookies::Cookies;
 
 /// RPC ID and Method Capture
 /// Note: This will be injected into the Axum Response extensions so that
@@ -16,9 +19,35 @@ pub struct RpcInfo {
 	pub method: String,
 }
 
+/// The cookies of the rpc request, as an rpc resource for the rpcs re-issuing the auth cookie
+/// (e.g., `change_my_password`).
+#[derive(Clone, RpcResource)]
+pub struct RpcCookies(pub Cookies);
+
+impl RpcCookies {
+	/// Issues a new auth cookie for the subject, if the request was authenticated by cookie
+	/// (a bearer client renews its token with its refresh token). Returns the issued token.
+	pub fn reissue_token_cookie(
+		&self,
+		subject: &TokenSubject,
+	) -> lib_auth::token::Result<Option<WebToken>> {
+		if self.0.get(AUTH_TOKEN).is_none() {
+			return Ok(None);
+		}
+
+		let token = generate_web_token(subject)?;
+		add_token_cookie(
+			&self.0, &token,
+		);
+
+		Ok(Some(token))
+	}
+}
+
 pub async fn rpc_axum_handler(
 	State(rpc_router): State<rpc_router::Router>,
 	ctx: CtxW,
+	cookies: Cookies,
 	Json(rpc_req): Json<Value>,
 ) -> Response {
 	let ctx = ctx.0;
@@ -43,7 +72,7 @@ pub async fn rpc_axum_handler(
 	// Note: Since Ctx is per axum request, we construct additional RPC resources.
 	//       These additional resources will be "overlayed" on top of the base router services,
 	//       meaning they will take precedence over the base router ones, but won't replace them.
-	let additional_resources = resources_builder![ctx].build();
+	let additional_resources = resources_builder![ctx, RpcCookies(cookies)].build();
 
 	// -- Exec Rpc Route
 	let rpc_call_result = rpc_router
@@ -0,0 +1,345 @@
+use crate::error::{Error, Result};
+use crate::handlers::handlers_login::{
+	login, login_mfa, mfa_required_body, LoginMfaPayload, LoginPayload, LoginStep, LoginTokens,
+};
+use crate::middleware::mw_auth::CtxW;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::token;
+use axum::extract::State;
+use axum::Json;
+use lib_auth::token::{generate_web_token, token_duration_sec, TokenSubject, WebToken};
+use lib_core::ctx::{Ctx, ROLE_ADMIN};
+use lib_core::model::oauth::OAUTH_DEVICE_PREFIX;
+use lib_core::model::refresh_token::RefreshTokenBmc;
+use lib_core::model::session::SessionBmc;
+use lib_core::model::token_revocation::TokenRevocationBmc;
+use lib_core::model::user::UserBmc;
+use lib_core::model::ModelManager;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tower_cookies::Cookies;
+use tracing::debug;
+
+// region:    --- Token
+
+/// Logs in as `/api/login`, but returns the tokens in the body instead of setting cookies,
+/// for the non-browser clients (`Authorization: Bearer <access_token>`).
+pub async fn api_token_handler(
+	State(mm): State<ModelManager>,
+	client: ClientInfo,
+	Json(payload): Json<LoginPayload>,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_token_handler",
+		"HANDLER"
+	);
+
+	let LoginTokens {
+		web_token,
+		refresh_token,
+	} = match login(
+		&mm, client, payload,
+	)
+	.await?
+	{
+		LoginStep::Tokens(tokens) => *tokens,
+		LoginStep::MfaRequired { mfa_token } => return Ok(mfa_required_body(&mfa_token)),
+	};
+
+	Ok(
+		tokens_body(
+			&web_token,
+			&refresh_token,
+		),
+	)
+}
+
+/// The code step of `/api/token`, for a user with MFA enabled (as `/api/login/mfa`).
+pub async fn api_token_mfa_handler(
+	State(mm): State<ModelManager>,
+	client: ClientInfo,
+	Json(payload): Json<LoginMfaPayload>,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_token_mfa_handler",
+		"HANDLER"
+	);
+
+	let LoginTokens {
+		web_token,
+		refresh_token,
+	} = login_mfa(
+		&mm, client, payload,
+	)
+	.await?;
+
+	Ok(
+		tokens_body(
+			&web_token,
+			&refresh_token,
+		),
+	)
+}
+
+// endregion: --- Token
+
+// region:    --- Refresh
+
+/// Exchanges the refresh token for a new access token and a new (rotated) refresh token.
+///
+/// The refresh token of the cookie is answered with cookies, the one of the body
+/// (see `/api/token`) in the body.
+pub async fn api_token_refresh_handler(
+	State(mm): State<ModelManager>,
+	cookies: Cookies,
+	payload: Option<Json<RefreshPayload>>,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_token_refresh_handler",
+		"HANDLER"
+	);
+
+	let body_refresh_token = payload.and_then(|Json(payload)| payload.refresh_token);
+	let in_body = body_refresh_token.is_some();
+	let refresh_token = body_refresh_token
+		.or_else(
+			|| {
+				cookies
+					.get(token::REFRESH_TOKEN)
+					.map(|cookie| cookie.value().to_string())
+			},
+		)
+		.ok_or(Error::RefreshTokenNotInRequest)?;
+	let root_ctx = Ctx::root_ctx();
+
+	// -- Reject the refresh tokens of the OAuth2 clients (renewed by `/api/oauth/token`),
+	//    which must not get a full user token.
+	let family = RefreshTokenBmc::get_family(
+		&root_ctx,
+		&mm,
+		&refresh_token,
+	)
+	.await?;
+	if family.is_some_and(|family| family.device_id.starts_with(OAUTH_DEVICE_PREFIX)) {
+		return Err(Error::RefreshTokenOfOAuthClient);
+	}
+
+	// -- Rotate the refresh token.
+	let refreshed = match RefreshTokenBmc::rotate(
+		&root_ctx,
+		&mm,
+		&refresh_token,
+	)
+	.await
+	{
+		Ok(refreshed) => refreshed,
+		Err(ex) => {
+			if !in_body {
+				token::remove_refresh_token_cookie(&cookies);
+			}
+			return Err(ex.into());
+		},
+	};
+
+	// -- Issue the new tokens.
+	let user = UserBmc::get_user_for_auth_by_id(
+		&root_ctx,
+		&mm,
+		&refreshed.user_id,
+	)
+	.await?;
+	user.check_active()?;
+	let web_token = generate_web_token(
+		&TokenSubject {
+			user_id: &user.id,
+			username: &user.username,
+			tenant_id: &root_ctx.tenant_id(),
+			roles: &user.roles,
+			salt: user.pwd_salt,
+			service_account: false,
+			grant: None,
+		},
+	)?;
+	SessionBmc::update_jti(
+		&root_ctx,
+		&mm,
+		&refreshed.family_id,
+		web_token.jti(),
+	)
+	.await?;
+
+	if in_body {
+		return Ok(
+			tokens_body(
+				&web_token,
+				&refreshed.refresh_token,
+			),
+		);
+	}
+
+	token::add_token_cookie(
+		&cookies,
+		&web_token,
+	);
+	token::set_refresh_token_cookie(
+		&cookies,
+		refreshed.refresh_token,
+	);
+
+	// Create the success body.
+	let body = Json(
+		json!({
+			"result": {
+				"success": true
+			}
+		}),
+	);
+
+	Ok(body)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshPayload {
+	/// The refresh token of `/api/token` (the cookie one is used otherwise).
+	refresh_token: Option<String>,
+}
+
+fn tokens_body(
+	web_token: &WebToken,
+	refresh_token: &str,
+) -> Json<Value> {
+	Json(
+		json!({
+			"result": {
+				"access_token": web_token.to_string(),
+				"token_type": "Bearer",
+				"expires_in": token_duration_sec() as i64,
+				"refresh_token": refresh_token,
+			}
+		}),
+	)
+}
+
+// endregion: --- Refresh
+
+// region:    --- Revoke
+
+/// Revokes the current session (access and refresh tokens), i.e., a server-side logoff.
+pub async fn api_token_revoke_handler(
+	State(mm): State<ModelManager>,
+	ctx: CtxW,
+	cookies: Cookies,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_token_revoke_handler",
+		"HANDLER"
+	);
+
+	revoke_current_session(
+		&mm,
+		Some(&ctx.0),
+		&cookies,
+	)
+	.await?;
+
+	Ok(revoked_body())
+}
+
+/// Revokes all the sessions of the current user ("log out everywhere").
+pub async fn api_token_revoke_all_handler(
+	State(mm): State<ModelManager>,
+	ctx: CtxW,
+	cookies: Cookies,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_token_revoke_all_handler",
+		"HANDLER"
+	);
+
+	TokenRevocationBmc::revoke_all_for_user(
+		&Ctx::root_ctx(),
+		&mm,
+		&ctx.0.user_id(),
+	)
+	.await?;
+	token::remove_token_cookie(&cookies)?;
+	token::remove_refresh_token_cookie(&cookies);
+
+	Ok(revoked_body())
+}
+
+/// Revokes all the sessions of any user, for the admins.
+pub async fn api_token_revoke_user_handler(
+	State(mm): State<ModelManager>,
+	ctx: CtxW,
+	Json(payload): Json<RevokeUserPayload>,
+) -> Result<Json<Value>> {
+	debug!(
+		"{:<12} - api_token_revoke_user_handler",
+		"HANDLER"
+	);
+
+	let ctx = ctx.0;
+	if !ctx.has_role(ROLE_ADMIN) {
+		return Err(Error::TokenRevokeNotAdmin { user_id: ctx.user_id() });
+	}
+
+	TokenRevocationBmc::revoke_all_for_user(
+		&Ctx::root_ctx(),
+		&mm,
+		&payload.user_id,
+	)
+	.await?;
+
+	Ok(revoked_body())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeUserPayload {
+	user_id: String,
+}
+
+/// Revokes the token of the `ctx` (if any) and the refresh token family of the cookie (if any),
+/// and removes both cookies.
+pub(crate) async fn revoke_current_session(
+	mm: &ModelManager,
+	ctx: Option<&Ctx>,
+	cookies: &Cookies,
+) -> Result<()> {
+	let root_ctx = Ctx::root_ctx();
+
+	if let Some((ctx, token_id)) = ctx.and_then(|ctx| Some((ctx, ctx.token_id()?))) {
+		TokenRevocationBmc::revoke_token(
+			&root_ctx,
+			mm,
+			&ctx.user_id(),
+			token_id,
+		)
+		.await?;
+	}
+	if let Some(refresh_token) = cookies.get(token::REFRESH_TOKEN) {
+		RefreshTokenBmc::revoke_family(
+			&root_ctx,
+			mm,
+			refresh_token.value(),
+		)
+		.await?;
+	}
+
+	token::remove_token_cookie(cookies)?;
+	token::remove_refresh_token_cookie(cookies);
+
+	Ok(())
+}
+
+fn revoked_body() -> Json<Value> {
+	Json(
+		json!({
+			"result": {
+				"revoked": true
+			}
+		}),
+	)
+}
+
+// endregion: --- Revoke
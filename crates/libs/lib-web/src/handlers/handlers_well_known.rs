@@ -0,0 +1,13 @@
+use axum::Json;
+use lib_auth::token::{web_token_jwks, Jwks};
+use tracing::debug;
+
+/// Public keys verifying the `jwt-eddsa` web tokens (RFC 7517), for downstream services.
+pub async fn jwks_handler() -> Json<Jwks> {
+	debug!(
+		"{:<12} - jwks_handler",
+		"HANDLER"
+	);
+
+	Json(web_token_jwks())
+}
@@ -1,2 +1,9 @@
+pub mod handlers_files;
 pub mod handlers_login;
+pub mod handlers_oauth;
+pub mod handlers_oidc;
+pub mod handlers_pwd_reset;
+pub mod handlers_register;
 pub mod handlers_rpc;
+pub mod handlers_token;
+pub mod handlers_well_known;
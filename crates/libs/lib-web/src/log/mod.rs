@@ -46,6 +46,7 @@ pub async fn log_request(
 		rpc_id: rpc_info.and_then(|rpc| rpc.id.as_ref().map(|id| id.to_string())),
 		rpc_method: rpc_info.map(|rpc| rpc.method.to_string()),
 
+		service_account: ctx.as_ref().map(|c| c.is_service_account()),
 		user_id: ctx.map(|c| c.user_id()),
 
 		client_error_type: client_error.map(|e| e.as_ref().to_string()),
@@ -74,6 +75,8 @@ struct RequestLogLine {
 
 	// -- User and context attributes.
 	user_id: Option<String>,
+	/// True when `user_id` is a service account (machine) rather than a user.
+	service_account: Option<bool>,
 
 	// -- http request attributes.
 	http_path: String,
@@ -2,17 +2,27 @@ use crate::error::{Error, Result};
 use crate::utils::token::{set_token_cookie, AUTH_TOKEN};
 use axum::body::Body;
 use axum::extract::{FromRequestParts, State};
+use axum::http::header::AUTHORIZATION;
 use axum::http::request::Parts;
-use axum::http::Request;
+use axum::http::{HeaderMap, Request};
 use axum::middleware::Next;
 use axum::response::Response;
-use lib_auth::token::{validate_web_token, Token};
+use lib_auth::token::{
+	is_web_token_key_outdated, token_source_first, validate_web_token, Claims, JwtToken, TokenSource,
+	TokenSubject, WebToken,
+};
 use lib_core::ctx::Ctx;
-use lib_core::model::user::{QUserForAuth, UserBmc};
-use lib_core::model::ModelManager;
+use lib_core::model::api_key::ApiKeyBmc;
+use lib_core::model::service_account::ServiceAccountBmc;
+use lib_core::model::session::SessionBmc;
+use lib_core::model::token_revocation::TokenRevocationBmc;
+use lib_core::model::user::{QUserForAuth, UserBmc, UserState};
+use lib_core::model::{self, ModelManager};
 use serde::Serialize;
 use tower_cookies::{Cookie, Cookies};
-use tracing::debug;
+use tracing::{debug, warn};
+
+const API_KEY_HEADER: &str = "x-api-key";
 
 pub async fn mw_ctx_require(
 	ctx: Result<CtxW>,
@@ -45,16 +55,29 @@ pub async fn mw_ctx_resolver(
 		"MIDDLEWARE"
 	);
 
-	let ctx_ext_result = ctx_resolve(
-		mm, &cookies,
-	)
-	.await;
+	let request_token = request_token(
+		&cookies,
+		req.headers(),
+	);
+	let api_key = api_key(req.headers());
+	let ctx_ext_result = match (&request_token, api_key) {
+		(Some((source, token)), _) => {
+			ctx_resolve(
+				mm, &cookies, *source, token,
+			)
+			.await
+		},
+		(None, Some(api_key)) => {
+			ctx_resolve_api_key(
+				mm, &api_key,
+			)
+			.await
+		},
+		(None, None) => Err(CtxExtError::TokenNotInRequest),
+	};
 
-	if ctx_ext_result.is_err()
-		&& !matches!(
-			ctx_ext_result,
-			Err(CtxExtError::TokenNotInCookie)
-		) {
+	// Note: The cookies are only managed for the cookie auth, a bearer token never touches them.
+	if ctx_ext_result.is_err() && matches!(request_token, Some((TokenSource::Cookie, _))) {
 		cookies.remove(Cookie::from(AUTH_TOKEN))
 	}
 
@@ -65,27 +88,133 @@ pub async fn mw_ctx_resolver(
 	next.run(req).await
 }
 
-async fn ctx_resolve(
-	mm: ModelManager,
+/// The token of the `auth-token` cookie or of the `Authorization: Bearer` header,
+/// in the `TOKEN_SOURCE_FIRST` order.
+fn request_token(
 	cookies: &Cookies,
-) -> CtxExtResult {
-	// -- Get Token String
-	let token = cookies
-		.get(AUTH_TOKEN)
-		.map(|c| c.value().to_string())
-		.ok_or(CtxExtError::TokenNotInCookie)?;
+	headers: &HeaderMap,
+) -> Option<(TokenSource, String)> {
+	let from_cookie = || {
+		cookies
+			.get(AUTH_TOKEN)
+			.map(|c| (TokenSource::Cookie, c.value().to_string()))
+	};
+	let from_header = || {
+		bearer_token(headers).map(|token| (TokenSource::Header, token.to_string()))
+	};
 
-	// -- Parse Token
-	let token: Token = token.parse().map_err(|_| CtxExtError::TokenWrongFormat)?;
+	match token_source_first() {
+		TokenSource::Cookie => from_cookie().or_else(from_header),
+		TokenSource::Header => from_header().or_else(from_cookie),
+	}
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+	let (scheme, token) = headers
+		.get(AUTHORIZATION)?
+		.to_str()
+		.ok()?
+		.split_once(' ')?;
+
+	let token = token.trim();
+
+	(scheme.eq_ignore_ascii_case("Bearer") && !token.is_empty()).then_some(token)
+}
+
+/// The API key of the `X-API-Key` header (see `lib_auth::api_key`).
+fn api_key(headers: &HeaderMap) -> Option<String> {
+	headers
+		.get(API_KEY_HEADER)?
+		.to_str()
+		.ok()
+		.map(|api_key| api_key.trim().to_string())
+}
+
+async fn ctx_resolve_api_key(
+	mm: ModelManager,
+	api_key: &str,
+) -> CtxExtResult {
+	// -- Authenticate the API key
+	let root_ctx = Ctx::root_ctx();
+	let api_key = ApiKeyBmc::authenticate(
+		&root_ctx, &mm, api_key,
+	)
+	.await
+	.map_err(
+		|ex| match ex {
+			model::Error::ApiKeyInvalid | model::Error::ApiKeyExpired => CtxExtError::ApiKeyInvalid,
+			ex => CtxExtError::ModelAccessError(ex.to_string()),
+		},
+	)?;
 
 	// -- Get UserForAuth
-	let user: QUserForAuth = UserBmc::get_user_for_auth(
-		&Ctx::root_ctx(),
+	let user: QUserForAuth = UserBmc::get_user_for_auth_by_id(
+		&root_ctx,
 		&mm,
-		&token.ident,
+		&api_key.user_id,
 	)
 	.await
 	.map_err(|ex| CtxExtError::ModelAccessError(ex.to_string()))?;
+	check_user_active(&user)?;
+
+	// -- Create Ctx
+	// Note: The key privileges no longer held by the user are dropped.
+	let roles = user
+		.roles
+		.into_iter()
+		.filter(|role| api_key.privileges.contains(role))
+		.collect();
+	let ctx = Ctx::new(user.id)
+		.map_err(|ex| CtxExtError::CtxCreateFail(ex.to_string()))?
+		.add_roles(roles)
+		.add_api_key_id(api_key.id);
+
+	Ok(CtxW(ctx))
+}
+
+async fn ctx_resolve(
+	mm: ModelManager,
+	cookies: &Cookies,
+	source: TokenSource,
+	token: &str,
+) -> CtxExtResult {
+	// -- Parse Token
+	let token: WebToken = token.parse().map_err(|_| CtxExtError::TokenWrongFormat)?;
+
+	// -- Service Account Token
+	if let WebToken::Jwt(JwtToken {
+		claims: Claims { sa: true, sub, .. },
+		..
+	}) = &token
+	{
+		return ctx_resolve_service_account(
+			mm, sub, &token,
+		)
+		.await;
+	}
+
+	// -- Get UserForAuth
+	// Note: The custom token identifies the user by username, the JWT by id (`sub`).
+	let root_ctx = Ctx::root_ctx();
+	let user: QUserForAuth = match &token {
+		WebToken::Custom(token) => {
+			UserBmc::get_user_for_auth(
+				&root_ctx,
+				&mm,
+				&token.ident,
+			)
+			.await
+		},
+		WebToken::Jwt(token) => {
+			UserBmc::get_user_for_auth_by_id(
+				&root_ctx,
+				&mm,
+				&token.claims.sub,
+			)
+			.await
+		},
+	}
+	.map_err(|ex| CtxExtError::ModelAccessError(ex.to_string()))?;
 
 	// -- Validate Token
 	validate_web_token(
@@ -94,18 +223,142 @@ async fn ctx_resolve(
 	)
 	.map_err(|_| CtxExtError::FailValidate)?;
 
+	// -- Check State & Revocation
+	check_user_active(&user)?;
+	check_not_revoked(
+		&mm, &user.id, &token,
+	)
+	.await?;
+
+	// -- Create Ctx
+	// Note: The token of an OAuth2 client only grants the user roles within its scopes.
+	let scopes = match &token {
+		WebToken::Jwt(token) => token.claims.scopes(),
+		WebToken::Custom(_) => None,
+	};
+	let roles = match &scopes {
+		Some(scopes) => user
+			.roles
+			.iter()
+			.filter(|role| scopes.contains(&role.as_str()))
+			.cloned()
+			.collect(),
+		None => user.roles.clone(),
+	};
+	let mut ctx = Ctx::new(user.id.clone())
+		.map_err(|ex| CtxExtError::CtxCreateFail(ex.to_string()))?
+		.add_roles(roles)
+		.add_token_id(token.jti().to_string());
+	if let WebToken::Jwt(token) = &token {
+		ctx = ctx.add_tenant_id(token.claims.tid.clone());
+	}
+	if let Some(scopes) = &scopes {
+		ctx = ctx.add_scopes(scopes.iter().map(|scope| scope.to_string()).collect());
+	}
+
+	// -- Update Session
+	// Note: Best effort, a failing `last_seen` update does not fail the auth.
+	if let Err(ex) = SessionBmc::touch(
+		&root_ctx,
+		&mm,
+		token.jti(),
+	)
+	.await
+	{
+		warn!("session last_seen update failed: {ex}");
+	}
+
 	// -- Update Token
-	set_token_cookie(
-		cookies,
-		&user.username,
-		user.pwd_salt,
+	// Note: The token is short-lived and renewed with `/api/token/refresh`,
+	//       it is only re-issued here when signed with a rotated key (and in a cookie, not for a client).
+	if source == TokenSource::Cookie && scopes.is_none() && is_web_token_key_outdated(&token) {
+		set_token_cookie(
+			cookies,
+			&TokenSubject {
+				user_id: &user.id,
+				username: &user.username,
+				tenant_id: &ctx.tenant_id(),
+				roles: &user.roles,
+				salt: user.pwd_salt,
+				service_account: false,
+				grant: None,
+			},
+		)
+		.map_err(|_| CtxExtError::CannotSetTokenCookie)?;
+	}
+
+	Ok(CtxW(ctx))
+}
+
+/// Note: The service account tokens are not re-issued on key rotation, the client requests
+///       a new one with its credentials.
+async fn ctx_resolve_service_account(
+	mm: ModelManager,
+	client_id: &str,
+	token: &WebToken,
+) -> CtxExtResult {
+	// -- Get ServiceAccountForAuth
+	let service_account = ServiceAccountBmc::get_for_auth(
+		&mm, client_id,
+	)
+	.await
+	.map_err(|ex| CtxExtError::ModelAccessError(ex.to_string()))?;
+
+	// -- Validate Token
+	validate_web_token(
+		token,
+		service_account.token_salt,
+	)
+	.map_err(|_| CtxExtError::FailValidate)?;
+
+	// -- Check Revocation
+	check_not_revoked(
+		&mm,
+		&service_account.client_id,
+		token,
 	)
-	.map_err(|_| CtxExtError::CannotSetTokenCookie)?;
+	.await?;
 
-	// -- Create CtxExtResult
-	Ctx::new(user.id)
-		.map(CtxW)
-		.map_err(|ex| CtxExtError::CtxCreateFail(ex.to_string()))
+	// -- Create Ctx
+	let ctx = Ctx::new_service_account(service_account.client_id)
+		.map_err(|ex| CtxExtError::CtxCreateFail(ex.to_string()))?
+		.add_tenant_id(service_account.tenant_id)
+		.add_roles(service_account.roles)
+		.add_token_id(token.jti().to_string());
+
+	Ok(CtxW(ctx))
+}
+
+/// Note: Checked on each request, so that a disabled (or locked) user is rejected right away.
+fn check_user_active(user: &QUserForAuth) -> core::result::Result<(), CtxExtError> {
+	match user.check_active() {
+		Err(model::Error::UserNotActive { state, .. }) => Err(CtxExtError::UserNotActive(state)),
+		Err(ex) => Err(CtxExtError::ModelAccessError(ex.to_string())),
+		Ok(()) => Ok(()),
+	}
+}
+
+pub(crate) async fn check_not_revoked(
+	mm: &ModelManager,
+	user_id: &str,
+	token: &WebToken,
+) -> core::result::Result<(), CtxExtError> {
+	let iat = token.iat().map_err(|_| CtxExtError::FailValidate)?;
+	let revoked = TokenRevocationBmc::is_revoked(
+		&Ctx::root_ctx(),
+		mm,
+		user_id,
+		token.jti(),
+		iat,
+	)
+	.await
+	.map_err(|ex| CtxExtError::ModelAccessError(ex.to_string()))?;
+
+	if revoked {
+		Err(CtxExtError::TokenRevoked)
+	} else {
+		Ok(())
+	}
 }
 
 // region:    --- Ctx Extractor
@@ -139,15 +392,60 @@ type CtxExtResult = core::result::Result<CtxW, CtxExtError>;
 
 #[derive(Clone, Serialize, Debug)]
 pub enum CtxExtError {
-	TokenNotInCookie,
+	TokenNotInRequest,
 	TokenWrongFormat,
 
 	UserNotFound,
 	ModelAccessError(String),
 	FailValidate,
+	TokenRevoked,
+	/// Disabled, locked, or not verified user (see `UserState`).
+	UserNotActive(UserState),
+	ApiKeyInvalid,
 	CannotSetTokenCookie,
 
 	CtxNotInRequestExt,
 	CtxCreateFail(String),
 }
 // endregion: --- Ctx Extractor Result/Error
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+
+	#[test]
+	fn test_bearer_token_ok() -> Result<()> {
+		// -- Fixtures
+		let fx_cases = [
+			("Bearer tok.en", Some("tok.en")),
+			("bearer tok.en", Some("tok.en")),
+			("Basic dXNlcjpwd2Q=", None),
+			("Bearer", None),
+			("Bearer  ", None),
+		];
+
+		for (fx_header, fx_token) in fx_cases {
+			let mut headers = HeaderMap::new();
+			headers.insert(
+				AUTHORIZATION,
+				fx_header.parse()?,
+			);
+
+			// -- Exec
+			let token = bearer_token(&headers);
+
+			// -- Check
+			assert_eq!(
+				token, fx_token,
+				"header: {fx_header}"
+			);
+		}
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
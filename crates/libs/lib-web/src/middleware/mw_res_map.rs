@@ -1,10 +1,11 @@
-use crate::error::{Error, Result};
+use crate::error::{ClientError, Error, Result};
 use crate::handlers::handlers_rpc::RpcInfo;
 use crate::log::log_request;
 use crate::middleware::mw_auth::CtxW;
 use crate::middleware::mw_req_stamp::ReqStamp;
 
-use axum::http::{Method, Uri};
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, Method, Uri};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde_json::{json, to_value};
@@ -36,9 +37,9 @@ pub async fn mw_reponse_map(
 	// -- If client error, build the new reponse.
 	let error_response = client_status_error.as_ref().map(
 		|(status_code, client_error)| {
-			let client_error = to_value(client_error).ok();
-			let message = client_error.as_ref().and_then(|v| v.get("message"));
-			let detail = client_error.as_ref().and_then(|v| v.get("detail"));
+			let client_error_value = to_value(client_error).ok();
+			let message = client_error_value.as_ref().and_then(|v| v.get("message"));
+			let detail = client_error_value.as_ref().and_then(|v| v.get("detail"));
 
 			let client_error_body = json!({
 				"id": rpc_info.as_ref().map(|rpc| rpc.id.clone()),
@@ -54,11 +55,20 @@ pub async fn mw_reponse_map(
 			debug!("CLIENT ERROR BODY:\n{client_error_body}");
 
 			// Build the new response from the client_error_body
-			(
+			let mut response = (
 				*status_code,
 				Json(client_error_body),
 			)
-				.into_response()
+				.into_response();
+
+			if let ClientError::LOGIN_LOCKED { retry_after_sec } = client_error {
+				response.headers_mut().insert(
+					RETRY_AFTER,
+					HeaderValue::from(*retry_after_sec),
+				);
+			}
+
+			response
 		},
 	);
 
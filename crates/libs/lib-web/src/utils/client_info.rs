@@ -0,0 +1,36 @@
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::header::USER_AGENT;
+use axum::http::request::Parts;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+/// The client of the request, as recorded in the session inventory.
+///
+/// Note: The ip is only known when the server is served with
+///       `into_make_service_with_connect_info::<SocketAddr>()`.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+	pub ip: Option<String>,
+	pub user_agent: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientInfo {
+	type Rejection = Infallible;
+
+	async fn from_request_parts(
+		parts: &mut Parts,
+		_state: &S,
+	) -> Result<Self, Self::Rejection> {
+		let ip = parts
+			.extensions
+			.get::<ConnectInfo<SocketAddr>>()
+			.map(|ConnectInfo(addr)| addr.ip().to_string());
+		let user_agent = parts
+			.headers
+			.get(USER_AGENT)
+			.and_then(|value| value.to_str().ok())
+			.map(str::to_string);
+
+		Ok(ClientInfo { ip, user_agent })
+	}
+}
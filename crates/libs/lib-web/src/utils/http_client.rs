@@ -0,0 +1,203 @@
+//! Minimal outbound HTTP/1.1 client (JSON responses), for the calls to the identity providers.
+//!
+//! Notes:
+//!
+//! - One connection per request (the OIDC calls are a few per login), over TLS with the
+//!   webpki roots for `https`, plain TCP for `http` (local providers, tests).
+//! - The whole request is bounded by `REQUEST_TIMEOUT`, and the response body by `BODY_MAX_BYTES`.
+
+use crate::error::{Error, Result};
+use axum::http::header::{ACCEPT, CONTENT_TYPE, HOST};
+use axum::http::{Method, Request};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Bytes;
+use hyper_util::rt::TokioIo;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, RootCertStore};
+use serde::de::DeserializeOwned;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use url::Url;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const BODY_MAX_BYTES: usize = 1024 * 1024;
+
+pub(crate) async fn get_json<T: DeserializeOwned>(url: &Url) -> Result<T> {
+	send_json(
+		url,
+		Method::GET,
+		None,
+	)
+	.await
+}
+
+/// POSTs the `form` as `application/x-www-form-urlencoded`.
+pub(crate) async fn post_form_json<T: DeserializeOwned>(
+	url: &Url,
+	form: &[(&str, &str)],
+) -> Result<T> {
+	let body = url::form_urlencoded::Serializer::new(String::new())
+		.extend_pairs(form)
+		.finish();
+
+	send_json(
+		url,
+		Method::POST,
+		Some(body),
+	)
+	.await
+}
+
+async fn send_json<T: DeserializeOwned>(
+	url: &Url,
+	method: Method,
+	form_body: Option<String>,
+) -> Result<T> {
+	let fail = |cause: String| Error::HttpClientFail {
+		url: url.to_string(),
+		cause,
+	};
+
+	let body = tokio::time::timeout(
+		REQUEST_TIMEOUT,
+		send(
+			url, method, form_body,
+		),
+	)
+	.await
+	.map_err(|_| fail("timeout".to_string()))?
+	.map_err(fail)?;
+
+	serde_json::from_slice(&body).map_err(|ex| fail(format!("invalid json response: {ex}")))
+}
+
+/// Returns the body of a successful (2xx) response.
+async fn send(
+	url: &Url,
+	method: Method,
+	form_body: Option<String>,
+) -> core::result::Result<Bytes, String> {
+	let host = url
+		.host_str()
+		.ok_or("url without host")?;
+	let port = url
+		.port_or_known_default()
+		.ok_or("url without port")?;
+
+	// -- Build the request.
+	let host_header = match url.port() {
+		Some(port) => format!("{host}:{port}"),
+		None => host.to_string(),
+	};
+	let mut req = Request::builder()
+		.method(method)
+		.uri(&url[url::Position::BeforePath..])
+		.header(
+			HOST,
+			host_header,
+		)
+		.header(
+			ACCEPT,
+			"application/json",
+		);
+	if form_body.is_some() {
+		req = req.header(
+			CONTENT_TYPE,
+			"application/x-www-form-urlencoded",
+		);
+	}
+	let req = req
+		.body(Full::new(Bytes::from(form_body.unwrap_or_default())))
+		.map_err(|ex| ex.to_string())?;
+
+	// -- Connect and send.
+	let tcp = TcpStream::connect((host, port))
+		.await
+		.map_err(|ex| ex.to_string())?;
+	let res = match url.scheme() {
+		"https" => {
+			let server_name = ServerName::try_from(host.to_string()).map_err(|ex| ex.to_string())?;
+			let tls = TlsConnector::from(tls_config())
+				.connect(
+					server_name,
+					tcp,
+				)
+				.await
+				.map_err(|ex| ex.to_string())?;
+			send_on(
+				tls, req,
+			)
+			.await?
+		},
+		"http" => {
+			send_on(
+				tcp, req,
+			)
+			.await?
+		},
+		scheme => return Err(format!("unsupported scheme '{scheme}'")),
+	};
+
+	// -- Read the body.
+	let status = res.status();
+	let body = Limited::new(
+		res.into_body(),
+		BODY_MAX_BYTES,
+	)
+	.collect()
+	.await
+	.map_err(|ex| ex.to_string())?
+	.to_bytes();
+	if !status.is_success() {
+		return Err(
+			format!(
+				"status {status}: {}",
+				String::from_utf8_lossy(&body)
+			),
+		);
+	}
+
+	Ok(body)
+}
+
+async fn send_on<S>(
+	stream: S,
+	req: Request<Full<Bytes>>,
+) -> core::result::Result<hyper::Response<hyper::body::Incoming>, String>
+where
+	S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+	let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+		.await
+		.map_err(|ex| ex.to_string())?;
+	tokio::spawn(conn);
+
+	sender
+		.send_request(req)
+		.await
+		.map_err(|ex| ex.to_string())
+}
+
+fn tls_config() -> Arc<ClientConfig> {
+	static INSTANCE: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+
+	INSTANCE
+		.get_or_init(
+			|| {
+				let roots = RootCertStore {
+					roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+				};
+				let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+					.with_safe_default_protocol_versions()
+					.expect("ring supports the default protocol versions")
+					.with_root_certificates(roots)
+					.with_no_client_auth();
+
+				Arc::new(config)
+			},
+		)
+		.clone()
+}
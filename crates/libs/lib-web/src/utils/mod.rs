@@ -1 +1,5 @@
+pub mod client_info;
+pub mod http_client;
+pub mod oidc;
+pub mod range;
 pub mod token;
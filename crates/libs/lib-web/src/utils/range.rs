@@ -0,0 +1,132 @@
+//! Minimal `Range` header support for file downloads.
+//!
+//! Only single `bytes` ranges are supported (`bytes=start-end`, `bytes=start-`, `bytes=-suffix`).
+//! Other range units and multi-ranges are ignored, and the full content is served (as allowed by RFC 9110).
+
+/// The requested range does not overlap the content
+/// (mapped to `Error::FileRangeNotSatisfiable` by the handlers).
+#[derive(Debug, PartialEq)]
+pub struct RangeNotSatisfiable;
+
+/// Parses the `Range` header value against the content `size`.
+///
+/// Returns the inclusive `(start, end)` byte range, or `None` if the header should be ignored.
+pub fn parse_range(
+	header: &str,
+	size: u64,
+) -> Result<Option<(u64, u64)>, RangeNotSatisfiable> {
+	let Some(spec) = header.trim().strip_prefix("bytes=") else {
+		return Ok(None);
+	};
+	if spec.contains(',') {
+		return Ok(None);
+	}
+	let Some((start, end)) = spec.trim().split_once('-') else {
+		return Ok(None);
+	};
+
+	let range = match (start.trim(), end.trim()) {
+		// -- Suffix range, the last `suffix` bytes.
+		("", suffix) => {
+			let suffix: u64 = suffix.parse().map_err(|_| RangeNotSatisfiable)?;
+			if suffix == 0 || size == 0 {
+				return Err(RangeNotSatisfiable);
+			}
+			(
+				size.saturating_sub(suffix),
+				size - 1,
+			)
+		},
+		// -- Open range, from `start` to the end.
+		(start, "") => {
+			let start: u64 = start.parse().map_err(|_| RangeNotSatisfiable)?;
+			(
+				start,
+				size.saturating_sub(1),
+			)
+		},
+		(start, end) => {
+			let start: u64 = start.parse().map_err(|_| RangeNotSatisfiable)?;
+			let end: u64 = end.parse().map_err(|_| RangeNotSatisfiable)?;
+			if end < start {
+				return Err(RangeNotSatisfiable);
+			}
+			(
+				start,
+				end.min(size.saturating_sub(1)),
+			)
+		},
+	};
+
+	if range.0 >= size {
+		return Err(RangeNotSatisfiable);
+	}
+
+	Ok(Some(range))
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+
+	#[test]
+	fn test_parse_range_ok() -> Result<()> {
+		// -- Fixtures
+		let fx_cases = [
+			("bytes=0-99", Some((0, 99))),
+			("bytes=100-", Some((100, 999))),
+			("bytes=-100", Some((900, 999))),
+			("bytes=900-2000", Some((900, 999))),
+			("bytes=0-0,10-20", None),
+			("items=0-10", None),
+		];
+
+		for (fx_header, fx_range) in fx_cases {
+			// -- Exec
+			let res = parse_range(
+				fx_header, 1000,
+			);
+
+			// -- Check
+			assert_eq!(
+				res,
+				Ok(fx_range),
+				"header: {fx_header}"
+			);
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_parse_range_err_not_satisfiable() -> Result<()> {
+		// -- Fixtures
+		let fx_headers = [
+			"bytes=1000-",
+			"bytes=50-10",
+			"bytes=-0",
+			"bytes=abc-",
+		];
+
+		for fx_header in fx_headers {
+			// -- Exec
+			let res = parse_range(
+				fx_header, 1000,
+			);
+
+			// -- Check
+			assert_eq!(
+				res,
+				Err(RangeNotSatisfiable),
+				"header: {fx_header}"
+			);
+		}
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -1,22 +1,30 @@
 pub use crate::error::ClientError;
 pub use crate::error::{Error, Result};
-use lib_auth::token::generate_web_token;
+use lib_auth::token::{generate_web_token, TokenSubject, WebToken};
 use tower_cookies::{Cookie, Cookies};
-use uuid::Uuid;
 
 // endregion: --- Modules
 
 pub(crate) const AUTH_TOKEN: &str = "auth-token";
+pub(crate) const REFRESH_TOKEN: &str = "refresh-token";
 
+/// Returns the issued token (e.g., for its `jti`).
 pub(crate) fn set_token_cookie(
 	cookies: &Cookies,
-	user: &str,
-	salt: Uuid,
-) -> Result<()> {
-	let token = generate_web_token(
-		user, salt,
-	)?;
+	subject: &TokenSubject,
+) -> Result<WebToken> {
+	let token = generate_web_token(subject)?;
+	add_token_cookie(
+		cookies, &token,
+	);
+
+	Ok(token)
+}
 
+pub(crate) fn add_token_cookie(
+	cookies: &Cookies,
+	token: &WebToken,
+) {
 	let mut cookie = Cookie::new(
 		AUTH_TOKEN,
 		token.to_string(),
@@ -25,8 +33,6 @@ pub(crate) fn set_token_cookie(
 	cookie.set_path("/");
 
 	cookies.add(cookie);
-
-	Ok(())
 }
 
 pub(crate) fn remove_token_cookie(cookies: &Cookies) -> Result<()> {
@@ -37,3 +43,26 @@ pub(crate) fn remove_token_cookie(cookies: &Cookies) -> Result<()> {
 
 	Ok(())
 }
+
+/// Note: Scoped to `/api`, for `/api/token/refresh` and `/api/logoff`.
+///       The bearer clients get theirs in the `/api/token` body instead.
+pub(crate) fn set_refresh_token_cookie(
+	cookies: &Cookies,
+	refresh_token: String,
+) {
+	let mut cookie = Cookie::new(
+		REFRESH_TOKEN,
+		refresh_token,
+	);
+	cookie.set_http_only(true);
+	cookie.set_path("/api");
+
+	cookies.add(cookie);
+}
+
+pub(crate) fn remove_refresh_token_cookie(cookies: &Cookies) {
+	let mut cookie = Cookie::from(REFRESH_TOKEN);
+	cookie.set_path("/api");
+
+	cookies.remove(cookie);
+}
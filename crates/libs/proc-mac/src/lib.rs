@@ -1,8 +1,13 @@
 extern crate proc_macro;
 
+mod options;
+mod relation;
+mod validate;
+
+use options::CrudOptions;
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, Ident, LitStr, Type};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, AttributeArgs, Data, DeriveInput, Field, Fields, Ident, LitStr, Type};
 
 fn is_option(ty: &Type) -> bool {
 	if let Type::Path(type_path) = ty {
@@ -28,20 +33,72 @@ fn has_skip_attribute(
 	field.attrs.iter().any(|attr| attr.path.is_ident(attr_name))
 }
 
+/// Field attributes consumed by the `crud` macro. They are removed from the
+/// emitted struct, since they are not declared anywhere else.
+const HELPER_ATTRIBUTES: &[&str] = &[
+	"skip_create",
+	"skip_update",
+	"skip_filter",
+	"attachment",
+	"text",
+	relation::BELONGS_TO_ATTRIBUTE,
+	relation::HAS_MANY_ATTRIBUTE,
+	validate::VALIDATE_ATTRIBUTE,
+];
+
+fn strip_helper_attributes(input_ast: &mut DeriveInput) {
+	if let Data::Struct(data_struct) = &mut input_ast.data {
+		for field in data_struct.fields.iter_mut() {
+			field.attrs.retain(
+				|attr| {
+					!HELPER_ATTRIBUTES
+						.iter()
+						.any(|name| attr.path.is_ident(name))
+				},
+			);
+		}
+	}
+}
+
 /// Generates RPC conversion types and CRUD functions. The macro creates:
 /// - ForCreate: All fields (except "id" and those marked with #[skip_create]) as Option types.
 /// - ForUpdate: All fields (except "id" and those marked with #[skip_update]) as Option types.
 /// - Filter: All fields (except those marked with #[skip_filter]) as Option types.
-/// - A BMC struct implementing DbBmc with TABLE set to "<StructName>Bmc".
+/// - A BMC struct implementing DbBmc with TABLE set to "<StructName>Bmc" (or the `table` option).
 /// - CRUD functions in the BMC impl that convert the provided types into the main struct,
 ///   filling missing fields with None.
+/// - Fields marked with #[attachment] hold attachment ids, which must exist (and be owned by the user) on create and update.
+/// - Fields marked with #[text] are covered by the full-text `search` of the BMC.
+/// - Fields marked with #[validate(...)] are validated before create and update
+///   (see `lib_core::model::validation` for the rules).
+/// - Fields marked with #[belongs_to(Entity)] (one id) or #[has_many(Entity)] (list of ids) reference
+///   documents of `EntityBmc`, which must exist on create and update, and can be expanded
+///   with `get_expanded` and `list_expanded`.
+///
+/// Entity options, e.g., `#[crud(table = "examples", owner, timestamps, soft_delete, rpc_suffix = "example")]`:
+/// - `table = ".."`: The collection name.
+/// - `owner`: Documents get the `owner_id` of the ctx user on create, and are only visible to their owner.
+/// - `timestamps`: Documents get `cid`/`ctime` on create, and `mid`/`mtime` on create and update.
+/// - `soft_delete`: Delete sets `dtime` instead of removing the document, which is then hidden.
+/// - `rpc`: Generates the RPC handlers (`create_<suffix>`, `get_<suffix>`, `list_<suffix>s`,
+///   `update_<suffix>`, `delete_<suffix>`, and `search_<suffix>s` with #[text] fields) and registers
+///   the BMC `rpc_router_builder` in `lib_core::rpc` (requires the `with-rpc` feature).
+/// - `rpc_suffix = ".."`: The suffix of the RPC function names (default the snake_case struct name).
+///
+/// The struct must have named fields, including an `id` field.
 #[proc_macro_attribute]
 pub fn crud(
-	_attr: TokenStream,
+	attr: TokenStream,
 	item: TokenStream,
 ) -> TokenStream {
-	let input_ast = parse_macro_input!(item as DeriveInput);
-	let struct_ident = &input_ast.ident;
+	let attr_args = parse_macro_input!(attr as AttributeArgs);
+	let mut input_ast = parse_macro_input!(item as DeriveInput);
+	let struct_ident = input_ast.ident.clone();
+
+	let options = match CrudOptions::parse(attr_args) {
+		Ok(options) => options,
+		Err(err) => return err.to_compile_error().into(),
+	};
 
 	let for_create_ident = Ident::new(
 		&format!(
@@ -72,25 +129,73 @@ pub fn crud(
 		struct_ident.span(),
 	);
 
-	let table_name = format!(
-		"{}Bmc",
-		struct_ident
+	let table_name_literal = options.table.clone().unwrap_or_else(
+		|| {
+			LitStr::new(
+				&format!(
+					"{}Bmc",
+					struct_ident
+				),
+				struct_ident.span(),
+			)
+		},
 	);
-	let table_name_literal = LitStr::new(
-		&table_name,
-		struct_ident.span(),
+	let rpc_suffix_literal = options.rpc_suffix.clone().unwrap_or_else(
+		|| {
+			LitStr::new(
+				&options::to_snake_case(&struct_ident.to_string()),
+				struct_ident.span(),
+			)
+		},
+	);
+	let (has_owner_id, has_timestamps, has_soft_delete) = (
+		options.owner,
+		options.timestamps,
+		options.soft_delete,
 	);
 
-	let fields = if let Data::Struct(data_struct) = &input_ast.data {
-		if let Fields::Named(fields_named) = &data_struct.fields {
-			fields_named.named.iter().collect::<Vec<&Field>>()
-		} else {
-			panic!("Only structs with named fields are supported!");
-		}
-	} else {
-		panic!("Only structs are supported!");
+	let fields = match &input_ast.data {
+		Data::Struct(data_struct) => match &data_struct.fields {
+			Fields::Named(fields_named) => fields_named.named.iter().cloned().collect::<Vec<Field>>(),
+			_ => {
+				return syn::Error::new_spanned(
+					&data_struct.fields,
+					"crud: only structs with named fields are supported",
+				)
+				.to_compile_error()
+				.into()
+			},
+		},
+		Data::Enum(data_enum) => {
+			return syn::Error::new(
+				data_enum.enum_token.span,
+				"crud: only structs are supported, not enums",
+			)
+			.to_compile_error()
+			.into()
+		},
+		Data::Union(data_union) => {
+			return syn::Error::new(
+				data_union.union_token.span,
+				"crud: only structs are supported, not unions",
+			)
+			.to_compile_error()
+			.into()
+		},
 	};
 
+	if !fields
+		.iter()
+		.any(|field| field.ident.as_ref().is_some_and(|ident| ident == "id"))
+	{
+		return syn::Error::new(
+			struct_ident.span(),
+			"crud: the struct must have an `id` field (e.g., `pub id: Option<String>`)",
+		)
+		.to_compile_error()
+		.into();
+	}
+
 	let create_fields = fields
 		.iter()
 		.filter(
@@ -207,6 +312,116 @@ pub fn crud(
 		},
 	);
 
+	let mut relations = Vec::new();
+	for field in &fields {
+		match relation::field_relation(field) {
+			Ok(Some(relation)) => relations.push(relation),
+			Ok(None) => (),
+			Err(err) => return err.to_compile_error().into(),
+		}
+	}
+	let relation_consts = relations.iter().map(relation::FieldRelation::relation);
+
+	let mut ref_validations = fields
+		.iter()
+		.filter(
+			|field| {
+				has_skip_attribute(
+					field,
+					"attachment",
+				)
+			},
+		)
+		.map(
+			|field| {
+				let ident = &field.ident;
+				quote! {
+					crate::model::attachment::AttachmentBmc::validate_refs(ctx, mm, &entity.#ident).await?;
+				}
+			},
+		)
+		.collect::<Vec<_>>();
+	ref_validations.extend(relations.iter().map(relation::FieldRelation::validation));
+
+	let text_field_names = fields
+		.iter()
+		.filter(
+			|field| {
+				has_skip_attribute(
+					field, "text",
+				)
+			},
+		)
+		.filter_map(|field| field.ident.as_ref())
+		.map(
+			|ident| {
+				LitStr::new(
+					&ident.to_string(),
+					ident.span(),
+				)
+			},
+		)
+		.collect::<Vec<_>>();
+
+	// -- All the entity fields are expanded as relation (the owner scope applies, see `scope_filter`).
+	let expand_field_names = fields
+		.iter()
+		.filter_map(|field| field.ident.as_ref())
+		.filter(|ident| *ident != "id")
+		.map(
+			|ident| {
+				LitStr::new(
+					&ident.to_string(),
+					ident.span(),
+				)
+			},
+		)
+		.collect::<Vec<_>>();
+
+	let mut create_checks = Vec::new();
+	let mut update_checks = Vec::new();
+	for field in &fields {
+		let rules = match validate::field_rules(field) {
+			Ok(rules) => rules,
+			Err(err) => return err.to_compile_error().into(),
+		};
+		let is_id = field.ident.as_ref().is_some_and(|ident| ident == "id");
+		if !is_id
+			&& !has_skip_attribute(
+				field,
+				"skip_create",
+			) {
+			create_checks.push(
+				validate::field_checks(
+					field, &rules, true,
+				),
+			);
+		}
+		if !is_id
+			&& !has_skip_attribute(
+				field,
+				"skip_update",
+			) {
+			update_checks.push(
+				validate::field_checks(
+					field, &rules, false,
+				),
+			);
+		}
+	}
+
+	let rpc_fns = if options.rpc {
+		rpc_fns(
+			&struct_ident,
+			&rpc_suffix_literal,
+			!text_field_names.is_empty(),
+		)
+	} else {
+		quote! {}
+	};
+
+	strip_helper_attributes(&mut input_ast);
+
 	let expanded = quote! {
 		#input_ast
 
@@ -228,16 +443,56 @@ pub fn crud(
 		pub struct #bmc_ident;
 		impl crate::model::base::DbBmc for #bmc_ident {
 			const TABLE: &'static str = #table_name_literal;
+			const TEXT_FIELDS: &'static [&'static str] = &[#(#text_field_names),*];
+			const RELATIONS: &'static [crate::model::base::Relation] = &[#(#relation_consts),*];
+			const EXPAND_FIELDS: &'static [&'static str] = &[#(#expand_field_names),*];
+
+			fn has_owner_id() -> bool {
+				#has_owner_id
+			}
+
+			fn has_timestamps() -> bool {
+				#has_timestamps
+			}
+
+			fn has_soft_delete() -> bool {
+				#has_soft_delete
+			}
+		}
+
+		impl crate::model::validation::Validate for #for_create_ident {
+			#[allow(unused_mut)]
+			fn validate(&self) -> crate::model::validation::ValidationResult {
+				let mut errors = crate::model::validation::ValidationError::default();
+				#(#create_checks)*
+				errors.into_result()
+			}
+		}
+
+		impl crate::model::validation::Validate for #for_update_ident {
+			#[allow(unused_mut)]
+			fn validate(&self) -> crate::model::validation::ValidationResult {
+				let mut errors = crate::model::validation::ValidationError::default();
+				#(#update_checks)*
+				errors.into_result()
+			}
 		}
 
 		impl #bmc_ident {
+			/// Suffix of the RPC function names of the entity (e.g., `create_<suffix>`).
+			pub const RPC_SUFFIX: &'static str = #rpc_suffix_literal;
+
 			pub async fn create(ctx: &crate::ctx::Ctx, mm: &crate::model::ModelManager, input: #for_create_ident) -> crate::model::Result<String> {
+				crate::model::validation::Validate::validate(&input)?;
 				let entity: #struct_ident = input.into();
+				#(#ref_validations)*
 				crate::model::base::create::<Self, #struct_ident>(ctx, mm, entity).await
 			}
 
 			pub async fn update(ctx: &crate::ctx::Ctx, mm: &crate::model::ModelManager, id: &String, input: #for_update_ident) -> crate::model::Result<()> {
+				crate::model::validation::Validate::validate(&input)?;
 				let entity: #struct_ident = input.into();
+				#(#ref_validations)*
 				crate::model::base::update::<Self, #struct_ident>(ctx, mm, id, entity).await
 			}
 
@@ -254,13 +509,33 @@ pub fn crud(
 				crate::model::base::list::<Self, #struct_ident>(ctx, mm, entity).await
 			}
 
+			pub async fn get_expanded(ctx: &crate::ctx::Ctx, mm: &crate::model::ModelManager, filter: Option<#filter_ident>, expand: &[String]) -> crate::model::Result<crate::model::base::Expanded<#struct_ident>> {
+				let entity: #struct_ident = filter.unwrap_or_default().into();
+				crate::model::base::get_expanded::<Self, #struct_ident>(ctx, mm, entity, expand).await
+			}
+
+			pub async fn list_expanded(ctx: &crate::ctx::Ctx, mm: &crate::model::ModelManager, filter: Option<#filter_ident>, expand: &[String]) -> crate::model::Result<Vec<crate::model::base::Expanded<#struct_ident>>> {
+				let entity: #struct_ident = filter.unwrap_or_default().into();
+				crate::model::base::list_expanded::<Self, #struct_ident>(ctx, mm, entity, expand).await
+			}
+
 			pub async fn delete(ctx: &crate::ctx::Ctx, mm: &crate::model::ModelManager, filter: Option<#filter_ident>) -> crate::model::Result<()> {
 				let stru = filter.unwrap_or_default();
 				let entity: #struct_ident = stru.into();
 				crate::model::base::delete::<Self, #struct_ident>(ctx, mm, entity).await
 			}
+
+			pub async fn search(ctx: &crate::ctx::Ctx, mm: &crate::model::ModelManager, query: &str, list_options: Option<crate::model::base::ListOptions>) -> crate::model::Result<Vec<crate::model::base::SearchHit<#struct_ident>>> {
+				crate::model::base::search::<Self, #struct_ident>(ctx, mm, query, list_options.unwrap_or_default()).await
+			}
+
+			pub async fn ensure_text_index(ctx: &crate::ctx::Ctx, mm: &crate::model::ModelManager) -> crate::model::Result<()> {
+				crate::model::base::ensure_text_index::<Self>(ctx, mm).await
+			}
 		}
 
+		#rpc_fns
+
 		impl From<#for_create_ident> for #struct_ident {
 			fn from(input: #for_create_ident) -> Self {
 				Self { #(#create_assignments)* }
@@ -282,3 +557,116 @@ pub fn crud(
 
 	TokenStream::from(expanded)
 }
+
+/// The RPC handlers of a `#[crud(rpc)]` entity, and their registration.
+fn rpc_fns(
+	struct_ident: &Ident,
+	rpc_suffix: &LitStr,
+	with_search: bool,
+) -> proc_macro2::TokenStream {
+	let bmc_ident = format_ident!("{}Bmc", struct_ident);
+	let for_create_ident = format_ident!("{}ForCreate", struct_ident);
+	let for_update_ident = format_ident!("{}ForUpdate", struct_ident);
+	let filter_ident = format_ident!("{}Filter", struct_ident);
+
+	let suffix = rpc_suffix.value();
+	let rpc_name = |prefix: &str, plural: &str| {
+		LitStr::new(
+			&format!("{prefix}_{suffix}{plural}"),
+			rpc_suffix.span(),
+		)
+	};
+	let (create_name, get_name, list_name, update_name, delete_name, search_name) = (
+		rpc_name("create", ""),
+		rpc_name("get", ""),
+		rpc_name("list", "s"),
+		rpc_name("update", ""),
+		rpc_name("delete", ""),
+		rpc_name("search", "s"),
+	);
+	let entity_name = LitStr::new(
+		&struct_ident.to_string(),
+		struct_ident.span(),
+	);
+
+	let (search_fn, search_append) = if with_search {
+		(
+			quote! {
+				pub async fn rpc_search(ctx: crate::ctx::Ctx, mm: crate::model::ModelManager, params: crate::rpc::ParamsSearch) -> crate::model::Result<crate::rpc::DataRpcResult<Vec<crate::model::base::SearchHit<#struct_ident>>>> {
+					let crate::rpc::ParamsSearch { query, list_options } = params;
+					let hits = Self::search(&ctx, &mm, &query, list_options).await?;
+					Ok(hits.into())
+				}
+			},
+			quote! { .append(#search_name, Self::rpc_search) },
+		)
+	} else {
+		(quote! {}, quote! {})
+	};
+
+	quote! {
+		#[cfg(feature = "with-rpc")]
+		impl #bmc_ident {
+			pub async fn rpc_create(ctx: crate::ctx::Ctx, mm: crate::model::ModelManager, params: crate::rpc::ParamsForCreate<#for_create_ident>) -> crate::model::Result<crate::rpc::DataRpcResult<#struct_ident>> {
+				let crate::rpc::ParamsForCreate { data } = params;
+				let id = Self::create(&ctx, &mm, data).await?;
+				let mut filter = #filter_ident::default();
+				filter.id = Some(id);
+				let entity = Self::get(&ctx, &mm, Some(filter)).await?;
+				Ok(entity.into())
+			}
+
+			pub async fn rpc_get(ctx: crate::ctx::Ctx, mm: crate::model::ModelManager, params: crate::rpc::ParamsGet) -> crate::model::Result<crate::rpc::DataRpcResult<crate::model::base::Expanded<#struct_ident>>> {
+				let crate::rpc::ParamsGet { id, expand } = params;
+				let mut filter = #filter_ident::default();
+				filter.id = Some(id);
+				let entity = Self::get_expanded(&ctx, &mm, Some(filter), &expand.unwrap_or_default()).await?;
+				Ok(entity.into())
+			}
+
+			pub async fn rpc_list(ctx: crate::ctx::Ctx, mm: crate::model::ModelManager, params: crate::rpc::ParamsList<#filter_ident>) -> crate::model::Result<crate::rpc::DataRpcResult<Vec<crate::model::base::Expanded<#struct_ident>>>> {
+				let crate::rpc::ParamsList { filter, expand, .. } = params;
+				let entities = Self::list_expanded(&ctx, &mm, filter, &expand.unwrap_or_default()).await?;
+				Ok(entities.into())
+			}
+
+			pub async fn rpc_update(ctx: crate::ctx::Ctx, mm: crate::model::ModelManager, params: crate::rpc::ParamsForUpdate<#for_update_ident>) -> crate::model::Result<crate::rpc::DataRpcResult<#struct_ident>> {
+				let crate::rpc::ParamsForUpdate { id, data } = params;
+				Self::update(&ctx, &mm, &id, data).await?;
+				let mut filter = #filter_ident::default();
+				filter.id = Some(id);
+				let entity = Self::get(&ctx, &mm, Some(filter)).await?;
+				Ok(entity.into())
+			}
+
+			pub async fn rpc_delete(ctx: crate::ctx::Ctx, mm: crate::model::ModelManager, params: crate::rpc::ParamsIded) -> crate::model::Result<crate::rpc::DataRpcResult<#struct_ident>> {
+				let crate::rpc::ParamsIded { id } = params;
+				let mut filter = #filter_ident::default();
+				filter.id = Some(id);
+				let entity = Self::get(&ctx, &mm, Some(filter.clone())).await?;
+				Self::delete(&ctx, &mm, Some(filter)).await?;
+				Ok(entity.into())
+			}
+
+			#search_fn
+
+			pub fn rpc_router_builder() -> rpc_router::RouterBuilder {
+				rpc_router::Router::builder()
+					.append(#create_name, Self::rpc_create)
+					.append(#get_name, Self::rpc_get)
+					.append(#list_name, Self::rpc_list)
+					.append(#update_name, Self::rpc_update)
+					.append(#delete_name, Self::rpc_delete)
+					#search_append
+			}
+		}
+
+		#[cfg(feature = "with-rpc")]
+		inventory::submit! {
+			crate::rpc::RpcRouterEntry {
+				entity: #entity_name,
+				router_builder: #bmc_ident::rpc_router_builder,
+			}
+		}
+	}
+}
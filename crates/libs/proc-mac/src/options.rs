@@ -0,0 +1,90 @@
+//! Parsing of the `#[crud(...)]` entity options.
+//!
+//! e.g., `#[crud(table = "examples", owner, timestamps, soft_delete, rpc, rpc_suffix = "example")]`
+
+use syn::spanned::Spanned;
+use syn::{AttributeArgs, Ident, Lit, LitStr, Meta, NestedMeta};
+
+#[derive(Default)]
+pub struct CrudOptions {
+	/// Collection name, default `"<StructName>Bmc"`.
+	pub table: Option<LitStr>,
+	/// Suffix of the generated RPC function names, default the snake_case struct name.
+	pub rpc_suffix: Option<LitStr>,
+	pub owner: bool,
+	pub timestamps: bool,
+	pub soft_delete: bool,
+	/// Generate the RPC handlers and register them in the `lib_core::rpc` registry.
+	pub rpc: bool,
+}
+
+impl CrudOptions {
+	pub fn parse(args: AttributeArgs) -> syn::Result<Self> {
+		let mut options = CrudOptions::default();
+
+		for arg in args {
+			match arg {
+				NestedMeta::Meta(Meta::Path(path)) => {
+					let flag = match path.get_ident().map(Ident::to_string).as_deref() {
+						Some("owner") => &mut options.owner,
+						Some("timestamps") => &mut options.timestamps,
+						Some("soft_delete") => &mut options.soft_delete,
+						Some("rpc") => &mut options.rpc,
+						_ => return Err(unknown_option(path.span())),
+					};
+					*flag = true;
+				},
+				NestedMeta::Meta(Meta::NameValue(name_value)) => {
+					let value = match name_value.path.get_ident().map(Ident::to_string).as_deref() {
+						Some("table") => &mut options.table,
+						Some("rpc_suffix") => &mut options.rpc_suffix,
+						_ => return Err(unknown_option(name_value.path.span())),
+					};
+					let Lit::Str(lit) = name_value.lit else {
+						return Err(
+							syn::Error::new(
+								name_value.lit.span(),
+								"crud: expected a string literal",
+							),
+						);
+					};
+					if lit.value().is_empty() {
+						return Err(
+							syn::Error::new(
+								lit.span(),
+								"crud: must not be empty",
+							),
+						);
+					}
+					*value = Some(lit);
+				},
+				other => return Err(unknown_option(other.span())),
+			}
+		}
+
+		Ok(options)
+	}
+}
+
+fn unknown_option(span: proc_macro2::Span) -> syn::Error {
+	syn::Error::new(
+		span,
+		"crud: unknown option, expected one of `table = \"..\"`, `owner`, `timestamps`, `soft_delete`, `rpc`, `rpc_suffix = \"..\"`",
+	)
+}
+
+/// e.g., `ProjectTask` -> `project_task`
+pub fn to_snake_case(name: &str) -> String {
+	let mut snake = String::with_capacity(name.len() + 4);
+	for (idx, c) in name.chars().enumerate() {
+		if c.is_uppercase() {
+			if idx > 0 {
+				snake.push('_');
+			}
+			snake.extend(c.to_lowercase());
+		} else {
+			snake.push(c);
+		}
+	}
+	snake
+}
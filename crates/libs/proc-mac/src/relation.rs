@@ -0,0 +1,100 @@
+//! Parsing and code generation for the `#[belongs_to(..)]` and `#[has_many(..)]` field attributes of `#[crud]`.
+//!
+//! e.g., `#[belongs_to(User)]` on `pub user_id: Option<String>` relates the field to `UserBmc`
+//! (the Bmc must be in scope, or given by path, e.g., `#[belongs_to(crate::model::user::User)]`).
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Field, LitStr, Path};
+
+pub const BELONGS_TO_ATTRIBUTE: &str = "belongs_to";
+pub const HAS_MANY_ATTRIBUTE: &str = "has_many";
+
+pub struct FieldRelation {
+	pub field: LitStr,
+	pub has_many: bool,
+	/// Path of the related Bmc (e.g., `UserBmc`).
+	pub bmc: Path,
+}
+
+/// Parses the relation attribute of the field, if any.
+pub fn field_relation(field: &Field) -> syn::Result<Option<FieldRelation>> {
+	let mut relation = None;
+	for attr in &field.attrs {
+		let has_many = if attr.path.is_ident(BELONGS_TO_ATTRIBUTE) {
+			false
+		} else if attr.path.is_ident(HAS_MANY_ATTRIBUTE) {
+			true
+		} else {
+			continue;
+		};
+		if relation.is_some() {
+			return Err(
+				syn::Error::new_spanned(
+					attr,
+					"crud: only one relation attribute per field",
+				),
+			);
+		}
+
+		let mut bmc: Path = attr.parse_args()?;
+		let Some(last) = bmc.segments.last_mut() else {
+			return Err(
+				syn::Error::new_spanned(
+					attr,
+					"crud: expected the related entity, e.g., `User`",
+				),
+			);
+		};
+		last.ident = format_ident!(
+			"{}Bmc",
+			last.ident
+		);
+
+		let Some(ident) = &field.ident else {
+			continue;
+		};
+		relation = Some(
+			FieldRelation {
+				field: LitStr::new(
+					&ident.to_string(),
+					ident.span(),
+				),
+				has_many,
+				bmc,
+			},
+		);
+	}
+	Ok(relation)
+}
+
+impl FieldRelation {
+	/// The `lib_core::model::base::Relation` of the `DbBmc::RELATIONS`.
+	pub fn relation(&self) -> TokenStream {
+		let FieldRelation { field, bmc, .. } = self;
+		let kind = if self.has_many {
+			quote! { crate::model::base::RelationKind::HasMany }
+		} else {
+			quote! { crate::model::base::RelationKind::BelongsTo }
+		};
+		quote! {
+			crate::model::base::Relation {
+				field: #field,
+				kind: #kind,
+				lookup: crate::model::base::lookup_stage::<#bmc>,
+			}
+		}
+	}
+
+	/// The check that the referenced ids exist, on create and update.
+	pub fn validation(&self) -> TokenStream {
+		let FieldRelation { field, bmc, .. } = self;
+		let ident = format_ident!(
+			"{}",
+			field.value()
+		);
+		quote! {
+			crate::model::base::validate_refs::<#bmc>(ctx, mm, #field, &entity.#ident).await?;
+		}
+	}
+}
@@ -0,0 +1,199 @@
+//! Parsing and code generation for the `#[validate(...)]` field attribute of `#[crud]`.
+//!
+//! e.g., `#[validate(required, length(min = 1, max = 64), email, regex = "^[a-z]+$", range(min = 0, max = 10))]`
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parenthesized, Expr, Field, Ident, LitStr, Token};
+
+pub const VALIDATE_ATTRIBUTE: &str = "validate";
+
+pub enum ValidateRule {
+	Required,
+	Email,
+	Regex(LitStr),
+	Length {
+		min: Option<Expr>,
+		max: Option<Expr>,
+	},
+	Range {
+		min: Option<Expr>,
+		max: Option<Expr>,
+	},
+}
+
+struct BoundArg {
+	name: Ident,
+	value: Expr,
+}
+
+impl Parse for BoundArg {
+	fn parse(input: ParseStream) -> syn::Result<Self> {
+		let name: Ident = input.parse()?;
+		input.parse::<Token![=]>()?;
+		let value: Expr = input.parse()?;
+		Ok(BoundArg { name, value })
+	}
+}
+
+impl Parse for ValidateRule {
+	fn parse(input: ParseStream) -> syn::Result<Self> {
+		let rule: Ident = input.parse()?;
+		match rule.to_string().as_str() {
+			"required" => Ok(ValidateRule::Required),
+			"email" => Ok(ValidateRule::Email),
+			"regex" => {
+				input.parse::<Token![=]>()?;
+				Ok(ValidateRule::Regex(input.parse()?))
+			},
+			"length" | "range" => {
+				let content;
+				parenthesized!(content in input);
+				let args = Punctuated::<BoundArg, Token![,]>::parse_terminated(&content)?;
+
+				let (mut min, mut max) = (None, None);
+				for BoundArg { name, value } in args {
+					match name.to_string().as_str() {
+						"min" => min = Some(value),
+						"max" => max = Some(value),
+						_ => {
+							return Err(
+								syn::Error::new(
+									name.span(),
+									"expected `min` or `max`",
+								),
+							)
+						},
+					}
+				}
+				if min.is_none() && max.is_none() {
+					return Err(
+						syn::Error::new(
+							rule.span(),
+							"expected at least one of `min` or `max`",
+						),
+					);
+				}
+
+				if rule == "length" {
+					Ok(ValidateRule::Length { min, max })
+				} else {
+					Ok(ValidateRule::Range { min, max })
+				}
+			},
+			_ => Err(
+				syn::Error::new(
+					rule.span(),
+					"unknown validation rule, expected one of `required`, `length`, `email`, `regex`, `range`",
+				),
+			),
+		}
+	}
+}
+
+/// Parses all the `#[validate(...)]` attributes of the field.
+pub fn field_rules(field: &Field) -> syn::Result<Vec<ValidateRule>> {
+	let mut rules = Vec::new();
+	for attr in field
+		.attrs
+		.iter()
+		.filter(|attr| attr.path.is_ident(VALIDATE_ATTRIBUTE))
+	{
+		let parsed =
+			attr.parse_args_with(Punctuated::<ValidateRule, Token![,]>::parse_terminated)?;
+		rules.extend(parsed);
+	}
+	Ok(rules)
+}
+
+/// Generates the checks of the field rules, for a ForCreate/ForUpdate field (always an `Option`).
+/// `required` is only checked on create, since updates are partial.
+pub fn field_checks(
+	field: &Field,
+	rules: &[ValidateRule],
+	for_create: bool,
+) -> TokenStream {
+	let ident = &field.ident;
+	let name = ident
+		.as_ref()
+		.map(|ident| ident.to_string())
+		.unwrap_or_default();
+
+	let mut required_check = quote! {};
+	let mut value_checks = Vec::new();
+
+	for rule in rules {
+		match rule {
+			ValidateRule::Required => {
+				if for_create {
+					required_check = quote! {
+						crate::model::validation::check_required(&mut errors, #name, &self.#ident);
+					};
+				}
+			},
+			ValidateRule::Email => value_checks.push(
+				quote! {
+					crate::model::validation::check_email(&mut errors, #name, value);
+				},
+			),
+			ValidateRule::Regex(regex) => value_checks.push(
+				quote! {
+					crate::model::validation::check_regex(&mut errors, #name, lazy_regex::regex_is_match!(#regex, value));
+				},
+			),
+			ValidateRule::Length { min, max } => {
+				let min = bound(
+					min,
+					quote! { usize },
+				);
+				let max = bound(
+					max,
+					quote! { usize },
+				);
+				value_checks.push(
+					quote! {
+						crate::model::validation::check_length(&mut errors, #name, value, #min, #max);
+					},
+				);
+			},
+			ValidateRule::Range { min, max } => {
+				let min = bound(
+					min,
+					quote! { f64 },
+				);
+				let max = bound(
+					max,
+					quote! { f64 },
+				);
+				value_checks.push(
+					quote! {
+						crate::model::validation::check_range(&mut errors, #name, value, #min, #max);
+					},
+				);
+			},
+		}
+	}
+
+	if value_checks.is_empty() {
+		return required_check;
+	}
+
+	quote! {
+		#required_check
+		if let Some(value) = &self.#ident {
+			#(#value_checks)*
+		}
+	}
+}
+
+fn bound(
+	value: &Option<Expr>,
+	ty: TokenStream,
+) -> TokenStream {
+	match value {
+		Some(value) => quote! { Some((#value) as #ty) },
+		None => quote! { None },
+	}
+}
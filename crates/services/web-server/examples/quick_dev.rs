@@ -21,6 +21,24 @@ async fn main() -> Result<()> {
 	);
 	req_login.await?.print().await?;
 
+	// -- Refresh Token
+	let req_refresh = hc.do_post(
+		"/api/token/refresh",
+		json!({}),
+	);
+	req_refresh.await?.print().await?;
+
+	// -- List Sessions
+	let req_list_sessions = hc.do_post(
+		"/api/rpc",
+		json!({
+			"jsonrpc": "2.0",
+			"id": 1,
+			"method": "list_my_sessions"
+		}),
+	);
+	req_list_sessions.await?.print().await?;
+
 	// -- Create Agent
 	let req_create_agent = hc.do_post(
 		"/api/rpc",
@@ -1,4 +1,4 @@
-use lib_utils::envs::get_env;
+use lib_utils::envs::{get_env, get_env_parse};
 use std::sync::OnceLock;
 
 pub fn web_config() -> &'static WebConfig {
@@ -15,6 +15,9 @@ pub fn web_config() -> &'static WebConfig {
 #[allow(non_snake_case)]
 pub struct WebConfig {
 	pub WEB_FOLDER: String,
+
+	// -- Files
+	pub FILES_MAX_UPLOAD_BYTES: usize,
 }
 
 impl WebConfig {
@@ -22,6 +25,9 @@ impl WebConfig {
 		Ok(
 			WebConfig {
 				WEB_FOLDER: get_env("SERVICE_WEB_FOLDER")?,
+
+				// -- Files
+				FILES_MAX_UPLOAD_BYTES: get_env_parse("SERVICE_FILES_MAX_UPLOAD_BYTES")?,
 			},
 		)
 	}
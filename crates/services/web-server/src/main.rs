@@ -12,14 +12,19 @@ use lib_web::middleware::mw_req_stamp::mw_req_stamp_resolver;
 use lib_web::middleware::mw_res_map::mw_reponse_map;
 use lib_web::routes::routes_static;
 
-use crate::web::routes_login;
+use crate::web::{
+	routes_login, routes_oauth, routes_oidc, routes_pwd_reset, routes_register, routes_token, routes_well_known,
+};
 
 use axum::{middleware, Router};
 use lib_core::_dev_utils;
+use lib_core::ctx::Ctx;
+use lib_core::model::user::UserBmc;
 use lib_core::model::ModelManager;
+use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tower_cookies::CookieManagerLayer;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
 // endregion: --- Modules
@@ -37,14 +42,41 @@ async fn main() -> Result<()> {
 
 	let mm = ModelManager::new().await?;
 
+	// -- Migrate the usernames and mails stored before their normalization (and create the user indexes).
+	let report = UserBmc::normalize_identifiers(
+		&Ctx::root_ctx(),
+		&mm,
+	)
+	.await?;
+	info!(
+		"{:<12} - {} users normalized",
+		"MIGRATION", report.normalized
+	);
+	if !report.conflicts.is_empty() || !report.indexes_pending.is_empty() {
+		warn!(
+			"{:<12} - indexes {:?} pending, users to rename (then run normalize_user_identifiers): {:?}",
+			"MIGRATION", report.indexes_pending, report.conflicts
+		);
+	}
+
 	// -- Define Routes
-	let routes_rpc =
-		web::routes_rpc::routes(mm.clone()).route_layer(middleware::from_fn(mw_ctx_require));
+	let routes_api = Router::new()
+		.merge(web::routes_rpc::routes(mm.clone()))
+		.merge(web::routes_files::routes(mm.clone()))
+		.merge(routes_token::routes_revoke(mm.clone()))
+		.merge(routes_oauth::routes_authorize(mm.clone()))
+		.route_layer(middleware::from_fn(mw_ctx_require));
 
 	let routes_all = Router::new()
 		.merge(routes_login::routes(mm.clone()))
+		.merge(routes_register::routes(mm.clone()))
+		.merge(routes_pwd_reset::routes(mm.clone()))
+		.merge(routes_token::routes(mm.clone()))
+		.merge(routes_oauth::routes(mm.clone()))
+		.merge(routes_oidc::routes(mm.clone()))
+		.merge(routes_well_known::routes())
 		.nest(
-			"/api", routes_rpc,
+			"/api", routes_api,
 		)
 		.layer(middleware::map_response(mw_reponse_map))
 		.layer(
@@ -67,7 +99,8 @@ async fn main() -> Result<()> {
 	);
 	axum::serve(
 		listener,
-		routes_all.into_make_service(),
+		// Note: The connect info provides the client ip of the sessions (see `ClientInfo`).
+		routes_all.into_make_service_with_connect_info::<SocketAddr>(),
 	)
 	.await
 	.unwrap();
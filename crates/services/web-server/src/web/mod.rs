@@ -1,6 +1,13 @@
 // region:    --- Modules
+pub mod routes_files;
 pub mod routes_login;
+pub mod routes_oauth;
+pub mod routes_oidc;
+pub mod routes_pwd_reset;
+pub mod routes_register;
 pub mod routes_rpc;
+pub mod routes_token;
+pub mod routes_well_known;
 pub mod rpcs;
 
 // endregion: --- Modules
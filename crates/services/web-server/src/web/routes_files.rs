@@ -0,0 +1,23 @@
+use crate::config::web_config;
+use axum::extract::DefaultBodyLimit;
+use axum::routing::{get, post};
+use axum::Router;
+use lib_core::model::ModelManager;
+use lib_web::handlers::handlers_files;
+
+///  Build the Axum router for '/api/files'
+/// Note: Must be nested behind `mw_ctx_require`, the handlers use the request `Ctx`.
+pub fn routes(mm: ModelManager) -> Router {
+	Router::new()
+		.route(
+			"/files",
+			post(handlers_files::api_file_upload_handler),
+		)
+		.route(
+			"/files/{id}",
+			get(handlers_files::api_file_download_handler)
+				.delete(handlers_files::api_file_delete_handler),
+		)
+		.layer(DefaultBodyLimit::max(web_config().FILES_MAX_UPLOAD_BYTES))
+		.with_state(mm)
+}
@@ -9,6 +9,10 @@ pub fn routes(mm: ModelManager) -> Router {
 			"/api/login",
 			post(handlers_login::api_login_handler),
 		)
+		.route(
+			"/api/login/mfa",
+			post(handlers_login::api_login_mfa_handler),
+		)
 		.route(
 			"/api/logoff",
 			post(handlers_login::api_logoff_handler),
@@ -0,0 +1,34 @@
+use axum::routing::{get, post};
+use axum::Router;
+use lib_core::model::ModelManager;
+use lib_web::handlers::handlers_oauth;
+
+/// The public OAuth2 routes (the clients authenticate with their credentials).
+pub fn routes(mm: ModelManager) -> Router {
+	Router::new()
+		.route(
+			"/api/oauth/token",
+			post(handlers_oauth::api_oauth_token_handler),
+		)
+		.route(
+			"/api/oauth/revoke",
+			post(handlers_oauth::api_oauth_revoke_handler),
+		)
+		.route(
+			"/api/oauth/introspect",
+			post(handlers_oauth::api_oauth_introspect_handler),
+		)
+		.with_state(mm)
+}
+
+/// Build the Axum router for '/api/oauth/authorize'
+/// Note: Must be nested behind `mw_ctx_require`, the user authorizes the client with its `Ctx`.
+pub fn routes_authorize(mm: ModelManager) -> Router {
+	Router::new()
+		.route(
+			"/oauth/authorize",
+			get(handlers_oauth::api_oauth_authorize_handler)
+				.post(handlers_oauth::api_oauth_authorize_decision_handler),
+		)
+		.with_state(mm)
+}
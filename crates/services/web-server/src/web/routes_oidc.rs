@@ -0,0 +1,18 @@
+use axum::routing::get;
+use axum::Router;
+use lib_core::model::ModelManager;
+use lib_web::handlers::handlers_oidc;
+
+/// The public OpenID Connect login routes (browser redirects).
+pub fn routes(mm: ModelManager) -> Router {
+	Router::new()
+		.route(
+			"/api/oidc/login/{provider_id}",
+			get(handlers_oidc::api_oidc_login_handler),
+		)
+		.route(
+			"/api/oidc/callback",
+			get(handlers_oidc::api_oidc_callback_handler),
+		)
+		.with_state(mm)
+}
@@ -0,0 +1,17 @@
+use axum::routing::post;
+use axum::Router;
+use lib_core::model::ModelManager;
+use lib_web::handlers::handlers_pwd_reset;
+
+pub fn routes(mm: ModelManager) -> Router {
+	Router::new()
+		.route(
+			"/api/pwd/forgot",
+			post(handlers_pwd_reset::api_pwd_forgot_handler),
+		)
+		.route(
+			"/api/pwd/reset",
+			post(handlers_pwd_reset::api_pwd_reset_handler),
+		)
+		.with_state(mm)
+}
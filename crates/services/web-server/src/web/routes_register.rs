@@ -0,0 +1,21 @@
+use axum::routing::{get, post};
+use axum::Router;
+use lib_core::model::ModelManager;
+use lib_web::handlers::handlers_register;
+
+pub fn routes(mm: ModelManager) -> Router {
+	Router::new()
+		.route(
+			"/api/register",
+			post(handlers_register::api_register_handler),
+		)
+		.route(
+			"/api/register/verify",
+			get(handlers_register::api_register_verify_handler),
+		)
+		.route(
+			"/api/profile/mail/verify",
+			get(handlers_register::api_mail_change_verify_handler),
+		)
+		.with_state(mm)
+}
@@ -0,0 +1,41 @@
+use axum::routing::post;
+use axum::Router;
+use lib_core::model::ModelManager;
+use lib_web::handlers::handlers_token;
+
+/// The public token routes (the access token may be missing or expired).
+pub fn routes(mm: ModelManager) -> Router {
+	Router::new()
+		.route(
+			"/api/token",
+			post(handlers_token::api_token_handler),
+		)
+		.route(
+			"/api/token/mfa",
+			post(handlers_token::api_token_mfa_handler),
+		)
+		.route(
+			"/api/token/refresh",
+			post(handlers_token::api_token_refresh_handler),
+		)
+		.with_state(mm)
+}
+
+/// Build the Axum router for '/api/token/revoke*'
+/// Note: Must be nested behind `mw_ctx_require`, the handlers use the request `Ctx`.
+pub fn routes_revoke(mm: ModelManager) -> Router {
+	Router::new()
+		.route(
+			"/token/revoke",
+			post(handlers_token::api_token_revoke_handler),
+		)
+		.route(
+			"/token/revoke_all",
+			post(handlers_token::api_token_revoke_all_handler),
+		)
+		.route(
+			"/token/revoke_user",
+			post(handlers_token::api_token_revoke_user_handler),
+		)
+		.with_state(mm)
+}
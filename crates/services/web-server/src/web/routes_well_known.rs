@@ -0,0 +1,10 @@
+use axum::routing::get;
+use axum::Router;
+use lib_web::handlers::handlers_well_known;
+
+pub fn routes() -> Router {
+	Router::new().route(
+		"/.well-known/jwks.json",
+		get(handlers_well_known::jwks_handler),
+	)
+}
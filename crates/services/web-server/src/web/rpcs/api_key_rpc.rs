@@ -0,0 +1,78 @@
+//! The API keys of the current user (see `ApiKeyBmc`).
+//!
+//! Note: The keys are stored with the users, in the root ctx database.
+
+use lib_core::model::api_key::{ApiKey, ApiKeyBmc, ApiKeyCreated, ApiKeyForCreate};
+use lib_rpc_core::prelude::*;
+
+pub fn rpc_router_builder() -> RouterBuilder {
+	router_builder!(
+		create_api_key,
+		list_api_keys,
+		revoke_api_key
+	)
+}
+
+/// Note: The returned `key` is only shown here, it cannot be retrieved later.
+pub async fn create_api_key(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreate<ApiKeyForCreate>,
+) -> Result<DataRpcResult<ApiKeyCreated>> {
+	require_account_access(
+		&ctx,
+		SCOPE_CREDENTIALS,
+	)?;
+	let ParamsForCreate { data } = params;
+
+	let api_key = ApiKeyBmc::create(
+		&Ctx::root_ctx(),
+		&mm,
+		&ctx.user_id(),
+		ctx.roles(),
+		data,
+	)
+	.await?;
+
+	Ok(api_key.into())
+}
+
+pub async fn list_api_keys(
+	ctx: Ctx,
+	mm: ModelManager,
+) -> Result<DataRpcResult<Vec<ApiKey>>> {
+	require_account_access(
+		&ctx,
+		SCOPE_CREDENTIALS,
+	)?;
+	let api_keys = ApiKeyBmc::list_for_user(
+		&Ctx::root_ctx(),
+		&mm,
+		&ctx.user_id(),
+	)
+	.await?;
+
+	Ok(api_keys.into())
+}
+
+pub async fn revoke_api_key(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<ApiKey>> {
+	require_account_access(
+		&ctx,
+		SCOPE_CREDENTIALS,
+	)?;
+	let ParamsIded { id } = params;
+
+	let api_key = ApiKeyBmc::revoke_for_user(
+		&Ctx::root_ctx(),
+		&mm,
+		&ctx.user_id(),
+		&id,
+	)
+	.await?;
+
+	Ok(api_key.into())
+}
@@ -0,0 +1,29 @@
+//! The login lockouts, for the admins (see `LoginThrottleBmc`).
+
+use lib_core::model::login_throttle::LoginThrottleBmc;
+use lib_rpc_core::prelude::*;
+
+pub fn rpc_router_builder() -> RouterBuilder {
+	router_builder!(unlock_user_login)
+}
+
+/// Removes the failed logins (and the locks) of the username and mail of the user `id`.
+///
+/// Note: The locks of the client ips expire on their own.
+pub async fn unlock_user_login(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<String>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsIded { id } = params;
+
+	LoginThrottleBmc::unlock_user(
+		&mm, &id,
+	)
+	.await?;
+
+	Ok(id.into())
+}
@@ -0,0 +1,83 @@
+//! The TOTP second factor of the current user, and its reset by the admins (see `MfaBmc`).
+//!
+//! Note: The MFA is stored with the users, in the root ctx database.
+
+use lib_core::model::mfa::{MfaBmc, RecoveryCodes, TotpConfirm, TotpEnrollment};
+use lib_core::model::user::UserBmc;
+use lib_rpc_core::prelude::*;
+
+pub fn rpc_router_builder() -> RouterBuilder {
+	router_builder!(
+		enroll_totp,
+		confirm_totp,
+		reset_user_mfa
+	)
+}
+
+/// Note: The enrollment is only enabled once confirmed (see `confirm_totp`).
+pub async fn enroll_totp(
+	ctx: Ctx,
+	mm: ModelManager,
+) -> Result<DataRpcResult<TotpEnrollment>> {
+	require_account_access(
+		&ctx,
+		SCOPE_CREDENTIALS,
+	)?;
+	let user = UserBmc::get_user_for_auth_by_id(
+		&Ctx::root_ctx(),
+		&mm,
+		&ctx.user_id(),
+	)
+	.await?;
+
+	let enrollment = MfaBmc::enroll(
+		&mm,
+		&user.id,
+		&user.username,
+	)
+	.await?;
+
+	Ok(enrollment.into())
+}
+
+/// Note: The returned `recovery_codes` are only shown here, they cannot be retrieved later.
+pub async fn confirm_totp(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreate<TotpConfirm>,
+) -> Result<DataRpcResult<RecoveryCodes>> {
+	require_account_access(
+		&ctx,
+		SCOPE_CREDENTIALS,
+	)?;
+	let ParamsForCreate { data } = params;
+
+	let recovery_codes = MfaBmc::confirm(
+		&mm,
+		&ctx.user_id(),
+		&data.code,
+	)
+	.await?;
+
+	Ok(recovery_codes.into())
+}
+
+/// Removes the MFA of the user `id` (e.g., lost device and recovery codes), who can then log in
+/// with the password only, and enroll again.
+pub async fn reset_user_mfa(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<String>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsIded { id } = params;
+
+	MfaBmc::reset(
+		&mm, &id,
+	)
+	.await?;
+
+	Ok(id.into())
+}
@@ -1,11 +1,191 @@
 // region:    --- Modules
 
-pub mod example_rpc;
+// NOTE: Hand-written rpc modules (e.g., `pub mod agent_rpc;`) go here,
+//       the `#[crud(rpc)]` entities register their rpc functions themselves.
+pub mod api_key_rpc;
+pub mod login_throttle_rpc;
+pub mod mfa_rpc;
+pub mod oauth_client_rpc;
+pub mod oidc_provider_rpc;
+pub mod profile_rpc;
+pub mod service_account_rpc;
+pub mod session_rpc;
+pub mod user_rpc;
 
-use rpc_router::{Router, RouterBuilder};
+use rpc_router::RouterBuilder;
 
 // endregion: --- Modules
 
 pub fn all_rpc_router_builder() -> RouterBuilder {
-	Router::builder().extend(example_rpc::rpc_router_builder())
+	lib_rpc_core::all_crud_rpc_router_builder()
+		.extend(session_rpc::rpc_router_builder())
+		.extend(api_key_rpc::rpc_router_builder())
+		.extend(service_account_rpc::rpc_router_builder())
+		.extend(oidc_provider_rpc::rpc_router_builder())
+		.extend(oauth_client_rpc::rpc_router_builder())
+		.extend(mfa_rpc::rpc_router_builder())
+		.extend(login_throttle_rpc::rpc_router_builder())
+		.extend(profile_rpc::rpc_router_builder())
+		.extend(user_rpc::rpc_router_builder())
 }
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	pub type Result<T> = core::result::Result<T, Error>;
+	pub type Error = Box<dyn std::error::Error>; // For tests.
+
+	use super::*;
+	use lib_core::ctx::{Ctx, ROLE_ADMIN, SCOPE_CREDENTIALS, SCOPE_PROFILE};
+	use lib_core::model::ModelManager;
+	use lib_web::handlers::handlers_rpc::RpcCookies;
+	use rpc_router::resources_builder;
+	use serde_json::{json, Value};
+	use tower_cookies::Cookies;
+
+	/// The account management rpcs, with their scope and valid params.
+	/// Note: The ctx is refused before any database access, so no database is needed.
+	fn fx_account_rpcs() -> Vec<(
+		&'static str,
+		&'static str,
+		Option<Value>,
+	)> {
+		vec![
+			(
+				"update_my_profile",
+				SCOPE_PROFILE,
+				Some(json!({"data": {"name": "fx-name"}})),
+			),
+			(
+				"change_my_password",
+				SCOPE_CREDENTIALS,
+				Some(json!({"data": {"pwd_current": "fx-pwd", "pwd_new": "fx-pwd-new"}})),
+			),
+			(
+				"create_api_key",
+				SCOPE_CREDENTIALS,
+				Some(json!({"data": {"name": "fx-key"}})),
+			),
+			(
+				"list_api_keys",
+				SCOPE_CREDENTIALS,
+				None,
+			),
+			(
+				"revoke_api_key",
+				SCOPE_CREDENTIALS,
+				Some(json!({"id": "fx-key-id"})),
+			),
+			(
+				"enroll_totp",
+				SCOPE_CREDENTIALS,
+				None,
+			),
+			(
+				"confirm_totp",
+				SCOPE_CREDENTIALS,
+				Some(json!({"data": {"code": "123456"}})),
+			),
+			(
+				"list_my_sessions",
+				SCOPE_CREDENTIALS,
+				None,
+			),
+			(
+				"revoke_my_session",
+				SCOPE_CREDENTIALS,
+				Some(json!({"id": "fx-session-id"})),
+			),
+		]
+	}
+
+	async fn exec_rpc(
+		ctx: Ctx,
+		method: &str,
+		params: Option<Value>,
+	) -> core::result::Result<Value, lib_web::Error> {
+		let mm = ModelManager::new().await.unwrap();
+		let rpc_router = all_rpc_router_builder().append_resource(mm).build();
+
+		let mut rpc_req = json!({"jsonrpc": "2.0", "id": 1, "method": method});
+		if let Some(params) = params {
+			rpc_req["params"] = params;
+		}
+		let rpc_req = rpc_router::Request::try_from(rpc_req).unwrap();
+
+		let call_success = rpc_router
+			.call_with_resources(
+				rpc_req,
+				resources_builder![ctx, RpcCookies(Cookies::default())].build(),
+			)
+			.await?;
+
+		Ok(call_success.value)
+	}
+
+	#[tokio::test]
+	async fn test_account_rpcs_api_key_ctx_err() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_ctx = Ctx::new("fx-user-id".to_string())?.add_api_key_id("fx-key-id".to_string());
+
+		for (method, _, params) in fx_account_rpcs() {
+			// -- Exec
+			let res = exec_rpc(
+				fx_ctx.clone(),
+				method,
+				params,
+			)
+			.await;
+
+			// -- Check
+			assert!(
+				matches!(
+					res,
+					Err(lib_web::Error::RpcLibRpc(lib_rpc_core::Error::ApiKeyNotAllowed))
+				),
+				"{method} should refuse the API key ctx, but got: {res:?}"
+			);
+		}
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_account_rpcs_delegated_ctx_err() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_ctx = Ctx::new("fx-user-id".to_string())?;
+		let fx_delegated_ctxs = [
+			fx_ctx.add_scopes(vec![ROLE_ADMIN.to_string()]),
+			fx_ctx.add_scopes(vec![SCOPE_PROFILE.to_string()]),
+		];
+
+		for fx_delegated_ctx in fx_delegated_ctxs {
+			for (method, scope, params) in fx_account_rpcs() {
+				if fx_delegated_ctx.has_scope(scope) {
+					continue;
+				}
+
+				// -- Exec
+				let res = exec_rpc(
+					fx_delegated_ctx.clone(),
+					method,
+					params,
+				)
+				.await;
+
+				// -- Check
+				assert!(
+					matches!(
+						&res,
+						Err(lib_web::Error::RpcLibRpc(lib_rpc_core::Error::ScopeMissing { scope: res_scope }))
+							if res_scope == scope
+					),
+					"{method} should refuse the delegated ctx without the {scope} scope, but got: {res:?}"
+				);
+			}
+		}
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -0,0 +1,65 @@
+//! The OAuth2 client apps of the ctx tenant, for the admins (see `OAuthClientBmc`).
+
+use lib_core::model::oauth::{OAuthClient, OAuthClientBmc, OAuthClientForCreate, OAuthClientWithSecret};
+use lib_rpc_core::prelude::*;
+
+pub fn rpc_router_builder() -> RouterBuilder {
+	router_builder!(
+		create_oauth_client,
+		list_oauth_clients,
+		delete_oauth_client
+	)
+}
+
+/// Note: The `client_secret` (confidential clients only) is only returned here.
+pub async fn create_oauth_client(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreate<OAuthClientForCreate>,
+) -> Result<DataRpcResult<OAuthClientWithSecret>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsForCreate { data } = params;
+
+	let client = OAuthClientBmc::create(
+		&ctx, &mm, data,
+	)
+	.await?;
+
+	Ok(client.into())
+}
+
+pub async fn list_oauth_clients(
+	ctx: Ctx,
+	mm: ModelManager,
+) -> Result<DataRpcResult<Vec<OAuthClient>>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+
+	let clients = OAuthClientBmc::list(
+		&ctx, &mm,
+	)
+	.await?;
+
+	Ok(clients.into())
+}
+
+pub async fn delete_oauth_client(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<OAuthClient>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsIded { id } = params;
+
+	let client = OAuthClientBmc::delete(
+		&ctx, &mm, &id,
+	)
+	.await?;
+
+	Ok(client.into())
+}
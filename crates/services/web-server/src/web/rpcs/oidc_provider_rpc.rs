@@ -0,0 +1,65 @@
+//! The OpenID Connect identity providers of the ctx tenant, for the admins (see `OidcProviderBmc`).
+
+use lib_core::model::oidc::{OidcProvider, OidcProviderBmc, OidcProviderForCreate};
+use lib_rpc_core::prelude::*;
+
+pub fn rpc_router_builder() -> RouterBuilder {
+	router_builder!(
+		create_oidc_provider,
+		list_oidc_providers,
+		delete_oidc_provider
+	)
+}
+
+/// Note: The users log in with `/api/oidc/login/{id}`, the provider redirecting them to its `redirect_uri`.
+pub async fn create_oidc_provider(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreate<OidcProviderForCreate>,
+) -> Result<DataRpcResult<OidcProvider>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsForCreate { data } = params;
+
+	let provider = OidcProviderBmc::create(
+		&ctx, &mm, data,
+	)
+	.await?;
+
+	Ok(provider.into())
+}
+
+pub async fn list_oidc_providers(
+	ctx: Ctx,
+	mm: ModelManager,
+) -> Result<DataRpcResult<Vec<OidcProvider>>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+
+	let providers = OidcProviderBmc::list(
+		&ctx, &mm,
+	)
+	.await?;
+
+	Ok(providers.into())
+}
+
+pub async fn delete_oidc_provider(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<OidcProvider>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsIded { id } = params;
+
+	let provider = OidcProviderBmc::delete(
+		&ctx, &mm, &id,
+	)
+	.await?;
+
+	Ok(provider.into())
+}
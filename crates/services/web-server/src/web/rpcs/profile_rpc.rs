@@ -0,0 +1,131 @@
+//! The profile and password of the current user (see `UserBmc::get_profile`).
+//!
+//! Note: The users are stored in the root ctx database, and their `login` is never returned.
+
+use lib_auth::token::TokenSubject;
+use lib_core::model::session::SessionBmc;
+use lib_core::model::user::{
+	normalize_identifier, QUserProfile, QUserProfileForUpdate, QUserPwdChange, UserBmc,
+};
+use lib_mail::send_mail;
+use lib_rpc_core::prelude::*;
+use lib_web::handlers::handlers_register::mail_change_mail;
+use lib_web::handlers::handlers_rpc::RpcCookies;
+
+pub fn rpc_router_builder() -> RouterBuilder {
+	router_builder!(
+		get_my_profile,
+		update_my_profile,
+		change_my_password
+	)
+}
+
+pub async fn get_my_profile(
+	ctx: Ctx,
+	mm: ModelManager,
+) -> Result<DataRpcResult<QUserProfile>> {
+	let profile = UserBmc::get_profile(
+		&Ctx::root_ctx(),
+		&mm,
+		&ctx.user_id(),
+	)
+	.await?;
+
+	Ok(profile.into())
+}
+
+/// Note: Only the given fields are updated. A new mail stays pending (`mail_pending`) until
+///       the link mailed to it is opened (see `api_mail_change_verify_handler`).
+pub async fn update_my_profile(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreate<QUserProfileForUpdate>,
+) -> Result<DataRpcResult<QUserProfile>> {
+	require_account_access(
+		&ctx,
+		SCOPE_PROFILE,
+	)?;
+	let ParamsForCreate { data } = params;
+	let root_ctx = Ctx::root_ctx();
+	let user_id = ctx.user_id();
+	let mail = data.mail.as_deref().map(normalize_identifier);
+
+	let profile = UserBmc::update_profile(
+		&root_ctx, &mm, &user_id, data,
+	)
+	.await?;
+
+	// -- Mail the verification link of the new mail.
+	if let Some(mail) = mail.filter(|mail| profile.mail_pending.as_ref() == Some(mail)) {
+		let user = UserBmc::get_user_for_auth_by_id(
+			&root_ctx, &mm, &user_id,
+		)
+		.await?;
+		send_mail(
+			mail_change_mail(
+				&user, mail,
+			)?,
+		)
+		.await?;
+	}
+
+	Ok(profile.into())
+}
+
+/// Note: The password change rotates the salt of the user, which invalidates all its web tokens,
+///       so the auth cookie of the request is re-issued (and its session updated).
+///       The other sessions are revoked, with their refresh tokens.
+pub async fn change_my_password(
+	ctx: Ctx,
+	mm: ModelManager,
+	cookies: RpcCookies,
+	params: ParamsForCreate<QUserPwdChange>,
+) -> Result<DataRpcResult<String>> {
+	require_account_access(
+		&ctx,
+		SCOPE_CREDENTIALS,
+	)?;
+	let ParamsForCreate { data } = params;
+	let root_ctx = Ctx::root_ctx();
+	let user_id = ctx.user_id();
+
+	UserBmc::change_pwd(
+		&root_ctx, &mm, &user_id, data,
+	)
+	.await?;
+	SessionBmc::revoke_others_for_user(
+		&root_ctx,
+		&mm,
+		&user_id,
+		ctx.token_id(),
+	)
+	.await?;
+
+	// -- Re-issue the auth cookie, with the new salt.
+	let user = UserBmc::get_user_for_auth_by_id(
+		&root_ctx, &mm, &user_id,
+	)
+	.await?;
+	let web_token = cookies.reissue_token_cookie(
+		&TokenSubject {
+			user_id: &user.id,
+			username: &user.username,
+			tenant_id: &ctx.tenant_id(),
+			roles: &user.roles,
+			salt: user.pwd_salt,
+			service_account: false,
+			grant: None,
+		},
+	)?;
+	if let (Some(web_token), Some(jti)) = (web_token, ctx.token_id()) {
+		SessionBmc::replace_jti(
+			&root_ctx,
+			&mm,
+			jti,
+			web_token.jti(),
+		)
+		.await?;
+	}
+
+	Ok(user_id.into())
+}
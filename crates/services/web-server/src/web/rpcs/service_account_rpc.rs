@@ -0,0 +1,108 @@
+//! The service accounts of the ctx tenant, for the admins (see `ServiceAccountBmc`).
+
+use lib_core::model::service_account::{
+	ServiceAccount, ServiceAccountBmc, ServiceAccountForCreate, ServiceAccountForUpdate,
+	ServiceAccountWithSecret,
+};
+use lib_rpc_core::prelude::*;
+
+pub fn rpc_router_builder() -> RouterBuilder {
+	router_builder!(
+		create_service_account,
+		list_service_accounts,
+		update_service_account,
+		rotate_service_account_secret,
+		delete_service_account
+	)
+}
+
+/// Note: The returned `client_secret` is only shown here, it cannot be retrieved later.
+pub async fn create_service_account(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreate<ServiceAccountForCreate>,
+) -> Result<DataRpcResult<ServiceAccountWithSecret>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsForCreate { data } = params;
+
+	let service_account = ServiceAccountBmc::create(
+		&ctx, &mm, data,
+	)
+	.await?;
+
+	Ok(service_account.into())
+}
+
+pub async fn list_service_accounts(
+	ctx: Ctx,
+	mm: ModelManager,
+) -> Result<DataRpcResult<Vec<ServiceAccount>>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+
+	let service_accounts = ServiceAccountBmc::list(
+		&ctx, &mm,
+	)
+	.await?;
+
+	Ok(service_accounts.into())
+}
+
+/// Replaces the role assignments of the service account.
+pub async fn update_service_account(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForUpdate<ServiceAccountForUpdate>,
+) -> Result<DataRpcResult<ServiceAccount>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsForUpdate { id, data } = params;
+
+	let service_account = ServiceAccountBmc::update_roles(
+		&ctx, &mm, &id, data.roles,
+	)
+	.await?;
+
+	Ok(service_account.into())
+}
+
+/// Note: The tokens issued with the previous secret are invalidated.
+pub async fn rotate_service_account_secret(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<ServiceAccountWithSecret>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsIded { id } = params;
+
+	let service_account = ServiceAccountBmc::rotate_secret(
+		&ctx, &mm, &id,
+	)
+	.await?;
+
+	Ok(service_account.into())
+}
+
+pub async fn delete_service_account(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<ServiceAccount>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsIded { id } = params;
+
+	let service_account = ServiceAccountBmc::delete(
+		&ctx, &mm, &id,
+	)
+	.await?;
+
+	Ok(service_account.into())
+}
@@ -0,0 +1,54 @@
+//! The session inventory of the current user (see `SessionBmc`).
+//!
+//! Note: The sessions are stored with the login, in the root ctx database.
+
+use lib_core::model::session::{Session, SessionBmc};
+use lib_rpc_core::prelude::*;
+
+pub fn rpc_router_builder() -> RouterBuilder {
+	router_builder!(
+		list_my_sessions,
+		revoke_my_session
+	)
+}
+
+pub async fn list_my_sessions(
+	ctx: Ctx,
+	mm: ModelManager,
+) -> Result<DataRpcResult<Vec<Session>>> {
+	require_account_access(
+		&ctx,
+		SCOPE_CREDENTIALS,
+	)?;
+	let sessions = SessionBmc::list_for_user(
+		&Ctx::root_ctx(),
+		&mm,
+		&ctx.user_id(),
+		ctx.token_id(),
+	)
+	.await?;
+
+	Ok(sessions.into())
+}
+
+pub async fn revoke_my_session(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<Session>> {
+	require_account_access(
+		&ctx,
+		SCOPE_CREDENTIALS,
+	)?;
+	let ParamsIded { id } = params;
+
+	let session = SessionBmc::revoke_for_user(
+		&Ctx::root_ctx(),
+		&mm,
+		&ctx.user_id(),
+		&id,
+	)
+	.await?;
+
+	Ok(session.into())
+}
@@ -0,0 +1,367 @@
+//! The user administration, for the admins (see `UserBmc`).
+//!
+//! Notes:
+//!
+//! - The users are stored in the root ctx database, and only returned as `QUserView`
+//!   (never their password hash and salt).
+//! - A deactivated (or locked), deleted, or forced to reset its password user is logged out everywhere.
+//! - `normalize_user_identifiers` is the migration of the users stored before the usernames and mails
+//!   were normalized (run at the server startup, then again after renaming the reported conflicts).
+
+use lib_core::model::mfa::MfaBmc;
+use lib_core::model::pwd_reset::PwdResetBmc;
+use lib_core::model::token_revocation::TokenRevocationBmc;
+use lib_core::model::user::{
+	QUserFilter, QUserForAdminCreate, QUserNormalizeReport, QUserRolesForUpdate,
+	QUserStateForUpdate, QUserView, UserBmc, UserState,
+};
+use lib_core::model;
+use lib_mail::send_mail;
+use lib_rpc_core::prelude::*;
+use lib_web::handlers::handlers_pwd_reset::pwd_link_mail;
+
+pub fn rpc_router_builder() -> RouterBuilder {
+	router_builder!(
+		create_user,
+		get_user,
+		list_users,
+		deactivate_user,
+		reactivate_user,
+		update_user_state,
+		force_user_pwd_reset,
+		update_user_roles,
+		delete_user,
+		normalize_user_identifiers
+	)
+}
+
+/// Note: A user created without `pwd_clear` is invited by mail to choose its password.
+pub async fn create_user(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreate<QUserForAdminCreate>,
+) -> Result<DataRpcResult<QUserView>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsForCreate { data } = params;
+	let root_ctx = Ctx::root_ctx();
+	let invite_mail = match data.pwd_clear {
+		Some(_) => None,
+		None => data.mail.clone(),
+	};
+
+	let id = UserBmc::create_by_admin(
+		&root_ctx, &mm, data,
+	)
+	.await?;
+
+	// -- Invite the user (removed if the invitation cannot be sent).
+	if let Some(mail) = invite_mail {
+		if let Err(ex) = send_pwd_link(
+			&mm, &id, mail, true,
+		)
+		.await
+		{
+			UserBmc::delete(
+				&root_ctx, &mm, &id,
+			)
+			.await?;
+			return Err(ex);
+		}
+	}
+
+	let user = UserBmc::get_view(
+		&root_ctx, &mm, &id,
+	)
+	.await?;
+
+	Ok(user.into())
+}
+
+pub async fn get_user(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<QUserView>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsIded { id } = params;
+
+	let user = UserBmc::get_view(
+		&Ctx::root_ctx(),
+		&mm,
+		&id,
+	)
+	.await?;
+
+	Ok(user.into())
+}
+
+pub async fn list_users(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsList<QUserFilter>,
+) -> Result<DataRpcResult<Vec<QUserView>>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsList {
+		filter,
+		list_options,
+		..
+	} = params;
+
+	let users = UserBmc::list_views(
+		&Ctx::root_ctx(),
+		&mm,
+		filter,
+		list_options.unwrap_or_default(),
+	)
+	.await?;
+
+	Ok(users.into())
+}
+
+/// Note: The user can no longer log in, and its current tokens are revoked.
+pub async fn deactivate_user(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<QUserView>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsIded { id } = params;
+
+	let user = set_user_state(
+		&mm,
+		&id,
+		UserState::Disabled,
+	)
+	.await?;
+
+	Ok(user.into())
+}
+
+pub async fn reactivate_user(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<QUserView>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsIded { id } = params;
+
+	let user = set_user_state(
+		&mm,
+		&id,
+		UserState::Active,
+	)
+	.await?;
+
+	Ok(user.into())
+}
+
+/// Sets any account state (e.g., `locked`), see `UserState`.
+pub async fn update_user_state(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForUpdate<QUserStateForUpdate>,
+) -> Result<DataRpcResult<QUserView>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsForUpdate { id, data } = params;
+
+	let user = set_user_state(
+		&mm, &id, data.state,
+	)
+	.await?;
+
+	Ok(user.into())
+}
+
+/// Removes the password of the user (and revokes its tokens), and mails it a reset link.
+pub async fn force_user_pwd_reset(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<QUserView>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsIded { id } = params;
+	let root_ctx = Ctx::root_ctx();
+
+	let user = UserBmc::get_view(
+		&root_ctx, &mm, &id,
+	)
+	.await?;
+	let mail = user
+		.mail
+		.clone()
+		.ok_or_else(|| model::Error::UserMailMissing { id: id.clone() })?;
+
+	UserBmc::clear_pwd(
+		&root_ctx, &mm, &id,
+	)
+	.await?;
+	TokenRevocationBmc::revoke_all_for_user(
+		&root_ctx, &mm, &id,
+	)
+	.await?;
+	send_pwd_link(
+		&mm, &id, mail, false,
+	)
+	.await?;
+
+	let user = UserBmc::get_view(
+		&root_ctx, &mm, &id,
+	)
+	.await?;
+
+	Ok(user.into())
+}
+
+/// Replaces the roles of the user (effective on its next request).
+pub async fn update_user_roles(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForUpdate<QUserRolesForUpdate>,
+) -> Result<DataRpcResult<QUserView>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsForUpdate { id, data } = params;
+	let root_ctx = Ctx::root_ctx();
+
+	UserBmc::get_view(
+		&root_ctx, &mm, &id,
+	)
+	.await?;
+	UserBmc::set_roles(
+		&root_ctx, &mm, &id, data.roles,
+	)
+	.await?;
+
+	let user = UserBmc::get_view(
+		&root_ctx, &mm, &id,
+	)
+	.await?;
+
+	Ok(user.into())
+}
+
+/// Note: The tokens and MFA of the user are removed with it.
+pub async fn delete_user(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<DataRpcResult<QUserView>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+	let ParamsIded { id } = params;
+	let root_ctx = Ctx::root_ctx();
+
+	let user = UserBmc::get_view(
+		&root_ctx, &mm, &id,
+	)
+	.await?;
+
+	TokenRevocationBmc::revoke_all_for_user(
+		&root_ctx, &mm, &id,
+	)
+	.await?;
+	match MfaBmc::reset(
+		&mm, &id,
+	)
+	.await
+	{
+		Ok(()) | Err(model::Error::MfaNotEnrolled) => (),
+		Err(ex) => return Err(ex.into()),
+	}
+	UserBmc::delete(
+		&root_ctx, &mm, &id,
+	)
+	.await?;
+
+	Ok(user.into())
+}
+
+pub async fn normalize_user_identifiers(
+	ctx: Ctx,
+	mm: ModelManager,
+) -> Result<DataRpcResult<QUserNormalizeReport>> {
+	require_role(
+		&ctx, ROLE_ADMIN,
+	)?;
+
+	let report = UserBmc::normalize_identifiers(
+		&Ctx::root_ctx(),
+		&mm,
+	)
+	.await?;
+
+	Ok(report.into())
+}
+
+// region:    --- Support
+
+async fn set_user_state(
+	mm: &ModelManager,
+	id: &String,
+	state: UserState,
+) -> Result<QUserView> {
+	let root_ctx = Ctx::root_ctx();
+
+	UserBmc::get_view(
+		&root_ctx, mm, id,
+	)
+	.await?;
+	UserBmc::set_state(
+		&root_ctx, mm, id, state,
+	)
+	.await?;
+
+	let user = UserBmc::get_view(
+		&root_ctx, mm, id,
+	)
+	.await?;
+
+	Ok(user)
+}
+
+/// Mails the user a link to choose its password (see `PwdResetBmc`).
+async fn send_pwd_link(
+	mm: &ModelManager,
+	id: &str,
+	mail: String,
+	invite: bool,
+) -> Result<()> {
+	let token = if invite {
+		PwdResetBmc::create_invite(
+			mm, id,
+		)
+		.await?
+	} else {
+		PwdResetBmc::create(
+			mm, id,
+		)
+		.await?
+	};
+
+	send_mail(
+		pwd_link_mail(
+			mail, &token, invite,
+		),
+	)
+	.await?;
+
+	Ok(())
+}
+
+// endregion: --- Support
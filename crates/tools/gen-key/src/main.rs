@@ -1,10 +1,25 @@
 pub type Result<T> = core::result::Result<T, Error>;
 pub type Error = Box<dyn std::error::Error>; // Ok for tools.
 
+use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+use ed25519_dalek::pkcs8::{EncodePrivateKey, EncodePublicKey};
+use ed25519_dalek::SigningKey;
 use lib_utils::b64::b64u_encode;
 use rand::RngCore;
 
+/// Usage:
+/// - `gen-key` for a 512 bits symmetric key (e.g., a `SERVICE_PWD_KEYS` or `SERVICE_TOKEN_KEYS` key).
+/// - `gen-key ed25519 [kid]` for an Ed25519 keypair (`SERVICE_TOKEN_ED25519_KEY`).
 fn main() -> Result<()> {
+	let mut args = std::env::args().skip(1);
+	match args.next().as_deref() {
+		None => gen_symmetric_key(),
+		Some("ed25519") => gen_ed25519_key(args.next()),
+		Some(other) => Err(format!("Unknown key type '{other}', expected nothing or 'ed25519'").into()),
+	}
+}
+
+fn gen_symmetric_key() -> Result<()> {
 	let mut key = [0u8; 64]; // 512 bits = 64 bytes
 	rand::thread_rng().fill_bytes(&mut key);
 	println!("\nGenerated key from rand::thread_rng():\n{key:?}");
@@ -14,3 +29,20 @@ fn main() -> Result<()> {
 
 	Ok(())
 }
+
+fn gen_ed25519_key(kid: Option<String>) -> Result<()> {
+	let signing_key = SigningKey::generate(&mut rand::thread_rng());
+	let kid = kid.unwrap_or_else(|| format!("ed25519-{}", lib_utils::time::now_utc().unix_timestamp()));
+
+	let private_pem = signing_key.to_pkcs8_pem(LineEnding::LF)?;
+	println!("\nPrivate key (SERVICE_TOKEN_ED25519_KEY, or a SERVICE_TOKEN_ED25519_KEY_FILE file):\n{}", private_pem.as_str());
+
+	let public_pem = signing_key.verifying_key().to_public_key_pem(LineEnding::LF)?;
+	println!("Public key:\n{public_pem}");
+
+	let x = b64u_encode(signing_key.verifying_key().to_bytes());
+	println!("Key id (SERVICE_TOKEN_ED25519_KID):\n{kid}");
+	println!("\nJWK x:\n{x}");
+
+	Ok(())
+}